@@ -1,17 +1,35 @@
 //! AI Vision provider abstraction and analysis pipeline.
 //!
-//! Supports three providers:
+//! Supports five providers:
 //! - **Anthropic Claude** — cloud vision via Claude Sonnet/Opus
 //! - **OpenAI GPT-4o** — cloud vision via GPT-4o
+//! - **Google Gemini** — cloud vision via the native Gemini API
 //! - **Ollama** — local vision via LLaVA or similar models
+//! - **OpenAI-compatible** — LM Studio, vLLM, llama.cpp and other servers
+//!   speaking the OpenAI chat format, with optional key and custom headers
 //!
 //! Each provider implements `AiVisionProvider` and returns a unified
 //! `AnalysisResult` struct matching the PRD Section 4.2 spec.
+//!
+//! The pipeline reports progress through `AnalysisEvents`, so the same code
+//! serves the app (Tauri events), the CLI and the HTTP API (`NoEvents`).
+//!
+//! A config can list fallback providers, tried in order when one fails or
+//! times out, and route tasks (analysis, connection test, generation) to
+//! providers of their own.
 
+use crate::analysis_schema::{extract_fields, AnalysisSchema, FieldDef};
+use crate::ratelimit::RateLimiter;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tauri::Emitter;
 
+/// Seconds a provider gets for one analysis when no timeout is configured.
+const DEFAULT_TIMEOUT_SECS: u64 = 120;
+
 // ---------------------------------------------------------------------------
 // Shared Types
 // ---------------------------------------------------------------------------
@@ -30,6 +48,14 @@ pub struct AnalysisResult {
     pub colors: Vec<String>,
     #[serde(default)]
     pub era: Option<String>,
+    /// Provider that produced this result (e.g. "anthropic").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Values of the project's own analysis fields (see `analysis_schema`).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra: BTreeMap<String, serde_json::Value>,
 }
 
 /// AI provider configuration stored in ~/.deco/config.json.
@@ -47,6 +73,34 @@ pub struct AiProviderConfig {
     pub temperature: Option<f32>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub max_tokens: Option<u32>,
+    /// Images analyzed in parallel by a batch.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub concurrency: Option<u32>,
+    /// Requests per minute sent to the endpoint (0 = unlimited).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_per_minute: Option<u32>,
+    /// Extra HTTP headers sent to an OpenAI-compatible server.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub headers: Option<BTreeMap<String, String>>,
+    /// Seconds to wait for an analysis, retries included, before giving up
+    /// on this provider.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    /// Providers tried in order when this one fails or times out.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallbacks: Vec<AiProviderConfig>,
+    /// Providers for individual tasks; a task without one uses this config.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tasks: BTreeMap<AiTask, AiProviderConfig>,
+}
+
+/// Work that can be routed to its own provider.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum AiTask {
+    Analysis,
+    Test,
+    Generation,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -54,7 +108,10 @@ pub struct AiProviderConfig {
 pub enum AiProviderKind {
     Anthropic,
     Openai,
+    Gemini,
     Ollama,
+    #[serde(rename = "openai-compatible")]
+    OpenaiCompatible,
 }
 
 impl AiProviderKind {
@@ -63,9 +120,143 @@ impl AiProviderKind {
         match self {
             AiProviderKind::Anthropic => "https://api.anthropic.com/v1",
             AiProviderKind::Openai => "https://api.openai.com/v1",
+            AiProviderKind::Gemini => "https://generativelanguage.googleapis.com/v1beta",
             AiProviderKind::Ollama => "http://localhost:11434",
+            AiProviderKind::OpenaiCompatible => "http://localhost:1234/v1",
+        }
+    }
+
+    /// Stable name used in cache keys (matches the serialized form).
+    fn id(&self) -> &'static str {
+        match self {
+            AiProviderKind::Anthropic => "anthropic",
+            AiProviderKind::Openai => "openai",
+            AiProviderKind::Gemini => "gemini",
+            AiProviderKind::Ollama => "ollama",
+            AiProviderKind::OpenaiCompatible => "openai-compatible",
+        }
+    }
+
+    /// Model used when none is configured. Empty for an OpenAI-compatible
+    /// server, which uses the first model it lists.
+    fn default_model(&self) -> &'static str {
+        match self {
+            AiProviderKind::Anthropic => "claude-sonnet-4-5-20250929",
+            AiProviderKind::Openai => "gpt-4o",
+            AiProviderKind::Gemini => "gemini-2.0-flash",
+            AiProviderKind::Ollama => "llava",
+            AiProviderKind::OpenaiCompatible => "",
+        }
+    }
+
+    /// Secrets entry holding this provider's API key.
+    fn key_account(&self) -> String {
+        format!("{}.{}", crate::keyring::AI_API_KEY, self.id())
+    }
+
+    /// Environment variable holding the API key, for providers that need one.
+    fn api_key_env(&self) -> Option<&'static str> {
+        match self {
+            AiProviderKind::Anthropic => Some("ANTHROPIC_API_KEY"),
+            AiProviderKind::Openai => Some("OPENAI_API_KEY"),
+            AiProviderKind::Gemini => Some("GEMINI_API_KEY"),
+            AiProviderKind::Ollama | AiProviderKind::OpenaiCompatible => None,
+        }
+    }
+
+    /// Parallel batch requests when none is configured. A local model
+    /// serves one image at a time anyway.
+    fn default_concurrency(&self) -> u32 {
+        match self {
+            AiProviderKind::Anthropic | AiProviderKind::Openai | AiProviderKind::Gemini => 4,
+            AiProviderKind::Ollama | AiProviderKind::OpenaiCompatible => 1,
+        }
+    }
+
+    /// Request rate when none is configured, below the entry-tier limits.
+    fn default_requests_per_minute(&self) -> u32 {
+        match self {
+            AiProviderKind::Anthropic => 50,
+            AiProviderKind::Openai => 60,
+            AiProviderKind::Gemini => 15,
+            AiProviderKind::Ollama | AiProviderKind::OpenaiCompatible => 0,
+        }
+    }
+}
+
+impl AiProviderConfig {
+    /// Configured endpoint, or the provider's default.
+    fn endpoint(&self) -> String {
+        self.endpoint
+            .clone()
+            .filter(|e| !e.is_empty())
+            .unwrap_or_else(|| self.provider.default_endpoint().to_string())
+    }
+
+    /// Configured model, or the provider's default.
+    fn model_name(&self) -> String {
+        self.model
+            .clone()
+            .filter(|m| !m.is_empty())
+            .unwrap_or_else(|| self.provider.default_model().to_string())
+    }
+
+    /// How many images a batch analyzes at once (at least 1).
+    pub fn concurrency(&self) -> usize {
+        self.concurrency.unwrap_or_else(|| self.provider.default_concurrency()).max(1) as usize
+    }
+
+    /// API key: configured, else stored for this provider, else the
+    /// provider's environment variable. Never another provider's key.
+    fn api_key(&self) -> Option<String> {
+        self.api_key
+            .clone()
+            .filter(|k| !k.is_empty())
+            .or_else(|| crate::keyring::get_secret(&self.provider.key_account()))
+            .or_else(|| self.provider.api_key_env().and_then(|var| std::env::var(var).ok()))
+    }
+
+    /// How long one provider may take on an analysis.
+    fn timeout(&self) -> Duration {
+        Duration::from_secs(self.timeout_secs.unwrap_or(DEFAULT_TIMEOUT_SECS).max(1))
+    }
+
+    /// This provider followed by its fallbacks, in the order they're tried.
+    pub fn chain(&self) -> Vec<&AiProviderConfig> {
+        std::iter::once(self).chain(&self.fallbacks).collect()
+    }
+
+    /// The config for `task`: its own route if one is set, else this config.
+    pub fn for_task(&self, task: AiTask) -> AiProviderConfig {
+        self.tasks.get(&task).cloned().unwrap_or_else(|| AiProviderConfig {
+            tasks: BTreeMap::new(),
+            ..self.clone()
+        })
+    }
+
+    /// Drop the API keys of this config, its fallbacks and task routes.
+    pub(crate) fn strip_api_keys(&mut self) {
+        self.api_key = None;
+        for nested in self.fallbacks.iter_mut().chain(self.tasks.values_mut()) {
+            nested.strip_api_keys();
+        }
+    }
+
+    /// "provider/model", for logs and errors.
+    fn label(&self) -> String {
+        match self.model_name() {
+            model if model.is_empty() => self.provider.id().to_string(),
+            model => format!("{}/{model}", self.provider.id()),
         }
     }
+
+    /// The rate limiter shared by all requests to this config's endpoint.
+    fn limiter(&self) -> Arc<RateLimiter> {
+        let per_minute = self
+            .requests_per_minute
+            .unwrap_or_else(|| self.provider.default_requests_per_minute());
+        crate::ratelimit::shared(&self.endpoint(), per_minute, self.concurrency() as u32)
+    }
 }
 
 impl Default for AiProviderConfig {
@@ -77,6 +268,12 @@ impl Default for AiProviderConfig {
             model: None,
             temperature: None,
             max_tokens: None,
+            concurrency: None,
+            requests_per_minute: None,
+            headers: None,
+            timeout_secs: None,
+            fallbacks: Vec::new(),
+            tasks: BTreeMap::new(),
         }
     }
 }
@@ -105,17 +302,27 @@ Guidelines:
 - era: approximate time period if identifiable, or null
 - Return ONLY the JSON object, no other text"##;
 
-/// Build a context-aware prompt that references existing board tags.
-/// Enables PRD user story U8: AI tag suggestions are context-aware.
-fn build_analysis_prompt(existing_tags: &[String]) -> String {
-    if existing_tags.is_empty() {
-        return ANALYSIS_PROMPT.to_string();
+/// A rendered analysis prompt and the project fields its answer must carry.
+#[derive(Debug, Clone, Default)]
+pub struct AnalysisPrompt {
+    pub text: String,
+    pub fields: Vec<FieldDef>,
+}
+
+impl From<&str> for AnalysisPrompt {
+    fn from(text: &str) -> Self {
+        Self { text: text.to_string(), fields: Vec::new() }
     }
+}
 
-    let tag_list = existing_tags.join(", ");
-    format!(
-        "{ANALYSIS_PROMPT}\n\nThe board already uses these tags: [{tag_list}]\nPrefer reusing existing tags when they apply. Only introduce new tags for concepts not covered."
-    )
+/// Build a context-aware prompt that references existing board tags and asks
+/// for the project's extra fields.
+/// Enables PRD user story U8: AI tag suggestions are context-aware.
+fn build_analysis_prompt(existing_tags: &[String], schema: &AnalysisSchema) -> AnalysisPrompt {
+    AnalysisPrompt {
+        text: schema.render_prompt(ANALYSIS_PROMPT, existing_tags),
+        fields: schema.fields.clone(),
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -151,12 +358,22 @@ fn encode_image(path: &Path) -> Result<(String, String), String> {
 // JSON Response Parser
 // ---------------------------------------------------------------------------
 
-/// Parse the AI model's text response into a structured AnalysisResult.
+/// Parse the AI model's text response into a structured AnalysisResult,
+/// validating the project's extra `fields` against it.
 /// Handles clean JSON, JSON in markdown code blocks, and embedded JSON.
-fn parse_analysis_json(text: &str) -> Result<AnalysisResult, String> {
+fn parse_analysis_json(text: &str, fields: &[FieldDef]) -> Result<AnalysisResult, String> {
+    let parse = |json: &str| -> Option<Result<AnalysisResult, String>> {
+        let value: serde_json::Value = serde_json::from_str(json).ok()?;
+        let mut result = serde_json::from_value::<AnalysisResult>(value.clone()).ok()?;
+        Some(extract_fields(fields, &value).map(|extra| {
+            result.extra = extra;
+            result
+        }))
+    };
+
     // Try direct parse first
-    if let Ok(result) = serde_json::from_str::<AnalysisResult>(text) {
-        return Ok(result);
+    if let Some(result) = parse(text) {
+        return result;
     }
 
     // Try extracting JSON from markdown code block: ```json ... ```
@@ -164,8 +381,8 @@ fn parse_analysis_json(text: &str) -> Result<AnalysisResult, String> {
         let json_start = start + 7;
         if let Some(end) = text[json_start..].find("```") {
             let json_str = text[json_start..json_start + end].trim();
-            if let Ok(result) = serde_json::from_str::<AnalysisResult>(json_str) {
-                return Ok(result);
+            if let Some(result) = parse(json_str) {
+                return result;
             }
         }
     }
@@ -188,12 +405,14 @@ fn parse_analysis_json(text: &str) -> Result<AnalysisResult, String> {
             }
         }
         let json_str = &text[start..end];
-        if let Ok(result) = serde_json::from_str::<AnalysisResult>(json_str) {
-            return Ok(result);
+        if let Some(result) = parse(json_str) {
+            return result;
         }
     }
 
-    // Last resort: wrap plain text as description with empty metadata
+    // Last resort: wrap plain text as description with empty metadata,
+    // unless the project requires fields that plain text can't provide
+    extract_fields(fields, &serde_json::Value::Null)?;
     Ok(AnalysisResult {
         description: text.to_string(),
         tags: Vec::new(),
@@ -201,6 +420,9 @@ fn parse_analysis_json(text: &str) -> Result<AnalysisResult, String> {
         mood: Vec::new(),
         colors: Vec::new(),
         era: None,
+        provider: None,
+        model: None,
+        extra: BTreeMap::new(),
     })
 }
 
@@ -211,11 +433,12 @@ fn parse_analysis_json(text: &str) -> Result<AnalysisResult, String> {
 /// Trait for AI vision providers. All providers must implement image analysis.
 #[async_trait::async_trait]
 pub trait AiVisionProvider: Send + Sync {
-    /// Analyze an image and return structured metadata.
+    /// Analyze an image and return structured metadata, including the
+    /// prompt's extra fields.
     async fn analyze_image(
         &self,
         image_path: &Path,
-        prompt: &str,
+        prompt: &AnalysisPrompt,
     ) -> Result<AnalysisResult, String>;
 
     /// Provider display name (for UI).
@@ -228,6 +451,7 @@ pub trait AiVisionProvider: Send + Sync {
 
 pub struct AnthropicProvider {
     client: reqwest::Client,
+    limiter: Arc<RateLimiter>,
     api_key: String,
     model: String,
     endpoint: String,
@@ -236,11 +460,12 @@ pub struct AnthropicProvider {
 }
 
 impl AnthropicProvider {
-    pub fn new(client: reqwest::Client, api_key: String, model: Option<String>, endpoint: String, temperature: f32, max_tokens: u32) -> Self {
+    pub fn new(client: reqwest::Client, limiter: Arc<RateLimiter>, api_key: String, model: Option<String>, endpoint: String, temperature: f32, max_tokens: u32) -> Self {
         Self {
             client,
+            limiter,
             api_key,
-            model: model.unwrap_or_else(|| "claude-sonnet-4-5-20250929".to_string()),
+            model: model.unwrap_or_else(|| AiProviderKind::Anthropic.default_model().to_string()),
             endpoint,
             temperature,
             max_tokens,
@@ -301,7 +526,7 @@ impl AiVisionProvider for AnthropicProvider {
     async fn analyze_image(
         &self,
         image_path: &Path,
-        prompt: &str,
+        prompt: &AnalysisPrompt,
     ) -> Result<AnalysisResult, String> {
         let (b64_data, mime) = encode_image(image_path)?;
 
@@ -321,23 +546,21 @@ impl AiVisionProvider for AnthropicProvider {
                         },
                     },
                     AnthropicContent::Text {
-                        text: prompt.to_string(),
+                        text: prompt.text.clone(),
                     },
                 ],
             }],
         };
 
         let url = format!("{}/messages", self.endpoint.trim_end_matches('/'));
-        let resp = self
+        let request = self
             .client
             .post(&url)
             .header("x-api-key", &self.api_key)
             .header("anthropic-version", "2023-06-01")
             .header("content-type", "application/json")
-            .json(&body)
-            .send()
-            .await
-            .map_err(|e| format!("Anthropic request failed: {e}"))?;
+            .json(&body);
+        let resp = self.limiter.send(request, "Anthropic").await?;
 
         let status = resp.status();
         let content_len = resp.content_length().unwrap_or(0);
@@ -361,7 +584,7 @@ impl AiVisionProvider for AnthropicProvider {
             })
             .unwrap_or_default();
 
-        parse_analysis_json(&text)
+        parse_analysis_json(&text, &prompt.fields)
     }
 
     fn name(&self) -> &str {
@@ -375,6 +598,7 @@ impl AiVisionProvider for AnthropicProvider {
 
 pub struct OpenAIProvider {
     client: reqwest::Client,
+    limiter: Arc<RateLimiter>,
     api_key: String,
     model: String,
     endpoint: String,
@@ -383,11 +607,12 @@ pub struct OpenAIProvider {
 }
 
 impl OpenAIProvider {
-    pub fn new(client: reqwest::Client, api_key: String, model: Option<String>, endpoint: String, temperature: f32, max_tokens: u32) -> Self {
+    pub fn new(client: reqwest::Client, limiter: Arc<RateLimiter>, api_key: String, model: Option<String>, endpoint: String, temperature: f32, max_tokens: u32) -> Self {
         Self {
             client,
+            limiter,
             api_key,
-            model: model.unwrap_or_else(|| "gpt-4o".to_string()),
+            model: model.unwrap_or_else(|| AiProviderKind::Openai.default_model().to_string()),
             endpoint,
             temperature,
             max_tokens,
@@ -454,7 +679,7 @@ impl AiVisionProvider for OpenAIProvider {
     async fn analyze_image(
         &self,
         image_path: &Path,
-        prompt: &str,
+        prompt: &AnalysisPrompt,
     ) -> Result<AnalysisResult, String> {
         let (b64_data, mime) = encode_image(image_path)?;
         let data_uri = format!("data:{mime};base64,{b64_data}");
@@ -470,7 +695,7 @@ impl AiVisionProvider for OpenAIProvider {
                 role: "user".to_string(),
                 content: vec![
                     OpenAIContent::Text {
-                        text: prompt.to_string(),
+                        text: prompt.text.clone(),
                     },
                     OpenAIContent::ImageUrl {
                         image_url: OpenAIImageUrl { url: data_uri },
@@ -480,15 +705,13 @@ impl AiVisionProvider for OpenAIProvider {
         };
 
         let url = format!("{}/chat/completions", self.endpoint.trim_end_matches('/'));
-        let resp = self
+        let request = self
             .client
             .post(&url)
             .header("Authorization", format!("Bearer {}", self.api_key))
             .header("Content-Type", "application/json")
-            .json(&body)
-            .send()
-            .await
-            .map_err(|e| format!("OpenAI request failed: {e}"))?;
+            .json(&body);
+        let resp = self.limiter.send(request, "OpenAI").await?;
 
         let status = resp.status();
         let content_len = resp.content_length().unwrap_or(0);
@@ -510,7 +733,7 @@ impl AiVisionProvider for OpenAIProvider {
             .and_then(|c| c.message.content.clone())
             .unwrap_or_default();
 
-        parse_analysis_json(&text)
+        parse_analysis_json(&text, &prompt.fields)
     }
 
     fn name(&self) -> &str {
@@ -518,23 +741,192 @@ impl AiVisionProvider for OpenAIProvider {
     }
 }
 
+// ---------------------------------------------------------------------------
+// Gemini Provider
+// ---------------------------------------------------------------------------
+
+pub struct GeminiProvider {
+    client: reqwest::Client,
+    limiter: Arc<RateLimiter>,
+    api_key: String,
+    model: String,
+    endpoint: String,
+    temperature: f32,
+    max_tokens: u32,
+}
+
+impl GeminiProvider {
+    pub fn new(client: reqwest::Client, limiter: Arc<RateLimiter>, api_key: String, model: Option<String>, endpoint: String, temperature: f32, max_tokens: u32) -> Self {
+        Self {
+            client,
+            limiter,
+            api_key,
+            model: model.unwrap_or_else(|| AiProviderKind::Gemini.default_model().to_string()),
+            endpoint,
+            temperature,
+            max_tokens,
+        }
+    }
+
+    /// Send one `generateContent` request and return the reply text.
+    async fn generate(&self, parts: Vec<GeminiPart>, config: GeminiGenerationConfig) -> Result<String, String> {
+        let body = GeminiRequest {
+            contents: vec![GeminiContent {
+                role: "user".to_string(),
+                parts,
+            }],
+            generation_config: config,
+        };
+
+        // Model names are listed as "models/…"; accept either form
+        let model = self.model.trim_start_matches("models/");
+        let url = format!("{}/models/{model}:generateContent", self.endpoint.trim_end_matches('/'));
+        let request = self
+            .client
+            .post(&url)
+            .header("x-goog-api-key", &self.api_key)
+            .json(&body);
+        let resp = self.limiter.send(request, "Gemini").await?;
+
+        let status = resp.status();
+        let content_len = resp.content_length().unwrap_or(0);
+        crate::log::log("AI", &format!("Response received: {status}, {content_len} bytes"));
+
+        if !status.is_success() {
+            let text = resp.text().await.unwrap_or_default();
+            return Err(format!("Gemini API error ({status}): {text}"));
+        }
+
+        let result: GeminiResponse = resp
+            .json()
+            .await
+            .map_err(|e| format!("Gemini response parse error: {e}"))?;
+
+        // A blocked prompt comes back with no candidates
+        let candidate = result
+            .candidates
+            .into_iter()
+            .next()
+            .ok_or("Gemini returned no candidates (the request may have been blocked)")?;
+        Ok(candidate
+            .content
+            .map(|c| c.parts.into_iter().filter_map(|p| p.text).collect())
+            .unwrap_or_default())
+    }
+}
+
+// Gemini request types
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiRequest {
+    contents: Vec<GeminiContent>,
+    generation_config: GeminiGenerationConfig,
+}
+
+#[derive(Serialize)]
+struct GeminiContent {
+    role: String,
+    parts: Vec<GeminiPart>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+enum GeminiPart {
+    Text(String),
+    InlineData(GeminiBlob),
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiBlob {
+    mime_type: String,
+    data: String,
+}
+
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct GeminiGenerationConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_output_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    response_mime_type: Option<String>,
+}
+
+// Gemini response types
+#[derive(Deserialize)]
+struct GeminiResponse {
+    #[serde(default)]
+    candidates: Vec<GeminiCandidate>,
+}
+
+#[derive(Deserialize)]
+struct GeminiCandidate {
+    content: Option<GeminiResponseContent>,
+}
+
+#[derive(Deserialize)]
+struct GeminiResponseContent {
+    #[serde(default)]
+    parts: Vec<GeminiResponsePart>,
+}
+
+#[derive(Deserialize)]
+struct GeminiResponsePart {
+    text: Option<String>,
+}
+
+#[async_trait::async_trait]
+impl AiVisionProvider for GeminiProvider {
+    async fn analyze_image(
+        &self,
+        image_path: &Path,
+        prompt: &AnalysisPrompt,
+    ) -> Result<AnalysisResult, String> {
+        let (b64_data, mime) = encode_image(image_path)?;
+
+        let parts = vec![
+            GeminiPart::InlineData(GeminiBlob {
+                mime_type: mime,
+                data: b64_data,
+            }),
+            GeminiPart::Text(prompt.text.clone()),
+        ];
+        let config = GeminiGenerationConfig {
+            temperature: Some(self.temperature),
+            max_output_tokens: Some(self.max_tokens),
+            response_mime_type: Some("application/json".to_string()),
+        };
+
+        let text = self.generate(parts, config).await?;
+        parse_analysis_json(&text, &prompt.fields)
+    }
+
+    fn name(&self) -> &str {
+        "Gemini Vision"
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Ollama Provider
 // ---------------------------------------------------------------------------
 
 pub struct OllamaProvider {
     client: reqwest::Client,
+    limiter: Arc<RateLimiter>,
     endpoint: String,
     model: String,
     temperature: f32,
 }
 
 impl OllamaProvider {
-    pub fn new(client: reqwest::Client, endpoint: Option<String>, model: Option<String>, temperature: f32) -> Self {
+    pub fn new(client: reqwest::Client, limiter: Arc<RateLimiter>, endpoint: Option<String>, model: Option<String>, temperature: f32) -> Self {
         Self {
             client,
+            limiter,
             endpoint: endpoint.unwrap_or_else(|| "http://localhost:11434".to_string()),
-            model: model.unwrap_or_else(|| "llava".to_string()),
+            model: model.unwrap_or_else(|| AiProviderKind::Ollama.default_model().to_string()),
             temperature,
         }
     }
@@ -576,7 +968,7 @@ impl AiVisionProvider for OllamaProvider {
     async fn analyze_image(
         &self,
         image_path: &Path,
-        prompt: &str,
+        prompt: &AnalysisPrompt,
     ) -> Result<AnalysisResult, String> {
         let (b64_data, _mime) = encode_image(image_path)?;
         // Ollama takes raw base64 -- no data: URI wrapper, no mime type
@@ -588,18 +980,16 @@ impl AiVisionProvider for OllamaProvider {
             format: Some("json".to_string()),
             messages: vec![OllamaMessage {
                 role: "user".to_string(),
-                content: prompt.to_string(),
+                content: prompt.text.clone(),
                 images: Some(vec![b64_data]),
             }],
         };
 
-        let resp = self
+        let request = self
             .client
             .post(format!("{}/api/chat", self.endpoint))
-            .json(&body)
-            .send()
-            .await
-            .map_err(|e| format!("Ollama request failed: {e}"))?;
+            .json(&body);
+        let resp = self.limiter.send(request, "Ollama").await?;
 
         let status = resp.status();
         let content_len = resp.content_length().unwrap_or(0);
@@ -613,13 +1003,197 @@ impl AiVisionProvider for OllamaProvider {
         let result: OllamaChatResponse = resp
             .json()
             .await
-            .map_err(|e| format!("Ollama response parse error: {e}"))?;
+            .map_err(|e| format!("Ollama response parse error: {e}"))?;
+
+        parse_analysis_json(&result.message.content, &prompt.fields)
+    }
+
+    fn name(&self) -> &str {
+        "Ollama (Local)"
+    }
+}
+
+// ---------------------------------------------------------------------------
+// OpenAI-compatible Provider
+// ---------------------------------------------------------------------------
+
+/// A server speaking the OpenAI chat format (LM Studio, vLLM, llama.cpp…).
+/// Unlike `OpenAIProvider` the key is optional, extra headers can be sent,
+/// and without a configured model the first one the server lists is used.
+pub struct OpenAICompatibleProvider {
+    client: reqwest::Client,
+    limiter: Arc<RateLimiter>,
+    api_key: Option<String>,
+    headers: BTreeMap<String, String>,
+    model: Option<String>,
+    /// The model discovered when none is configured, looked up once.
+    discovered_model: Arc<tokio::sync::OnceCell<String>>,
+    endpoint: String,
+    temperature: f32,
+    max_tokens: u32,
+}
+
+/// Models discovered per endpoint, shared by every provider built for it in
+/// this session so `GET /models` isn't repeated for each image.
+static DISCOVERED_MODELS: Mutex<Option<HashMap<String, Arc<tokio::sync::OnceCell<String>>>>> = Mutex::new(None);
+
+fn discovered_model_cell(endpoint: &str) -> Arc<tokio::sync::OnceCell<String>> {
+    let mut guard = DISCOVERED_MODELS.lock().unwrap_or_else(|e| e.into_inner());
+    guard
+        .get_or_insert_with(HashMap::new)
+        .entry(endpoint.to_string())
+        .or_default()
+        .clone()
+}
+
+impl OpenAICompatibleProvider {
+    pub fn new(client: reqwest::Client, limiter: Arc<RateLimiter>, api_key: Option<String>, model: Option<String>, endpoint: String, temperature: f32, max_tokens: u32) -> Self {
+        Self {
+            client,
+            limiter,
+            api_key: api_key.filter(|k| !k.is_empty()),
+            headers: BTreeMap::new(),
+            model: model.filter(|m| !m.is_empty()),
+            discovered_model: Arc::default(),
+            endpoint,
+            temperature,
+            max_tokens,
+        }
+    }
+
+    pub fn with_headers(mut self, headers: BTreeMap<String, String>) -> Self {
+        self.headers = headers;
+        self
+    }
+
+    /// Share model discovery with other providers for the same server.
+    pub fn with_discovered_model(mut self, cell: Arc<tokio::sync::OnceCell<String>>) -> Self {
+        self.discovered_model = cell;
+        self
+    }
+
+    /// Add the key (if any) and the custom headers to a request.
+    fn authorize(&self, mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if let Some(ref key) = self.api_key {
+            request = request.header("Authorization", format!("Bearer {key}"));
+        }
+        for (name, value) in &self.headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        request
+    }
+
+    /// Model ids the server offers (`GET /models`).
+    pub async fn list_models(&self) -> Result<Vec<String>, String> {
+        let url = format!("{}/models", self.endpoint.trim_end_matches('/'));
+        let resp = self
+            .limiter
+            .send(self.authorize(self.client.get(&url)), "OpenAI-compatible")
+            .await?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let text = resp.text().await.unwrap_or_default();
+            return Err(format!("Cannot list models at {url} ({status}): {text}"));
+        }
+
+        let list: OpenAIModelList = resp
+            .json()
+            .await
+            .map_err(|e| format!("Cannot parse model list: {e}"))?;
+        Ok(list.data.into_iter().map(|m| m.id).collect())
+    }
+
+    /// The configured model, or else the first one the server lists (asked
+    /// once; a failed lookup is retried on the next call).
+    async fn resolve_model(&self) -> Result<String, String> {
+        if let Some(ref model) = self.model {
+            return Ok(model.clone());
+        }
+        self.discovered_model
+            .get_or_try_init(|| async {
+                self.list_models()
+                    .await?
+                    .into_iter()
+                    .next()
+                    .ok_or_else(|| format!("No model configured and {} lists none", self.endpoint))
+            })
+            .await
+            .cloned()
+    }
+}
+
+// Model discovery response types
+#[derive(Deserialize)]
+struct OpenAIModelList {
+    #[serde(default)]
+    data: Vec<OpenAIModelInfo>,
+}
+
+#[derive(Deserialize)]
+struct OpenAIModelInfo {
+    id: String,
+}
+
+#[async_trait::async_trait]
+impl AiVisionProvider for OpenAICompatibleProvider {
+    async fn analyze_image(
+        &self,
+        image_path: &Path,
+        prompt: &AnalysisPrompt,
+    ) -> Result<AnalysisResult, String> {
+        let (b64_data, mime) = encode_image(image_path)?;
+        let data_uri = format!("data:{mime};base64,{b64_data}");
+
+        // No response_format: servers disagree on which kinds they accept,
+        // and parse_analysis_json copes with JSON wrapped in prose.
+        let body = OpenAIRequest {
+            model: self.resolve_model().await?,
+            max_tokens: self.max_tokens,
+            temperature: Some(self.temperature),
+            response_format: None,
+            messages: vec![OpenAIMessage {
+                role: "user".to_string(),
+                content: vec![
+                    OpenAIContent::Text {
+                        text: prompt.text.clone(),
+                    },
+                    OpenAIContent::ImageUrl {
+                        image_url: OpenAIImageUrl { url: data_uri },
+                    },
+                ],
+            }],
+        };
+
+        let url = format!("{}/chat/completions", self.endpoint.trim_end_matches('/'));
+        let request = self.authorize(self.client.post(&url).json(&body));
+        let resp = self.limiter.send(request, "OpenAI-compatible").await?;
+
+        let status = resp.status();
+        let content_len = resp.content_length().unwrap_or(0);
+        crate::log::log("AI", &format!("Response received: {status}, {content_len} bytes"));
+
+        if !status.is_success() {
+            let text = resp.text().await.unwrap_or_default();
+            return Err(format!("OpenAI-compatible API error ({status}): {text}"));
+        }
+
+        let result: OpenAIResponse = resp
+            .json()
+            .await
+            .map_err(|e| format!("OpenAI-compatible response parse error: {e}"))?;
+
+        let text = result
+            .choices
+            .first()
+            .and_then(|c| c.message.content.clone())
+            .unwrap_or_default();
 
-        parse_analysis_json(&result.message.content)
+        parse_analysis_json(&text, &prompt.fields)
     }
 
     fn name(&self) -> &str {
-        "Ollama (Local)"
+        "OpenAI-compatible"
     }
 }
 
@@ -633,11 +1207,8 @@ fn create_provider(
     config: &AiProviderConfig,
 ) -> Result<Box<dyn AiVisionProvider>, String> {
     // Resolve endpoint: use configured value or provider-specific default.
-    let endpoint = config
-        .endpoint
-        .clone()
-        .filter(|e| !e.is_empty())
-        .unwrap_or_else(|| config.provider.default_endpoint().to_string());
+    let endpoint = config.endpoint();
+    let limiter = config.limiter();
 
     let temperature = config.temperature.unwrap_or(0.7);
     let max_tokens = config.max_tokens.unwrap_or(4096);
@@ -645,13 +1216,11 @@ fn create_provider(
     match config.provider {
         AiProviderKind::Anthropic => {
             let key = config
-                .api_key
-                .clone()
-                .or_else(|| crate::keyring::get_secret(crate::keyring::AI_API_KEY))
-                .or_else(|| std::env::var("ANTHROPIC_API_KEY").ok())
+                .api_key()
                 .ok_or("No Anthropic API key configured. Set it in settings or ANTHROPIC_API_KEY env var.")?;
             Ok(Box::new(AnthropicProvider::new(
                 client.clone(),
+                limiter,
                 key,
                 config.model.clone(),
                 endpoint,
@@ -661,13 +1230,25 @@ fn create_provider(
         }
         AiProviderKind::Openai => {
             let key = config
-                .api_key
-                .clone()
-                .or_else(|| crate::keyring::get_secret(crate::keyring::AI_API_KEY))
-                .or_else(|| std::env::var("OPENAI_API_KEY").ok())
+                .api_key()
                 .ok_or("No OpenAI API key configured. Set it in settings or OPENAI_API_KEY env var.")?;
             Ok(Box::new(OpenAIProvider::new(
                 client.clone(),
+                limiter,
+                key,
+                config.model.clone(),
+                endpoint,
+                temperature,
+                max_tokens,
+            )))
+        }
+        AiProviderKind::Gemini => {
+            let key = config
+                .api_key()
+                .ok_or("No Gemini API key configured. Set it in settings or GEMINI_API_KEY env var.")?;
+            Ok(Box::new(GeminiProvider::new(
+                client.clone(),
+                limiter,
                 key,
                 config.model.clone(),
                 endpoint,
@@ -677,13 +1258,34 @@ fn create_provider(
         }
         AiProviderKind::Ollama => Ok(Box::new(OllamaProvider::new(
             client.clone(),
+            limiter,
             Some(endpoint),
             config.model.clone(),
             temperature,
         ))),
+        AiProviderKind::OpenaiCompatible => Ok(Box::new(compatible_provider(client, config, limiter))),
     }
 }
 
+/// An OpenAI-compatible provider; the key is optional for these servers.
+fn compatible_provider(
+    client: &reqwest::Client,
+    config: &AiProviderConfig,
+    limiter: Arc<RateLimiter>,
+) -> OpenAICompatibleProvider {
+    OpenAICompatibleProvider::new(
+        client.clone(),
+        limiter,
+        config.api_key(),
+        config.model.clone(),
+        config.endpoint(),
+        config.temperature.unwrap_or(0.7),
+        config.max_tokens.unwrap_or(4096),
+    )
+    .with_headers(config.headers.clone().unwrap_or_default())
+    .with_discovered_model(discovered_model_cell(&config.endpoint()))
+}
+
 // ---------------------------------------------------------------------------
 // Config Persistence (~/.deco/config.json)
 // ---------------------------------------------------------------------------
@@ -725,38 +1327,170 @@ fn save_app_config(config: &AppConfig) -> Result<(), String> {
 }
 
 // ---------------------------------------------------------------------------
-// Tauri Commands
+// Analysis Events
+// ---------------------------------------------------------------------------
+
+/// Receives progress from the analysis pipeline. Every method defaults to
+/// doing nothing.
+pub trait AnalysisEvents: Send + Sync {
+    fn started(&self, _image_path: &str) {}
+    fn completed(&self, _image_path: &str) {}
+    fn failed(&self, _error: &str) {}
+    fn batch_progress(&self, _progress: &BatchProgress) {}
+    fn batch_complete(&self, _items: &[BatchAnalysisItem]) {}
+}
+
+/// The app forwards analysis progress to the frontend.
+impl AnalysisEvents for tauri::AppHandle {
+    fn started(&self, image_path: &str) {
+        let _ = self.emit("ai:analysis:start", image_path);
+    }
+
+    fn completed(&self, image_path: &str) {
+        let _ = self.emit("ai:analysis:complete", image_path);
+    }
+
+    fn failed(&self, error: &str) {
+        let _ = self.emit("ai:analysis:error", error);
+    }
+
+    fn batch_progress(&self, progress: &BatchProgress) {
+        let _ = self.emit("ai:batch:progress", progress);
+    }
+
+    fn batch_complete(&self, items: &[BatchAnalysisItem]) {
+        let _ = self.emit("ai:batch:complete", items);
+    }
+}
+
+/// Discards analysis progress (CLI, HTTP API).
+pub struct NoEvents;
+
+impl AnalysisEvents for NoEvents {}
+
+// ---------------------------------------------------------------------------
+// Analysis Pipeline
 // ---------------------------------------------------------------------------
 
 /// Analyze a single image and optionally save results to search DB.
-/// Core logic shared by the Tauri command and batch analysis.
-async fn do_analyze_image(
-    app: &tauri::AppHandle,
+/// Core logic shared by the Tauri command, batches, the CLI and the HTTP API.
+///
+/// Uses the analysis route of `provider_config`, trying its fallbacks in
+/// order when a provider fails or times out. Results are cached in the
+/// image's project (see `analysis_cache`), even when `project_path` is not
+/// given, and a cached result from any provider in the chain is returned
+/// without calling one unless `force` is set.
+pub(crate) async fn do_analyze_image(
+    events: &dyn AnalysisEvents,
     client: &reqwest::Client,
     image_path: &str,
     provider_config: &AiProviderConfig,
     existing_tags: &[String],
     project_path: Option<&str>,
+    force: bool,
 ) -> Result<AnalysisResult, String> {
     let filename = Path::new(image_path)
         .file_name()
         .map(|n| n.to_string_lossy().to_string())
         .unwrap_or_else(|| image_path.to_string());
+    let provider_config = &provider_config.for_task(AiTask::Analysis);
     let provider_name = format!("{:?}", provider_config.provider);
 
     crate::log::log("AI", &format!("Analyzing image: {filename} (provider: {provider_name})"));
 
-    let provider = create_provider(client, provider_config)?;
-    let prompt = build_analysis_prompt(existing_tags);
     let path = PathBuf::from(image_path);
 
     if !path.exists() {
         return Err(format!("Image file not found: {image_path}"));
     }
 
-    let _ = app.emit("ai:analysis:start", image_path);
+    // The image's project, for its analysis fields, database and cache
+    let cache_project = project_path
+        .map(str::to_string)
+        .or_else(|| crate::analysis_cache::project_of(&path));
+    let (schema, vocab) = match &cache_project {
+        Some(proj) => (AnalysisSchema::load(proj)?, crate::vocab::Vocabulary::load_or_default(proj)),
+        None => Default::default(),
+    };
+    let prompt = build_analysis_prompt(&vocab.with_canonical(existing_tags), &schema);
+    // The tag context grows with every analysis, so it isn't part of the key
+    let cache_prompt = build_analysis_prompt(&[], &schema).text;
+
+    events.started(image_path);
+
+    let cache = cache_project.as_deref().and_then(|proj| {
+        let conn = crate::search::open_db(proj).ok()?;
+        let hash = crate::dedup::content_hash_conn(&conn, &path).ok()?;
+        Some((proj, conn, hash))
+    });
+    // An unnamed model is whatever the server has loaded, so it isn't cached
+    let hash = cache.as_ref().map(|(_, _, hash)| hash.clone());
+    let cache_key = |cfg: &AiProviderConfig| {
+        let hash = hash.as_ref()?;
+        let model = cfg.model_name();
+        (!model.is_empty()).then(|| {
+            crate::analysis_cache::CacheKey::new(hash.clone(), cfg.provider.id(), &model, &cache_prompt)
+        })
+    };
+
+    let chain = provider_config.chain();
+    let cached = chain.iter().filter(|_| !force).find_map(|cfg| {
+        let (proj, conn, _) = cache.as_ref()?;
+        let key = cache_key(cfg)?;
+        let mut analysis = crate::analysis_cache::lookup(conn, proj, &key).unwrap_or_else(|e| {
+            crate::log::log("AI", &format!("Warning: analysis cache unavailable: {e}"));
+            None
+        })?;
+        // Entries cached before results recorded their provider
+        analysis.provider.get_or_insert(key.provider);
+        analysis.model.get_or_insert(key.model);
+        Some(analysis)
+    });
+
+    let result = match cached {
+        Some(analysis) => {
+            crate::log::log("AI", &format!("Using cached analysis for {filename}"));
+            Ok(analysis)
+        }
+        None => {
+            let mut errors = Vec::new();
+            let mut answer = None;
+            for cfg in &chain {
+                match analyze_with(client, cfg, &path, &prompt).await {
+                    Ok(analysis) => {
+                        if let (Some((_, conn, _)), Some(key)) = (&cache, cache_key(cfg)) {
+                            if let Err(e) = crate::analysis_cache::put(conn, &key, &analysis) {
+                                crate::log::log("AI", &format!("Warning: failed to cache analysis: {e}"));
+                            }
+                        }
+                        answer = Some(analysis);
+                        break;
+                    }
+                    Err(e) => {
+                        if chain.len() > 1 {
+                            crate::log::log("AI", &format!("{} failed, trying next provider: {e}", cfg.label()));
+                        }
+                        errors.push((cfg.label(), e));
+                    }
+                }
+            }
+            match answer {
+                Some(analysis) => Ok(analysis),
+                // A lone provider's error is passed on as is
+                None if errors.len() == 1 => Err(errors.remove(0).1),
+                None => Err(format!(
+                    "All AI providers failed: {}",
+                    errors.iter().map(|(label, e)| format!("{label}: {e}")).collect::<Vec<_>>().join("; ")
+                )),
+            }
+        }
+    };
 
-    let result = provider.analyze_image(&path, &prompt).await;
+    // Tags as the project's vocabulary spells them
+    let result = result.map(|mut analysis| {
+        analysis.tags = vocab.apply(&analysis.tags);
+        analysis
+    });
 
     match &result {
         Ok(analysis) => {
@@ -777,25 +1511,164 @@ async fn do_analyze_image(
                     mood: analysis.mood.clone(),
                     colors: analysis.colors.clone(),
                     era: analysis.era.clone(),
+                    extra: analysis.extra.clone(),
+                    ..Default::default()
                 };
                 if let Err(e) = crate::search::update_image_metadata(proj, &meta) {
                     crate::log::log("AI", &format!("Warning: failed to save metadata to DB: {e}"));
                 }
             }
 
-            let _ = app.emit("ai:analysis:complete", image_path);
+            events.completed(image_path);
         }
         Err(e) => {
             crate::log::log("AI", &format!("Error: {e}"));
-            let _ = app.emit("ai:analysis:error", e.as_str());
+            events.failed(e);
         }
     }
 
     result
 }
 
+/// Analyze with one provider of a chain, giving up after its timeout, and
+/// record which provider answered.
+async fn analyze_with(
+    client: &reqwest::Client,
+    cfg: &AiProviderConfig,
+    path: &Path,
+    prompt: &AnalysisPrompt,
+) -> Result<AnalysisResult, String> {
+    let provider = create_provider(client, cfg)?;
+    let timeout = cfg.timeout();
+    let mut analysis = tokio::time::timeout(timeout, provider.analyze_image(path, prompt))
+        .await
+        .map_err(|_| format!("{} timed out after {}s", provider.name(), timeout.as_secs()))??;
+    analysis.provider = Some(cfg.provider.id().to_string());
+    analysis.model = Some(cfg.model_name()).filter(|m| !m.is_empty());
+    Ok(analysis)
+}
+
+/// Batch analysis result for a single image.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchAnalysisItem {
+    pub image_path: String,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<AnalysisResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Batch analysis progress event payload (`ai:batch:progress`), sent as each
+/// image finishes.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchProgress {
+    pub current: usize,
+    pub total: usize,
+    pub image_path: String,
+    pub success: bool,
+    /// The analysis, so the board can update the card right away.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<AnalysisResult>,
+}
+
+/// Analyze one image of a batch. The prompt uses the tags known when the
+/// image starts; its new tags are added to `running_tags` so images started
+/// later benefit from them. Safe to call for several images at once.
+pub(crate) async fn analyze_batch_item(
+    events: &dyn AnalysisEvents,
+    client: &reqwest::Client,
+    image_path: &str,
+    provider_config: &AiProviderConfig,
+    running_tags: &Mutex<Vec<String>>,
+    project_path: &str,
+    force: bool,
+) -> BatchAnalysisItem {
+    let known_tags = running_tags.lock().map(|t| t.clone()).unwrap_or_default();
+    match do_analyze_image(events, client, image_path, provider_config, &known_tags, Some(project_path), force).await {
+        Ok(analysis) => {
+            if let Ok(mut running_tags) = running_tags.lock() {
+                for tag in &analysis.tags {
+                    if !running_tags.contains(tag) {
+                        running_tags.push(tag.clone());
+                    }
+                }
+            }
+            BatchAnalysisItem {
+                image_path: image_path.to_string(),
+                success: true,
+                result: Some(analysis),
+                error: None,
+            }
+        }
+        Err(e) => {
+            crate::log::log("AI", &format!("Batch error for {image_path}: {e}"));
+            BatchAnalysisItem {
+                image_path: image_path.to_string(),
+                success: false,
+                result: None,
+                error: Some(e),
+            }
+        }
+    }
+}
+
+/// Tags already used in the project, for context-aware prompts (levels
+/// only used as a prefix of other tags are left out).
+pub(crate) fn project_tags(project_path: &str) -> Vec<String> {
+    crate::search::get_all_tags(project_path)
+        .map(|tags| tags.into_iter().filter(|t| t.direct > 0).map(|t| t.tag).collect())
+        .unwrap_or_default()
+}
+
+/// Analyze `image_paths` in `project_path`, `concurrency` at a time, handing
+/// each item to `on_item` as it finishes (in completion order). Tags found so
+/// far are fed into the prompts of images started later. An error from
+/// `on_item` stops the batch and aborts the images still in flight.
+pub(crate) async fn analyze_batch(
+    events: Arc<dyn AnalysisEvents>,
+    image_paths: Vec<String>,
+    project_path: &str,
+    provider_config: &AiProviderConfig,
+    existing_tags: Vec<String>,
+    force: bool,
+    mut on_item: impl FnMut(BatchAnalysisItem) -> Result<(), String>,
+) -> Result<(), String> {
+    let client = reqwest::Client::new();
+    let provider_config = Arc::new(provider_config.for_task(AiTask::Analysis));
+    let concurrency = provider_config.concurrency();
+    let running_tags = Arc::new(Mutex::new(existing_tags));
+    let mut pending = image_paths.into_iter();
+
+    let mut tasks = tokio::task::JoinSet::new();
+    loop {
+        while tasks.len() < concurrency {
+            let Some(image_path) = pending.next() else { break };
+            let (events, client, config, tags, project) = (
+                events.clone(),
+                client.clone(),
+                provider_config.clone(),
+                running_tags.clone(),
+                project_path.to_string(),
+            );
+            tasks.spawn(async move {
+                analyze_batch_item(events.as_ref(), &client, &image_path, &config, &tags, &project, force).await
+            });
+        }
+        let Some(joined) = tasks.join_next().await else { break };
+        on_item(joined.map_err(|e| format!("Task join error: {e}"))?)?;
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Tauri Commands
+// ---------------------------------------------------------------------------
+
 /// Analyze a single image using the configured AI provider.
-/// Called from frontend via: invoke('analyze_image', { imagePath, providerConfig, existingTags, projectPath })
+/// Called from frontend via: invoke('analyze_image', { imagePath, providerConfig, existingTags, projectPath, force })
 #[tauri::command]
 pub async fn analyze_image(
     app: tauri::AppHandle,
@@ -803,6 +1676,7 @@ pub async fn analyze_image(
     provider_config: AiProviderConfig,
     existing_tags: Vec<String>,
     project_path: Option<String>,
+    force: Option<bool>,
 ) -> Result<AnalysisResult, String> {
     let provider_name = format!("{:?}", provider_config.provider);
     let model_name = provider_config.model.clone().unwrap_or_else(|| "default".to_string());
@@ -816,113 +1690,75 @@ pub async fn analyze_image(
         &provider_config,
         &existing_tags,
         project_path.as_deref(),
+        force.unwrap_or(false),
     )
     .await
 }
 
-/// Batch analysis result for a single image.
-#[derive(Debug, Clone, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct BatchAnalysisItem {
-    pub image_path: String,
-    pub success: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub result: Option<AnalysisResult>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub error: Option<String>,
-}
-
-/// Batch analysis progress event payload.
-#[derive(Debug, Clone, Serialize)]
-#[serde(rename_all = "camelCase")]
-struct BatchProgress {
-    current: usize,
-    total: usize,
-    image_path: String,
-}
-
-/// Analyze multiple images sequentially, emitting progress events.
+/// Queue analysis of multiple images as a background job. Images run
+/// `concurrency` at a time; progress arrives as `ai:batch:progress` and
+/// `jobs:update` events, and the finished job's `result` holds the items.
 #[tauri::command]
 pub async fn cmd_analyze_batch(
-    app: tauri::AppHandle,
+    jobs: tauri::State<'_, crate::jobs::Jobs>,
     image_paths: Vec<String>,
     project_path: String,
     provider_config: AiProviderConfig,
     existing_tags: Vec<String>,
-) -> Result<Vec<BatchAnalysisItem>, String> {
-    let total = image_paths.len();
-    let client = reqwest::Client::new();
-    let mut results = Vec::with_capacity(total);
-
-    // Collect tags as we go so later images benefit from earlier results
-    let mut running_tags = existing_tags;
-
-    for (i, img_path) in image_paths.iter().enumerate() {
-        let _ = app.emit("ai:batch:progress", BatchProgress {
-            current: i + 1,
-            total,
-            image_path: img_path.clone(),
-        });
-
-        match do_analyze_image(
-            &app,
-            &client,
-            img_path,
-            &provider_config,
-            &running_tags,
-            Some(&project_path),
-        )
-        .await
-        {
-            Ok(analysis) => {
-                // Add new tags to running set for context-aware prompts
-                for tag in &analysis.tags {
-                    if !running_tags.contains(tag) {
-                        running_tags.push(tag.clone());
-                    }
-                }
-                results.push(BatchAnalysisItem {
-                    image_path: img_path.clone(),
-                    success: true,
-                    result: Some(analysis),
-                    error: None,
-                });
-            }
-            Err(e) => {
-                crate::log::log("AI", &format!("Batch error for {img_path}: {e}"));
-                results.push(BatchAnalysisItem {
-                    image_path: img_path.clone(),
-                    success: false,
-                    result: None,
-                    error: Some(e),
-                });
-            }
-        }
-    }
-
-    let _ = app.emit("ai:batch:complete", &results);
-    Ok(results)
+    force: Option<bool>,
+) -> Result<crate::jobs::Job, String> {
+    jobs.submit(crate::jobs::JobSpec::Analyze {
+        project_path,
+        image_paths,
+        provider_config: Box::new(provider_config),
+        existing_tags,
+        force: force.unwrap_or(false),
+    })
 }
 
 /// Get the current AI provider configuration.
 #[tauri::command]
 pub fn get_ai_config() -> Result<AiProviderConfig, String> {
     let mut config = load_app_config().ai.unwrap_or_default();
+    adopt_legacy_key(&config.provider);
+    hydrate_keys(&mut config);
+    Ok(config)
+}
 
-    // Hydrate api_key: Keychain → env var → None
-    if config.api_key.is_none() {
-        config.api_key = crate::keyring::get_secret(crate::keyring::AI_API_KEY);
+/// Move the key older versions kept in one shared entry to the entry of the
+/// provider it was saved for (the primary one), unless that has its own.
+fn adopt_legacy_key(provider: &AiProviderKind) {
+    let Some(key) = crate::keyring::get_secret(crate::keyring::AI_API_KEY) else {
+        return;
+    };
+    let account = provider.key_account();
+    if crate::keyring::get_secret(&account).is_none() && crate::keyring::set_secret(&account, &key).is_err() {
+        return;
     }
+    let _ = crate::keyring::delete_secret(crate::keyring::AI_API_KEY);
+}
+
+/// Fill in api_key for a config, its fallbacks and task routes:
+/// Keychain → env var → None.
+fn hydrate_keys(config: &mut AiProviderConfig) {
     if config.api_key.is_none() {
-        let env_var = match config.provider {
-            AiProviderKind::Anthropic => "ANTHROPIC_API_KEY",
-            AiProviderKind::Openai => "OPENAI_API_KEY",
-            AiProviderKind::Ollama => return Ok(config),
-        };
-        config.api_key = std::env::var(env_var).ok();
+        config.api_key = config.api_key();
+    }
+    for nested in config.fallbacks.iter_mut().chain(config.tasks.values_mut()) {
+        hydrate_keys(nested);
     }
+}
 
-    Ok(config)
+/// Move the keys of fallbacks and task routes into the Keychain, one entry
+/// per provider.
+fn store_nested_keys(config: &mut AiProviderConfig) -> Result<(), String> {
+    for nested in config.fallbacks.iter_mut().chain(config.tasks.values_mut()) {
+        if let Some(key) = nested.api_key.take().filter(|k| !k.is_empty()) {
+            crate::keyring::set_secret(&nested.provider.key_account(), &key)?;
+        }
+        store_nested_keys(nested)?;
+    }
+    Ok(())
 }
 
 /// Save AI provider configuration.
@@ -932,15 +1768,17 @@ pub fn set_ai_config(config: AiProviderConfig) -> Result<(), String> {
     // Store API key in Keychain (if provided)
     if let Some(ref key) = config.api_key {
         if !key.is_empty() {
-            crate::keyring::set_secret(crate::keyring::AI_API_KEY, key)?;
+            crate::keyring::set_secret(&config.provider.key_account(), key)?;
         }
     } else {
         let _ = crate::keyring::delete_secret(crate::keyring::AI_API_KEY);
+        let _ = crate::keyring::delete_secret(&config.provider.key_account());
     }
 
-    // Save config WITHOUT api_key to JSON
+    // Save config WITHOUT api keys to JSON
     let mut sanitized = config;
     sanitized.api_key = None;
+    store_nested_keys(&mut sanitized)?;
 
     let mut app_config = load_app_config();
     app_config.ai = Some(sanitized);
@@ -962,30 +1800,26 @@ pub async fn check_ollama() -> Result<bool, String> {
 
 /// Test AI provider connection by sending a small text prompt.
 /// Returns a success message with the model's reply, or an error.
+/// Without a config, tests the provider routed to the test task.
 #[tauri::command]
 pub async fn cmd_test_ai_vision(
-    provider_config: AiProviderConfig,
+    provider_config: Option<AiProviderConfig>,
 ) -> Result<String, String> {
+    let provider_config = match provider_config {
+        Some(config) => config,
+        None => get_ai_config()?.for_task(AiTask::Test),
+    };
     let client = reqwest::Client::builder()
         .timeout(std::time::Duration::from_secs(30))
         .build()
         .map_err(|e| format!("Cannot create HTTP client: {e}"))?;
 
-    let endpoint = provider_config
-        .endpoint
-        .clone()
-        .filter(|e| !e.is_empty())
-        .unwrap_or_else(|| provider_config.provider.default_endpoint().to_string());
+    let endpoint = provider_config.endpoint();
     let base = endpoint.trim_end_matches('/');
 
     match provider_config.provider {
         AiProviderKind::Anthropic => {
-            let key = provider_config
-                .api_key
-                .clone()
-                .or_else(|| crate::keyring::get_secret(crate::keyring::AI_API_KEY))
-                .or_else(|| std::env::var("ANTHROPIC_API_KEY").ok())
-                .ok_or("No API key configured")?;
+            let key = provider_config.api_key().ok_or("No API key configured")?;
             let model = provider_config
                 .model
                 .unwrap_or_else(|| "claude-sonnet-4-5-20250929".to_string());
@@ -1034,12 +1868,7 @@ pub async fn cmd_test_ai_vision(
             Ok(format!("Connection successful. Model replied: {text}"))
         }
         AiProviderKind::Openai => {
-            let key = provider_config
-                .api_key
-                .clone()
-                .or_else(|| crate::keyring::get_secret(crate::keyring::AI_API_KEY))
-                .or_else(|| std::env::var("OPENAI_API_KEY").ok())
-                .ok_or("No API key configured")?;
+            let key = provider_config.api_key().ok_or("No API key configured")?;
             let model = provider_config
                 .model
                 .unwrap_or_else(|| "gpt-4o".to_string());
@@ -1078,6 +1907,46 @@ pub async fn cmd_test_ai_vision(
 
             Ok(format!("Connection successful. Model replied: {text}"))
         }
+        AiProviderKind::Gemini => {
+            let key = provider_config.api_key().ok_or("No API key configured")?;
+            let provider = GeminiProvider::new(
+                client,
+                Arc::new(RateLimiter::unlimited()),
+                key,
+                provider_config.model,
+                endpoint.clone(),
+                0.0,
+                20,
+            );
+            let parts = vec![GeminiPart::Text("Reply with exactly one word: OK".to_string())];
+            let config = GeminiGenerationConfig {
+                max_output_tokens: Some(20),
+                ..Default::default()
+            };
+            let text = provider.generate(parts, config).await?;
+
+            Ok(format!("Connection successful. Model replied: {text}"))
+        }
+        AiProviderKind::OpenaiCompatible => {
+            // These servers all list their models, which needs no vision
+            // support and tells us whether the configured one is loaded
+            let provider = compatible_provider(&client, &provider_config, Arc::new(RateLimiter::unlimited()));
+            let models = provider.list_models().await?;
+
+            match provider_config.model.filter(|m| !m.is_empty()) {
+                Some(model) if models.contains(&model) => {
+                    Ok(format!("Connection successful. Model '{model}' is available."))
+                }
+                Some(model) => Ok(format!(
+                    "Connected to {base} but model '{model}' not found. Available: {}",
+                    models.join(", ")
+                )),
+                None => match models.first() {
+                    Some(first) => Ok(format!("Connection successful. No model set, will use '{first}'.")),
+                    None => Err(format!("Connected to {base} but it lists no models")),
+                },
+            }
+        }
         AiProviderKind::Ollama => {
             let resp = client
                 .get(format!("{base}/api/tags"))
@@ -1205,6 +2074,32 @@ fn parse_size(size: &str) -> (u32, u32) {
     }
 }
 
+/// The provider for image generation, and whether it was routed there
+/// explicitly. Generation speaks the OpenAI Images API, so without a route
+/// the analysis provider is used only if it is OpenAI or compatible, and
+/// OpenAI otherwise.
+fn generation_config(config: &AiProviderConfig) -> Result<(AiProviderConfig, bool), String> {
+    let speaks_images_api =
+        |kind: &AiProviderKind| matches!(kind, AiProviderKind::Openai | AiProviderKind::OpenaiCompatible);
+    if let Some(routed) = config.tasks.get(&AiTask::Generation) {
+        if !speaks_images_api(&routed.provider) {
+            return Err(format!(
+                "Image generation needs an OpenAI or OpenAI-compatible provider, not {}",
+                routed.provider.id()
+            ));
+        }
+        return Ok((routed.clone(), true));
+    }
+    if speaks_images_api(&config.provider) {
+        return Ok((config.for_task(AiTask::Generation), false));
+    }
+    let fallback = AiProviderConfig {
+        provider: AiProviderKind::Openai,
+        ..Default::default()
+    };
+    Ok((fallback, false))
+}
+
 /// Generate images using OpenAI-compatible Images API (DALL-E 3, etc).
 #[tauri::command]
 pub async fn cmd_generate_image(
@@ -1216,24 +2111,21 @@ pub async fn cmd_generate_image(
     size: Option<String>,
     count: Option<u32>,
 ) -> Result<Vec<ImageGenerateResult>, String> {
-    let ai_config = load_app_config().ai.unwrap_or_default();
+    let (gen_config, routed) = generation_config(&get_ai_config()?)?;
 
-    let api_key = ai_config
-        .api_key
-        .clone()
-        .or_else(|| crate::keyring::get_secret(crate::keyring::AI_API_KEY))
-        .or_else(|| std::env::var("OPENAI_API_KEY").ok())
-        .ok_or("No API key configured for image generation. Configure it in Settings > AI.")?;
+    // OpenAI itself needs a key; compatible servers may not
+    let api_key = gen_config.api_key();
+    if api_key.is_none() && gen_config.provider == AiProviderKind::Openai {
+        return Err("No API key configured for image generation. Configure it in Settings > AI.".to_string());
+    }
 
-    // Resolve endpoint — generation always uses OpenAI-compatible API
-    let endpoint = ai_config
-        .endpoint
-        .clone()
-        .filter(|e| !e.is_empty())
-        .unwrap_or_else(|| AiProviderKind::Openai.default_endpoint().to_string());
+    let endpoint = gen_config.endpoint();
     let base = endpoint.trim_end_matches('/');
 
-    let gen_model = model.unwrap_or_else(|| "dall-e-3".to_string());
+    // A generation route names an image model; the analysis model is not one
+    let gen_model = model
+        .or_else(|| gen_config.model.clone().filter(|_| routed))
+        .unwrap_or_else(|| "dall-e-3".to_string());
     let gen_size = size.unwrap_or_else(|| "1024x1024".to_string());
     let gen_count = count.unwrap_or(1).min(4).max(1);
     let (width, height) = parse_size(&gen_size);
@@ -1272,10 +2164,14 @@ pub async fn cmd_generate_image(
             "response_format": "url",
         });
 
-        let resp = client
-            .post(format!("{base}/images/generations"))
-            .header("Authorization", format!("Bearer {api_key}"))
-            .json(&body)
+        let mut request = client.post(format!("{base}/images/generations")).json(&body);
+        if let Some(ref key) = api_key {
+            request = request.header("Authorization", format!("Bearer {key}"));
+        }
+        for (name, value) in gen_config.headers.iter().flatten() {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        let resp = request
             .send()
             .await
             .map_err(|e| format!("Image generation request failed: {e}"))?;
@@ -1356,7 +2252,7 @@ mod tests {
     #[test]
     fn test_parse_clean_json() {
         let json = r##"{"description":"A test image","tags":["test","image"],"style":["minimal"],"mood":["calm"],"colors":["#fff"],"era":"modern"}"##;
-        let result = parse_analysis_json(json).unwrap();
+        let result = parse_analysis_json(json, &[]).unwrap();
         assert_eq!(result.description, "A test image");
         assert_eq!(result.tags, vec!["test", "image"]);
         assert_eq!(result.style, vec!["minimal"]);
@@ -1371,7 +2267,7 @@ mod tests {
 {"description":"Art deco lamp","tags":["art-deco","lamp"],"style":["geometric"],"mood":["elegant"],"colors":["#D4AF37"],"era":"1920s"}
 ```
 "##;
-        let result = parse_analysis_json(text).unwrap();
+        let result = parse_analysis_json(text, &[]).unwrap();
         assert_eq!(result.description, "Art deco lamp");
         assert_eq!(result.tags, vec!["art-deco", "lamp"]);
     }
@@ -1379,18 +2275,40 @@ mod tests {
     #[test]
     fn test_parse_embedded_json() {
         let text = r#"The image shows: {"description":"A dancer","tags":["dancer","bronze"],"style":[],"mood":[],"colors":[],"era":null} which is interesting."#;
-        let result = parse_analysis_json(text).unwrap();
+        let result = parse_analysis_json(text, &[]).unwrap();
         assert_eq!(result.description, "A dancer");
     }
 
     #[test]
     fn test_parse_plain_text_fallback() {
         let text = "This is just a plain description with no JSON.";
-        let result = parse_analysis_json(text).unwrap();
+        let result = parse_analysis_json(text, &[]).unwrap();
         assert_eq!(result.description, text);
         assert!(result.tags.is_empty());
     }
 
+    #[test]
+    fn test_parse_validates_project_fields() {
+        use crate::analysis_schema::FieldKind;
+        let fields = vec![FieldDef {
+            name: "fabric".to_string(),
+            description: None,
+            kind: FieldKind::List,
+            options: Vec::new(),
+            required: true,
+        }];
+        let text = r#"```json
+{"description":"A coat","tags":["coat"],"Fabric":["wool","silk"],"silhouette":"a-line"}
+```"#;
+        let result = parse_analysis_json(text, &fields).unwrap();
+        assert_eq!(result.extra.len(), 1);
+        assert_eq!(result.extra["fabric"], serde_json::json!(["wool", "silk"]));
+
+        let err = parse_analysis_json(r#"{"description":"A coat","tags":[]}"#, &fields).unwrap_err();
+        assert!(err.contains("\"fabric\""), "{err}");
+        assert!(parse_analysis_json("Just a coat.", &fields).is_err());
+    }
+
     #[test]
     fn test_default_config() {
         let config = AiProviderConfig::default();
@@ -1421,16 +2339,33 @@ mod tests {
         assert!(config.endpoint.is_none());
     }
 
+    #[test]
+    fn test_config_batch_limits() {
+        let json = r#"{"provider":"ollama"}"#;
+        let config: AiProviderConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.concurrency(), 1);
+
+        let json = r#"{"provider":"anthropic","concurrency":8,"requestsPerMinute":120}"#;
+        let config: AiProviderConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.concurrency(), 8);
+        assert_eq!(config.requests_per_minute, Some(120));
+
+        let json = r#"{"provider":"openai","concurrency":0}"#;
+        let config: AiProviderConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.concurrency(), 1);
+    }
+
     #[test]
     fn test_build_prompt_no_tags() {
-        let prompt = build_analysis_prompt(&[]);
-        assert_eq!(prompt, ANALYSIS_PROMPT);
+        let prompt = build_analysis_prompt(&[], &AnalysisSchema::default());
+        assert_eq!(prompt.text, ANALYSIS_PROMPT);
+        assert!(prompt.fields.is_empty());
     }
 
     #[test]
     fn test_build_prompt_with_tags() {
         let tags = vec!["art-deco".to_string(), "sculpture".to_string()];
-        let prompt = build_analysis_prompt(&tags);
+        let prompt = build_analysis_prompt(&tags, &AnalysisSchema::default()).text;
         assert!(prompt.contains("art-deco, sculpture"));
         assert!(prompt.contains("Prefer reusing existing tags"));
     }
@@ -1473,4 +2408,401 @@ mod tests {
         assert!(json.contains("\"revisedPrompt\""));
         assert!(json.contains("gen.png"));
     }
+
+    #[test]
+    fn test_config_gemini_and_compatible() {
+        let json = r#"{"provider":"gemini"}"#;
+        let config: AiProviderConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.provider, AiProviderKind::Gemini);
+        assert_eq!(config.endpoint(), "https://generativelanguage.googleapis.com/v1beta");
+        assert_eq!(config.model_name(), "gemini-2.0-flash");
+
+        let json = r#"{"provider":"openai-compatible","endpoint":"http://gpu-box:8000/v1","headers":{"X-Api-Token":"t"}}"#;
+        let config: AiProviderConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.provider, AiProviderKind::OpenaiCompatible);
+        assert_eq!(config.headers.as_ref().unwrap()["X-Api-Token"], "t");
+        assert_eq!(config.model_name(), "");
+        assert_eq!(config.concurrency(), 1);
+        assert_eq!(AiProviderKind::OpenaiCompatible.api_key_env(), None);
+        assert_eq!(
+            serde_json::to_string(&AiProviderKind::OpenaiCompatible).unwrap(),
+            format!(r#""{}""#, AiProviderKind::OpenaiCompatible.id())
+        );
+    }
+
+    const MOCK_ANALYSIS: &str = r#"{"description":"A chair","tags":["chair"],"style":[],"mood":[],"colors":[],"era":null}"#;
+
+    type SeenRequests = Arc<Mutex<Vec<(String, axum::http::HeaderMap, String)>>>;
+
+    /// Serve canned JSON replies by request path on a local port, recording
+    /// each request. Returns the base URL and the recorded requests.
+    async fn mock_server(replies: Vec<(&'static str, serde_json::Value)>) -> (String, SeenRequests) {
+        use axum::response::IntoResponse;
+
+        let seen = SeenRequests::default();
+        let recorder = seen.clone();
+        let app = axum::Router::new().fallback(
+            move |uri: axum::http::Uri, headers: axum::http::HeaderMap, body: String| {
+                let reply = replies.iter().find(|(path, _)| *path == uri.path()).map(|(_, r)| r.clone());
+                recorder.lock().unwrap().push((uri.path().to_string(), headers, body));
+                async move {
+                    match reply {
+                        Some(json) => axum::Json(json).into_response(),
+                        None => axum::http::StatusCode::NOT_FOUND.into_response(),
+                    }
+                }
+            },
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{addr}"), seen)
+    }
+
+    fn test_image() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("chair.png");
+        std::fs::write(&path, b"not really a png").unwrap();
+        (dir, path)
+    }
+
+    #[tokio::test]
+    async fn test_gemini_provider_against_mock() {
+        let (base, seen) = mock_server(vec![
+            (
+                "/v1beta/models/gemini-test:generateContent",
+                serde_json::json!({"candidates": [{"content": {"parts": [{"text": MOCK_ANALYSIS}]}}]}),
+            ),
+            ("/v1beta/models/blocked:generateContent", serde_json::json!({"candidates": []})),
+        ])
+        .await;
+        let (_dir, image) = test_image();
+        let provider = |model: &str| {
+            GeminiProvider::new(
+                reqwest::Client::new(),
+                Arc::new(RateLimiter::unlimited()),
+                "g-key".to_string(),
+                Some(model.to_string()),
+                format!("{base}/v1beta"),
+                0.2,
+                512,
+            )
+        };
+
+        // "models/" prefixes, as listed by the API, are accepted
+        let result = provider("models/gemini-test").analyze_image(&image, &"Describe".into()).await.unwrap();
+        assert_eq!(result.description, "A chair");
+        assert_eq!(result.tags, vec!["chair"]);
+
+        let (_, headers, body) = seen.lock().unwrap()[0].clone();
+        assert_eq!(headers["x-goog-api-key"], "g-key");
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        let parts = &body["contents"][0]["parts"];
+        assert_eq!(parts[0]["inlineData"]["mimeType"], "image/png");
+        assert_eq!(parts[1]["text"], "Describe");
+        assert_eq!(body["generationConfig"]["maxOutputTokens"], 512);
+        assert_eq!(body["generationConfig"]["responseMimeType"], "application/json");
+
+        let err = provider("blocked").analyze_image(&image, &"Describe".into()).await.unwrap_err();
+        assert!(err.contains("no candidates"), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_openai_compatible_provider_against_mock() {
+        let (base, seen) = mock_server(vec![
+            ("/v1/models", serde_json::json!({"data": [{"id": "local-vlm"}, {"id": "other"}]})),
+            (
+                "/v1/chat/completions",
+                serde_json::json!({"choices": [{"message": {"content": MOCK_ANALYSIS}}]}),
+            ),
+        ])
+        .await;
+        let (_dir, image) = test_image();
+        let provider = |key: Option<&str>, model: Option<&str>| {
+            OpenAICompatibleProvider::new(
+                reqwest::Client::new(),
+                Arc::new(RateLimiter::unlimited()),
+                key.map(str::to_string),
+                model.map(str::to_string),
+                format!("{base}/v1/"),
+                0.2,
+                512,
+            )
+            .with_headers(BTreeMap::from([("X-Api-Token".to_string(), "t".to_string())]))
+        };
+
+        // No key and no model: the first listed model is used, unauthenticated
+        let result = provider(None, Some("")).analyze_image(&image, &"Describe".into()).await.unwrap();
+        assert_eq!(result.description, "A chair");
+        {
+            let seen = seen.lock().unwrap();
+            assert_eq!(seen.len(), 2);
+            assert_eq!(seen[0].0, "/v1/models");
+            let (path, headers, body) = &seen[1];
+            assert_eq!(path, "/v1/chat/completions");
+            assert!(headers.get("authorization").is_none());
+            assert_eq!(headers["x-api-token"], "t");
+            let body: serde_json::Value = serde_json::from_str(body).unwrap();
+            assert_eq!(body["model"], "local-vlm");
+            assert!(body.get("response_format").is_none());
+        }
+
+        // The discovered model is remembered
+        let unnamed = provider(None, None);
+        unnamed.analyze_image(&image, &"Describe".into()).await.unwrap();
+        unnamed.analyze_image(&image, &"Describe".into()).await.unwrap();
+        assert_eq!(seen.lock().unwrap().iter().filter(|(path, _, _)| path == "/v1/models").count(), 2);
+
+        // So is one shared between providers for the same server
+        seen.lock().unwrap().clear();
+        let cell = Arc::new(tokio::sync::OnceCell::new());
+        for _ in 0..3 {
+            provider(None, None).with_discovered_model(cell.clone()).analyze_image(&image, &"Describe".into()).await.unwrap();
+        }
+        assert_eq!(seen.lock().unwrap().iter().filter(|(path, _, _)| path == "/v1/models").count(), 1);
+
+        // A key and a configured model skip discovery
+        seen.lock().unwrap().clear();
+        provider(Some("sk-local"), Some("other")).analyze_image(&image, &"Describe".into()).await.unwrap();
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].1["authorization"], "Bearer sk-local");
+        assert_eq!(seen[0].1["x-api-token"], "t");
+    }
+
+    #[tokio::test]
+    async fn test_connection_test_for_new_providers() {
+        let (base, _seen) = mock_server(vec![
+            ("/v1/models", serde_json::json!({"data": [{"id": "local-vlm"}]})),
+            (
+                "/v1beta/models/gemini-test:generateContent",
+                serde_json::json!({"candidates": [{"content": {"parts": [{"text": "OK"}]}}]}),
+            ),
+        ])
+        .await;
+        let compatible = |model: Option<&str>| AiProviderConfig {
+            provider: AiProviderKind::OpenaiCompatible,
+            endpoint: Some(format!("{base}/v1")),
+            model: model.map(str::to_string),
+            ..Default::default()
+        };
+
+        let reply = cmd_test_ai_vision(Some(compatible(Some("local-vlm")))).await.unwrap();
+        assert!(reply.contains("'local-vlm' is available"), "{reply}");
+        let reply = cmd_test_ai_vision(Some(compatible(Some("missing")))).await.unwrap();
+        assert!(reply.contains("not found. Available: local-vlm"), "{reply}");
+        let reply = cmd_test_ai_vision(Some(compatible(None))).await.unwrap();
+        assert!(reply.contains("will use 'local-vlm'"), "{reply}");
+
+        let gemini = AiProviderConfig {
+            provider: AiProviderKind::Gemini,
+            api_key: Some("g-key".to_string()),
+            endpoint: Some(format!("{base}/v1beta")),
+            model: Some("gemini-test".to_string()),
+            ..Default::default()
+        };
+        let reply = cmd_test_ai_vision(Some(gemini)).await.unwrap();
+        assert_eq!(reply, "Connection successful. Model replied: OK");
+    }
+
+    #[test]
+    fn test_config_fallbacks_and_tasks() {
+        let json = r#"{
+            "provider": "anthropic", "model": "claude-test",
+            "fallbacks": [{"provider": "ollama"}],
+            "tasks": {
+                "test": {"provider": "ollama", "model": "moondream"},
+                "generation": {"provider": "openai-compatible", "endpoint": "http://gpu-box/v1", "model": "flux"}
+            }
+        }"#;
+        let config: AiProviderConfig = serde_json::from_str(json).unwrap();
+        let labels: Vec<_> = config.chain().iter().map(|c| c.label()).collect();
+        assert_eq!(labels, vec!["anthropic/claude-test", "ollama/llava"]);
+
+        // Tasks without a route use the config itself, minus the routes
+        let analysis = config.for_task(AiTask::Analysis);
+        assert_eq!(analysis.model.as_deref(), Some("claude-test"));
+        assert_eq!(analysis.fallbacks.len(), 1);
+        assert!(analysis.tasks.is_empty());
+        assert_eq!(config.for_task(AiTask::Test).label(), "ollama/moondream");
+
+        let (generation, routed) = generation_config(&config).unwrap();
+        assert!(routed);
+        assert_eq!(generation.endpoint(), "http://gpu-box/v1");
+
+        // Without a route, generation never borrows a non-OpenAI endpoint
+        let (generation, routed) = generation_config(&config.for_task(AiTask::Analysis)).unwrap();
+        assert!(!routed);
+        assert_eq!(generation.provider, AiProviderKind::Openai);
+        assert_eq!(generation.endpoint(), "https://api.openai.com/v1");
+
+        let json = r#"{"provider": "openai", "tasks": {"generation": {"provider": "gemini"}}}"#;
+        let config: AiProviderConfig = serde_json::from_str(json).unwrap();
+        assert!(generation_config(&config).is_err());
+    }
+
+    fn compatible_config(endpoint: String, model: &str) -> AiProviderConfig {
+        AiProviderConfig {
+            provider: AiProviderKind::OpenaiCompatible,
+            endpoint: Some(endpoint),
+            model: Some(model.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_analysis_falls_back_to_next_provider() {
+        let (down, _) = mock_server(Vec::new()).await;
+        let (up, _) = mock_server(vec![(
+            "/v1/chat/completions",
+            serde_json::json!({"choices": [{"message": {"content": MOCK_ANALYSIS}}]}),
+        )])
+        .await;
+        let (_dir, image) = test_image();
+        let image = image.to_str().unwrap();
+        let client = reqwest::Client::new();
+
+        let mut config = compatible_config(format!("{down}/v1"), "first");
+        config.fallbacks.push(compatible_config(format!("{up}/v1"), "second"));
+        let result = do_analyze_image(&NoEvents, &client, image, &config, &[], None, false).await.unwrap();
+        assert_eq!(result.description, "A chair");
+        assert_eq!(result.provider.as_deref(), Some("openai-compatible"));
+        assert_eq!(result.model.as_deref(), Some("second"));
+
+        // Every provider failing reports each error
+        config.fallbacks = vec![compatible_config(format!("{down}/v1"), "other")];
+        let err = do_analyze_image(&NoEvents, &client, image, &config, &[], None, false).await.unwrap_err();
+        assert!(err.starts_with("All AI providers failed: openai-compatible/first: "), "{err}");
+        assert!(err.contains("; openai-compatible/other: "), "{err}");
+    }
+
+    #[tokio::test]
+    async fn test_keyless_fallback_gets_no_other_key() {
+        use crate::keyring::{get_secret, set_secret, AI_API_KEY};
+        let (down, _) = mock_server(Vec::new()).await;
+        let (up, seen) = mock_server(vec![(
+            "/v1/chat/completions",
+            serde_json::json!({"choices": [{"message": {"content": MOCK_ANALYSIS}}]}),
+        )])
+        .await;
+        let (_dir, image) = test_image();
+
+        // A key left in the shared entry by an older version (the secret
+        // store is private to this test thread)
+        set_secret(AI_API_KEY, "sk-legacy").unwrap();
+        assert!(get_secret(&AiProviderKind::OpenaiCompatible.key_account()).is_none());
+
+        let mut config = compatible_config(format!("{down}/v1"), "first");
+        config.api_key = Some("sk-primary".to_string());
+        config.fallbacks.push(compatible_config(format!("{up}/v1"), "second"));
+        let result = do_analyze_image(&NoEvents, &reqwest::Client::new(), image.to_str().unwrap(), &config, &[], None, false).await;
+        let generation = generation_config(&AiProviderConfig { provider: AiProviderKind::Gemini, ..Default::default() })
+            .unwrap()
+            .0;
+
+        assert_eq!(result.unwrap().model.as_deref(), Some("second"));
+        assert!(seen.lock().unwrap()[0].1.get("authorization").is_none());
+        assert_eq!(generation.provider, AiProviderKind::Openai);
+        assert_ne!(generation.api_key().as_deref(), Some("sk-legacy"));
+    }
+
+    #[tokio::test]
+    async fn test_analysis_falls_back_on_timeout() {
+        let slow = axum::Router::new().fallback(|| async {
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            "too late"
+        });
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let slow_addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, slow).await.unwrap() });
+        let (up, _) = mock_server(vec![(
+            "/v1/chat/completions",
+            serde_json::json!({"choices": [{"message": {"content": MOCK_ANALYSIS}}]}),
+        )])
+        .await;
+        let (_dir, image) = test_image();
+
+        let mut config = compatible_config(format!("http://{slow_addr}/v1"), "slow");
+        config.timeout_secs = Some(1);
+        config.fallbacks.push(compatible_config(format!("{up}/v1"), "fast"));
+        let start = std::time::Instant::now();
+        let result = do_analyze_image(&NoEvents, &reqwest::Client::new(), image.to_str().unwrap(), &config, &[], None, false)
+            .await
+            .unwrap();
+        assert_eq!(result.model.as_deref(), Some("fast"));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_project_fields_are_requested_saved_and_searchable() {
+        let (bare, _) = mock_server(vec![(
+            "/v1/chat/completions",
+            serde_json::json!({"choices": [{"message": {"content": MOCK_ANALYSIS}}]}),
+        )])
+        .await;
+        let answer = r#"{"description":"A chair","tags":["chair"],"material":["Bent plywood","steel"],"structural-system":"cantilever"}"#;
+        let (full, seen) = mock_server(vec![(
+            "/v1/chat/completions",
+            serde_json::json!({"choices": [{"message": {"content": answer}}]}),
+        )])
+        .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_str().unwrap();
+        std::fs::create_dir(dir.path().join("images")).unwrap();
+        let image = dir.path().join("images").join("chair.png");
+        std::fs::write(&image, b"not really a png").unwrap();
+        std::fs::write(
+            dir.path().join("deco.json"),
+            r#"{"version": 2, "analysis": {"fields": [
+                {"name": "material", "type": "list"},
+                {"name": "structural-system", "options": ["frame", "cantilever"], "required": true}
+            ]}}"#,
+        )
+        .unwrap();
+
+        // The first provider's answer lacks the required field, so the next one is asked
+        let mut config = compatible_config(format!("{bare}/v1"), "bare");
+        config.fallbacks.push(compatible_config(format!("{full}/v1"), "full"));
+        let image = image.to_str().unwrap();
+        let result = do_analyze_image(&NoEvents, &reqwest::Client::new(), image, &config, &[], Some(project), false)
+            .await
+            .unwrap();
+        assert_eq!(result.model.as_deref(), Some("full"));
+        assert_eq!(result.extra["material"], serde_json::json!(["Bent plywood", "steel"]));
+        assert_eq!(result.extra["structural-system"], "cantilever");
+        assert!(seen.lock().unwrap()[0].2.contains("structural-system"));
+
+        let meta = crate::search::get_image_metadata(project, image).unwrap().unwrap();
+        assert_eq!(meta.extra, result.extra);
+        let hits = crate::search::search_text(project, "plywood", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        let hits = crate::search::search_text(project, "extra:cantilever", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert!(crate::search::search_text(project, "tag:cantilever", 10).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cache_hits_after_tags_grow() {
+        let (base, seen) = mock_server(vec![(
+            "/v1/chat/completions",
+            serde_json::json!({"choices": [{"message": {"content": MOCK_ANALYSIS}}]}),
+        )])
+        .await;
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_str().unwrap();
+        std::fs::create_dir(dir.path().join("images")).unwrap();
+        let image = dir.path().join("images").join("chair.png");
+        std::fs::write(&image, b"not really a png").unwrap();
+        let image = image.to_str().unwrap();
+        let config = compatible_config(format!("{base}/v1"), "local-vlm");
+        let client = reqwest::Client::new();
+
+        do_analyze_image(&NoEvents, &client, image, &config, &[], Some(project), false).await.unwrap();
+        // The board has more tags now, so the rendered prompt differs
+        let tags = vec!["chair".to_string(), "bentwood".to_string()];
+        let result = do_analyze_image(&NoEvents, &client, image, &config, &tags, Some(project), false).await.unwrap();
+        assert_eq!(result.description, "A chair");
+        assert_eq!(seen.lock().unwrap().len(), 1);
+    }
 }
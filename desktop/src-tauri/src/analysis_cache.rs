@@ -0,0 +1,216 @@
+//! Cache of AI analysis results, so re-analyzing an image doesn't pay for
+//! another provider call.
+//!
+//! Results are stored in `search.db` (`analysis_cache`) keyed by the image's
+//! SHA-256, provider, model and a hash of the prompt template and analysis
+//! fields — changing any of them is a miss. The board's tags fed into the
+//! prompt are left out of the key, since they change with every analysis.
+//! Keys don't include the path, so a copy of an image in another known
+//! project reuses that project's result; it is copied into this project's
+//! cache on first use.
+
+use crate::ai::AnalysisResult;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use serde::Serialize;
+use std::path::Path;
+
+/// What an analysis result is cached under.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheKey {
+    pub content_hash: String,
+    pub provider: String,
+    pub model: String,
+    pub prompt_hash: String,
+}
+
+impl CacheKey {
+    pub fn new(content_hash: String, provider: &str, model: &str, prompt: &str) -> Self {
+        Self {
+            content_hash,
+            provider: provider.to_string(),
+            model: model.to_string(),
+            prompt_hash: crate::dedup::sha256_hex(prompt.as_bytes()),
+        }
+    }
+}
+
+/// Size and usefulness of a project's cache, for `deco status`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CacheStats {
+    pub entries: usize,
+    pub hits: u64,
+}
+
+/// The project an image belongs to: the folder holding the `images/`
+/// directory it sits in, if that folder has been indexed (has `.deco/`).
+pub fn project_of(image_path: &Path) -> Option<String> {
+    image_path
+        .ancestors()
+        .skip(1)
+        .find(|dir| dir.file_name().is_some_and(|n| n == "images"))
+        .and_then(Path::parent)
+        .filter(|project| project.join(".deco").is_dir())
+        .map(|project| project.to_string_lossy().to_string())
+}
+
+/// Cached result for `key` in this database, counting the hit.
+pub fn get(conn: &Connection, key: &CacheKey) -> Result<Option<AnalysisResult>, String> {
+    let Some(json) = read(conn, key)? else {
+        return Ok(None);
+    };
+    conn.execute(
+        "UPDATE analysis_cache SET hits = hits + 1
+         WHERE content_hash = ?1 AND provider = ?2 AND model = ?3 AND prompt_hash = ?4",
+        params![key.content_hash, key.provider, key.model, key.prompt_hash],
+    )
+    .map_err(|e| format!("Cannot update analysis cache: {e}"))?;
+    Ok(serde_json::from_str(&json).ok())
+}
+
+/// Store (or replace) the result for `key`.
+pub fn put(conn: &Connection, key: &CacheKey, result: &AnalysisResult) -> Result<(), String> {
+    let json = serde_json::to_string(result)
+        .map_err(|e| format!("Cannot serialize analysis: {e}"))?;
+    conn.execute(
+        "INSERT OR REPLACE INTO analysis_cache (content_hash, provider, model, prompt_hash, result)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![key.content_hash, key.provider, key.model, key.prompt_hash, json],
+    )
+    .map_err(|e| format!("Cannot write analysis cache: {e}"))?;
+    Ok(())
+}
+
+/// Cached result from this project, or else from any other known project.
+pub fn lookup(conn: &Connection, project_path: &str, key: &CacheKey) -> Result<Option<AnalysisResult>, String> {
+    if let Some(result) = get(conn, key)? {
+        return Ok(Some(result));
+    }
+    let others: Vec<String> = crate::ops::list_all_projects()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|p| p.path)
+        .filter(|p| p != project_path)
+        .collect();
+    let Some(result) = find_in(&others, key) else {
+        return Ok(None);
+    };
+    put(conn, key, &result)?;
+    Ok(Some(result))
+}
+
+/// First cached result for `key` among `projects`. Their databases are only
+/// read, and skipped when missing or older than the cache table.
+fn find_in(projects: &[String], key: &CacheKey) -> Option<AnalysisResult> {
+    projects.iter().find_map(|project| {
+        let path = Path::new(project).join(".deco").join("search.db");
+        if !path.exists() {
+            return None;
+        }
+        let conn = Connection::open_with_flags(&path, OpenFlags::SQLITE_OPEN_READ_ONLY).ok()?;
+        let json = read(&conn, key).ok()??;
+        serde_json::from_str(&json).ok()
+    })
+}
+
+fn read(conn: &Connection, key: &CacheKey) -> Result<Option<String>, String> {
+    conn.query_row(
+        "SELECT result FROM analysis_cache
+         WHERE content_hash = ?1 AND provider = ?2 AND model = ?3 AND prompt_hash = ?4",
+        params![key.content_hash, key.provider, key.model, key.prompt_hash],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| format!("Cannot read analysis cache: {e}"))
+}
+
+pub fn stats(conn: &Connection) -> Result<CacheStats, String> {
+    conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(hits), 0) FROM analysis_cache",
+        [],
+        |row| {
+            Ok(CacheStats {
+                entries: row.get::<_, i64>(0)? as usize,
+                hits: row.get::<_, i64>(1)? as u64,
+            })
+        },
+    )
+    .map_err(|e| format!("Cannot read analysis cache stats: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn analysis(description: &str) -> AnalysisResult {
+        AnalysisResult {
+            description: description.to_string(),
+            tags: vec!["chair".to_string()],
+            style: Vec::new(),
+            mood: Vec::new(),
+            colors: Vec::new(),
+            era: None,
+            provider: None,
+            model: None,
+            extra: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_cache_roundtrip_and_stats() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_str().unwrap();
+        let conn = crate::search::open_db(project).unwrap();
+
+        let key = CacheKey::new("abc".into(), "anthropic", "claude", "prompt");
+        assert!(get(&conn, &key).unwrap().is_none());
+        put(&conn, &key, &analysis("A chair")).unwrap();
+
+        assert_eq!(get(&conn, &key).unwrap().unwrap().description, "A chair");
+        assert_eq!(get(&conn, &key).unwrap().unwrap().description, "A chair");
+        assert_eq!(stats(&conn).unwrap(), CacheStats { entries: 1, hits: 2 });
+
+        // Any part of the key changing is a miss
+        let other_prompt = CacheKey::new("abc".into(), "anthropic", "claude", "prompt with tags");
+        let other_model = CacheKey::new("abc".into(), "anthropic", "haiku", "prompt");
+        let other_image = CacheKey::new("def".into(), "anthropic", "claude", "prompt");
+        assert!(get(&conn, &other_prompt).unwrap().is_none());
+        assert!(get(&conn, &other_model).unwrap().is_none());
+        assert!(get(&conn, &other_image).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_project_of() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("board");
+        std::fs::create_dir_all(project.join("images").join("generated")).unwrap();
+        assert_eq!(project_of(&project.join("images").join("a.png")), None);
+
+        std::fs::create_dir_all(project.join(".deco")).unwrap();
+        let expected = Some(project.to_string_lossy().to_string());
+        assert_eq!(project_of(&project.join("images").join("a.png")), expected);
+        assert_eq!(project_of(&project.join("images").join("generated").join("b.png")), expected);
+        assert_eq!(project_of(&dir.path().join("a.png")), None);
+    }
+
+    #[test]
+    fn test_cache_is_shared_across_projects() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first");
+        let second = dir.path().join("second");
+        let missing = dir.path().join("missing");
+        let key = CacheKey::new("abc".into(), "openai", "gpt-4o", "prompt");
+
+        let conn = crate::search::open_db(first.to_str().unwrap()).unwrap();
+        put(&conn, &key, &analysis("Shared")).unwrap();
+        crate::search::open_db(second.to_str().unwrap()).unwrap();
+
+        let projects: Vec<String> = [&missing, &second, &first]
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect();
+        assert_eq!(find_in(&projects, &key).unwrap().description, "Shared");
+        assert!(find_in(&projects[..2], &key).is_none());
+        assert!(!missing.join(".deco").exists());
+    }
+}
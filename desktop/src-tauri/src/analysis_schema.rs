@@ -0,0 +1,389 @@
+//! Project-defined analysis fields and prompt template.
+//!
+//! A project can ask the AI for more than the built-in description, tags,
+//! style, mood, colours and era by adding an `analysis` section to its
+//! `deco.json`:
+//!
+//! ```json
+//! "analysis": {
+//!   "fields": [
+//!     { "name": "material", "type": "list", "description": "main building materials" },
+//!     { "name": "structural-system", "options": ["frame", "load-bearing", "shell"], "required": true }
+//!   ],
+//!   "prompt": "You are cataloguing architecture references.\n\n{{default}}\n\n{{fields}}"
+//! }
+//! ```
+//!
+//! Field types are `text` (the default), `list`, `number` and `boolean`.
+//! `options` restricts a text or list field to those values. A response that
+//! lacks a `required` field is rejected, so the next provider in the chain is
+//! tried. Values land in the `extra` column of `images` as a JSON object.
+//!
+//! The prompt template may use `{{default}}` (the built-in instructions),
+//! `{{fields}}` (the extra fields) and `{{tags}}` (the tags the board already
+//! uses). The fields and tags are appended when the template leaves them out.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Keys of `AnalysisResult` that a project field can't reuse.
+const BUILT_IN_FIELDS: &[&str] = &["description", "tags", "style", "mood", "colors", "era", "provider", "model"];
+
+/// The `analysis` section of `deco.json`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalysisSchema {
+    #[serde(default)]
+    pub fields: Vec<FieldDef>,
+    /// Prompt template; the built-in prompt when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+}
+
+/// One extra analysis field.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldDef {
+    pub name: String,
+    /// What the field means, for the model.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, rename = "type")]
+    pub kind: FieldKind,
+    /// Allowed values (matched case-insensitively); any value when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldKind {
+    #[default]
+    Text,
+    List,
+    Number,
+    Boolean,
+}
+
+impl AnalysisSchema {
+    /// Read the schema from a project's `deco.json`. A project without one,
+    /// or without an `analysis` section, gets the default schema.
+    pub fn load(project_path: &str) -> Result<Self, String> {
+        let path = Path::new(project_path).join("deco.json");
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("Cannot read deco.json: {e}")),
+        };
+        let config: Value =
+            serde_json::from_str(&content).map_err(|e| format!("Invalid deco.json: {e}"))?;
+        let Some(section) = config.get("analysis") else {
+            return Ok(Self::default());
+        };
+        let schema: Self = serde_json::from_value(section.clone())
+            .map_err(|e| format!("Invalid analysis section in deco.json: {e}"))?;
+        schema.check()?;
+        Ok(schema)
+    }
+
+    fn check(&self) -> Result<(), String> {
+        let mut seen = Vec::new();
+        for field in &self.fields {
+            let key = normalize(&field.name);
+            if key.is_empty() {
+                return Err("Analysis field names cannot be empty".to_string());
+            }
+            if BUILT_IN_FIELDS.contains(&key.as_str()) {
+                return Err(format!("Analysis field \"{}\" is built in", field.name));
+            }
+            if seen.contains(&key) {
+                return Err(format!("Analysis field \"{}\" is defined twice", field.name));
+            }
+            if !field.options.is_empty() && matches!(field.kind, FieldKind::Number | FieldKind::Boolean) {
+                return Err(format!("Analysis field \"{}\": options only apply to text and list fields", field.name));
+            }
+            seen.push(key);
+        }
+        Ok(())
+    }
+
+    /// Render the prompt around the built-in instructions (`default_prompt`).
+    pub fn render_prompt(&self, default_prompt: &str, existing_tags: &[String]) -> String {
+        let fields = fields_section(&self.fields);
+        let tags = tags_section(existing_tags);
+        let Some(template) = &self.prompt else {
+            return [default_prompt, &fields, &tags]
+                .into_iter()
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n\n");
+        };
+
+        let mut prompt = template.replace("{{default}}", default_prompt);
+        for (placeholder, section) in [("{{fields}}", &fields), ("{{tags}}", &tags)] {
+            if prompt.contains(placeholder) {
+                prompt = prompt.replace(placeholder, section);
+            } else if !section.is_empty() {
+                prompt = format!("{}\n\n{section}", prompt.trim_end());
+            }
+        }
+        prompt.trim().to_string()
+    }
+}
+
+/// Instructions asking for the extra fields, or nothing when there are none.
+fn fields_section(fields: &[FieldDef]) -> String {
+    if fields.is_empty() {
+        return String::new();
+    }
+    let lines: Vec<String> = fields
+        .iter()
+        .map(|field| {
+            let mut line = format!("- \"{}\": ", field.name);
+            line.push_str(match (field.kind, field.options.is_empty()) {
+                (FieldKind::Text, true) => "a string",
+                (FieldKind::Text, false) => "one of",
+                (FieldKind::List, true) => "an array of strings",
+                (FieldKind::List, false) => "an array of any of",
+                (FieldKind::Number, _) => "a number",
+                (FieldKind::Boolean, _) => "true or false",
+            });
+            if !field.options.is_empty() {
+                let options: Vec<String> = field.options.iter().map(|o| format!("\"{o}\"")).collect();
+                line.push(' ');
+                line.push_str(&options.join(", "));
+            }
+            if let Some(description) = &field.description {
+                line.push_str(&format!(" ({description})"));
+            }
+            if !field.required {
+                line.push_str(", or null if it doesn't apply");
+            }
+            line
+        })
+        .collect();
+    format!("Also include these fields in the JSON object:\n{}", lines.join("\n"))
+}
+
+/// Hint to reuse the board's tags, or nothing when it has none.
+fn tags_section(existing_tags: &[String]) -> String {
+    if existing_tags.is_empty() {
+        return String::new();
+    }
+    format!(
+        "The board already uses these tags: [{}]\nPrefer reusing existing tags when they apply. Only introduce new tags for concepts not covered.",
+        existing_tags.join(", ")
+    )
+}
+
+/// Field names compare case-insensitively, with `_` and spaces as `-`.
+fn normalize(name: &str) -> String {
+    name.trim().to_lowercase().replace(['_', ' '], "-")
+}
+
+/// Pull the schema's fields out of a parsed response, coerced to their types.
+/// Unknown keys are dropped; a missing or unusable required field is an error.
+pub fn extract_fields(fields: &[FieldDef], response: &Value) -> Result<BTreeMap<String, Value>, String> {
+    let object = response.as_object();
+    let mut extra = BTreeMap::new();
+    for field in fields {
+        let key = normalize(&field.name);
+        let value = object
+            .and_then(|o| o.iter().find(|(k, _)| normalize(k) == key))
+            .and_then(|(_, v)| coerce(field, v));
+        match value {
+            Some(value) => {
+                extra.insert(field.name.clone(), value);
+            }
+            None if field.required => {
+                return Err(format!("Analysis is missing required field \"{}\"", field.name));
+            }
+            None => {}
+        }
+    }
+    Ok(extra)
+}
+
+/// `value` as the field's type, or `None` if it can't be read as one.
+fn coerce(field: &FieldDef, value: &Value) -> Option<Value> {
+    match field.kind {
+        FieldKind::Text => {
+            let text = match value {
+                Value::String(s) => s.trim().to_string(),
+                Value::Number(n) => n.to_string(),
+                Value::Bool(b) => b.to_string(),
+                Value::Array(items) => items.iter().filter_map(scalar_text).collect::<Vec<_>>().join(", "),
+                _ => return None,
+            };
+            let text = match_option(field, &text)?;
+            (!text.is_empty()).then_some(Value::String(text))
+        }
+        FieldKind::List => {
+            let items: Vec<String> = match value {
+                Value::Array(items) => items.iter().filter_map(scalar_text).collect(),
+                Value::String(s) => s.split(',').map(|item| item.trim().to_string()).collect(),
+                _ => return None,
+            };
+            let mut list: Vec<Value> = Vec::new();
+            for item in items.iter().filter(|item| !item.is_empty()) {
+                if let Some(item) = match_option(field, item).map(Value::String) {
+                    if !list.contains(&item) {
+                        list.push(item);
+                    }
+                }
+            }
+            (!list.is_empty()).then_some(Value::Array(list))
+        }
+        FieldKind::Number => match value {
+            Value::Number(_) => Some(value.clone()),
+            Value::String(s) => s.trim().parse::<f64>().ok().and_then(serde_json::Number::from_f64).map(Value::Number),
+            _ => None,
+        },
+        FieldKind::Boolean => match value {
+            Value::Bool(_) => Some(value.clone()),
+            Value::String(s) => match s.trim().to_lowercase().as_str() {
+                "true" | "yes" => Some(Value::Bool(true)),
+                "false" | "no" => Some(Value::Bool(false)),
+                _ => None,
+            },
+            _ => None,
+        },
+    }
+}
+
+fn scalar_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.trim().to_string()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// The option `value` names, spelled as in the schema; `value` itself when
+/// the field has no options.
+fn match_option(field: &FieldDef, value: &str) -> Option<String> {
+    if field.options.is_empty() {
+        return Some(value.to_string());
+    }
+    field.options.iter().find(|o| normalize(o) == normalize(value)).cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn field(name: &str, kind: FieldKind, options: &[&str], required: bool) -> FieldDef {
+        FieldDef {
+            name: name.to_string(),
+            description: None,
+            kind,
+            options: options.iter().map(|o| o.to_string()).collect(),
+            required,
+        }
+    }
+
+    #[test]
+    fn test_load_schema_from_deco_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_str().unwrap();
+        assert_eq!(AnalysisSchema::load(project).unwrap(), AnalysisSchema::default());
+
+        std::fs::write(dir.path().join("deco.json"), r#"{"version": 2, "name": "Refs"}"#).unwrap();
+        assert_eq!(AnalysisSchema::load(project).unwrap(), AnalysisSchema::default());
+
+        std::fs::write(
+            dir.path().join("deco.json"),
+            r#"{"version": 2, "analysis": {"fields": [
+                {"name": "material", "type": "list", "description": "main materials"},
+                {"name": "structural-system", "options": ["frame", "shell"], "required": true}
+            ], "prompt": "Architecture. {{default}}"}}"#,
+        )
+        .unwrap();
+        let schema = AnalysisSchema::load(project).unwrap();
+        assert_eq!(schema.fields.len(), 2);
+        assert_eq!(schema.fields[0].kind, FieldKind::List);
+        assert_eq!(schema.fields[1].kind, FieldKind::Text);
+        assert!(schema.fields[1].required);
+        assert_eq!(schema.prompt.as_deref(), Some("Architecture. {{default}}"));
+
+        std::fs::write(dir.path().join("deco.json"), r#"{"analysis": {"fields": [{"name": "Tags"}]}}"#).unwrap();
+        let err = AnalysisSchema::load(project).unwrap_err();
+        assert!(err.contains("built in"), "{err}");
+
+        std::fs::write(dir.path().join("deco.json"), r#"{"analysis": {"fields": [{"name": "x", "type": "date"}]}}"#).unwrap();
+        assert!(AnalysisSchema::load(project).is_err());
+    }
+
+    #[test]
+    fn test_render_prompt() {
+        let tags = vec!["brutalism".to_string()];
+        let default = AnalysisSchema::default();
+        assert_eq!(default.render_prompt("BASE", &[]), "BASE");
+        assert!(default.render_prompt("BASE", &tags).contains("[brutalism]"));
+
+        let schema = AnalysisSchema {
+            fields: vec![
+                field("material", FieldKind::List, &[], false),
+                field("structural-system", FieldKind::Text, &["frame", "shell"], true),
+            ],
+            prompt: None,
+        };
+        let prompt = schema.render_prompt("BASE", &tags);
+        assert!(prompt.starts_with("BASE\n\nAlso include these fields"), "{prompt}");
+        assert!(prompt.contains("- \"material\": an array of strings, or null"), "{prompt}");
+        assert!(prompt.contains("- \"structural-system\": one of \"frame\", \"shell\"\n"), "{prompt}");
+        assert!(prompt.ends_with("concepts not covered."), "{prompt}");
+
+        let templated = AnalysisSchema {
+            prompt: Some("Fashion board.\n{{fields}}\n{{default}}".to_string()),
+            ..schema
+        };
+        let prompt = templated.render_prompt("BASE", &tags);
+        assert!(prompt.starts_with("Fashion board.\nAlso include"), "{prompt}");
+        assert!(prompt.contains("\nBASE\n\nThe board already uses"), "{prompt}");
+    }
+
+    #[test]
+    fn test_extract_fields_coerces_and_validates() {
+        let fields = vec![
+            field("material", FieldKind::List, &[], false),
+            field("structural-system", FieldKind::Text, &["frame", "load-bearing"], true),
+            field("storeys", FieldKind::Number, &[], false),
+            field("listed", FieldKind::Boolean, &[], false),
+            field("fabric", FieldKind::List, &["silk", "wool"], false),
+        ];
+        let response = json!({
+            "description": "A tower",
+            "Material": "concrete, glass, concrete",
+            "structural_system": "Load-Bearing",
+            "storeys": "12",
+            "listed": "yes",
+            "fabric": ["Wool", "denim"],
+            "unrelated": 1
+        });
+        let extra = extract_fields(&fields, &response).unwrap();
+        assert_eq!(
+            serde_json::to_value(&extra).unwrap(),
+            json!({
+                "material": ["concrete", "glass"],
+                "structural-system": "load-bearing",
+                "storeys": 12.0,
+                "listed": true,
+                "fabric": ["wool"]
+            })
+        );
+
+        let err = extract_fields(&fields, &json!({"structural-system": "dome"})).unwrap_err();
+        assert!(err.contains("structural-system"), "{err}");
+        let err = extract_fields(&fields, &json!("plain text")).unwrap_err();
+        assert!(err.contains("missing required"), "{err}");
+
+        let optional = &fields[..1];
+        assert!(extract_fields(optional, &json!({"material": null})).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,736 @@
+//! Approximate nearest-neighbour index (HNSW) over CLIP embeddings.
+//!
+//! The graph lives in the project's `search.db` next to the vectors it
+//! indexes, so the desktop app and the CLI share one index and graph updates
+//! commit together with the embedding rows. Each embedding model gets its own
+//! graph, since vectors from different models aren't comparable:
+//! - `ann_nodes` — one row per indexed image and model (id, model, path, top layer)
+//! - `ann_links` — neighbour ids per node and layer (i64 LE BLOB)
+//! - `ann_meta` — entry point, top layer and vector dimensions per model
+//!
+//! A model's index counts as present once `ann_meta` has its `dimensions`
+//! row. The first vector stored for a model starts its index; vectors stored
+//! before that (older databases) use brute-force scans until `rebuild` runs.
+
+use rusqlite::{params, Connection, OptionalExtension};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use sha2::{Digest, Sha256};
+
+/// Neighbours kept per node on layers above 0.
+const M: usize = 16;
+/// Neighbours kept per node on layer 0.
+const M0: usize = 32;
+/// Candidate list size while inserting.
+const EF_CONSTRUCTION: usize = 64;
+/// Minimum candidate list size while querying.
+const EF_SEARCH: usize = 64;
+/// Upper bound on node levels.
+const MAX_LEVEL: usize = 16;
+
+// ---------------------------------------------------------------------------
+// Metadata
+// ---------------------------------------------------------------------------
+
+fn meta_get(conn: &Connection, model: &str, key: &str) -> Result<Option<i64>, String> {
+    conn.query_row(
+        "SELECT value FROM ann_meta WHERE model = ?1 AND key = ?2",
+        params![model, key],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| format!("Cannot read ANN metadata: {e}"))
+}
+
+fn meta_set(conn: &Connection, model: &str, key: &str, value: i64) -> Result<(), String> {
+    conn.execute(
+        "INSERT OR REPLACE INTO ann_meta (model, key, value) VALUES (?1, ?2, ?3)",
+        params![model, key, value],
+    )
+    .map_err(|e| format!("Cannot write ANN metadata: {e}"))?;
+    Ok(())
+}
+
+/// Whether this database has an ANN index for `model` to query and maintain.
+pub fn is_present(conn: &Connection, model: &str) -> Result<bool, String> {
+    Ok(meta_get(conn, model, "dimensions")?.is_some())
+}
+
+// ---------------------------------------------------------------------------
+// Public operations
+// ---------------------------------------------------------------------------
+
+/// Add (or replace) a vector in the model's index. No-op when the index is
+/// absent or the vector's dimensions don't match the indexed ones.
+pub fn insert(conn: &Connection, model: &str, path: &str, vector: &[f32]) -> Result<(), String> {
+    let dims = match meta_get(conn, model, "dimensions")? {
+        Some(dims) => dims,
+        None if !has_other_vectors(conn, model, path)? => 0,
+        None => return Ok(()),
+    };
+    if dims == 0 {
+        meta_set(conn, model, "dimensions", vector.len() as i64)?;
+    } else if dims as usize != vector.len() {
+        crate::log::log(
+            "ANN",
+            &format!("Skipping {path}: {} dims, index has {dims}", vector.len()),
+        );
+        return Ok(());
+    }
+
+    // Re-embedding an image replaces its node
+    remove(conn, model, path)?;
+
+    let mut graph = Graph::new(conn, model);
+    graph.insert(path, vector)?;
+    graph.flush()
+}
+
+/// Whether `model` has stored vectors other than `path`'s.
+fn has_other_vectors(conn: &Connection, model: &str, path: &str) -> Result<bool, String> {
+    conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM embeddings WHERE model = ?1 AND path != ?2)",
+        params![model, path],
+        |row| row.get(0),
+    )
+    .map_err(|e| format!("Cannot check embeddings: {e}"))
+}
+
+/// Remove a path from every model's index.
+pub fn remove_path(conn: &Connection, path: &str) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare("SELECT DISTINCT model FROM ann_nodes WHERE path = ?1")
+        .map_err(|e| format!("Cannot look up ANN nodes: {e}"))?;
+    let models: Vec<String> = stmt
+        .query_map(params![path], |row| row.get(0))
+        .map_err(|e| format!("Cannot look up ANN nodes: {e}"))?
+        .filter_map(|r| r.ok())
+        .collect();
+    for model in models {
+        remove(conn, &model, path)?;
+    }
+    Ok(())
+}
+
+/// Remove a path from the model's index, reconnecting its former neighbours.
+pub fn remove(conn: &Connection, model: &str, path: &str) -> Result<(), String> {
+    let node: Option<(i64, usize)> = conn
+        .query_row(
+            "SELECT id, level FROM ann_nodes WHERE model = ?1 AND path = ?2",
+            params![model, path],
+            |row| Ok((row.get(0)?, row.get::<_, i64>(1)? as usize)),
+        )
+        .optional()
+        .map_err(|e| format!("Cannot look up ANN node: {e}"))?;
+    let Some((id, level)) = node else {
+        return Ok(());
+    };
+
+    let mut graph = Graph::new(conn, model);
+    for layer in 0..=level {
+        let orphans = graph.neighbors(id, layer)?;
+        for &n in &orphans {
+            let mut list: Vec<i64> = graph
+                .neighbors(n, layer)?
+                .into_iter()
+                .filter(|&x| x != id)
+                .collect();
+            for &m in &orphans {
+                if m != n && !list.contains(&m) {
+                    list.push(m);
+                }
+            }
+            let list = graph.prune(n, list, layer)?;
+            graph.set_neighbors(n, layer, list);
+        }
+    }
+    graph.flush()?;
+
+    conn.execute("DELETE FROM ann_links WHERE id = ?1", params![id])
+        .map_err(|e| format!("Cannot delete ANN links: {e}"))?;
+    conn.execute("DELETE FROM ann_nodes WHERE id = ?1", params![id])
+        .map_err(|e| format!("Cannot delete ANN node: {e}"))?;
+
+    if meta_get(conn, model, "entry")? == Some(id) {
+        let next: Option<(i64, i64)> = conn
+            .query_row(
+                "SELECT id, level FROM ann_nodes WHERE model = ?1 ORDER BY level DESC, id LIMIT 1",
+                params![model],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(|e| format!("Cannot pick ANN entry point: {e}"))?;
+        match next {
+            Some((next_id, next_level)) => {
+                meta_set(conn, model, "entry", next_id)?;
+                meta_set(conn, model, "max_level", next_level)?;
+            }
+            None => {
+                conn.execute(
+                    "DELETE FROM ann_meta WHERE model = ?1 AND key IN ('entry', 'max_level')",
+                    params![model],
+                )
+                .map_err(|e| format!("Cannot reset ANN entry point: {e}"))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Query the `k` nearest paths to `query` in the model's index as
+/// `(path, cosine similarity)`, best first. Returns `None` when the index is
+/// absent or can't answer this query, so callers fall back to a brute-force scan.
+pub fn search(
+    conn: &Connection,
+    model: &str,
+    query: &[f32],
+    k: usize,
+) -> Result<Option<Vec<(String, f64)>>, String> {
+    let Some(dims) = meta_get(conn, model, "dimensions")? else {
+        return Ok(None);
+    };
+    let Some(entry) = meta_get(conn, model, "entry")? else {
+        return Ok(Some(Vec::new()));
+    };
+    if dims as usize != query.len() {
+        return Ok(None);
+    }
+    let max_level = meta_get(conn, model, "max_level")?.unwrap_or(0) as usize;
+
+    let mut graph = Graph::new(conn, model);
+    let Some(dist) = graph.distance(query, entry)? else {
+        return Ok(None);
+    };
+    let mut eps = vec![Candidate { dist, id: entry }];
+    for layer in (1..=max_level).rev() {
+        eps = graph.search_layer(query, &eps, 1, layer)?;
+    }
+    let found = graph.search_layer(query, &eps, k.max(EF_SEARCH), 0)?;
+
+    let mut stmt = conn
+        .prepare_cached("SELECT path FROM ann_nodes WHERE id = ?1")
+        .map_err(|e| format!("ANN query failed: {e}"))?;
+    let mut results = Vec::with_capacity(k.min(found.len()));
+    for c in found.into_iter().take(k) {
+        let path: Option<String> = stmt
+            .query_row(params![c.id], |row| row.get(0))
+            .optional()
+            .map_err(|e| format!("ANN query failed: {e}"))?;
+        if let Some(path) = path {
+            results.push((path, 1.0 - c.dist));
+        }
+    }
+    Ok(Some(results))
+}
+
+/// Drop and rebuild every model's index. Returns the number of vectors indexed.
+pub fn rebuild_all(conn: &Connection) -> Result<usize, String> {
+    conn.execute_batch("DELETE FROM ann_links; DELETE FROM ann_nodes; DELETE FROM ann_meta;")
+        .map_err(|e| format!("Cannot clear ANN index: {e}"))?;
+    let mut stmt = conn
+        .prepare("SELECT DISTINCT model FROM embeddings ORDER BY model")
+        .map_err(|e| format!("Embedding query failed: {e}"))?;
+    let models: Vec<String> = stmt
+        .query_map([], |row| row.get(0))
+        .map_err(|e| format!("Embedding query failed: {e}"))?
+        .filter_map(|r| r.ok())
+        .collect();
+    drop(stmt);
+
+    let mut count = 0;
+    for model in &models {
+        count += rebuild(conn, model)?;
+    }
+    Ok(count)
+}
+
+/// Drop and rebuild the model's index from its stored embeddings. Returns
+/// the number of vectors indexed.
+pub fn rebuild(conn: &Connection, model: &str) -> Result<usize, String> {
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Cannot begin transaction: {e}"))?;
+
+    tx.execute(
+        "DELETE FROM ann_links WHERE id IN (SELECT id FROM ann_nodes WHERE model = ?1)",
+        params![model],
+    )
+    .and_then(|_| tx.execute("DELETE FROM ann_nodes WHERE model = ?1", params![model]))
+    .and_then(|_| tx.execute("DELETE FROM ann_meta WHERE model = ?1", params![model]))
+    .map_err(|e| format!("Cannot clear ANN index: {e}"))?;
+
+    let mut stmt = tx
+        .prepare("SELECT path, vector FROM embeddings WHERE model = ?1 ORDER BY path")
+        .map_err(|e| format!("Embedding query failed: {e}"))?;
+    let vectors: Vec<(String, Vec<f32>)> = stmt
+        .query_map(params![model], |row| {
+            let path: String = row.get(0)?;
+            let bytes: Vec<u8> = row.get(1)?;
+            Ok((path, decode_vector(&bytes)))
+        })
+        .map_err(|e| format!("Embedding fetch failed: {e}"))?
+        .filter_map(|r| r.ok())
+        .collect();
+    drop(stmt);
+
+    // Index the dominant dimensionality; stragglers stay brute-force only
+    let mut dim_counts: HashMap<usize, usize> = HashMap::new();
+    for (_, v) in &vectors {
+        *dim_counts.entry(v.len()).or_default() += 1;
+    }
+    let dims = dim_counts
+        .into_iter()
+        .max_by_key(|&(d, n)| (n, d))
+        .map(|(d, _)| d)
+        .unwrap_or(0);
+    meta_set(&tx, model, "dimensions", dims as i64)?;
+
+    let mut graph = Graph::new(&tx, model);
+    let mut count = 0;
+    for (path, vector) in vectors.iter().filter(|(_, v)| v.len() == dims) {
+        graph.insert(path, vector)?;
+        count += 1;
+    }
+    graph.flush()?;
+
+    tx.commit()
+        .map_err(|e| format!("Cannot commit transaction: {e}"))?;
+    crate::log::log("ANN", &format!("Rebuilt {model} index: {count} vectors ({dims} dims)"));
+    Ok(count)
+}
+
+/// Build the model's index if this database doesn't have one yet.
+pub fn ensure_built(conn: &Connection, model: &str) -> Result<(), String> {
+    if !is_present(conn, model)? {
+        rebuild(conn, model)?;
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Graph
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Candidate {
+    dist: f64,
+    id: i64,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.dist
+            .total_cmp(&other.dist)
+            .then(self.id.cmp(&other.id))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Lazily-loaded view of the graph. Vectors and neighbour lists are read on
+/// first use and cached; modified neighbour lists are written by `flush`.
+struct Graph<'a> {
+    conn: &'a Connection,
+    model: &'a str,
+    vectors: HashMap<i64, Option<Vec<f32>>>,
+    links: HashMap<(i64, usize), Vec<i64>>,
+    dirty: HashSet<(i64, usize)>,
+}
+
+impl<'a> Graph<'a> {
+    fn new(conn: &'a Connection, model: &'a str) -> Self {
+        Graph {
+            conn,
+            model,
+            vectors: HashMap::new(),
+            links: HashMap::new(),
+            dirty: HashSet::new(),
+        }
+    }
+
+    fn load_vector(&mut self, id: i64) -> Result<(), String> {
+        if self.vectors.contains_key(&id) {
+            return Ok(());
+        }
+        let bytes: Option<Vec<u8>> = self
+            .conn
+            .prepare_cached(
+                "SELECT e.vector FROM ann_nodes n
+                 JOIN embeddings e ON e.path = n.path AND e.model = n.model
+                 WHERE n.id = ?1",
+            )
+            .and_then(|mut stmt| stmt.query_row(params![id], |row| row.get(0)).optional())
+            .map_err(|e| format!("Cannot load ANN vector: {e}"))?;
+        self.vectors.insert(id, bytes.map(|b| decode_vector(&b)));
+        Ok(())
+    }
+
+    /// Cosine distance from `query` to node `id` (`None` if its vector is gone).
+    fn distance(&mut self, query: &[f32], id: i64) -> Result<Option<f64>, String> {
+        self.load_vector(id)?;
+        Ok(self.vectors[&id]
+            .as_deref()
+            .map(|v| 1.0 - crate::ops::cosine_sim(query, v)))
+    }
+
+    fn distance_between(&mut self, a: i64, b: i64) -> Result<Option<f64>, String> {
+        self.load_vector(a)?;
+        self.load_vector(b)?;
+        match (self.vectors[&a].as_deref(), self.vectors[&b].as_deref()) {
+            (Some(va), Some(vb)) => Ok(Some(1.0 - crate::ops::cosine_sim(va, vb))),
+            _ => Ok(None),
+        }
+    }
+
+    fn neighbors(&mut self, id: i64, layer: usize) -> Result<Vec<i64>, String> {
+        if let Some(list) = self.links.get(&(id, layer)) {
+            return Ok(list.clone());
+        }
+        let bytes: Option<Vec<u8>> = self
+            .conn
+            .prepare_cached("SELECT neighbors FROM ann_links WHERE id = ?1 AND layer = ?2")
+            .and_then(|mut stmt| {
+                stmt.query_row(params![id, layer as i64], |row| row.get(0))
+                    .optional()
+            })
+            .map_err(|e| format!("Cannot load ANN links: {e}"))?;
+        let list: Vec<i64> = bytes
+            .unwrap_or_default()
+            .chunks_exact(8)
+            .map(|c| i64::from_le_bytes([c[0], c[1], c[2], c[3], c[4], c[5], c[6], c[7]]))
+            .collect();
+        self.links.insert((id, layer), list.clone());
+        Ok(list)
+    }
+
+    fn set_neighbors(&mut self, id: i64, layer: usize, list: Vec<i64>) {
+        self.links.insert((id, layer), list);
+        self.dirty.insert((id, layer));
+    }
+
+    fn flush(&mut self) -> Result<(), String> {
+        let mut stmt = self
+            .conn
+            .prepare_cached(
+                "INSERT OR REPLACE INTO ann_links (id, layer, neighbors) VALUES (?1, ?2, ?3)",
+            )
+            .map_err(|e| format!("Cannot write ANN links: {e}"))?;
+        for (id, layer) in self.dirty.drain() {
+            let bytes: Vec<u8> = self.links[&(id, layer)]
+                .iter()
+                .flat_map(|n| n.to_le_bytes())
+                .collect();
+            stmt.execute(params![id, layer as i64, bytes])
+                .map_err(|e| format!("Cannot write ANN links: {e}"))?;
+        }
+        Ok(())
+    }
+
+    /// Best-first search on one layer. Returns up to `ef` candidates, nearest first.
+    fn search_layer(
+        &mut self,
+        query: &[f32],
+        entry: &[Candidate],
+        ef: usize,
+        layer: usize,
+    ) -> Result<Vec<Candidate>, String> {
+        let mut visited: HashSet<i64> = entry.iter().map(|c| c.id).collect();
+        let mut candidates: BinaryHeap<Reverse<Candidate>> =
+            entry.iter().copied().map(Reverse).collect();
+        let mut found: BinaryHeap<Candidate> = entry.iter().copied().collect();
+
+        while let Some(Reverse(current)) = candidates.pop() {
+            let furthest = found.peek().map(|c| c.dist).unwrap_or(f64::INFINITY);
+            if current.dist > furthest && found.len() >= ef {
+                break;
+            }
+            for n in self.neighbors(current.id, layer)? {
+                if !visited.insert(n) {
+                    continue;
+                }
+                let Some(dist) = self.distance(query, n)? else {
+                    continue;
+                };
+                let furthest = found.peek().map(|c| c.dist).unwrap_or(f64::INFINITY);
+                if found.len() < ef || dist < furthest {
+                    candidates.push(Reverse(Candidate { dist, id: n }));
+                    found.push(Candidate { dist, id: n });
+                    if found.len() > ef {
+                        found.pop();
+                    }
+                }
+            }
+        }
+
+        Ok(found.into_sorted_vec())
+    }
+
+    /// Keep the closest `max_neighbors(layer)` of `candidates` to `id`.
+    fn prune(&mut self, id: i64, candidates: Vec<i64>, layer: usize) -> Result<Vec<i64>, String> {
+        let max = max_neighbors(layer);
+        if candidates.len() <= max {
+            return Ok(candidates);
+        }
+        let mut scored = Vec::with_capacity(candidates.len());
+        for c in candidates {
+            if let Some(dist) = self.distance_between(id, c)? {
+                scored.push(Candidate { dist, id: c });
+            }
+        }
+        scored.sort();
+        Ok(scored.into_iter().take(max).map(|c| c.id).collect())
+    }
+
+    fn insert(&mut self, path: &str, vector: &[f32]) -> Result<(), String> {
+        let level = level_for(path);
+        self.conn
+            .execute(
+                "INSERT INTO ann_nodes (model, path, level) VALUES (?1, ?2, ?3)",
+                params![self.model, path, level as i64],
+            )
+            .map_err(|e| format!("Cannot insert ANN node: {e}"))?;
+        let id = self.conn.last_insert_rowid();
+        self.vectors.insert(id, Some(vector.to_vec()));
+        for layer in 0..=level {
+            self.set_neighbors(id, layer, Vec::new());
+        }
+
+        let entry = match meta_get(self.conn, self.model, "entry")? {
+            Some(entry) => self.distance(vector, entry)?.map(|dist| Candidate { dist, id: entry }),
+            None => None,
+        };
+        let Some(entry) = entry else {
+            meta_set(self.conn, self.model, "entry", id)?;
+            meta_set(self.conn, self.model, "max_level", level as i64)?;
+            return Ok(());
+        };
+        let max_level = meta_get(self.conn, self.model, "max_level")?.unwrap_or(0) as usize;
+
+        let mut eps = vec![entry];
+        for layer in (level + 1..=max_level).rev() {
+            eps = self.search_layer(vector, &eps, 1, layer)?;
+        }
+        for layer in (0..=level.min(max_level)).rev() {
+            let found = self.search_layer(vector, &eps, EF_CONSTRUCTION, layer)?;
+            let selected: Vec<i64> = found
+                .iter()
+                .filter(|c| c.id != id)
+                .take(M)
+                .map(|c| c.id)
+                .collect();
+            for &n in &selected {
+                let mut list = self.neighbors(n, layer)?;
+                if !list.contains(&id) {
+                    list.push(id);
+                    let list = self.prune(n, list, layer)?;
+                    self.set_neighbors(n, layer, list);
+                }
+            }
+            self.set_neighbors(id, layer, selected);
+            eps = found;
+        }
+
+        if level > max_level {
+            meta_set(self.conn, self.model, "entry", id)?;
+            meta_set(self.conn, self.model, "max_level", level as i64)?;
+        }
+        Ok(())
+    }
+}
+
+fn max_neighbors(layer: usize) -> usize {
+    if layer == 0 { M0 } else { M }
+}
+
+/// Deterministic HNSW level for a path: geometric with p = 1/M, derived from
+/// SHA-256 (stable across Rust releases) so rebuilds produce the same layer
+/// assignment.
+fn level_for(path: &str) -> usize {
+    let digest = Sha256::digest(path.as_bytes());
+    let hash = u64::from_be_bytes(digest[..8].try_into().expect("digest is 32 bytes"));
+    // Top 53 bits → uniform in (0, 1]
+    let u = ((hash >> 11) + 1) as f64 / (1u64 << 53) as f64;
+    let level = (-u.ln() / (M as f64).ln()).floor() as usize;
+    level.min(MAX_LEVEL)
+}
+
+fn decode_vector(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(4)
+        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_project() -> (String, tempfile::TempDir) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().to_string_lossy().to_string();
+        (path, dir)
+    }
+
+    /// Deterministic pseudo-random unit-ish vectors.
+    fn test_vectors(n: usize, dims: usize) -> Vec<Vec<f32>> {
+        let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+        (0..n)
+            .map(|_| {
+                (0..dims)
+                    .map(|_| {
+                        state ^= state << 13;
+                        state ^= state >> 7;
+                        state ^= state << 17;
+                        (state % 2000) as f32 / 1000.0 - 1.0
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn brute_force_top(vectors: &[Vec<f32>], query: &[f32], k: usize) -> Vec<usize> {
+        let mut scored: Vec<(usize, f64)> = vectors
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (i, crate::ops::cosine_sim(query, v)))
+            .collect();
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.into_iter().take(k).map(|(i, _)| i).collect()
+    }
+
+    #[test]
+    fn test_levels_are_pinned() {
+        // Layer assignment must not change between builds or toolchains
+        let raised: Vec<usize> = (0..20).filter(|i| level_for(&format!("/images/{i}.jpg")) > 0).collect();
+        assert_eq!(raised, vec![10, 12, 17]);
+        let above = (0..2000).filter(|i| level_for(&format!("/images/{i}.jpg")) > 0).count();
+        assert_eq!(above, 120);
+    }
+
+    #[test]
+    fn test_index_matches_brute_force() {
+        let (project, _dir) = temp_project();
+        let vectors = test_vectors(300, 16);
+        for (i, v) in vectors.iter().enumerate() {
+            crate::search::store_embedding(&project, &format!("/img/{i}.jpg"), "test", v)
+                .unwrap();
+        }
+
+        let conn = crate::search::open_db(&project).unwrap();
+        assert!(is_present(&conn, "test").unwrap());
+
+        let mut hits = 0;
+        for q in 0..20 {
+            let expected = brute_force_top(&vectors, &vectors[q], 10);
+            let got = search(&conn, "test", &vectors[q], 10).unwrap().unwrap();
+            assert_eq!(got[0].0, format!("/img/{q}.jpg"));
+            hits += expected
+                .iter()
+                .filter(|i| got.iter().any(|(p, _)| *p == format!("/img/{i}.jpg")))
+                .count();
+        }
+        // Recall@10 over 20 queries
+        assert!(hits >= 190, "recall too low: {hits}/200");
+    }
+
+    #[test]
+    fn test_remove_and_reinsert() {
+        let (project, _dir) = temp_project();
+        let vectors = test_vectors(50, 8);
+        for (i, v) in vectors.iter().enumerate() {
+            crate::search::store_embedding(&project, &format!("/img/{i}.jpg"), "test", v)
+                .unwrap();
+        }
+
+        for i in 0..25 {
+            crate::search::delete_image_data(&project, &format!("/img/{i}.jpg")).unwrap();
+        }
+        let conn = crate::search::open_db(&project).unwrap();
+        let nodes: i64 = conn
+            .query_row("SELECT COUNT(*) FROM ann_nodes", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(nodes, 25);
+
+        // Remaining images are still reachable, deleted ones never come back
+        for (q, v) in vectors.iter().enumerate().skip(25) {
+            let got = search(&conn, "test", v, 5).unwrap().unwrap();
+            assert_eq!(got[0].0, format!("/img/{q}.jpg"));
+            assert!(got.iter().all(|(p, _)| {
+                let n: usize = p[5..p.len() - 4].parse().unwrap();
+                n >= 25
+            }));
+        }
+
+        // Re-storing an existing path replaces its node
+        crate::search::store_embedding(&project, "/img/30.jpg", "test", &vectors[0]).unwrap();
+        let nodes: i64 = conn
+            .query_row("SELECT COUNT(*) FROM ann_nodes", [], |r| r.get(0))
+            .unwrap();
+        assert_eq!(nodes, 25);
+        let got = search(&conn, "test", &vectors[0], 1).unwrap().unwrap();
+        assert_eq!(got[0].0, "/img/30.jpg");
+    }
+
+    #[test]
+    fn test_legacy_database_needs_rebuild() {
+        let (project, _dir) = temp_project();
+        let conn = crate::search::open_db(&project).unwrap();
+        let vectors = test_vectors(20, 4);
+        for (i, v) in vectors.iter().enumerate() {
+            crate::search::store_embedding_conn(&conn, &format!("/img/{i}.jpg"), "test", v)
+                .unwrap();
+        }
+
+        // Simulate a database from before the index existed
+        conn.execute_batch("DELETE FROM ann_links; DELETE FROM ann_nodes; DELETE FROM ann_meta;")
+            .unwrap();
+        drop(conn);
+        let conn = crate::search::open_db(&project).unwrap();
+        assert!(!is_present(&conn, "test").unwrap());
+        assert!(search(&conn, "test", &vectors[0], 5).unwrap().is_none());
+
+        // New vectors don't start a partial index over the existing ones
+        crate::search::store_embedding_conn(&conn, "/img/new.jpg", "test", &vectors[0]).unwrap();
+        assert!(!is_present(&conn, "test").unwrap());
+
+        ensure_built(&conn, "test").unwrap();
+        assert!(is_present(&conn, "test").unwrap());
+        let got = search(&conn, "test", &vectors[3], 1).unwrap().unwrap();
+        assert_eq!(got[0].0, "/img/3.jpg");
+    }
+
+    #[test]
+    fn test_models_have_separate_indexes() {
+        let (project, _dir) = temp_project();
+        let conn = crate::search::open_db(&project).unwrap();
+        let small = test_vectors(10, 4);
+        let large = test_vectors(10, 6);
+        for i in 0..10 {
+            let path = format!("/img/{i}.jpg");
+            crate::search::store_embedding_conn(&conn, &path, "small", &small[i]).unwrap();
+            crate::search::store_embedding_conn(&conn, &path, "large", &large[i]).unwrap();
+        }
+
+        let got = search(&conn, "large", &large[7], 3).unwrap().unwrap();
+        assert_eq!(got[0].0, "/img/7.jpg");
+        assert_eq!(got.len(), 3);
+        // A query from the other model's space isn't answered by this index
+        assert!(search(&conn, "large", &small[7], 3).unwrap().is_none());
+
+        remove_path(&conn, "/img/7.jpg").unwrap();
+        for model in ["small", "large"] {
+            let nodes: i64 = conn
+                .query_row("SELECT COUNT(*) FROM ann_nodes WHERE model = ?1", params![model], |r| r.get(0))
+                .unwrap();
+            assert_eq!(nodes, 9);
+        }
+        assert_eq!(rebuild_all(&conn).unwrap(), 20);
+    }
+}
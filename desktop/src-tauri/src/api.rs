@@ -8,21 +8,54 @@
 //! - `GET /api/projects` - List all known projects
 //! - `GET /api/list?project=PATH` - List images in a project
 //! - `GET /api/search?project=PATH&q=QUERY&limit=N` - Full-text search
+//!   (`q` accepts field filters: `tag:x style:y -mood:z color:#rrggbb`)
+//!   (`&mode=hybrid[&fusion=rrf|weighted&textWeight=W&semanticWeight=W]` fuses
+//!   FTS5 with CLIP similarity)
 //! - `GET /api/info?project=PATH&filename=NAME` - Image metadata
-//! - `GET /api/tags?project=PATH` - All tags with counts
+//! - `GET /api/thumbnail?project=PATH&filename=NAME&size=small|medium|large` -
+//!   Thumbnail bytes (JPEG or WebP; generated on first request)
+//! - `GET /api/tags?project=PATH[&tree=true]` - All tags with counts, each
+//!   level of hierarchical tags rolled up (`tree` nests them)
+//! - `POST /api/tags/merge` - Merge tags into one across the project (the
+//!   sources become synonyms in the vocabulary)
+//! - `POST /api/tags/rename` - Rename a tag on every image
+//! - `GET /api/vocabulary?project=PATH` - The project's tag vocabulary
+//! - `PUT /api/vocabulary` - Replace the vocabulary and re-apply it to every image
+//! - `GET /api/query?project=PATH&q=QUERY` - Images matching a boolean query
+//!   (`material/bronze (style:geometric OR era:1920s) -mood:dark width:>=2000`)
+//! - `GET /api/collections?project=PATH` - Smart collections with member counts
+//!   (an `error` instead for a saved query that no longer parses)
+//! - `POST /api/collections` - Save a query as a smart collection (`replace`
+//!   overwrites one with the same name)
+//! - `GET /api/collections/{name}?project=PATH` - A collection's current images
+//! - `DELETE /api/collections/{name}?project=PATH` - Delete a collection
 //! - `POST /api/import` - Import image from file upload or URL
+//!   (`duplicate_policy` field: skip | link | keep-both; `analyze=true` returns
+//!   the AI analysis; the URL and an optional `source_page` are kept as the
+//!   image's source)
 //! - `DELETE /api/delete` - Delete an image from the project
 //! - `POST /api/move` - Move an item's position on the board
-//! - `PATCH /api/item` - Update item metadata (tags, description, etc.)
-//! - `POST /api/embed` - Generate CLIP embedding for a single image
-//! - `POST /api/embed-batch` - Batch-generate CLIP embeddings
-//! - `POST /api/similar` - Find visually similar images (top-N)
-//! - `POST /api/search-semantic` - Text-to-image semantic search (FTS5)
-//! - `POST /api/cluster` - Auto-cluster images by visual similarity
+//! - `PATCH /api/item` - Update item metadata (tags, description, etc.) and
+//!   annotations (`sourceUrl`, `sourcePage`, `license`, `credit`, `author`,
+//!   `rating` 0–5, `favorite`, `notes`)
+//! - `POST /api/analyze` - AI-analyze images (`force` skips the cache,
+//!   `background: true` queues a job instead of waiting)
+//! - `POST /api/embed` - Generate an image embedding (optional `model`)
+//! - `POST /api/embed-batch` - Batch-generate image embeddings (optional `model`, `force`)
+//! - `POST /api/similar` - Find visually similar images (top-N, per model)
+//! - `POST /api/search-semantic` - Text-to-image semantic search (CLIP)
+//! - `POST /api/cluster` - Auto-cluster images by visual similarity (per model)
+//! - `GET /api/duplicates?project=PATH&maxDistance=N` - Groups of near-identical images
+//! - `POST /api/doctor` - Check a project's index, board and metadata
+//!   (`fix: true` repairs what it can)
+//! - `GET /api/jobs` - Background jobs (embedding, analysis, downloads)
+//! - `POST /api/jobs` - Queue a job (`{"kind": "embed" | "analyze" | "download", ...}`)
+//! - `POST /api/jobs/{id}/cancel` - Cancel a queued or running job
+//! - `POST /api/jobs/{id}/retry` - Re-queue a failed or cancelled job
 
 use axum::{
-    extract::{Multipart, Query, State},
-    http::StatusCode,
+    extract::{Multipart, Path, Query, State},
+    http::{header, StatusCode},
     response::IntoResponse,
     routing::{delete, get, patch, post},
     Json, Router,
@@ -38,6 +71,7 @@ use tauri::{AppHandle, Emitter};
 struct ApiState {
     app: AppHandle,
     storage: crate::storage::Storage,
+    jobs: crate::jobs::Jobs,
 }
 
 // ---------------------------------------------------------------------------
@@ -77,6 +111,28 @@ struct UpdateItemRequest {
     moods: Option<Vec<String>>,
     #[serde(default)]
     era: Option<String>,
+    /// Project-defined analysis fields.
+    #[serde(default)]
+    extra: Option<std::collections::BTreeMap<String, serde_json::Value>>,
+    /// Notes, rating and attribution; only the fields given change.
+    #[serde(flatten)]
+    annotations: crate::search::AnnotationUpdate,
+}
+
+impl UpdateItemRequest {
+    /// The fields this request changes; everything it leaves out keeps its
+    /// saved value.
+    fn into_fields(self) -> crate::ops::UpdateFields {
+        crate::ops::UpdateFields {
+            description: self.title.or(self.description),
+            tags: self.tags,
+            styles: self.styles,
+            moods: self.moods,
+            era: self.era,
+            extra: self.extra,
+            annotations: self.annotations,
+        }
+    }
 }
 
 #[derive(Serialize)]
@@ -86,7 +142,11 @@ struct ImportResponse {
     filename: String,
     path: String,
     position: Option<Position>,
-    analysis: Option<serde_json::Value>,
+    analysis: Option<crate::ai::AnalysisResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    analysis_error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duplicate: Option<crate::dedup::DuplicateInfo>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -138,6 +198,15 @@ struct SearchQuery {
     q: String,
     #[serde(default = "default_search_limit")]
     limit: usize,
+    /// `text` (default) or `hybrid`
+    #[serde(default)]
+    mode: Option<String>,
+    #[serde(default)]
+    fusion: Option<crate::search::Fusion>,
+    #[serde(default)]
+    text_weight: Option<f64>,
+    #[serde(default)]
+    semantic_weight: Option<f64>,
 }
 
 fn default_search_limit() -> usize {
@@ -155,6 +224,9 @@ struct InfoQuery {
 #[serde(rename_all = "camelCase")]
 struct TagsQuery {
     project: String,
+    /// Nest hierarchical tags (`material/bronze`) instead of listing every level.
+    #[serde(default)]
+    tree: bool,
 }
 
 #[derive(Serialize)]
@@ -211,9 +283,11 @@ async fn handle_import(
     let mut file_ext: Option<String> = None;
     let mut file_name: Option<String> = None;
     let mut url: Option<String> = None;
+    let mut source_page: Option<String> = None;
     let mut project_path: Option<String> = None;
     let mut analyze = false;
     let mut position: Option<Position> = None;
+    let mut duplicate_policy: Option<crate::dedup::DuplicatePolicy> = None;
 
     // Parse multipart fields
     while let Ok(Some(field)) = multipart.next_field().await {
@@ -250,6 +324,9 @@ async fn handle_import(
                         .map_err(|e| api_error(StatusCode::BAD_REQUEST, format!("Cannot read url: {e}")))?,
                 );
             }
+            "source_page" => {
+                source_page = Some(field.text().await.unwrap_or_default());
+            }
             "project_path" => {
                 project_path = Some(
                     field
@@ -266,6 +343,10 @@ async fn handle_import(
                 let val = field.text().await.unwrap_or_default();
                 position = serde_json::from_str(&val).ok();
             }
+            "duplicate_policy" => {
+                let val = field.text().await.unwrap_or_default();
+                duplicate_policy = Some(val.parse().map_err(|e| api_error(StatusCode::BAD_REQUEST, e))?);
+            }
             _ => {
                 // Skip unknown fields
                 let _ = field.bytes().await;
@@ -299,22 +380,45 @@ async fn handle_import(
         ));
     };
 
-    // Import into project using shared import logic
-    let info = crate::import_image_bytes(data, ext, project.clone())
+    // Import into project using shared import logic (checks for duplicate content)
+    let policy = duplicate_policy.unwrap_or_else(crate::dedup::default_policy);
+    let info = crate::import_image_bytes(data, ext, project.clone(), policy)
         .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Import failed: {e}")))?;
 
     crate::log::log("API", &format!("Imported: {} → {}", original_name, info.path));
 
-    // Index + embed in background (don't block the API response).
+    if let Some(ref image_url) = url {
+        if let Err(e) = crate::search::record_source(&project, &info.path, image_url, source_page.as_deref()) {
+            crate::log::log("API", &format!("Cannot record source: {e}"));
+        }
+    }
+
+    if let Some(dup) = info.duplicate.as_ref().filter(|d| d.action == crate::dedup::DuplicatePolicy::Skip) {
+        crate::log::log("API", &format!("Skipped duplicate of {}", dup.existing_path));
+        return Ok((
+            StatusCode::OK,
+            Json(ImportResponse {
+                id: info.name.clone(),
+                filename: info.name,
+                path: info.path,
+                position,
+                analysis: None,
+                analysis_error: None,
+                duplicate: info.duplicate,
+            }),
+        ));
+    }
+
+    // Index + embed as a background job (don't block the API response).
     // This ensures the new image appears in search, tags, and find_similar.
-    {
-        let storage = state.storage.clone();
-        let bg_project = project.clone();
-        tokio::spawn(async move {
-            if let Err(e) = storage.embed_project(&bg_project).await {
-                crate::log::log("API", &format!("Background index+embed failed: {e}"));
-            }
-        });
+    let spec = crate::jobs::JobSpec::Embed {
+        project_path: project.clone(),
+        image_paths: None,
+        model: None,
+        force: false,
+    };
+    if let Err(e) = state.jobs.submit(spec) {
+        crate::log::log("API", &format!("Cannot queue index+embed: {e}"));
     }
 
     // Emit event so frontend can add the card to canvas
@@ -324,16 +428,26 @@ async fn handle_import(
     });
     let _ = state.app.emit("api:image-imported", &event_payload);
 
-    // Optionally trigger AI analysis
-    let analysis = if analyze {
-        crate::log::log("API", "Triggering AI analysis...");
-        // Analysis runs async in the Tauri command; emit event for frontend to handle
-        let _ = state.app.emit("api:analyze-request", &info.path);
-        // Return null for now — analysis result comes via Tauri events
-        None
+    // Optionally run AI analysis and return its result
+    let (analysis, analysis_error) = if analyze {
+        crate::log::log("API", "Running AI analysis...");
+        match analyze_images(&project, vec![info.path.clone()], false).await {
+            Ok(mut items) => match items.pop() {
+                Some(item) => (item.result, item.error),
+                None => (None, None),
+            },
+            Err(e) => (None, Some(e)),
+        }
     } else {
-        None
+        (None, None)
     };
+    if let Some(ref result) = analysis {
+        // Let the board pick up the new tags and description
+        let _ = state.app.emit("api:image-analyzed", serde_json::json!({
+            "imagePath": &info.path,
+            "analysis": result,
+        }));
+    }
 
     let response = ImportResponse {
         id: info.name.clone(),
@@ -341,6 +455,8 @@ async fn handle_import(
         path: info.path,
         position,
         analysis,
+        analysis_error,
+        duplicate: info.duplicate,
     };
 
     crate::log::log("API", "Import complete, response sent");
@@ -396,22 +512,9 @@ async fn handle_delete(
     std::fs::remove_file(&file_path)
         .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to delete file: {e}")))?;
 
-    // Delete thumbnails
-    if let Some(stem) = file_path.file_stem() {
-        let thumb_dir = project_dir.join("thumbnails");
-        if thumb_dir.is_dir() {
-            let stem_str = stem.to_string_lossy();
-            for ext in &["jpg", "jpeg", "png", "webp"] {
-                let thumb = thumb_dir.join(format!("{stem_str}.{ext}"));
-                if thumb.exists() { let _ = std::fs::remove_file(&thumb); }
-            }
-            let thumb_exact = thumb_dir.join(&filename);
-            if thumb_exact.exists() { let _ = std::fs::remove_file(&thumb_exact); }
-        }
-    }
-
-    // Delete from search database (metadata + embeddings)
+    // Delete thumbnails, then search database (metadata + embeddings)
     let image_path_str = file_path.to_string_lossy().to_string();
+    crate::thumbs::remove_for(&project_path, &image_path_str);
     if let Err(e) = crate::search::delete_image_data(&project_path, &image_path_str) {
         crate::log::log("API", &format!("DB cleanup warning: {e}"));
     }
@@ -505,54 +608,27 @@ async fn handle_update_item(
     State(state): State<Arc<ApiState>>,
     Json(payload): Json<UpdateItemRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let project_path = payload.project_path;
-    let filename = payload.filename;
+    let filename = payload.filename.clone();
 
-    crate::log::log("API", &format!("PATCH /api/item → project: {project_path}, file: {filename}"));
+    crate::log::log("API", &format!("PATCH /api/item → project: {}, file: {filename}", payload.project_path));
 
-    // Validate project path exists
-    let project_dir = std::path::Path::new(&project_path);
-    if !project_dir.exists() || !project_dir.is_dir() {
-        return Err(api_error(
-            StatusCode::BAD_REQUEST,
-            format!("Project path does not exist or is not a directory: {project_path}"),
-        ));
-    }
-
-    // Construct full image path for database lookup
-    let images_dir = project_dir.join("images");
-    let image_path = images_dir.join(&filename);
-    let image_path_str = image_path.to_string_lossy().to_string();
-
-    // Build metadata update
-    use crate::search::ImageMetadataRow;
-
-    let metadata = ImageMetadataRow {
-        image_path: image_path_str.clone(),
-        name: filename.clone(),
-        description: payload.title.or(payload.description),
-        tags: payload.tags.unwrap_or_default(),
-        style: payload.styles.unwrap_or_default(),
-        mood: payload.moods.unwrap_or_default(),
-        colors: Vec::new(),  // Not provided in this API
-        era: payload.era,
-    };
-
-    // Update in search database via storage backend
-    state.storage.upsert_image_metadata(&project_path, &metadata).await
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update metadata: {e}")))?;
+    let metadata = tokio::task::spawn_blocking(move || update_item(payload))
+        .await
+        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Task join: {e}")))??;
 
     crate::log::log("API", &format!("Updated metadata for: {filename}"));
 
     // Build response metadata object
     let response_metadata = serde_json::json!({
-        "path": image_path_str,
+        "path": metadata.image_path,
         "name": filename,
         "description": metadata.description,
         "tags": metadata.tags,
         "style": metadata.style,
         "mood": metadata.mood,
         "era": metadata.era,
+        "extra": metadata.extra,
+        "annotations": metadata.annotations,
     });
 
     // Emit event so frontend can refresh the card
@@ -572,6 +648,27 @@ async fn handle_update_item(
     Ok((StatusCode::OK, Json(response)))
 }
 
+/// Apply a PATCH /api/item body, merging it into the item's saved metadata.
+/// A bad annotation update is rejected before anything is written.
+fn update_item(payload: UpdateItemRequest) -> Result<crate::search::ImageMetadataRow, (StatusCode, Json<ErrorResponse>)> {
+    let project_path = payload.project_path.clone();
+    let filename = payload.filename.clone();
+
+    // Validate project path exists
+    let project_dir = std::path::Path::new(&project_path);
+    if !project_dir.exists() || !project_dir.is_dir() {
+        return Err(api_error(
+            StatusCode::BAD_REQUEST,
+            format!("Project path does not exist or is not a directory: {project_path}"),
+        ));
+    }
+
+    payload.annotations.validate().map_err(|e| api_error(StatusCode::BAD_REQUEST, e))?;
+
+    crate::ops::update_item_metadata(&project_path, &filename, payload.into_fields())
+        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to update metadata: {e}")))
+}
+
 // ---------------------------------------------------------------------------
 // URL Download
 // ---------------------------------------------------------------------------
@@ -671,10 +768,35 @@ async fn handle_search(
     let project = &params.project;
     let query = &params.q;
     let limit = params.limit;
-    crate::log::log("API", &format!("GET /api/search → project: {project}, q: {query}, limit: {limit}"));
-
-    let results = crate::search::search_text(project, query, limit)
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Search failed: {e}")))?;
+    let mode = params.mode.as_deref().unwrap_or("text");
+    crate::log::log("API", &format!("GET /api/search → project: {project}, q: {query}, limit: {limit}, mode: {mode}"));
+
+    let results = match mode {
+        "text" => crate::search::search_text(project, query, limit),
+        "hybrid" => {
+            let defaults = crate::search::HybridOptions::default();
+            let options = crate::search::HybridOptions {
+                fusion: params.fusion.unwrap_or(defaults.fusion),
+                text_weight: params.text_weight.unwrap_or(defaults.text_weight),
+                semantic_weight: params.semantic_weight.unwrap_or(defaults.semantic_weight),
+            };
+            let project = project.clone();
+            let query = query.clone();
+            // CLIP text encoding is CPU-bound
+            tokio::task::spawn_blocking(move || {
+                crate::search::hybrid_search(&project, &query, limit, &options)
+            })
+            .await
+            .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Task join error: {e}")))?
+        }
+        other => {
+            return Err(api_error(
+                StatusCode::BAD_REQUEST,
+                format!("Unknown search mode: {other} (expected text or hybrid)"),
+            ))
+        }
+    }
+    .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Search failed: {e}")))?;
 
     crate::log::log("API", &format!("Search: {} results", results.len()));
     Ok(Json(results))
@@ -713,7 +835,225 @@ async fn handle_tags(
         .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Cannot get tags: {e}")))?;
 
     crate::log::log("API", &format!("Tags: {} unique tags", tags.len()));
-    Ok(Json(tags))
+    if params.tree {
+        return Ok(Json(serde_json::json!(crate::search::tag_tree(&tags))));
+    }
+    Ok(Json(serde_json::json!(tags)))
+}
+
+// ---------------------------------------------------------------------------
+// Tag Vocabulary
+// ---------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MergeTagsRequest {
+    project_path: String,
+    sources: Vec<String>,
+    target: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RenameTagRequest {
+    project_path: String,
+    from: String,
+    to: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VocabularyRequest {
+    project_path: String,
+    vocabulary: crate::vocab::Vocabulary,
+}
+
+/// Map a tag rewrite error to a status: unknown tags are 404, renaming onto
+/// a tag in use is 409, empty names are 400.
+fn tag_error(e: String) -> (StatusCode, Json<ErrorResponse>) {
+    let status = if e.starts_with("Tag not found") {
+        StatusCode::NOT_FOUND
+    } else if e.contains("already in use") {
+        StatusCode::CONFLICT
+    } else if e.contains("cannot be empty") {
+        StatusCode::BAD_REQUEST
+    } else {
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+    api_error(status, e)
+}
+
+/// Run a bulk tag rewrite off the async runtime and tell the board which
+/// tags changed.
+async fn rewrite_tags(
+    state: &ApiState,
+    project_path: String,
+    rewrite: impl FnOnce(&str) -> Result<crate::ops::TagRewrite, String> + Send + 'static,
+) -> Result<Json<crate::ops::TagRewrite>, (StatusCode, Json<ErrorResponse>)> {
+    let project = project_path.clone();
+    let result = tokio::task::spawn_blocking(move || rewrite(&project))
+        .await
+        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Task join: {e}")))?
+        .map_err(tag_error)?;
+
+    crate::log::log("API", &format!("Rewrote tags on {} images", result.images));
+    let _ = state.app.emit(
+        "api:tags-changed",
+        serde_json::json!({
+            "projectPath": project_path,
+            "renamed": &result.renamed,
+            "removed": &result.removed,
+        }),
+    );
+    Ok(Json(result))
+}
+
+/// Merge tags into one on every image of a project.
+async fn handle_merge_tags(
+    State(state): State<Arc<ApiState>>,
+    Json(payload): Json<MergeTagsRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    crate::log::log(
+        "API",
+        &format!("POST /api/tags/merge → project: {}, {:?} → {}", payload.project_path, payload.sources, payload.target),
+    );
+    let MergeTagsRequest { project_path, sources, target } = payload;
+    rewrite_tags(&state, project_path, move |project| crate::ops::merge_tags(project, &sources, &target)).await
+}
+
+/// Rename a tag on every image of a project.
+async fn handle_rename_tag(
+    State(state): State<Arc<ApiState>>,
+    Json(payload): Json<RenameTagRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    crate::log::log(
+        "API",
+        &format!("POST /api/tags/rename → project: {}, {} → {}", payload.project_path, payload.from, payload.to),
+    );
+    let RenameTagRequest { project_path, from, to } = payload;
+    rewrite_tags(&state, project_path, move |project| crate::ops::rename_tag(project, &from, &to)).await
+}
+
+/// The project's tag vocabulary (empty if it has none).
+async fn handle_get_vocabulary(
+    Query(params): Query<TagsQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let project = &params.project;
+    crate::log::log("API", &format!("GET /api/vocabulary → project: {project}"));
+
+    let vocab = crate::vocab::Vocabulary::load(project)
+        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Cannot load vocabulary: {e}")))?;
+    Ok(Json(vocab))
+}
+
+/// Replace the project's vocabulary and normalize every image's tags with it.
+async fn handle_put_vocabulary(
+    State(state): State<Arc<ApiState>>,
+    Json(payload): Json<VocabularyRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    crate::log::log("API", &format!("PUT /api/vocabulary → project: {}", payload.project_path));
+    let VocabularyRequest { project_path, vocabulary } = payload;
+    rewrite_tags(&state, project_path, move |project| {
+        vocabulary.save(project)?;
+        crate::ops::normalize_tags(project)
+    })
+    .await
+}
+
+// ---------------------------------------------------------------------------
+// Boolean Queries & Smart Collections
+// ---------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+struct BooleanQuery {
+    project: String,
+    q: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SaveCollectionRequest {
+    project_path: String,
+    name: String,
+    query: String,
+    #[serde(default)]
+    replace: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CollectionImagesResponse {
+    name: String,
+    images: Vec<String>,
+}
+
+/// Missing collections are 404, name clashes 409, storage failures 500 and
+/// anything else (a malformed query or name) 400.
+fn collection_error(e: String) -> (StatusCode, Json<ErrorResponse>) {
+    let status = if e.starts_with("Collection not found") {
+        StatusCode::NOT_FOUND
+    } else if e.contains("already exists") {
+        StatusCode::CONFLICT
+    } else if e.starts_with("Cannot") || e.starts_with("Invalid collections.json") || e.contains("failed") {
+        StatusCode::INTERNAL_SERVER_ERROR
+    } else {
+        StatusCode::BAD_REQUEST
+    };
+    api_error(status, e)
+}
+
+async fn handle_query(
+    State(state): State<Arc<ApiState>>,
+    Query(params): Query<BooleanQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    crate::log::log("API", &format!("GET /api/query → project: {}, q: {}", params.project, params.q));
+    let images = state.storage.query_images(&params.project, &params.q).await.map_err(collection_error)?;
+    Ok(Json(images))
+}
+
+async fn handle_list_collections(
+    State(state): State<Arc<ApiState>>,
+    Query(params): Query<TagsQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    crate::log::log("API", &format!("GET /api/collections → project: {}", params.project));
+    let collections = state.storage.list_collections(&params.project).await.map_err(collection_error)?;
+    Ok(Json(collections))
+}
+
+async fn handle_save_collection(
+    State(state): State<Arc<ApiState>>,
+    Json(payload): Json<SaveCollectionRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    crate::log::log("API", &format!("POST /api/collections → {}: {}", payload.name, payload.query));
+    let collection = state
+        .storage
+        .save_collection(&payload.project_path, &payload.name, &payload.query, payload.replace)
+        .await
+        .map_err(collection_error)?;
+    Ok(Json(collection))
+}
+
+async fn handle_collection_images(
+    State(state): State<Arc<ApiState>>,
+    Path(name): Path<String>,
+    Query(params): Query<TagsQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    crate::log::log("API", &format!("GET /api/collections/{name} → project: {}", params.project));
+    let images = state.storage.collection_images(&params.project, &name).await.map_err(collection_error)?;
+    Ok(Json(CollectionImagesResponse { name, images }))
+}
+
+async fn handle_delete_collection(
+    State(state): State<Arc<ApiState>>,
+    Path(name): Path<String>,
+    Query(params): Query<TagsQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    crate::log::log("API", &format!("DELETE /api/collections/{name} → project: {}", params.project));
+    state.storage.delete_collection(&params.project, &name).await.map_err(collection_error)?;
+    Ok(Json(DeleteResponse {
+        success: true,
+        message: format!("Deleted collection {name}"),
+    }))
 }
 
 // ---------------------------------------------------------------------------
@@ -725,50 +1065,57 @@ async fn handle_tags(
 struct EmbedRequest {
     project_path: String,
     image_path: String,
+    /// Embedding model id (default: the configured vision model).
+    #[serde(default)]
+    model: Option<String>,
+    /// Recompute even if the stored embedding matches the file.
+    #[serde(default)]
+    force: bool,
 }
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 struct EmbedResponse {
     image_path: String,
+    model: String,
     dimensions: usize,
     embedding: Vec<f32>,
 }
 
-/// Generate (or retrieve cached) CLIP embedding for a single image.
+/// Resolve a request's optional `model`, rejecting unknown names.
+fn request_model(model: Option<&str>) -> Result<crate::embed::ImageModel, (StatusCode, Json<ErrorResponse>)> {
+    crate::embed::model_or_default(model).map_err(|e| api_error(StatusCode::BAD_REQUEST, e))
+}
+
+/// Generate (or retrieve cached) embedding for a single image.
+/// The cached vector is regenerated if the file changed since it was computed.
+/// Runs through the job queue, so it waits its turn behind other embeddings.
 async fn handle_embed(
     State(state): State<Arc<ApiState>>,
     Json(payload): Json<EmbedRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
     let project_path = payload.project_path;
     let image_path = payload.image_path;
+    let model = request_model(payload.model.as_deref())?;
+    let force = payload.force;
 
-    crate::log::log("API", &format!("POST /api/embed → {image_path}"));
-
-    // Ensure image is indexed
-    let img_path = image_path.clone();
-    let proj = project_path.clone();
-    let has = state.storage.has_embedding(&proj, &img_path).await
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    crate::log::log("API", &format!("POST /api/embed → {image_path} ({model})"));
 
-    if !has {
-        // Generate embedding via CLIP
-        let proj2 = project_path.clone();
-        let img2 = image_path.clone();
-        tokio::task::spawn_blocking(move || {
-            crate::embed::embed_and_store(&proj2, &[img2])
-        })
-        .await
-        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Task join: {e}")))?
+    let spec = crate::jobs::JobSpec::Embed {
+        project_path: project_path.clone(),
+        image_paths: Some(vec![image_path.clone()]),
+        model: Some(model),
+        force,
+    };
+    state.jobs.run(spec).await
         .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
-    }
 
     // Retrieve the embedding
     let proj3 = project_path.clone();
     let img3 = image_path.clone();
     let embedding = tokio::task::spawn_blocking(move || {
         let conn = crate::search::open_db(&proj3)?;
-        crate::search::get_embedding(&conn, &img3)
+        crate::search::get_embedding(&conn, &img3, model.id())
     })
     .await
     .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Task join: {e}")))?
@@ -777,6 +1124,7 @@ async fn handle_embed(
 
     let response = EmbedResponse {
         image_path,
+        model: model.id().to_string(),
         dimensions: embedding.len(),
         embedding,
     };
@@ -785,6 +1133,109 @@ async fn handle_embed(
     Ok(Json(response))
 }
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AnalyzeRequest {
+    project_path: String,
+    /// Image paths or filenames in images/. If empty/omitted, analyzes the entire project.
+    #[serde(default)]
+    image_paths: Option<Vec<String>>,
+    /// Call the provider even when a cached result exists.
+    #[serde(default)]
+    force: bool,
+    /// Queue a job and return it instead of waiting for the results.
+    #[serde(default)]
+    background: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AnalyzeResponse {
+    analyzed: usize,
+    failed: usize,
+    items: Vec<crate::ai::BatchAnalysisItem>,
+}
+
+/// Analyze images with the configured provider (no frontend involved),
+/// returning the items in request order.
+async fn analyze_images(
+    project_path: &str,
+    image_paths: Vec<String>,
+    force: bool,
+) -> Result<Vec<crate::ai::BatchAnalysisItem>, String> {
+    let config = crate::ai::get_ai_config()?;
+    let existing_tags = crate::ai::project_tags(project_path);
+    let mut items = Vec::with_capacity(image_paths.len());
+    crate::ai::analyze_batch(
+        Arc::new(crate::ai::NoEvents),
+        image_paths.clone(),
+        project_path,
+        &config,
+        existing_tags,
+        force,
+        |item| {
+            items.push(item);
+            Ok(())
+        },
+    )
+    .await?;
+    items.sort_by_key(|item| image_paths.iter().position(|p| p == &item.image_path));
+    Ok(items)
+}
+
+/// AI-analyze images and return the results, or queue them as a job.
+async fn handle_analyze(
+    State(state): State<Arc<ApiState>>,
+    Json(payload): Json<AnalyzeRequest>,
+) -> Result<axum::response::Response, (StatusCode, Json<ErrorResponse>)> {
+    let project_path = payload.project_path;
+    crate::log::log("API", &format!("POST /api/analyze → project: {project_path}"));
+
+    let image_paths: Vec<String> = match payload.image_paths {
+        Some(paths) if !paths.is_empty() => paths
+            .into_iter()
+            .map(|p| {
+                if std::path::Path::new(&p).is_absolute() {
+                    p
+                } else {
+                    std::path::Path::new(&project_path).join("images").join(&p).to_string_lossy().to_string()
+                }
+            })
+            .collect(),
+        _ => crate::scan_images_in(&project_path)
+            .map_err(|e| api_error(StatusCode::BAD_REQUEST, e))?
+            .into_iter()
+            .map(|img| img.path)
+            .collect(),
+    };
+    if let Some(missing) = image_paths.iter().find(|p| !std::path::Path::new(p).exists()) {
+        return Err(api_error(StatusCode::NOT_FOUND, format!("Image file not found: {missing}")));
+    }
+
+    if payload.background {
+        let provider_config = crate::ai::get_ai_config()
+            .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+        let job = state
+            .jobs
+            .submit(crate::jobs::JobSpec::Analyze {
+                existing_tags: crate::ai::project_tags(&project_path),
+                project_path,
+                image_paths,
+                provider_config: Box::new(provider_config),
+                force: payload.force,
+            })
+            .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+        return Ok((StatusCode::ACCEPTED, Json(job)).into_response());
+    }
+
+    let items = analyze_images(&project_path, image_paths, payload.force)
+        .await
+        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    let failed = items.iter().filter(|i| !i.success).count();
+    crate::log::log("API", &format!("Analyzed {} images ({failed} failed)", items.len()));
+    Ok(Json(AnalyzeResponse { analyzed: items.len() - failed, failed, items }).into_response())
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct EmbedBatchRequest {
@@ -792,51 +1243,81 @@ struct EmbedBatchRequest {
     /// Optional list of image paths. If empty/omitted, embeds the entire project.
     #[serde(default)]
     image_paths: Option<Vec<String>>,
+    /// Embedding model id (default: the configured vision model).
+    #[serde(default)]
+    model: Option<String>,
+    /// Re-embed every image, not just new or changed ones.
+    #[serde(default)]
+    force: bool,
 }
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
 struct EmbedBatchResponse {
+    /// Embeddings computed by this request (`fresh + stale`).
     embedded: usize,
+    fresh: usize,
+    stale: usize,
+    reused: usize,
     total_images: usize,
 }
 
-/// Batch-generate CLIP embeddings for multiple images (or entire project).
+impl EmbedBatchResponse {
+    fn new(stats: crate::embed::EmbedStats, total_images: usize) -> Self {
+        Self {
+            embedded: stats.embedded(),
+            fresh: stats.fresh,
+            stale: stats.stale,
+            reused: stats.reused,
+            total_images,
+        }
+    }
+}
+
+/// Batch-generate embeddings for multiple images (or entire project).
 async fn handle_embed_batch(
     State(state): State<Arc<ApiState>>,
     Json(payload): Json<EmbedBatchRequest>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
     let project_path = payload.project_path;
+    let model = request_model(payload.model.as_deref())?;
+    let force = payload.force;
 
-    crate::log::log("API", &format!("POST /api/embed-batch → project: {project_path}"));
+    crate::log::log("API", &format!("POST /api/embed-batch → project: {project_path} ({model})"));
 
     let result = match payload.image_paths {
         Some(paths) if !paths.is_empty() => {
             let total = paths.len();
             let proj = project_path.clone();
-            let embedded = tokio::task::spawn_blocking(move || {
-                crate::embed::embed_and_store(&proj, &paths)
+            let stats = tokio::task::spawn_blocking(move || {
+                crate::embed::embed_and_store(&proj, &paths, model, force)
             })
             .await
             .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Task join: {e}")))?
             .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
-            EmbedBatchResponse { embedded, total_images: total }
+            EmbedBatchResponse::new(stats, total)
         }
         _ => {
             // Embed entire project
             let proj = project_path.clone();
-            let embedded = state.storage.embed_project(&proj).await
+            let stats = state.storage.embed_project(&proj, model, force).await
                 .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
             let images = crate::scan_images_in(&project_path)
                 .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
-            EmbedBatchResponse { embedded, total_images: images.len() }
+            EmbedBatchResponse::new(stats, images.len())
         }
     };
 
-    crate::log::log("API", &format!("Batch embed: {}/{} new", result.embedded, result.total_images));
+    crate::log::log(
+        "API",
+        &format!(
+            "Batch embed: {} new, {} changed, {} reused of {}",
+            result.fresh, result.stale, result.reused, result.total_images
+        ),
+    );
     Ok(Json(result))
 }
 
@@ -847,6 +1328,9 @@ struct SimilarRequest {
     image_path: String,
     #[serde(default = "default_limit")]
     limit: usize,
+    /// Embedding model to compare in (default: the configured vision model).
+    #[serde(default)]
+    model: Option<String>,
 }
 
 fn default_limit() -> usize { 10 }
@@ -869,7 +1353,7 @@ struct SimilarItem {
     tags: Vec<String>,
 }
 
-/// Find visually similar images using CLIP embeddings (falls back to tag similarity).
+/// Find visually similar images using one model's embeddings (falls back to tag similarity).
 async fn handle_similar(
     State(state): State<Arc<ApiState>>,
     Json(payload): Json<SimilarRequest>,
@@ -877,10 +1361,11 @@ async fn handle_similar(
     let project_path = payload.project_path;
     let image_path = payload.image_path;
     let limit = payload.limit;
+    let model = request_model(payload.model.as_deref())?;
 
-    crate::log::log("API", &format!("POST /api/similar → {image_path} (top {limit})"));
+    crate::log::log("API", &format!("POST /api/similar → {image_path} (top {limit}, {model})"));
 
-    let results = state.storage.find_similar(&project_path, &image_path, limit).await
+    let results = state.storage.find_similar(&project_path, &image_path, model, limit).await
         .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
     let items: Vec<SimilarItem> = results
@@ -916,8 +1401,8 @@ struct SemanticSearchResponse {
 
 /// Text-to-image semantic search.
 ///
-/// Uses FTS5 full-text search over image metadata (description, tags, style, mood).
-/// A future version could use CLIP text encoder for true cross-modal search.
+/// Encodes the query with the CLIP text encoder and ranks image embeddings by
+/// cosine similarity. Falls back to FTS5 when no embeddings are available.
 async fn handle_search_semantic(
     State(state): State<Arc<ApiState>>,
     Json(payload): Json<SemanticSearchRequest>,
@@ -928,7 +1413,7 @@ async fn handle_search_semantic(
 
     crate::log::log("API", &format!("POST /api/search-semantic → \"{query}\" (top {limit})"));
 
-    let results = state.storage.search_text(&project_path, &query, limit).await
+    let results = state.storage.semantic_search(&project_path, &query, limit).await
         .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
 
     let items: Vec<SimilarItem> = results
@@ -953,6 +1438,9 @@ struct ClusterRequest {
     /// Cosine similarity threshold for grouping (0.0–1.0). Default 0.7.
     #[serde(default = "default_threshold")]
     threshold: f64,
+    /// Embedding model to cluster in (default: the configured vision model).
+    #[serde(default)]
+    model: Option<String>,
 }
 
 fn default_threshold() -> f64 { 0.7 }
@@ -973,7 +1461,7 @@ struct Cluster {
     images: Vec<String>,
 }
 
-/// Auto-cluster images by visual similarity using CLIP embeddings.
+/// Auto-cluster images by visual similarity using one model's embeddings.
 ///
 /// Uses greedy agglomerative clustering: picks the first unassigned image as
 /// a seed, groups all images within the cosine similarity `threshold`, repeats.
@@ -983,11 +1471,12 @@ async fn handle_cluster(
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
     let project_path = payload.project_path;
     let threshold = payload.threshold;
+    let model = request_model(payload.model.as_deref())?;
 
-    crate::log::log("API", &format!("POST /api/cluster → threshold: {threshold}"));
+    crate::log::log("API", &format!("POST /api/cluster → threshold: {threshold} ({model})"));
 
     let all_embeddings = tokio::task::spawn_blocking(move || {
-        crate::search::get_all_embeddings(&project_path)
+        crate::search::get_all_embeddings(&project_path, model.id())
     })
     .await
     .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Task join: {e}")))?
@@ -1013,15 +1502,184 @@ async fn handle_cluster(
     }))
 }
 
+// ---------------------------------------------------------------------------
+// Thumbnails
+// ---------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ThumbnailQuery {
+    project: String,
+    filename: String,
+    #[serde(default)]
+    size: Option<String>,
+}
+
+/// Serve a thumbnail so clients don't have to load the full-resolution original.
+async fn handle_thumbnail(
+    State(state): State<Arc<ApiState>>,
+    Query(params): Query<ThumbnailQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let project = &params.project;
+    let filename = &params.filename;
+    crate::log::log("API", &format!("GET /api/thumbnail → project: {project}, filename: {filename}"));
+
+    let size = match params.size.as_deref() {
+        Some(s) => s.parse::<crate::thumbs::ThumbSize>()
+            .map_err(|e| api_error(StatusCode::BAD_REQUEST, e))?,
+        None => crate::thumbs::ThumbSize::default(),
+    };
+
+    // Filenames must stay inside the project's images directory
+    if filename.is_empty() || filename.contains(['/', '\\']) || filename == ".." {
+        return Err(api_error(StatusCode::BAD_REQUEST, format!("Invalid filename: {filename}")));
+    }
+    let image_path = std::path::Path::new(project).join("images").join(filename);
+    if !image_path.is_file() {
+        return Err(api_error(StatusCode::NOT_FOUND, format!("Image not found: {filename}")));
+    }
+
+    let thumb = state.storage
+        .get_thumbnail(project, &image_path.to_string_lossy(), size).await
+        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Thumbnail failed: {e}")))?;
+    let thumb = std::path::PathBuf::from(thumb);
+
+    let bytes = tokio::fs::read(&thumb).await
+        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Cannot read thumbnail: {e}")))?;
+    let ext = thumb.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    let content_type = match ext.as_str() {
+        "jpg" | "jpeg" => "image/jpeg",
+        "webp" => "image/webp",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "avif" => "image/avif",
+        "bmp" => "image/bmp",
+        "tiff" => "image/tiff",
+        _ => "application/octet-stream",
+    };
+
+    Ok(([(header::CONTENT_TYPE, content_type)], bytes))
+}
+
+// ---------------------------------------------------------------------------
+// Duplicate Detection
+// ---------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DuplicatesQuery {
+    project: String,
+    #[serde(default = "default_duplicate_distance")]
+    max_distance: u32,
+}
+
+fn default_duplicate_distance() -> u32 {
+    crate::search::DEFAULT_DUPLICATE_DISTANCE
+}
+
+/// Groups of near-identical images (perceptual hash within `maxDistance` bits).
+async fn handle_duplicates(
+    State(state): State<Arc<ApiState>>,
+    Query(params): Query<DuplicatesQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let project = &params.project;
+    let max_distance = params.max_distance;
+    crate::log::log("API", &format!("GET /api/duplicates → project: {project}, maxDistance: {max_distance}"));
+
+    let groups = state.storage.find_duplicates(project, max_distance).await
+        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Duplicate search failed: {e}")))?;
+
+    crate::log::log("API", &format!("Duplicates: {} groups", groups.len()));
+    Ok(Json(groups))
+}
+
+// ---------------------------------------------------------------------------
+// Maintenance
+// ---------------------------------------------------------------------------
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DoctorRequest {
+    project_path: String,
+    #[serde(default)]
+    fix: bool,
+}
+
+/// Integrity check of a project (orphans, missing rows, bad vectors, FTS),
+/// optionally repairing it.
+async fn handle_doctor(
+    State(state): State<Arc<ApiState>>,
+    Json(payload): Json<DoctorRequest>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let project = &payload.project_path;
+    let fix = payload.fix;
+    crate::log::log("API", &format!("POST /api/doctor → project: {project}, fix: {fix}"));
+
+    let report = state.storage.check_project(project, fix).await
+        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, format!("Doctor failed: {e}")))?;
+
+    crate::log::log("API", &format!("Doctor: {} issues, {} remaining", report.issues.len(), report.remaining()));
+    Ok(Json(report))
+}
+
+// ---------------------------------------------------------------------------
+// Jobs
+// ---------------------------------------------------------------------------
+
+/// Map a job queue error to a status: unknown ids are 404, the rest 409.
+fn job_error(e: String) -> (StatusCode, Json<ErrorResponse>) {
+    let status = if e.starts_with("No job") { StatusCode::NOT_FOUND } else { StatusCode::CONFLICT };
+    api_error(status, e)
+}
+
+/// All queued, running and recently finished jobs, oldest first.
+async fn handle_list_jobs(
+    State(state): State<Arc<ApiState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    let jobs = state.jobs.store().list()
+        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(jobs))
+}
+
+/// Queue a job. The body is a `JobSpec` tagged by `kind`.
+async fn handle_enqueue_job(
+    State(state): State<Arc<ApiState>>,
+    Json(spec): Json<crate::jobs::JobSpec>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    crate::log::log("API", &format!("POST /api/jobs → {} ({})", spec.kind().label(), spec.project_path()));
+    let job = state.jobs.submit(spec)
+        .map_err(|e| api_error(StatusCode::INTERNAL_SERVER_ERROR, e))?;
+    Ok(Json(job))
+}
+
+async fn handle_cancel_job(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<u64>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    crate::log::log("API", &format!("POST /api/jobs/{id}/cancel"));
+    let job = state.jobs.cancel(id).map_err(job_error)?;
+    Ok(Json(job))
+}
+
+async fn handle_retry_job(
+    State(state): State<Arc<ApiState>>,
+    Path(id): Path<u64>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    crate::log::log("API", &format!("POST /api/jobs/{id}/retry"));
+    let job = state.jobs.retry(id).map_err(job_error)?;
+    Ok(Json(job))
+}
+
 // ---------------------------------------------------------------------------
 // Server Lifecycle
 // ---------------------------------------------------------------------------
 
 /// Start the HTTP API server in the background.
 /// Called from Tauri's setup hook.
-pub async fn start_server(app: AppHandle, storage: crate::storage::Storage) {
+pub async fn start_server(app: AppHandle, storage: crate::storage::Storage, jobs: crate::jobs::Jobs) {
     let port = storage.get_api_port().await;
-    let state = Arc::new(ApiState { app, storage });
+    let state = Arc::new(ApiState { app, storage, jobs });
 
     let router = Router::new()
         .route("/api/status", get(handle_status))
@@ -1029,17 +1687,30 @@ pub async fn start_server(app: AppHandle, storage: crate::storage::Storage) {
         .route("/api/list", get(handle_list))
         .route("/api/search", get(handle_search))
         .route("/api/info", get(handle_info))
+        .route("/api/thumbnail", get(handle_thumbnail))
         .route("/api/tags", get(handle_tags))
+        .route("/api/tags/merge", post(handle_merge_tags))
+        .route("/api/tags/rename", post(handle_rename_tag))
+        .route("/api/vocabulary", get(handle_get_vocabulary).put(handle_put_vocabulary))
+        .route("/api/query", get(handle_query))
+        .route("/api/collections", get(handle_list_collections).post(handle_save_collection))
+        .route("/api/collections/{name}", get(handle_collection_images).delete(handle_delete_collection))
         .route("/api/import", post(handle_import))
         .route("/api/delete", delete(handle_delete))
         .route("/api/move", post(handle_move))
         .route("/api/item", patch(handle_update_item))
         // CLIP embedding & similarity endpoints
+        .route("/api/analyze", post(handle_analyze))
         .route("/api/embed", post(handle_embed))
         .route("/api/embed-batch", post(handle_embed_batch))
         .route("/api/similar", post(handle_similar))
         .route("/api/search-semantic", post(handle_search_semantic))
         .route("/api/cluster", post(handle_cluster))
+        .route("/api/duplicates", get(handle_duplicates))
+        .route("/api/doctor", post(handle_doctor))
+        .route("/api/jobs", get(handle_list_jobs).post(handle_enqueue_job))
+        .route("/api/jobs/{id}/cancel", post(handle_cancel_job))
+        .route("/api/jobs/{id}/retry", post(handle_retry_job))
         .with_state(state);
 
     let addr = format!("127.0.0.1:{port}");
@@ -1067,3 +1738,52 @@ pub async fn cmd_get_api_port(
 ) -> Result<u16, String> {
     Ok(storage.get_api_port().await)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rating_only_patch_keeps_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_string_lossy().to_string();
+        let image_path = dir.path().join("images").join("a.jpg").to_string_lossy().to_string();
+        let conn = crate::search::open_db(&project).unwrap();
+        crate::search::upsert_image(&conn, &crate::search::ImageMetadataRow {
+            image_path: image_path.clone(),
+            name: "a.jpg".to_string(),
+            description: Some("Brass wall sconce".to_string()),
+            tags: vec!["lamp".to_string()],
+            colors: vec!["#c0a060".to_string()],
+            ..Default::default()
+        }, &Default::default()).unwrap();
+
+        let patch = |body: serde_json::Value| -> UpdateItemRequest {
+            let mut body = body;
+            body["projectPath"] = serde_json::json!(project);
+            body["filename"] = serde_json::json!("a.jpg");
+            serde_json::from_value(body).unwrap()
+        };
+
+        let meta = update_item(patch(serde_json::json!({ "rating": 4, "notes": "lobby" })))
+            .map_err(|(_, Json(e))| e.error)
+            .unwrap();
+        assert_eq!(meta.description.as_deref(), Some("Brass wall sconce"));
+        assert_eq!(meta.tags, vec!["lamp"]);
+        assert_eq!(meta.annotations.as_ref().unwrap().rating, Some(4));
+
+        let saved = crate::search::get_image_metadata(&project, &image_path).unwrap().unwrap();
+        assert_eq!(saved.description.as_deref(), Some("Brass wall sconce"));
+        assert_eq!(saved.tags, vec!["lamp"]);
+        assert_eq!(saved.colors, vec!["#c0a060"]);
+        assert_eq!(saved.annotations.unwrap().notes.as_deref(), Some("lobby"));
+        assert_eq!(crate::search::get_images_by_tag(&project, "lamp").unwrap(), vec![image_path.clone()]);
+
+        // An out-of-range rating is a bad request and changes nothing
+        let (status, _) = update_item(patch(serde_json::json!({ "rating": 6, "tags": ["chrome"] }))).unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let saved = crate::search::get_image_metadata(&project, &image_path).unwrap().unwrap();
+        assert_eq!(saved.tags, vec!["lamp"]);
+        assert_eq!(saved.annotations.unwrap().rating, Some(4));
+    }
+}
@@ -1,10 +1,10 @@
 //! Deco CLI command definitions and handlers.
 //!
 //! Phase 1 commands: status, list, import, delete, search.
-//! Phase 2 commands: embed, similar, semantic, cluster, info, tags.
+//! Phase 2 commands: embed, analyze, similar, semantic, cluster, dupes, watch, doctor, jobs, info, tags.
 //! All commands call shared business logic directly (no Tauri, no HTTP API).
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
 use std::path::Path;
 
 // ---------------------------------------------------------------------------
@@ -48,6 +48,10 @@ pub enum Command {
         /// Target project directory
         #[arg(short, long)]
         project: String,
+        /// When a file's content already exists: skip, link or keep-both
+        /// (default from config, else link)
+        #[arg(long)]
+        on_duplicate: Option<String>,
     },
 
     /// Delete an image from a project
@@ -59,7 +63,7 @@ pub enum Command {
         project: String,
     },
 
-    /// Search images by text query (FTS5)
+    /// Search images by text query (FTS5; supports tag:, style:, era:, -mood:, color:#hex)
     Search {
         /// Search query
         query: String,
@@ -69,6 +73,18 @@ pub enum Command {
         /// Maximum number of results
         #[arg(short = 'n', long, default_value = "20")]
         limit: usize,
+        /// Fuse full-text results with CLIP semantic similarity
+        #[arg(long)]
+        hybrid: bool,
+        /// Hybrid fusion method: rrf or weighted
+        #[arg(long, default_value = "rrf", requires = "hybrid")]
+        fusion: String,
+        /// Hybrid weight of the full-text ranking
+        #[arg(long, default_value = "0.5", requires = "hybrid")]
+        text_weight: f64,
+        /// Hybrid weight of the semantic ranking
+        #[arg(long, default_value = "0.5", requires = "hybrid")]
+        semantic_weight: f64,
     },
 
     /// Generate CLIP embeddings for images in a project
@@ -76,12 +92,34 @@ pub enum Command {
         /// Project directory path
         #[arg(short, long)]
         project: String,
-        /// Embed all images (including already embedded)
+        /// Re-embed all images, including unchanged ones
+        #[arg(long)]
+        all: bool,
+        /// Rebuild the similarity index from stored embeddings
         #[arg(long)]
+        rebuild_index: bool,
+        /// Embedding model (clip-vit-b-32, unicom-vit-b-16, nomic-embed-vision-v1.5, resnet50;
+        /// default: visionModel from config)
+        #[arg(short, long)]
+        model: Option<crate::embed::ImageModel>,
+    },
+
+    /// Describe and tag images with the configured AI provider
+    Analyze {
+        /// Image paths or filenames in the project's images/ folder
+        files: Vec<String>,
+        /// Project directory path
+        #[arg(short, long)]
+        project: String,
+        /// Analyze every image in the project
+        #[arg(long, conflicts_with = "files")]
         all: bool,
+        /// Call the provider even when a cached result exists
+        #[arg(long)]
+        no_cache: bool,
     },
 
-    /// Find visually similar images using CLIP cosine similarity
+    /// Find visually similar images by embedding cosine similarity
     Similar {
         /// Image path to find similarities for
         image_path: String,
@@ -91,9 +129,12 @@ pub enum Command {
         /// Maximum number of results
         #[arg(short = 'n', long, default_value = "10")]
         limit: usize,
+        /// Embedding model to compare in (default: visionModel from config)
+        #[arg(short, long)]
+        model: Option<crate::embed::ImageModel>,
     },
 
-    /// Text-to-image semantic search (currently FTS5, CLIP text encoder planned)
+    /// Text-to-image semantic search using the CLIP text encoder
     Semantic {
         /// Search query
         query: String,
@@ -116,6 +157,45 @@ pub enum Command {
         /// Cosine similarity threshold for grouping (0.0-1.0)
         #[arg(short, long, default_value = "0.7")]
         threshold: f64,
+        /// Embedding model to cluster in (default: visionModel from config)
+        #[arg(short, long)]
+        model: Option<crate::embed::ImageModel>,
+    },
+
+    /// Find groups of near-identical images (perceptual hash)
+    Dupes {
+        /// Project directory path
+        #[arg(short, long)]
+        project: String,
+        /// Max differing hash bits (of 64) to count as a duplicate
+        #[arg(short = 'd', long, default_value_t = crate::search::DEFAULT_DUPLICATE_DISTANCE)]
+        max_distance: u32,
+    },
+
+    /// Keep the index in sync with the project's images/ folder
+    Watch {
+        /// Project directory path
+        #[arg(short, long)]
+        project: String,
+        /// Reconcile once and exit instead of watching
+        #[arg(long)]
+        once: bool,
+    },
+
+    /// Check the project's index, board and metadata for inconsistencies
+    Doctor {
+        /// Project directory path
+        #[arg(short, long)]
+        project: String,
+        /// Repair what can be repaired
+        #[arg(long)]
+        fix: bool,
+    },
+
+    /// List, cancel or retry background jobs (embedding, analysis, downloads)
+    Jobs {
+        #[command(subcommand)]
+        action: JobsAction,
     },
 
     /// Show metadata for a single image
@@ -127,11 +207,23 @@ pub enum Command {
         project: String,
     },
 
-    /// List all tags in the project with counts
+    /// List all tags in the project with counts, or merge and rename them
+    #[command(subcommand_negates_reqs = true)]
     Tags {
         /// Project directory path
-        #[arg(short, long)]
-        project: String,
+        #[arg(short, long, required = true)]
+        project: Option<String>,
+        /// Show hierarchical tags (`material/bronze`) as a tree
+        #[arg(long)]
+        tree: bool,
+        #[command(subcommand)]
+        action: Option<TagsAction>,
+    },
+
+    /// Save, list and show smart collections (saved boolean image queries)
+    Collection {
+        #[command(subcommand)]
+        action: CollectionAction,
     },
 
     /// List all known projects (recent + default folder)
@@ -174,6 +266,144 @@ pub enum Command {
         /// Era string
         #[arg(long)]
         era: Option<String>,
+        #[command(flatten)]
+        annotations: Box<AnnotationArgs>,
+    },
+}
+
+/// Notes, rating and attribution options for `deco update`. An empty value
+/// clears a text field.
+#[derive(Args, Debug, Default)]
+pub struct AnnotationArgs {
+    /// URL the image file came from
+    #[arg(long)]
+    source_url: Option<String>,
+    /// Page the image was found on
+    #[arg(long)]
+    source_page: Option<String>,
+    /// License, e.g. "CC BY 4.0"
+    #[arg(long)]
+    license: Option<String>,
+    /// Credit line to show with the image
+    #[arg(long)]
+    credit: Option<String>,
+    /// Author or photographer
+    #[arg(long)]
+    author: Option<String>,
+    /// Rating from 0 to 5
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=5))]
+    rating: Option<u8>,
+    /// Mark (true) or unmark (false) as a favorite
+    #[arg(long)]
+    favorite: Option<bool>,
+    /// Free-form notes (searchable)
+    #[arg(long)]
+    notes: Option<String>,
+}
+
+impl From<AnnotationArgs> for crate::search::AnnotationUpdate {
+    fn from(args: AnnotationArgs) -> Self {
+        Self {
+            source_url: args.source_url,
+            source_page: args.source_page,
+            license: args.license,
+            credit: args.credit,
+            author: args.author,
+            rating: args.rating,
+            favorite: args.favorite,
+            notes: args.notes,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum JobsAction {
+    /// List queued and running jobs
+    List {
+        /// Include finished jobs
+        #[arg(long)]
+        all: bool,
+    },
+    /// Cancel a queued or running job
+    Cancel {
+        /// Job id
+        id: u64,
+    },
+    /// Re-queue a failed or cancelled job
+    Retry {
+        /// Job id
+        id: u64,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TagsAction {
+    /// Merge tags into one: images tagged with a source get the target, and
+    /// the sources become synonyms of it in the project's vocabulary
+    Merge {
+        /// Tags to merge away
+        #[arg(required = true, num_args = 1..)]
+        sources: Vec<String>,
+        /// Tag they become
+        target: String,
+        /// Project directory path
+        #[arg(short, long)]
+        project: String,
+    },
+    /// Rename a tag on every image
+    Rename {
+        /// Current tag
+        from: String,
+        /// New tag
+        to: String,
+        /// Project directory path
+        #[arg(short, long)]
+        project: String,
+    },
+    /// Re-apply the project's vocabulary (.deco/vocabulary.json) to every image
+    Normalize {
+        /// Project directory path
+        #[arg(short, long)]
+        project: String,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum CollectionAction {
+    /// Save a query as a collection, e.g. `material/bronze -mood:dark width:>=2000`
+    Create {
+        /// Collection name
+        name: String,
+        /// Boolean query over tags, style, mood, era, color and dimensions
+        query: String,
+        /// Overwrite an existing collection with this name
+        #[arg(long)]
+        replace: bool,
+        /// Project directory path
+        #[arg(short, long)]
+        project: String,
+    },
+    /// List collections with their current image counts
+    List {
+        /// Project directory path
+        #[arg(short, long)]
+        project: String,
+    },
+    /// Show the images currently in a collection
+    Show {
+        /// Collection name
+        name: String,
+        /// Project directory path
+        #[arg(short, long)]
+        project: String,
+    },
+    /// Delete a collection (its images are untouched)
+    Delete {
+        /// Collection name
+        name: String,
+        /// Project directory path
+        #[arg(short, long)]
+        project: String,
     },
 }
 
@@ -186,19 +416,61 @@ pub async fn run(cli: Cli) -> Result<(), String> {
     match cli.command {
         Command::Status { project } => cmd_status(project, cli.json),
         Command::List { project } => cmd_list(&project, cli.json),
-        Command::Import { paths, project } => cmd_import(paths, &project, cli.json),
+        Command::Import {
+            paths,
+            project,
+            on_duplicate,
+        } => {
+            let policy = match on_duplicate {
+                Some(p) => p.parse()?,
+                None => crate::dedup::default_policy(),
+            };
+            cmd_import(paths, &project, policy, cli.json)
+        }
         Command::Delete { filename, project } => cmd_delete(&filename, &project, cli.json),
         Command::Search {
             query,
             project,
             limit,
-        } => cmd_search(&query, &project, limit, cli.json),
-        Command::Embed { project, all } => cmd_embed(&project, all, cli.json),
+            hybrid,
+            fusion,
+            text_weight,
+            semantic_weight,
+        } => {
+            let hybrid = if hybrid {
+                let fusion = match fusion.as_str() {
+                    "rrf" => crate::search::Fusion::Rrf,
+                    "weighted" => crate::search::Fusion::Weighted,
+                    other => return Err(format!("Unknown fusion method: {other} (expected rrf or weighted)")),
+                };
+                Some(crate::search::HybridOptions {
+                    fusion,
+                    text_weight,
+                    semantic_weight,
+                })
+            } else {
+                None
+            };
+            cmd_search(&query, &project, limit, hybrid, cli.json)
+        }
+        Command::Embed {
+            project,
+            all,
+            rebuild_index,
+            model,
+        } => cmd_embed(&project, all, rebuild_index, model, cli.json),
+        Command::Analyze {
+            files,
+            project,
+            all,
+            no_cache,
+        } => cmd_analyze(files, &project, all, no_cache, cli.json).await,
         Command::Similar {
             image_path,
             project,
             limit,
-        } => cmd_similar(&image_path, &project, limit, cli.json),
+            model,
+        } => cmd_similar(&image_path, &project, limit, model, cli.json),
         Command::Semantic {
             query,
             project,
@@ -208,12 +480,24 @@ pub async fn run(cli: Cli) -> Result<(), String> {
             project,
             num_clusters: _,
             threshold,
-        } => cmd_cluster(&project, threshold, cli.json),
+            model,
+        } => cmd_cluster(&project, threshold, model, cli.json),
+        Command::Dupes {
+            project,
+            max_distance,
+        } => cmd_dupes(&project, max_distance, cli.json),
+        Command::Watch { project, once } => cmd_watch(&project, once, cli.json),
+        Command::Doctor { project, fix } => cmd_doctor(&project, fix, cli.json),
+        Command::Jobs { action } => cmd_jobs(action, cli.json),
         Command::Info {
             image_path,
             project,
         } => cmd_info(&image_path, &project, cli.json),
-        Command::Tags { project } => cmd_tags(&project, cli.json),
+        Command::Tags { project, tree, action } => match action {
+            None => cmd_tags(&project.unwrap_or_default(), tree, cli.json),
+            Some(action) => cmd_tags_rewrite(action, cli.json),
+        },
+        Command::Collection { action } => cmd_collection(action, cli.json),
         Command::Projects => cmd_projects(cli.json),
         Command::Move {
             filename,
@@ -229,7 +513,8 @@ pub async fn run(cli: Cli) -> Result<(), String> {
             styles,
             moods,
             era,
-        } => cmd_update(&filename, &project, description, tags, styles, moods, era, cli.json),
+            annotations,
+        } => cmd_update(&filename, &project, description, tags, styles, moods, era, *annotations, cli.json),
     }
 }
 
@@ -254,6 +539,12 @@ fn cmd_status(project: Option<String>, json: bool) -> Result<(), String> {
     } else {
         (None, None)
     };
+    let cache = match (&project, indexed) {
+        (Some(project_path), Some(true)) => crate::search::open_db(project_path)
+            .and_then(|conn| crate::analysis_cache::stats(&conn))
+            .ok(),
+        _ => None,
+    };
 
     if json {
         let mut obj = serde_json::json!({
@@ -268,6 +559,9 @@ fn cmd_status(project: Option<String>, json: bool) -> Result<(), String> {
         if let Some(i) = indexed {
             obj["indexed"] = serde_json::json!(i);
         }
+        if let Some(ref c) = cache {
+            obj["analysisCache"] = serde_json::json!(c);
+        }
         println!("{}", serde_json::to_string_pretty(&obj).unwrap());
     } else {
         println!("Deco v{version}");
@@ -279,6 +573,9 @@ fn cmd_status(project: Option<String>, json: bool) -> Result<(), String> {
             if let Some(i) = indexed {
                 println!("Indexed: {}", if i { "yes" } else { "no" });
             }
+            if let Some(ref c) = cache {
+                println!("Analysis cache: {} result(s), {} hit(s)", c.entries, c.hits);
+            }
         }
     }
 
@@ -308,66 +605,23 @@ fn cmd_list(project: &str, json: bool) -> Result<(), String> {
 }
 
 /// Import image files into a project.
-fn cmd_import(paths: Vec<String>, project: &str, json: bool) -> Result<(), String> {
+fn cmd_import(
+    paths: Vec<String>,
+    project: &str,
+    policy: crate::dedup::DuplicatePolicy,
+    json: bool,
+) -> Result<(), String> {
     if paths.is_empty() {
         return Err("No file paths provided".to_string());
     }
 
-    let images_dir = Path::new(project).join("images");
-    std::fs::create_dir_all(&images_dir)
-        .map_err(|e| format!("Cannot create images dir: {e}"))?;
-
     let mut imported: Vec<crate::ImageInfo> = Vec::new();
     let mut errors: Vec<String> = Vec::new();
 
     for src_path_str in &paths {
-        let src = Path::new(src_path_str);
-        if !src.is_file() {
-            errors.push(format!("Not a file: {src_path_str}"));
-            continue;
-        }
-
-        let ext = match src.extension() {
-            Some(e) => e.to_string_lossy().to_lowercase(),
-            None => {
-                errors.push(format!("No extension: {src_path_str}"));
-                continue;
-            }
-        };
-
-        // Validate extension using the same list as the core library
-        let valid_extensions = ["png", "jpg", "jpeg", "gif", "webp", "svg", "bmp", "avif", "tiff"];
-        if !valid_extensions.contains(&ext.as_str()) {
-            errors.push(format!("Unsupported format: {src_path_str}"));
-            continue;
-        }
-
-        // Determine destination filename, adding counter suffix if needed
-        let stem = src
-            .file_stem()
-            .map(|s| s.to_string_lossy().to_string())
-            .unwrap_or_else(|| "image".to_string());
-
-        let mut dest = images_dir.join(format!("{stem}.{ext}"));
-        let mut counter = 2u32;
-        while dest.exists() {
-            dest = images_dir.join(format!("{stem}-{counter}.{ext}"));
-            counter += 1;
-        }
-
-        match std::fs::copy(src, &dest) {
-            Ok(_) => {
-                let metadata = std::fs::metadata(&dest).map_err(|e| e.to_string())?;
-                imported.push(crate::ImageInfo {
-                    name: dest.file_name().unwrap().to_string_lossy().to_string(),
-                    path: dest.to_string_lossy().to_string(),
-                    size_bytes: metadata.len(),
-                    extension: ext,
-                });
-            }
-            Err(e) => {
-                errors.push(format!("Cannot copy {src_path_str}: {e}"));
-            }
+        match crate::import_image_file(Path::new(src_path_str), project, policy) {
+            Ok(info) => imported.push(info),
+            Err(e) => errors.push(e),
         }
     }
 
@@ -383,7 +637,21 @@ fn cmd_import(paths: Vec<String>, project: &str, json: bool) -> Result<(), Strin
         );
     } else {
         for img in &imported {
-            println!("Imported: {}", img.name);
+            match &img.duplicate {
+                None => println!("Imported: {}", img.name),
+                Some(dup) => match dup.action {
+                    crate::dedup::DuplicatePolicy::Skip => {
+                        println!("Skipped: already in project as {}", img.name)
+                    }
+                    crate::dedup::DuplicatePolicy::Link => {
+                        println!("Linked to existing: {}", img.name)
+                    }
+                    crate::dedup::DuplicatePolicy::KeepBoth => println!(
+                        "Imported: {} (duplicate of {})",
+                        img.name, dup.existing_path
+                    ),
+                },
+            }
         }
         for err in &errors {
             eprintln!("  Warning: {err}");
@@ -410,18 +678,10 @@ fn cmd_delete(filename: &str, project: &str, json: bool) -> Result<(), String> {
     std::fs::remove_file(&file_path)
         .map_err(|e| format!("Cannot delete {}: {e}", file_path.display()))?;
 
-    // Remove from search index (best-effort)
+    // Remove thumbnails and search index entries (best-effort)
     let full_path = file_path.to_string_lossy().to_string();
-    if let Ok(conn) = crate::search::open_db(project) {
-        let _ = conn.execute(
-            "DELETE FROM images WHERE path = ?1",
-            rusqlite::params![full_path],
-        );
-        let _ = conn.execute(
-            "DELETE FROM embeddings WHERE path = ?1",
-            rusqlite::params![full_path],
-        );
-    }
+    crate::thumbs::remove_for(project, &full_path);
+    let _ = crate::search::delete_image_data(project, &full_path);
 
     if json {
         let output = serde_json::json!({
@@ -437,8 +697,17 @@ fn cmd_delete(filename: &str, project: &str, json: bool) -> Result<(), String> {
 }
 
 /// Search images by text query using FTS5.
-fn cmd_search(query: &str, project: &str, limit: usize, json: bool) -> Result<(), String> {
-    let results = crate::search::search_text(project, query, limit)?;
+fn cmd_search(
+    query: &str,
+    project: &str,
+    limit: usize,
+    hybrid: Option<crate::search::HybridOptions>,
+    json: bool,
+) -> Result<(), String> {
+    let results = match hybrid {
+        Some(options) => crate::search::hybrid_search(project, query, limit, &options)?,
+        None => crate::search::search_text(project, query, limit)?,
+    };
 
     if json {
         let output = serde_json::to_string_pretty(&results)
@@ -462,10 +731,27 @@ fn cmd_search(query: &str, project: &str, limit: usize, json: bool) -> Result<()
                 } else {
                     format!(" [{}]", r.tags.join(", "))
                 };
-                println!(
-                    "  {:.2}  {}{}  {}",
-                    r.score, r.name, tags, desc
-                );
+                if r.matched_by.is_empty() {
+                    println!(
+                        "  {:.2}  {}{}  {}",
+                        r.score, r.name, tags, desc
+                    );
+                } else {
+                    // Fused scores are small (RRF) — show more precision and the signals
+                    let signals = r
+                        .matched_by
+                        .iter()
+                        .map(|m| match m {
+                            crate::search::MatchSignal::Text => "text",
+                            crate::search::MatchSignal::Semantic => "semantic",
+                        })
+                        .collect::<Vec<_>>()
+                        .join("+");
+                    println!(
+                        "  {:.4}  {}{} ({})  {}",
+                        r.score, r.name, tags, signals, desc
+                    );
+                }
             }
         }
     }
@@ -477,13 +763,33 @@ fn cmd_search(query: &str, project: &str, limit: usize, json: bool) -> Result<()
 // Phase 2: CLIP Embedding & Similarity Commands
 // ---------------------------------------------------------------------------
 
-/// Generate CLIP embeddings for images in a project.
-fn cmd_embed(project: &str, _all: bool, json: bool) -> Result<(), String> {
+/// Generate image embeddings for a project with one model.
+fn cmd_embed(
+    project: &str,
+    all: bool,
+    rebuild_index: bool,
+    model: Option<crate::embed::ImageModel>,
+    json: bool,
+) -> Result<(), String> {
+    if rebuild_index {
+        let indexed = crate::search::rebuild_ann_index(project)?;
+        if json {
+            let output = serde_json::json!({ "indexed": indexed });
+            println!("{}", serde_json::to_string_pretty(&output).unwrap());
+        } else {
+            println!("Rebuilt similarity index ({indexed} embeddings)");
+        }
+        return Ok(());
+    }
+
     let images = crate::scan_images_in(project)?;
     if images.is_empty() {
         if json {
             let output = serde_json::json!({
                 "embedded": 0,
+                "fresh": 0,
+                "stale": 0,
+                "reused": 0,
                 "cached": 0,
                 "total": 0,
             });
@@ -496,40 +802,114 @@ fn cmd_embed(project: &str, _all: bool, json: bool) -> Result<(), String> {
 
     let image_paths: Vec<String> = images.iter().map(|img| img.path.clone()).collect();
     let total = image_paths.len();
+    let model = model.unwrap_or_else(crate::embed::default_model);
 
     // Index images first so the metadata table has entries
     crate::search::index_project_images(project, &images)?;
 
-    let embedded = crate::embed::embed_and_store(project, &image_paths)?;
-    let cached = total - embedded;
+    let stats = crate::embed::embed_and_store(project, &image_paths, model, all)?;
 
     if json {
         let output = serde_json::json!({
-            "embedded": embedded,
-            "cached": cached,
+            "model": model,
+            "embedded": stats.embedded(),
+            "fresh": stats.fresh,
+            "stale": stats.stale,
+            "reused": stats.reused,
+            "cached": stats.reused,
             "total": total,
         });
         println!("{}", serde_json::to_string_pretty(&output).unwrap());
     } else {
         println!(
-            "Embedded {} new images ({} already cached)",
-            embedded, cached
+            "Embedded {} images with {} ({} new, {} changed, {} unchanged)",
+            stats.embedded(),
+            model,
+            stats.fresh,
+            stats.stale,
+            stats.reused
         );
     }
 
     Ok(())
 }
 
-/// Find visually similar images using CLIP cosine similarity.
-fn cmd_similar(image_path: &str, project: &str, limit: usize, json: bool) -> Result<(), String> {
+/// Analyze images with the configured AI provider, saving the results to the
+/// project's search index.
+async fn cmd_analyze(files: Vec<String>, project: &str, all: bool, no_cache: bool, json: bool) -> Result<(), String> {
+    let image_paths: Vec<String> = if all {
+        crate::scan_images_in(project)?.into_iter().map(|img| img.path).collect()
+    } else {
+        files.iter().map(|f| resolve_image_path(f, project)).collect()
+    };
+    if image_paths.is_empty() {
+        return Err("Nothing to analyze: pass image files or --all".to_string());
+    }
+    if let Some(missing) = image_paths.iter().find(|p| !Path::new(p).exists()) {
+        return Err(format!("Image file not found: {missing}"));
+    }
+
+    let config = crate::ai::get_ai_config()?;
+    let existing_tags = crate::ai::project_tags(project);
+
+    let total = image_paths.len();
+    let mut items = Vec::with_capacity(total);
+    crate::ai::analyze_batch(
+        std::sync::Arc::new(crate::ai::NoEvents),
+        image_paths.clone(),
+        project,
+        &config,
+        existing_tags,
+        no_cache,
+        |item| {
+            if !json {
+                let name = Path::new(&item.image_path).file_name().unwrap_or_default().to_string_lossy();
+                match (&item.result, &item.error) {
+                    (Some(result), _) => {
+                        let via = result.provider.as_deref().map(|p| format!(" ({p})")).unwrap_or_default();
+                        println!("[{}/{total}] {name}{via}: {}", items.len() + 1, result.tags.join(", "))
+                    }
+                    (None, error) => println!("[{}/{total}] {name}: failed: {}", items.len() + 1, error.as_deref().unwrap_or("")),
+                }
+            }
+            items.push(item);
+            Ok(())
+        },
+    )
+    .await?;
+    items.sort_by_key(|item| image_paths.iter().position(|p| p == &item.image_path));
+
+    let failed = items.iter().filter(|i| !i.success).count();
+    if json {
+        let output = serde_json::to_string_pretty(&items)
+            .map_err(|e| format!("Cannot serialize analysis: {e}"))?;
+        println!("{output}");
+    } else {
+        println!("Analyzed {} of {total} image(s)", total - failed);
+    }
+    if failed > 0 {
+        return Err(format!("{failed} image(s) could not be analyzed"));
+    }
+    Ok(())
+}
+
+/// Find visually similar images by cosine similarity within one model.
+fn cmd_similar(
+    image_path: &str,
+    project: &str,
+    limit: usize,
+    model: Option<crate::embed::ImageModel>,
+    json: bool,
+) -> Result<(), String> {
     // Resolve image path: if it's just a filename, prepend project/images/
     let resolved = resolve_image_path(image_path, project);
+    let model = model.unwrap_or_else(crate::embed::default_model);
 
-    let results = crate::search::find_similar(project, &resolved, limit).map_err(|e| {
-        if e.contains("No embedding found") {
+    let results = crate::search::find_similar(project, &resolved, model.id(), limit).map_err(|e| {
+        if e.contains("embedding found") {
             format!(
-                "No embedding found for \"{}\". Run `deco embed -p {}` first.",
-                image_path, project
+                "No {} embedding found for \"{}\". Run `deco embed -p {} --model {}` first.",
+                model, image_path, project, model
             )
         } else {
             e
@@ -554,12 +934,20 @@ fn cmd_similar(image_path: &str, project: &str, limit: usize, json: bool) -> Res
     Ok(())
 }
 
-/// Text-to-image semantic search (currently uses FTS5; CLIP text encoder planned).
+/// Text-to-image semantic search using the CLIP text encoder.
 fn cmd_semantic(query: &str, project: &str, limit: usize, json: bool) -> Result<(), String> {
-    // Currently delegates to the same FTS5 search as `search`.
-    // A future version will use CLIP text-to-image embeddings for true
-    // cross-modal semantic search.
-    let results = crate::search::search_text(project, query, limit)?;
+    // Fall back to FTS5 when the model is unavailable or nothing is embedded yet.
+    let results = match crate::search::semantic_search(project, query, limit) {
+        Ok(results) if !results.is_empty() => results,
+        Ok(_) => {
+            eprintln!("No embeddings found. Run `deco embed -p {project}` for semantic search; using text search.");
+            crate::search::search_text(project, query, limit)?
+        }
+        Err(e) => {
+            eprintln!("Semantic search unavailable ({e}); using text search.");
+            crate::search::search_text(project, query, limit)?
+        }
+    };
 
     if json {
         let output = serde_json::to_string_pretty(&results)
@@ -592,9 +980,15 @@ fn cmd_semantic(query: &str, project: &str, limit: usize, json: bool) -> Result<
 }
 
 /// Auto-cluster images by visual similarity using greedy agglomerative clustering.
-fn cmd_cluster(project: &str, threshold: f64, json: bool) -> Result<(), String> {
+fn cmd_cluster(
+    project: &str,
+    threshold: f64,
+    model: Option<crate::embed::ImageModel>,
+    json: bool,
+) -> Result<(), String> {
     let threshold = threshold.clamp(0.0, 1.0);
-    let all_embeddings = crate::search::get_all_embeddings(project)?;
+    let model = model.unwrap_or_else(crate::embed::default_model);
+    let all_embeddings = crate::search::get_all_embeddings(project, model.id())?;
 
     if all_embeddings.is_empty() {
         if json {
@@ -604,7 +998,7 @@ fn cmd_cluster(project: &str, threshold: f64, json: bool) -> Result<(), String>
             });
             println!("{}", serde_json::to_string_pretty(&output).unwrap());
         } else {
-            println!("No embeddings found. Run `deco embed -p {project}` first.");
+            println!("No {model} embeddings found. Run `deco embed -p {project} --model {model}` first.");
         }
         return Ok(());
     }
@@ -683,6 +1077,176 @@ fn cmd_cluster(project: &str, threshold: f64, json: bool) -> Result<(), String>
     Ok(())
 }
 
+/// Find groups of near-identical images using perceptual hashes.
+fn cmd_dupes(project: &str, max_distance: u32, json: bool) -> Result<(), String> {
+    // Indexing computes hashes for new or changed files
+    let images = crate::scan_images_in(project)?;
+    crate::search::index_project_images(project, &images)?;
+    let groups = crate::search::find_duplicates(project, max_distance)?;
+
+    if json {
+        let output = serde_json::to_string_pretty(&groups)
+            .map_err(|e| format!("Cannot serialize duplicates: {e}"))?;
+        println!("{output}");
+    } else if groups.is_empty() {
+        println!("No duplicates found");
+    } else {
+        println!("{} duplicate group(s):", groups.len());
+        for (i, group) in groups.iter().enumerate() {
+            println!("  Group {} ({} images):", i + 1, group.images.len());
+            for img in &group.images {
+                println!(
+                    "    {:>2} bits  {}  ({} bytes)",
+                    img.distance, img.name, img.size_bytes
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Print one sync batch (a JSON line each in JSON mode).
+fn print_sync_report(report: &crate::watch::SyncReport, json: bool) {
+    if json {
+        println!("{}", serde_json::to_string(report).unwrap());
+        return;
+    }
+    let name = |path: &str| {
+        Path::new(path).file_name().unwrap_or_default().to_string_lossy().to_string()
+    };
+    for img in &report.added {
+        println!("+ {}", img.name);
+    }
+    for path in &report.removed {
+        println!("- {}", name(path));
+    }
+    for r in &report.renamed {
+        println!("~ {} → {}", name(&r.from), r.to.name);
+    }
+}
+
+/// Watch a project's images/ folder and keep the index in sync.
+fn cmd_watch(project: &str, once: bool, json: bool) -> Result<(), String> {
+    if once {
+        let report = crate::watch::sync_all(project)?;
+        crate::watch::embed_added(&report);
+        if report.is_empty() && !json {
+            println!("Index is up to date");
+        } else {
+            print_sync_report(&report, json);
+        }
+        return Ok(());
+    }
+
+    let _watcher = crate::watch::start(project, move |report| print_sync_report(&report, json))?;
+    if !json {
+        eprintln!("Watching {project}/images (Ctrl-C to stop)");
+    }
+    loop {
+        std::thread::park();
+    }
+}
+
+/// Check (and optionally repair) a project's index, board and metadata.
+fn cmd_doctor(project: &str, fix: bool, json: bool) -> Result<(), String> {
+    let report = crate::doctor::run(project, fix)?;
+
+    if json {
+        let output = serde_json::to_string_pretty(&report)
+            .map_err(|e| format!("Cannot serialize report: {e}"))?;
+        println!("{output}");
+        return Ok(());
+    }
+
+    println!("Checked {} images in {}", report.images, report.project_path);
+    if report.issues.is_empty() {
+        println!("No problems found");
+        return Ok(());
+    }
+    for summary in &report.summary {
+        if fix {
+            println!("  {}: {} ({} fixed)", summary.kind.label(), summary.found, summary.fixed);
+        } else {
+            println!("  {}: {}", summary.kind.label(), summary.found);
+        }
+        for issue in report.issues.iter().filter(|i| i.kind == summary.kind) {
+            let name = issue.path.as_deref().map(|p| {
+                Path::new(p).file_name().unwrap_or_default().to_string_lossy().to_string()
+            });
+            match name {
+                Some(name) => println!("    {name}: {}", issue.detail),
+                None => println!("    {}", issue.detail),
+            }
+        }
+    }
+    let remaining = report.remaining();
+    if !fix && remaining > 0 {
+        println!("Run `deco doctor -p {project} --fix` to repair");
+    } else if fix && remaining > 0 {
+        println!("{remaining} problem(s) left unfixed");
+    }
+    Ok(())
+}
+
+/// List, cancel or retry jobs in the shared queue (~/.deco/jobs.json).
+/// Jobs are executed by the desktop app, so a retried job runs once Deco is open.
+fn cmd_jobs(action: JobsAction, json: bool) -> Result<(), String> {
+    let store = crate::jobs::JobStore::open_default();
+
+    let job = match action {
+        JobsAction::List { all } => {
+            let jobs: Vec<_> = store
+                .list()?
+                .into_iter()
+                .filter(|j| all || !j.status.is_finished())
+                .collect();
+            if json {
+                let output = serde_json::to_string_pretty(&jobs)
+                    .map_err(|e| format!("Cannot serialize jobs: {e}"))?;
+                println!("{output}");
+                return Ok(());
+            }
+            if jobs.is_empty() {
+                println!("No jobs");
+                return Ok(());
+            }
+            for job in &jobs {
+                let project = Path::new(job.spec.project_path())
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy();
+                let detail = job.error.as_deref().or(job.message.as_deref()).unwrap_or("");
+                println!(
+                    "{:>4}  {:<8}  {:<9}  {:>5}/{:<5}  {:<20}  {}",
+                    job.id,
+                    job.spec.kind().label(),
+                    job.status.label(),
+                    job.done,
+                    job.total,
+                    project,
+                    detail
+                );
+            }
+            return Ok(());
+        }
+        JobsAction::Cancel { id } => store.cancel(id)?,
+        JobsAction::Retry { id } => store.retry(id)?,
+    };
+
+    if json {
+        let output = serde_json::to_string_pretty(&job)
+            .map_err(|e| format!("Cannot serialize job: {e}"))?;
+        println!("{output}");
+    } else {
+        println!("Job {} is {}", job.id, job.status.label());
+        if job.status == crate::jobs::JobStatus::Queued {
+            println!("It will run while Deco is open");
+        }
+    }
+    Ok(())
+}
+
 /// Show metadata for a single image.
 fn cmd_info(image_path: &str, project: &str, json: bool) -> Result<(), String> {
     let resolved = resolve_image_path(image_path, project);
@@ -714,11 +1278,53 @@ fn cmd_info(image_path: &str, project: &str, json: bool) -> Result<(), String> {
             println!("Mood:        {}", meta.mood.join(", "));
         }
         if !meta.colors.is_empty() {
-            println!("Colors:      {}", meta.colors.join(", "));
+            let colors: Vec<String> = if meta.color_weights.len() == meta.colors.len() {
+                meta.colors
+                    .iter()
+                    .zip(&meta.color_weights)
+                    .map(|(c, w)| format!("{c} ({:.0}%)", w * 100.0))
+                    .collect()
+            } else {
+                meta.colors.clone()
+            };
+            println!("Colors:      {}", colors.join(", "));
         }
         if let Some(ref era) = meta.era {
             println!("Era:         {era}");
         }
+        for (field, value) in &meta.extra {
+            println!("{:<13}{}", format!("{field}:"), field_text(value));
+        }
+        if let Some(ref p) = meta.properties {
+            if let (Some((w, h)), Some(ratio)) = (p.display_size(), p.aspect_ratio()) {
+                println!("Dimensions:  {w} × {h} ({ratio:.2}:1)");
+            }
+            let camera = [p.camera_make.as_deref(), p.camera_model.as_deref()]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+                .join(" ");
+            if !camera.is_empty() {
+                println!("Camera:      {camera}");
+            }
+            if let Some(ref lens) = p.lens {
+                println!("Lens:        {lens}");
+            }
+            if let Some(ref taken) = p.taken_at {
+                println!("Taken:       {taken}");
+            }
+            if let (Some(lat), Some(lon)) = (p.gps_latitude, p.gps_longitude) {
+                println!("GPS:         {lat:.5}, {lon:.5}");
+            }
+            if let Some(ref profile) = p.color_profile {
+                println!("Profile:     {profile}");
+            }
+        }
+        if let Some(ref a) = meta.annotations {
+            for line in annotation_lines(a) {
+                println!("{line}");
+            }
+        }
         println!("Path:        {}", meta.image_path);
     }
 
@@ -726,26 +1332,140 @@ fn cmd_info(image_path: &str, project: &str, json: bool) -> Result<(), String> {
 }
 
 /// List all tags in the project with counts.
-fn cmd_tags(project: &str, json: bool) -> Result<(), String> {
+fn cmd_tags(project: &str, tree: bool, json: bool) -> Result<(), String> {
     let tags = crate::search::get_all_tags(project)?;
 
     if json {
-        let output = serde_json::to_string_pretty(&tags)
-            .map_err(|e| format!("Cannot serialize tags: {e}"))?;
+        let output = if tree {
+            serde_json::to_string_pretty(&crate::search::tag_tree(&tags))
+        } else {
+            serde_json::to_string_pretty(&tags)
+        }
+        .map_err(|e| format!("Cannot serialize tags: {e}"))?;
         println!("{output}");
+    } else if tags.is_empty() {
+        println!("No tags found in project");
+    } else if tree {
+        print_tag_tree(&crate::search::tag_tree(&tags), 0);
     } else {
-        if tags.is_empty() {
-            println!("No tags found in project");
-        } else {
-            for t in &tags {
-                println!("{} ({})", t.tag, t.count);
-            }
+        for t in &tags {
+            println!("{} ({})", t.tag, t.count);
         }
     }
 
     Ok(())
 }
 
+fn print_tag_tree(nodes: &[crate::search::TagNode], depth: usize) {
+    for node in nodes {
+        println!("{}{} ({})", "  ".repeat(depth), node.name, node.count);
+        print_tag_tree(&node.children, depth + 1);
+    }
+}
+
+/// Merge, rename or normalize tags across a project.
+fn cmd_tags_rewrite(action: TagsAction, json: bool) -> Result<(), String> {
+    let rewrite = match &action {
+        TagsAction::Merge { sources, target, project } => crate::ops::merge_tags(project, sources, target)?,
+        TagsAction::Rename { from, to, project } => crate::ops::rename_tag(project, from, to)?,
+        TagsAction::Normalize { project } => crate::ops::normalize_tags(project)?,
+    };
+
+    if json {
+        let output = serde_json::to_string_pretty(&rewrite)
+            .map_err(|e| format!("Cannot serialize tag changes: {e}"))?;
+        println!("{output}");
+    } else {
+        for (from, to) in &rewrite.renamed {
+            println!("{from} → {to}");
+        }
+        for tag in &rewrite.removed {
+            println!("{tag} removed");
+        }
+        println!("Updated {} image(s)", rewrite.images);
+    }
+
+    Ok(())
+}
+
+/// `Label:       value` lines for the annotations that are set.
+fn annotation_lines(a: &crate::search::ImageAnnotations) -> Vec<String> {
+    let mut lines = Vec::new();
+    if let Some(rating) = a.rating {
+        lines.push(format!("Rating:      {}{}", "★".repeat(rating as usize), "☆".repeat(5 - rating as usize)));
+    }
+    if a.favorite {
+        lines.push("Favorite:    yes".to_string());
+    }
+    for (label, value) in [
+        ("Author:", &a.author),
+        ("Credit:", &a.credit),
+        ("License:", &a.license),
+        ("Source:", &a.source_url),
+        ("Found on:", &a.source_page),
+        ("Notes:", &a.notes),
+    ] {
+        if let Some(value) = value {
+            lines.push(format!("{label:<13}{value}"));
+        }
+    }
+    lines
+}
+
+/// Create, list, show or delete smart collections.
+fn cmd_collection(action: CollectionAction, json: bool) -> Result<(), String> {
+    let output = match action {
+        CollectionAction::Create { name, query, replace, project } => {
+            let collection = crate::collections::save_collection(&project, &name, &query, replace)?;
+            let count = crate::collections::collection_images(&project, &collection.name)?.len();
+            if !json {
+                println!("Saved collection \"{}\" ({count} images)", collection.name);
+                return Ok(());
+            }
+            serde_json::to_string_pretty(&collection)
+        }
+        CollectionAction::List { project } => {
+            let collections = crate::collections::list_collections(&project)?;
+            if !json {
+                if collections.is_empty() {
+                    println!("No collections in project");
+                }
+                for c in &collections {
+                    match &c.error {
+                        Some(e) => println!("{} (error: {e})  {}", c.name, c.query),
+                        None => println!("{} ({})  {}", c.name, c.count, c.query),
+                    }
+                }
+                return Ok(());
+            }
+            serde_json::to_string_pretty(&collections)
+        }
+        CollectionAction::Show { name, project } => {
+            let images = crate::collections::collection_images(&project, &name)?;
+            if !json {
+                println!("{} image(s) in \"{name}\":", images.len());
+                for path in &images {
+                    println!("  {}", Path::new(path).file_name().unwrap_or_default().to_string_lossy());
+                }
+                return Ok(());
+            }
+            serde_json::to_string_pretty(&images)
+        }
+        CollectionAction::Delete { name, project } => {
+            crate::collections::delete_collection(&project, &name)?;
+            if !json {
+                println!("Deleted collection \"{name}\"");
+                return Ok(());
+            }
+            serde_json::to_string_pretty(&serde_json::json!({ "deleted": name }))
+        }
+    };
+
+    let output = output.map_err(|e| format!("Cannot serialize collections: {e}"))?;
+    println!("{output}");
+    Ok(())
+}
+
 // ---------------------------------------------------------------------------
 // Phase 3: Projects, Move, Update Commands
 // ---------------------------------------------------------------------------
@@ -800,6 +1520,7 @@ fn cmd_update(
     styles: Option<String>,
     moods: Option<String>,
     era: Option<String>,
+    annotations: AnnotationArgs,
     json: bool,
 ) -> Result<(), String> {
     // Parse comma-separated strings into Vec<String>
@@ -816,6 +1537,8 @@ fn cmd_update(
         styles: styles.map(parse_csv),
         moods: moods.map(parse_csv),
         era,
+        extra: None,
+        annotations: annotations.into(),
     };
 
     let meta = crate::ops::update_item_metadata(project, filename, fields)?;
@@ -841,6 +1564,11 @@ fn cmd_update(
         if let Some(ref era) = meta.era {
             println!("  Era: {era}");
         }
+        if let Some(ref a) = meta.annotations {
+            for line in annotation_lines(a) {
+                println!("  {line}");
+            }
+        }
     }
 
     Ok(())
@@ -850,6 +1578,15 @@ fn cmd_update(
 // Helpers
 // ---------------------------------------------------------------------------
 
+/// A project-defined field's value as plain text (lists comma-separated).
+fn field_text(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Array(items) => items.iter().map(field_text).collect::<Vec<_>>().join(", "),
+        other => other.to_string(),
+    }
+}
+
 /// Resolve an image path: if it's just a filename, prepend {project}/images/.
 fn resolve_image_path(image_path: &str, project: &str) -> String {
     let p = Path::new(image_path);
@@ -930,9 +1667,14 @@ mod tests {
         let cli =
             Cli::try_parse_from(["deco", "import", "a.png", "b.jpg", "-p", "/tmp/test"]).unwrap();
         match cli.command {
-            Command::Import { paths, project } => {
+            Command::Import {
+                paths,
+                project,
+                on_duplicate,
+            } => {
                 assert_eq!(paths, vec!["a.png", "b.jpg"]);
                 assert_eq!(project, "/tmp/test");
+                assert!(on_duplicate.is_none());
             }
             _ => panic!("Expected Import command"),
         }
@@ -959,15 +1701,48 @@ mod tests {
                 query,
                 project,
                 limit,
+                hybrid,
+                ..
             } => {
                 assert_eq!(query, "art deco");
                 assert_eq!(project, "/tmp/test");
                 assert_eq!(limit, 20);
+                assert!(!hybrid);
             }
             _ => panic!("Expected Search command"),
         }
     }
 
+    #[test]
+    fn test_cli_parse_search_hybrid() {
+        let cli = Cli::try_parse_from([
+            "deco", "search", "brass", "-p", "/tmp/test", "--hybrid", "--fusion", "weighted",
+            "--text-weight", "0.3",
+        ])
+        .unwrap();
+        match cli.command {
+            Command::Search {
+                hybrid,
+                fusion,
+                text_weight,
+                semantic_weight,
+                ..
+            } => {
+                assert!(hybrid);
+                assert_eq!(fusion, "weighted");
+                assert_eq!(text_weight, 0.3);
+                assert_eq!(semantic_weight, 0.5);
+            }
+            _ => panic!("Expected Search command"),
+        }
+
+        // Fusion settings only make sense with --hybrid
+        assert!(Cli::try_parse_from([
+            "deco", "search", "brass", "-p", "/tmp/test", "--fusion", "weighted",
+        ])
+        .is_err());
+    }
+
     #[test]
     fn test_cli_parse_json_flag() {
         let cli = Cli::try_parse_from(["deco", "--json", "status"]).unwrap();
@@ -995,7 +1770,7 @@ mod tests {
 
     #[test]
     fn test_import_no_paths() {
-        let result = cmd_import(vec![], "/tmp", false);
+        let result = cmd_import(vec![], "/tmp", crate::dedup::DuplicatePolicy::Link, false);
         assert!(result.is_err());
         assert_eq!(result.unwrap_err(), "No file paths provided");
     }
@@ -1033,6 +1808,7 @@ mod tests {
         let result = cmd_import(
             vec![src_file.to_string_lossy().to_string()],
             &project,
+            crate::dedup::DuplicatePolicy::Link,
             false,
         );
         assert!(result.is_ok());
@@ -1043,6 +1819,43 @@ mod tests {
         assert_eq!(images[0].name, "test.png");
     }
 
+    #[test]
+    fn test_import_duplicate_policies() {
+        use crate::dedup::DuplicatePolicy;
+
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_string_lossy().to_string();
+        let src_dir = tempfile::tempdir().unwrap();
+        let original = src_dir.path().join("lamp.png");
+        let renamed = src_dir.path().join("lamp-from-web.png");
+        std::fs::write(&original, b"same pixels").unwrap();
+        std::fs::write(&renamed, b"same pixels").unwrap();
+
+        let first = crate::import_image_file(&original, &project, DuplicatePolicy::Link).unwrap();
+        assert!(first.duplicate.is_none());
+
+        for policy in [DuplicatePolicy::Skip, DuplicatePolicy::Link] {
+            let info = crate::import_image_file(&renamed, &project, policy).unwrap();
+            let dup = info.duplicate.expect("should be flagged as duplicate");
+            assert_eq!(dup.action, policy);
+            assert_eq!(dup.existing_path, first.path);
+            assert_eq!(info.path, first.path);
+            assert_eq!(crate::scan_images_in(&project).unwrap().len(), 1);
+        }
+
+        let info = crate::import_image_file(&renamed, &project, DuplicatePolicy::KeepBoth).unwrap();
+        assert_eq!(info.name, "lamp-from-web.png");
+        assert_eq!(info.duplicate.unwrap().existing_path, first.path);
+        assert_eq!(crate::scan_images_in(&project).unwrap().len(), 2);
+
+        // Pasted bytes go through the same check
+        let pasted =
+            crate::import_image_bytes(b"same pixels".to_vec(), "png".into(), project.clone(), DuplicatePolicy::Skip)
+                .unwrap();
+        assert!(pasted.duplicate.is_some());
+        assert_eq!(crate::scan_images_in(&project).unwrap().len(), 2);
+    }
+
     #[test]
     fn test_delete_removes_file() {
         let dir = tempfile::tempdir().unwrap();
@@ -1068,9 +1881,16 @@ mod tests {
     fn test_cli_parse_embed() {
         let cli = Cli::try_parse_from(["deco", "embed", "-p", "/tmp/test"]).unwrap();
         match cli.command {
-            Command::Embed { project, all } => {
+            Command::Embed {
+                project,
+                all,
+                rebuild_index,
+                model,
+            } => {
                 assert_eq!(project, "/tmp/test");
                 assert!(!all);
+                assert!(!rebuild_index);
+                assert_eq!(model, None);
             }
             _ => panic!("Expected Embed command"),
         }
@@ -1080,7 +1900,7 @@ mod tests {
     fn test_cli_parse_embed_all() {
         let cli = Cli::try_parse_from(["deco", "embed", "-p", "/tmp/test", "--all"]).unwrap();
         match cli.command {
-            Command::Embed { project, all } => {
+            Command::Embed { project, all, .. } => {
                 assert_eq!(project, "/tmp/test");
                 assert!(all);
             }
@@ -1088,6 +1908,67 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cli_parse_embed_rebuild_index() {
+        let cli =
+            Cli::try_parse_from(["deco", "embed", "-p", "/tmp/test", "--rebuild-index"]).unwrap();
+        match cli.command {
+            Command::Embed { rebuild_index, .. } => assert!(rebuild_index),
+            _ => panic!("Expected Embed command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_parse_analyze() {
+        let cli = Cli::parse_from(["deco", "analyze", "a.jpg", "b.png", "-p", "/tmp/test", "--no-cache"]);
+        match cli.command {
+            Command::Analyze { files, project, all, no_cache } => {
+                assert_eq!(files, vec!["a.jpg", "b.png"]);
+                assert_eq!(project, "/tmp/test");
+                assert!(!all);
+                assert!(no_cache);
+            }
+            _ => panic!("Expected Analyze command"),
+        }
+
+        let cli = Cli::parse_from(["deco", "analyze", "--all", "-p", "/tmp/test"]);
+        match cli.command {
+            Command::Analyze { files, all, no_cache, .. } => {
+                assert!(files.is_empty());
+                assert!(all);
+                assert!(!no_cache);
+            }
+            _ => panic!("Expected Analyze command"),
+        }
+
+        assert!(Cli::try_parse_from(["deco", "analyze", "a.jpg", "--all", "-p", "/tmp/test"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_parse_embed_model() {
+        let cli = Cli::try_parse_from([
+            "deco", "embed", "-p", "/tmp/test", "--model", "unicom-vit-b-16",
+        ])
+        .unwrap();
+        match cli.command {
+            Command::Embed { model, .. } => {
+                assert_eq!(model, Some(crate::embed::ImageModel::UnicomVitB16));
+            }
+            _ => panic!("Expected Embed command"),
+        }
+
+        let cli = Cli::try_parse_from(["deco", "similar", "a.jpg", "-p", "/tmp/test", "-m", "clip"])
+            .unwrap();
+        match cli.command {
+            Command::Similar { model, .. } => {
+                assert_eq!(model, Some(crate::embed::ImageModel::ClipVitB32));
+            }
+            _ => panic!("Expected Similar command"),
+        }
+
+        assert!(Cli::try_parse_from(["deco", "cluster", "-p", "/tmp/test", "--model", "dinov2"]).is_err());
+    }
+
     #[test]
     fn test_cli_parse_similar() {
         let cli = Cli::try_parse_from([
@@ -1099,10 +1980,12 @@ mod tests {
                 image_path,
                 project,
                 limit,
+                model,
             } => {
                 assert_eq!(image_path, "/tmp/test/images/photo.jpg");
                 assert_eq!(project, "/tmp/test");
                 assert_eq!(limit, 10);
+                assert_eq!(model, None);
             }
             _ => panic!("Expected Similar command"),
         }
@@ -1119,6 +2002,7 @@ mod tests {
                 image_path,
                 project,
                 limit,
+                ..
             } => {
                 assert_eq!(image_path, "photo.jpg");
                 assert_eq!(project, "/tmp/test");
@@ -1154,8 +2038,10 @@ mod tests {
                 project,
                 num_clusters,
                 threshold,
+                model,
             } => {
                 assert_eq!(project, "/tmp/test");
+                assert_eq!(model, None);
                 assert_eq!(num_clusters, 5);
                 assert!((threshold - 0.7).abs() < 1e-6);
             }
@@ -1163,6 +2049,123 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cli_parse_watch() {
+        let cli = Cli::try_parse_from(["deco", "watch", "-p", "/tmp/test"]).unwrap();
+        match cli.command {
+            Command::Watch { project, once } => {
+                assert_eq!(project, "/tmp/test");
+                assert!(!once);
+            }
+            _ => panic!("Expected Watch command"),
+        }
+
+        let cli = Cli::try_parse_from(["deco", "watch", "-p", "/tmp/test", "--once"]).unwrap();
+        match cli.command {
+            Command::Watch { once, .. } => assert!(once),
+            _ => panic!("Expected Watch command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_parse_doctor() {
+        let cli = Cli::try_parse_from(["deco", "doctor", "-p", "/tmp/test"]).unwrap();
+        match cli.command {
+            Command::Doctor { project, fix } => {
+                assert_eq!(project, "/tmp/test");
+                assert!(!fix);
+            }
+            _ => panic!("Expected Doctor command"),
+        }
+
+        let cli = Cli::try_parse_from(["deco", "doctor", "-p", "/tmp/test", "--fix"]).unwrap();
+        match cli.command {
+            Command::Doctor { fix, .. } => assert!(fix),
+            _ => panic!("Expected Doctor command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_parse_jobs() {
+        let cli = Cli::parse_from(["deco", "jobs", "list", "--all"]);
+        match cli.command {
+            Command::Jobs { action: JobsAction::List { all } } => assert!(all),
+            _ => panic!("Expected Jobs list command"),
+        }
+
+        let cli = Cli::parse_from(["deco", "jobs", "cancel", "7"]);
+        match cli.command {
+            Command::Jobs { action: JobsAction::Cancel { id } } => assert_eq!(id, 7),
+            _ => panic!("Expected Jobs cancel command"),
+        }
+
+        let cli = Cli::parse_from(["deco", "jobs", "retry", "3"]);
+        match cli.command {
+            Command::Jobs { action: JobsAction::Retry { id } } => assert_eq!(id, 3),
+            _ => panic!("Expected Jobs retry command"),
+        }
+    }
+
+    #[test]
+    fn test_cli_parse_collection() {
+        let cli = Cli::parse_from([
+            "deco", "collection", "create", "Bronze", "material/bronze -mood:dark", "--replace", "-p", "/tmp/test",
+        ]);
+        match cli.command {
+            Command::Collection { action: CollectionAction::Create { name, query, replace, project } } => {
+                assert_eq!(name, "Bronze");
+                assert_eq!(query, "material/bronze -mood:dark");
+                assert!(replace);
+                assert_eq!(project, "/tmp/test");
+            }
+            _ => panic!("Expected Collection create command"),
+        }
+
+        let cli = Cli::parse_from(["deco", "collection", "show", "Bronze", "-p", "/tmp/test"]);
+        assert!(matches!(cli.command, Command::Collection { action: CollectionAction::Show { .. } }));
+        assert!(Cli::try_parse_from(["deco", "collection", "list"]).is_err());
+    }
+
+    #[test]
+    fn test_collection_commands() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_string_lossy().to_string();
+
+        let create = |query: &str| CollectionAction::Create {
+            name: "Deco".to_string(),
+            query: query.to_string(),
+            replace: false,
+            project: project.clone(),
+        };
+        assert!(cmd_collection(create("art-deco"), false).is_ok());
+        assert!(cmd_collection(create("bauhaus"), false).unwrap_err().contains("already exists"));
+        assert!(cmd_collection(CollectionAction::List { project: project.clone() }, true).is_ok());
+        assert!(cmd_collection(CollectionAction::Show { name: "deco".to_string(), project: project.clone() }, false).is_ok());
+        assert!(cmd_collection(CollectionAction::Delete { name: "Deco".to_string(), project: project.clone() }, true).is_ok());
+        assert!(cmd_collection(CollectionAction::Show { name: "Deco".to_string(), project }, false).is_err());
+    }
+
+    #[test]
+    fn test_cli_parse_dupes() {
+        let cli = Cli::try_parse_from(["deco", "dupes", "-p", "/tmp/test"]).unwrap();
+        match cli.command {
+            Command::Dupes {
+                project,
+                max_distance,
+            } => {
+                assert_eq!(project, "/tmp/test");
+                assert_eq!(max_distance, 6);
+            }
+            _ => panic!("Expected Dupes command"),
+        }
+
+        let cli = Cli::try_parse_from(["deco", "dupes", "-p", "/tmp/test", "-d", "10"]).unwrap();
+        match cli.command {
+            Command::Dupes { max_distance, .. } => assert_eq!(max_distance, 10),
+            _ => panic!("Expected Dupes command"),
+        }
+    }
+
     #[test]
     fn test_cli_parse_cluster_with_threshold() {
         let cli = Cli::try_parse_from([
@@ -1174,6 +2177,7 @@ mod tests {
                 project,
                 num_clusters,
                 threshold,
+                ..
             } => {
                 assert_eq!(project, "/tmp/test");
                 assert_eq!(num_clusters, 8);
@@ -1203,11 +2207,32 @@ mod tests {
     fn test_cli_parse_tags() {
         let cli = Cli::try_parse_from(["deco", "tags", "-p", "/tmp/test"]).unwrap();
         match cli.command {
-            Command::Tags { project } => {
-                assert_eq!(project, "/tmp/test");
+            Command::Tags { project, tree, action: None } => {
+                assert_eq!(project.as_deref(), Some("/tmp/test"));
+                assert!(!tree);
             }
             _ => panic!("Expected Tags command"),
         }
+        assert!(Cli::try_parse_from(["deco", "tags"]).is_err());
+        let cli = Cli::try_parse_from(["deco", "tags", "--tree", "-p", "/tmp/test"]).unwrap();
+        assert!(matches!(cli.command, Command::Tags { tree: true, .. }));
+
+        let cli = Cli::try_parse_from(["deco", "tags", "merge", "artdeco", "art_deco", "art-deco", "-p", "/tmp/test"]).unwrap();
+        match cli.command {
+            Command::Tags { action: Some(TagsAction::Merge { sources, target, project }), .. } => {
+                assert_eq!(sources, vec!["artdeco", "art_deco"]);
+                assert_eq!(target, "art-deco");
+                assert_eq!(project, "/tmp/test");
+            }
+            _ => panic!("Expected Tags merge command"),
+        }
+
+        let cli = Cli::try_parse_from(["deco", "tags", "rename", "lamp", "light", "-p", "/tmp/test"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Command::Tags { action: Some(TagsAction::Rename { .. }), .. }
+        ));
+        assert!(Cli::try_parse_from(["deco", "tags", "merge", "only-target", "-p", "/tmp/test"]).is_err());
     }
 
     // -----------------------------------------------------------------------
@@ -1262,7 +2287,7 @@ mod tests {
         let dir = tempfile::tempdir().unwrap();
         let project = dir.path().to_string_lossy().to_string();
 
-        let result = cmd_tags(&project, false);
+        let result = cmd_tags(&project, false, false);
         assert!(result.is_ok());
     }
 
@@ -1278,17 +2303,14 @@ mod tests {
             &crate::search::ImageMetadataRow {
                 image_path: "/test/a.jpg".to_string(),
                 name: "a.jpg".to_string(),
-                description: None,
                 tags: vec!["art-deco".to_string(), "sculpture".to_string()],
-                style: vec![],
-                mood: vec![],
-                colors: vec![],
-                era: None,
+                ..Default::default()
             },
+            &Default::default(),
         )
         .unwrap();
 
-        let result = cmd_tags(&project, true);
+        let result = cmd_tags(&project, false, true);
         assert!(result.is_ok());
     }
 
@@ -1322,7 +2344,9 @@ mod tests {
                 mood: vec!["elegant".to_string()],
                 colors: vec!["#D4AF37".to_string()],
                 era: Some("1920s".to_string()),
+                ..Default::default()
             },
+            &Default::default(),
         )
         .unwrap();
 
@@ -1336,7 +2360,7 @@ mod tests {
         let project = dir.path().to_string_lossy().to_string();
         let _conn = crate::search::open_db(&project).unwrap();
 
-        let result = cmd_cluster(&project, 0.7, false);
+        let result = cmd_cluster(&project, 0.7, None, false);
         assert!(result.is_ok());
     }
 
@@ -1345,7 +2369,7 @@ mod tests {
         let dir = tempfile::tempdir().unwrap();
         let project = dir.path().to_string_lossy().to_string();
 
-        let result = cmd_embed(&project, false, false);
+        let result = cmd_embed(&project, false, false, None, false);
         assert!(result.is_ok());
     }
 
@@ -1407,6 +2431,7 @@ mod tests {
                 styles,
                 moods,
                 era,
+                annotations,
             } => {
                 assert_eq!(filename, "photo.png");
                 assert_eq!(project, "/tmp/test");
@@ -1415,9 +2440,27 @@ mod tests {
                 assert!(styles.is_none());
                 assert!(moods.is_none());
                 assert!(era.is_none());
+                assert!(crate::search::AnnotationUpdate::from(*annotations).is_empty());
             }
             _ => panic!("Expected Update command"),
         }
+
+        let cli = Cli::try_parse_from([
+            "deco", "update", "photo.png", "-p", "/tmp/test", "--rating", "5", "--favorite", "true",
+            "--source-url", "https://example.com/a.jpg", "--notes", "",
+        ])
+        .unwrap();
+        match cli.command {
+            Command::Update { annotations, .. } => {
+                let update = crate::search::AnnotationUpdate::from(*annotations);
+                assert_eq!(update.rating, Some(5));
+                assert_eq!(update.favorite, Some(true));
+                assert_eq!(update.source_url.as_deref(), Some("https://example.com/a.jpg"));
+                assert_eq!(update.notes.as_deref(), Some(""));
+            }
+            _ => panic!("Expected Update command"),
+        }
+        assert!(Cli::try_parse_from(["deco", "update", "photo.png", "-p", "/tmp/test", "--rating", "6"]).is_err());
     }
 
     #[test]
@@ -1492,6 +2535,7 @@ mod tests {
             Some("impressionism".to_string()),
             None,
             Some("1920s".to_string()),
+            AnnotationArgs::default(),
             false,
         );
         assert!(result.is_ok());
@@ -1527,7 +2571,9 @@ mod tests {
                 mood: vec!["calm".to_string()],
                 colors: vec!["#FF0000".to_string()],
                 era: Some("1600s".to_string()),
+                ..Default::default()
             },
+            &Default::default(),
         )
         .unwrap();
 
@@ -1540,6 +2586,7 @@ mod tests {
             None,
             None,
             None,
+            AnnotationArgs::default(),
             true,
         );
         assert!(result.is_ok());
@@ -1552,5 +2599,21 @@ mod tests {
         assert_eq!(meta.style, vec!["baroque"]);
         assert_eq!(meta.mood, vec!["calm"]);
         assert_eq!(meta.era, Some("1600s".to_string()));
+        assert!(meta.annotations.is_none());
+
+        // Annotations alone leave the rest be
+        let annotations = AnnotationArgs {
+            author: Some("Berenice Abbott".to_string()),
+            rating: Some(4),
+            notes: Some("Use for the lobby board".to_string()),
+            ..Default::default()
+        };
+        cmd_update("photo.jpg", &project, None, None, None, None, None, annotations, false).unwrap();
+        let meta = crate::search::get_image_metadata(&project, &img_path).unwrap().unwrap();
+        assert_eq!(meta.tags, vec!["updated", "tags"]);
+        let a = meta.annotations.expect("annotations should be set");
+        assert_eq!(a.author.as_deref(), Some("Berenice Abbott"));
+        assert_eq!(a.rating, Some(4));
+        assert_eq!(a.notes.as_deref(), Some("Use for the lobby board"));
     }
 }
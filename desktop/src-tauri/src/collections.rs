@@ -0,0 +1,681 @@
+//! Boolean image queries and saved smart collections.
+//!
+//! ```text
+//! material/bronze (style:geometric OR style:streamline) -mood:dark width:>=2000
+//! ```
+//!
+//! - a bare word is a tag and also matches the tags beneath it (`material`
+//!   matches `material/bronze`); `tag:x` is the same
+//! - `style:x`, `mood:x`, `era:x` → the image has that value
+//! - `color:#rrggbb` → a palette colour within ΔE of it
+//! - `width:`, `height:`, `aspect:` → a number (`aspect:16:9` works too),
+//!   optionally after `>`, `>=`, `<`, `<=` or `=`
+//! - `orientation:landscape|portrait|square`
+//! - terms side by side must all match; `OR`, `AND`, `NOT` / `-term` and
+//!   parentheses combine them, `NOT` binding tightest and `OR` loosest
+//!
+//! A smart collection is a named query kept in `.deco/collections.json`.
+//! Its members are worked out each time it is read, so they follow tag
+//! edits, re-analysis and imports without being stored anywhere.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// A saved query.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Collection {
+    pub name: String,
+    pub query: String,
+}
+
+/// A collection with its current number of members.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionInfo {
+    pub name: String,
+    pub query: String,
+    pub count: usize,
+    /// Why the saved query can't be run (e.g. after a hand edit); `count`
+    /// is 0 then.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Dimension {
+    Width,
+    Height,
+    Aspect,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Less,
+    AtMost,
+    Equal,
+    AtLeast,
+    Greater,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Orientation {
+    Landscape,
+    Portrait,
+    Square,
+}
+
+/// A parsed boolean query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    All(Vec<Filter>),
+    Any(Vec<Filter>),
+    Not(Box<Filter>),
+    Tag(String),
+    Style(String),
+    Mood(String),
+    Era(String),
+    Color((u8, u8, u8)),
+    Size(Dimension, Comparison, f64),
+    Orientation(Orientation),
+}
+
+/// The parts of an image a filter looks at.
+#[derive(Debug, Clone, Default)]
+struct Candidate {
+    path: String,
+    tags: Vec<String>,
+    style: Vec<String>,
+    mood: Vec<String>,
+    era: Option<String>,
+    colors: Vec<(u8, u8, u8)>,
+    /// Displayed width and height, when the file has been read.
+    size: Option<(u32, u32)>,
+}
+
+impl Filter {
+    fn matches(&self, image: &Candidate) -> bool {
+        let has_words = |values: &[String], wanted: &str| {
+            wanted.split_whitespace().all(|w| values.iter().any(|v| v.eq_ignore_ascii_case(w)))
+        };
+        match self {
+            Filter::All(filters) => filters.iter().all(|f| f.matches(image)),
+            Filter::Any(filters) => filters.iter().any(|f| f.matches(image)),
+            Filter::Not(filter) => !filter.matches(image),
+            Filter::Tag(tag) => image.tags.iter().any(|t| {
+                t == tag || t.strip_prefix(tag.as_str()).is_some_and(|rest| rest.starts_with('/'))
+            }),
+            Filter::Style(style) => has_words(&image.style, style),
+            Filter::Mood(mood) => has_words(&image.mood, mood),
+            Filter::Era(era) => image.era.as_deref().is_some_and(|e| e.eq_ignore_ascii_case(era)),
+            Filter::Color(color) => image
+                .colors
+                .iter()
+                .any(|&c| crate::palette::delta_e(*color, c) <= crate::search::DEFAULT_COLOR_THRESHOLD),
+            Filter::Size(dimension, comparison, value) => {
+                let Some((w, h)) = image.size.filter(|&(_, h)| h > 0) else {
+                    return false;
+                };
+                let actual = match dimension {
+                    Dimension::Width => w as f64,
+                    Dimension::Height => h as f64,
+                    Dimension::Aspect => w as f64 / h as f64,
+                };
+                match comparison {
+                    Comparison::Less => actual < *value,
+                    Comparison::AtMost => actual <= *value,
+                    Comparison::Equal => (actual - value).abs() < 0.01,
+                    Comparison::AtLeast => actual >= *value,
+                    Comparison::Greater => actual > *value,
+                }
+            }
+            Filter::Orientation(orientation) => {
+                let Some((w, h)) = image.size.filter(|&(_, h)| h > 0) else {
+                    return false;
+                };
+                // Within 1% of square counts as square
+                let ratio = w as f64 / h as f64;
+                match orientation {
+                    Orientation::Square => (ratio - 1.0).abs() <= 0.01,
+                    Orientation::Landscape => ratio > 1.01,
+                    Orientation::Portrait => ratio < 0.99,
+                }
+            }
+        }
+    }
+
+    /// Map tag terms to their canonical spelling, so `artdeco` finds images
+    /// the vocabulary files under `art-deco`.
+    fn with_vocabulary(self, vocab: &crate::vocab::Vocabulary) -> Filter {
+        let map = |filters: Vec<Filter>| filters.into_iter().map(|f| f.with_vocabulary(vocab)).collect();
+        match self {
+            Filter::All(filters) => Filter::All(map(filters)),
+            Filter::Any(filters) => Filter::Any(map(filters)),
+            Filter::Not(filter) => Filter::Not(Box::new(filter.with_vocabulary(vocab))),
+            Filter::Tag(tag) => Filter::Tag(vocab.canonical(&tag).unwrap_or(tag)),
+            other => other,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Parsing
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Term(String),
+}
+
+/// Split a query into tokens. Parentheses stand alone, quotes keep spaces
+/// in a term, and a leading `-` becomes `NOT`.
+fn tokenize(query: &str) -> Vec<Token> {
+    fn flush(current: &mut String, tokens: &mut Vec<Token>) {
+        let word = std::mem::take(current);
+        let (negated, term) = match word.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, word.as_str()),
+        };
+        if negated {
+            tokens.push(Token::Not);
+        }
+        match term {
+            "" => {}
+            "AND" => tokens.push(Token::And),
+            "OR" => tokens.push(Token::Or),
+            "NOT" => tokens.push(Token::Not),
+            _ => tokens.push(Token::Term(term.replace('"', ""))),
+        }
+    }
+
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in query.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            '(' | ')' if !in_quotes => {
+                flush(&mut current, &mut tokens);
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            c if c.is_whitespace() && !in_quotes => flush(&mut current, &mut tokens),
+            c => current.push(c),
+        }
+    }
+    flush(&mut current, &mut tokens);
+    tokens
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn any(&mut self) -> Result<Filter, String> {
+        let mut filters = vec![self.all()?];
+        while self.eat(&Token::Or) {
+            filters.push(self.all()?);
+        }
+        Ok(if filters.len() == 1 { filters.remove(0) } else { Filter::Any(filters) })
+    }
+
+    fn all(&mut self) -> Result<Filter, String> {
+        let mut filters = vec![self.unary()?];
+        loop {
+            if self.eat(&Token::And) || matches!(self.peek(), Some(Token::Open | Token::Not | Token::Term(_))) {
+                filters.push(self.unary()?);
+            } else {
+                break;
+            }
+        }
+        Ok(if filters.len() == 1 { filters.remove(0) } else { Filter::All(filters) })
+    }
+
+    fn unary(&mut self) -> Result<Filter, String> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        match token {
+            Some(Token::Not) => Ok(Filter::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let filter = self.any()?;
+                if !self.eat(&Token::Close) {
+                    return Err("Missing \")\" in query".to_string());
+                }
+                Ok(filter)
+            }
+            Some(Token::Term(term)) => parse_term(&term),
+            Some(Token::Close) => Err("Unexpected \")\" in query".to_string()),
+            Some(Token::And | Token::Or) => Err("AND/OR needs a term on both sides".to_string()),
+            None => Err("Query ends too early".to_string()),
+        }
+    }
+}
+
+fn parse_term(term: &str) -> Result<Filter, String> {
+    let Some((field, value)) = term.split_once(':') else {
+        return Ok(Filter::Tag(crate::vocab::normalize_tag(term)));
+    };
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(format!("Missing value for {field}:"));
+    }
+
+    let size = |dimension| parse_size(value).map(|(cmp, n)| Filter::Size(dimension, cmp, n));
+    match field.to_lowercase().as_str() {
+        "tag" | "tags" => Ok(Filter::Tag(crate::vocab::normalize_tag(value))),
+        "style" => Ok(Filter::Style(value.to_lowercase())),
+        "mood" => Ok(Filter::Mood(value.to_lowercase())),
+        "era" => Ok(Filter::Era(value.to_string())),
+        "color" | "colour" => crate::search::hex_to_rgb(value)
+            .map(Filter::Color)
+            .ok_or_else(|| format!("Invalid hex color: {value}")),
+        "width" => size(Dimension::Width),
+        "height" => size(Dimension::Height),
+        "aspect" => size(Dimension::Aspect),
+        "orientation" => match value.to_lowercase().as_str() {
+            "landscape" => Ok(Filter::Orientation(Orientation::Landscape)),
+            "portrait" => Ok(Filter::Orientation(Orientation::Portrait)),
+            "square" => Ok(Filter::Orientation(Orientation::Square)),
+            _ => Err(format!("Unknown orientation \"{value}\" (landscape, portrait or square)")),
+        },
+        _ => Err(format!("Unknown field \"{field}\" in query")),
+    }
+}
+
+/// `>=2000`, `<1.5`, `16:9`, `4/3` → comparison and number.
+fn parse_size(value: &str) -> Result<(Comparison, f64), String> {
+    let (comparison, number) = [
+        (">=", Comparison::AtLeast),
+        ("<=", Comparison::AtMost),
+        (">", Comparison::Greater),
+        ("<", Comparison::Less),
+        ("=", Comparison::Equal),
+    ]
+    .into_iter()
+    .find_map(|(op, cmp)| value.strip_prefix(op).map(|rest| (cmp, rest)))
+    .unwrap_or((Comparison::Equal, value));
+
+    let parsed = match number.split_once([':', '/']) {
+        Some((a, b)) => a.parse::<f64>().ok().zip(b.parse::<f64>().ok()).filter(|(_, b)| *b > 0.0).map(|(a, b)| a / b),
+        None => number.parse::<f64>().ok(),
+    };
+    parsed
+        .map(|n| (comparison, n))
+        .ok_or_else(|| format!("Invalid number: {value}"))
+}
+
+/// Parse a boolean image query (see the module docs for the grammar).
+pub fn parse(query: &str) -> Result<Filter, String> {
+    let tokens = tokenize(query);
+    if tokens.is_empty() {
+        return Err("Query is empty".to_string());
+    }
+    let mut parser = Parser { tokens, pos: 0 };
+    let filter = parser.any()?;
+    match parser.peek() {
+        None => Ok(filter),
+        Some(Token::Close) => Err("Unexpected \")\" in query".to_string()),
+        Some(_) => Err("Query ends with a dangling AND/OR".to_string()),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Evaluation
+// ---------------------------------------------------------------------------
+
+fn load_candidates(conn: &rusqlite::Connection) -> Result<Vec<Candidate>, String> {
+    let words = |s: Option<String>| -> Vec<String> {
+        s.unwrap_or_default().split_whitespace().map(|w| w.to_lowercase()).collect()
+    };
+    let mut stmt = conn
+        .prepare(
+            "SELECT path, style, mood, era, colors, width, height, orientation,
+                    (SELECT group_concat(tag, ' ') FROM image_tags t WHERE t.path = images.path)
+             FROM images
+             ORDER BY path",
+        )
+        .map_err(|e| format!("Collection query failed: {e}"))?;
+    let rows = stmt
+        .query_map([], |row| {
+            let properties = crate::properties::ImageProperties {
+                width: row.get(5)?,
+                height: row.get(6)?,
+                orientation: row.get(7)?,
+                ..Default::default()
+            };
+            Ok(Candidate {
+                path: row.get(0)?,
+                style: words(row.get(1)?),
+                mood: words(row.get(2)?),
+                era: row.get(3)?,
+                colors: row
+                    .get::<_, Option<String>>(4)?
+                    .unwrap_or_default()
+                    .split_whitespace()
+                    .filter_map(crate::search::hex_to_rgb)
+                    .collect(),
+                size: properties.display_size(),
+                tags: words(row.get(8)?),
+            })
+        })
+        .map_err(|e| format!("Collection query failed: {e}"))?;
+    rows.collect::<Result<_, _>>()
+        .map_err(|e| format!("Collection query failed: {e}"))
+}
+
+fn members(candidates: &[Candidate], filter: &Filter) -> Vec<String> {
+    candidates.iter().filter(|c| filter.matches(c)).map(|c| c.path.clone()).collect()
+}
+
+/// Paths of the images matching a boolean query, sorted.
+pub fn query_images(project_path: &str, query: &str) -> Result<Vec<String>, String> {
+    let vocab = crate::vocab::Vocabulary::load(project_path)?;
+    let filter = parse(query)?.with_vocabulary(&vocab);
+    let conn = crate::search::open_db(project_path)?;
+    Ok(members(&load_candidates(&conn)?, &filter))
+}
+
+// ---------------------------------------------------------------------------
+// Saved collections
+// ---------------------------------------------------------------------------
+
+/// Where a project's collections live.
+pub fn collections_path(project_path: &str) -> PathBuf {
+    Path::new(project_path).join(".deco").join("collections.json")
+}
+
+/// The project's saved collections, in the order they were created.
+pub fn load_collections(project_path: &str) -> Result<Vec<Collection>, String> {
+    let content = match std::fs::read_to_string(collections_path(project_path)) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Cannot read collections.json: {e}")),
+    };
+    serde_json::from_str(&content).map_err(|e| format!("Invalid collections.json: {e}"))
+}
+
+fn write_collections(project_path: &str, collections: &[Collection]) -> Result<(), String> {
+    let path = collections_path(project_path);
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("Cannot create .deco dir: {e}"))?;
+    }
+    let json = serde_json::to_string_pretty(collections)
+        .map_err(|e| format!("Cannot serialize collections: {e}"))?;
+    std::fs::write(&path, json).map_err(|e| format!("Cannot write collections.json: {e}"))
+}
+
+fn find<'a>(collections: &'a [Collection], name: &str) -> Option<&'a Collection> {
+    collections.iter().find(|c| c.name.eq_ignore_ascii_case(name.trim()))
+}
+
+/// Save a named query. An existing collection of the same name is only
+/// replaced when `replace` is set.
+pub fn save_collection(project_path: &str, name: &str, query: &str, replace: bool) -> Result<Collection, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Collection name cannot be empty".to_string());
+    }
+    parse(query)?;
+
+    let mut collections = load_collections(project_path)?;
+    let collection = Collection { name: name.to_string(), query: query.trim().to_string() };
+    match collections.iter_mut().find(|c| c.name.eq_ignore_ascii_case(name)) {
+        Some(_) if !replace => return Err(format!("Collection already exists: {name}")),
+        Some(existing) => *existing = collection.clone(),
+        None => collections.push(collection.clone()),
+    }
+    write_collections(project_path, &collections)?;
+    crate::log::log("SEARCH", &format!("Saved collection \"{name}\": {query}"));
+    Ok(collection)
+}
+
+pub fn delete_collection(project_path: &str, name: &str) -> Result<(), String> {
+    let mut collections = load_collections(project_path)?;
+    let before = collections.len();
+    collections.retain(|c| !c.name.eq_ignore_ascii_case(name.trim()));
+    if collections.len() == before {
+        return Err(format!("Collection not found: {name}"));
+    }
+    write_collections(project_path, &collections)
+}
+
+/// Every saved collection with its current member count.
+pub fn list_collections(project_path: &str) -> Result<Vec<CollectionInfo>, String> {
+    let collections = load_collections(project_path)?;
+    if collections.is_empty() {
+        return Ok(Vec::new());
+    }
+    let vocab = crate::vocab::Vocabulary::load(project_path)?;
+    let conn = crate::search::open_db(project_path)?;
+    let candidates = load_candidates(&conn)?;
+
+    collections
+        .into_iter()
+        .map(|c| {
+            let (count, error) = match parse(&c.query) {
+                Ok(filter) => {
+                    let filter = filter.with_vocabulary(&vocab);
+                    (candidates.iter().filter(|image| filter.matches(image)).count(), None)
+                }
+                Err(e) => (0, Some(e)),
+            };
+            Ok(CollectionInfo { name: c.name, query: c.query, count, error })
+        })
+        .collect()
+}
+
+/// Current members of a saved collection.
+pub fn collection_images(project_path: &str, name: &str) -> Result<Vec<String>, String> {
+    let collections = load_collections(project_path)?;
+    let collection = find(&collections, name).ok_or_else(|| format!("Collection not found: {name}"))?;
+    query_images(project_path, &collection.query)
+}
+
+// ---------------------------------------------------------------------------
+// Tauri Commands
+// ---------------------------------------------------------------------------
+
+/// Image paths matching a boolean query via storage backend.
+#[tauri::command]
+pub async fn cmd_query_images(
+    storage: tauri::State<'_, crate::storage::Storage>,
+    project_path: String,
+    query: String,
+) -> Result<Vec<String>, String> {
+    storage.query_images(&project_path, &query).await
+}
+
+/// Saved collections with member counts via storage backend.
+#[tauri::command]
+pub async fn cmd_list_collections(
+    storage: tauri::State<'_, crate::storage::Storage>,
+    project_path: String,
+) -> Result<Vec<CollectionInfo>, String> {
+    storage.list_collections(&project_path).await
+}
+
+/// Save a smart collection via storage backend.
+#[tauri::command]
+pub async fn cmd_save_collection(
+    storage: tauri::State<'_, crate::storage::Storage>,
+    project_path: String,
+    name: String,
+    query: String,
+    replace: Option<bool>,
+) -> Result<Collection, String> {
+    storage.save_collection(&project_path, &name, &query, replace.unwrap_or(false)).await
+}
+
+/// Delete a smart collection via storage backend.
+#[tauri::command]
+pub async fn cmd_delete_collection(
+    storage: tauri::State<'_, crate::storage::Storage>,
+    project_path: String,
+    name: String,
+) -> Result<(), String> {
+    storage.delete_collection(&project_path, &name).await
+}
+
+/// Current members of a smart collection via storage backend.
+#[tauri::command]
+pub async fn cmd_collection_images(
+    storage: tauri::State<'_, crate::storage::Storage>,
+    project_path: String,
+    name: String,
+) -> Result<Vec<String>, String> {
+    storage.collection_images(&project_path, &name).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{upsert_image, ImageMetadataRow};
+
+    fn image(path: &str, tags: &[&str], style: &[&str], era: Option<&str>, colors: &[&str]) -> ImageMetadataRow {
+        let strings = |list: &[&str]| list.iter().map(|s| s.to_string()).collect();
+        ImageMetadataRow {
+            image_path: path.to_string(),
+            name: path.trim_start_matches("/p/").to_string(),
+            tags: strings(tags),
+            style: strings(style),
+            colors: strings(colors),
+            era: era.map(String::from),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        let tag = |t: &str| Filter::Tag(t.to_string());
+        assert_eq!(
+            parse("a b OR c").unwrap(),
+            Filter::Any(vec![Filter::All(vec![tag("a"), tag("b")]), tag("c")])
+        );
+        assert_eq!(
+            parse("a AND (b OR NOT c) -d").unwrap(),
+            Filter::All(vec![
+                tag("a"),
+                Filter::Any(vec![tag("b"), Filter::Not(Box::new(tag("c")))]),
+                Filter::Not(Box::new(tag("d"))),
+            ])
+        );
+        assert_eq!(parse("Tag:Art_Deco").unwrap(), tag("art-deco"));
+        assert_eq!(
+            parse("width:>=2000 aspect:16:9").unwrap(),
+            Filter::All(vec![
+                Filter::Size(Dimension::Width, Comparison::AtLeast, 2000.0),
+                Filter::Size(Dimension::Aspect, Comparison::Equal, 16.0 / 9.0),
+            ])
+        );
+        assert_eq!(parse("era:\"late 1920s\"").unwrap(), Filter::Era("late 1920s".to_string()));
+
+        for bad in ["", "(a", "a)", "a OR", "shape:round", "width:big", "color:#zz", "color:aéabc", "orientation:round", "style:"] {
+            assert!(parse(bad).is_err(), "{bad:?} should not parse");
+        }
+    }
+
+    #[test]
+    fn test_query_images() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_string_lossy().to_string();
+        let conn = crate::search::open_db(&project).unwrap();
+
+        upsert_image(&conn, &image("/p/lamp.jpg", &["material/bronze", "lamp"], &["geometric"], Some("1920s"), &["#C0A060"]), &Default::default()).unwrap();
+        upsert_image(&conn, &image("/p/vase.jpg", &["material/glass", "vase"], &["organic"], Some("1920s"), &["#2040C0"]), &Default::default()).unwrap();
+        upsert_image(&conn, &image("/p/chair.jpg", &["furniture"], &["geometric"], Some("1950s"), &[]), &Default::default()).unwrap();
+        for (path, w, h, orientation) in [("/p/lamp.jpg", 3000, 2000, 1), ("/p/vase.jpg", 3000, 2000, 6), ("/p/chair.jpg", 1000, 1000, 1)] {
+            conn.execute(
+                "UPDATE images SET width = ?2, height = ?3, orientation = ?4 WHERE path = ?1",
+                rusqlite::params![path, w, h, orientation],
+            )
+            .unwrap();
+        }
+
+        let q = |query: &str| query_images(&project, query).unwrap();
+        assert_eq!(q("material"), vec!["/p/lamp.jpg", "/p/vase.jpg"]);
+        assert_eq!(q("material -material/glass"), vec!["/p/lamp.jpg"]);
+        assert_eq!(q("style:geometric OR vase"), vec!["/p/chair.jpg", "/p/lamp.jpg", "/p/vase.jpg"]);
+        assert_eq!(q("era:1920s NOT (lamp OR color:#c8a868)"), vec!["/p/vase.jpg"]);
+        assert_eq!(q("color:#c8a868"), vec!["/p/lamp.jpg"]);
+        // The vase is stored rotated, so it displays as portrait
+        assert_eq!(q("orientation:landscape"), vec!["/p/lamp.jpg"]);
+        assert_eq!(q("orientation:portrait"), vec!["/p/vase.jpg"]);
+        assert_eq!(q("orientation:square width:<2000"), vec!["/p/chair.jpg"]);
+        assert_eq!(q("aspect:3/2"), vec!["/p/lamp.jpg"]);
+
+        // Tag terms go through the vocabulary
+        let vocab = crate::vocab::Vocabulary {
+            tags: std::collections::BTreeMap::from([("lamp".to_string(), vec!["light".to_string()])]),
+            ..Default::default()
+        };
+        vocab.save(&project).unwrap();
+        assert_eq!(q("light"), vec!["/p/lamp.jpg"]);
+    }
+
+    #[test]
+    fn test_saved_collections_follow_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_string_lossy().to_string();
+        let conn = crate::search::open_db(&project).unwrap();
+        upsert_image(&conn, &image("/p/a.jpg", &["art-deco"], &[], None, &[]), &Default::default()).unwrap();
+        upsert_image(&conn, &image("/p/b.jpg", &["bauhaus"], &[], None, &[]), &Default::default()).unwrap();
+
+        save_collection(&project, "Deco", "art-deco", false).unwrap();
+        assert!(save_collection(&project, "deco", "bauhaus", false).unwrap_err().contains("already exists"));
+        assert!(save_collection(&project, " ", "bauhaus", false).unwrap_err().contains("empty"));
+        assert!(save_collection(&project, "Broken", "(bauhaus", false).is_err());
+        assert_eq!(collection_images(&project, "deco").unwrap(), vec!["/p/a.jpg"]);
+
+        // Membership follows tag edits
+        upsert_image(&conn, &image("/p/b.jpg", &["bauhaus", "art-deco"], &[], None, &[]), &Default::default()).unwrap();
+        assert_eq!(collection_images(&project, "Deco").unwrap(), vec!["/p/a.jpg", "/p/b.jpg"]);
+
+        save_collection(&project, "Deco", "art-deco -bauhaus", true).unwrap();
+        save_collection(&project, "Bauhaus", "bauhaus", false).unwrap();
+        let listed = list_collections(&project).unwrap();
+        assert_eq!(
+            listed,
+            vec![
+                CollectionInfo { name: "Deco".to_string(), query: "art-deco -bauhaus".to_string(), count: 1, error: None },
+                CollectionInfo { name: "Bauhaus".to_string(), query: "bauhaus".to_string(), count: 1, error: None },
+            ]
+        );
+
+        // A query broken by hand is reported, not fatal to the listing
+        let path = collections_path(&project);
+        let edited = std::fs::read_to_string(&path).unwrap().replace("\"bauhaus\"", "\"(bauhaus\"");
+        std::fs::write(&path, edited).unwrap();
+        let listed = list_collections(&project).unwrap();
+        assert_eq!(listed[0].count, 1);
+        assert_eq!((listed[1].count, listed[1].error.is_some()), (0, true));
+        assert!(collection_images(&project, "Bauhaus").is_err());
+
+        delete_collection(&project, "bauhaus").unwrap();
+        assert!(delete_collection(&project, "bauhaus").unwrap_err().contains("not found"));
+        assert!(collection_images(&project, "Bauhaus").unwrap_err().contains("not found"));
+        assert_eq!(load_collections(&project).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,215 @@
+//! Content-hash (SHA-256) duplicate detection for imports.
+//!
+//! Every import path (Tauri, CLI, HTTP API, web download) hashes the incoming
+//! bytes and looks for an identical file in the project's `images/` folder
+//! before writing. Hashes are cached in `search.db` (`content_hashes`) keyed
+//! by path and invalidated by size/mtime. They are recorded when a file is
+//! imported or indexed, so a lookup only reads the files whose cached row
+//! carries the incoming hash, and re-hashes them only if they changed since.
+
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// What to do when imported content already exists in the project.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DuplicatePolicy {
+    /// Don't import; report the existing file.
+    Skip,
+    /// Don't write a copy; return the existing file so it can be placed again.
+    #[default]
+    Link,
+    /// Write a second copy anyway (previous behaviour).
+    KeepBoth,
+}
+
+impl std::str::FromStr for DuplicatePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(DuplicatePolicy::Skip),
+            "link" => Ok(DuplicatePolicy::Link),
+            "keep-both" | "keep" => Ok(DuplicatePolicy::KeepBoth),
+            other => Err(format!(
+                "Unknown duplicate policy: {other} (expected skip, link or keep-both)"
+            )),
+        }
+    }
+}
+
+/// Attached to an `ImageInfo` when its content was already in the project.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateInfo {
+    /// The file that already held this content.
+    pub existing_path: String,
+    /// The policy that was applied.
+    pub action: DuplicatePolicy,
+}
+
+/// Default policy from `duplicatePolicy` in ~/.deco/config.json.
+pub fn default_policy() -> DuplicatePolicy {
+    let home = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
+    let path = Path::new(&home).join(".deco").join("config.json");
+
+    #[derive(Deserialize, Default)]
+    #[serde(rename_all = "camelCase", default)]
+    struct FullConfig {
+        duplicate_policy: Option<DuplicatePolicy>,
+    }
+
+    std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|s| serde_json::from_str::<FullConfig>(&s).ok())
+        .and_then(|c| c.duplicate_policy)
+        .unwrap_or_default()
+}
+
+/// Lowercase hex SHA-256 of `data`.
+pub fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// Size and mtime (nanoseconds) of a file, used to tell when cached hashes go stale.
+pub fn file_stamp(path: &Path) -> Option<(i64, i64)> {
+    let meta = std::fs::metadata(path).ok()?;
+    let mtime = meta
+        .modified()
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_nanos() as i64;
+    Some((meta.len() as i64, mtime))
+}
+
+/// Record the hash of a file in the project (after writing it).
+pub fn record(project_path: &str, file_path: &Path, hash: &str) -> Result<(), String> {
+    let Some((size, mtime)) = file_stamp(file_path) else {
+        return Ok(());
+    };
+    let conn = crate::search::open_db(project_path)?;
+    conn.execute(
+        "INSERT OR REPLACE INTO content_hashes (path, sha256, size, mtime) VALUES (?1, ?2, ?3, ?4)",
+        params![file_path.to_string_lossy(), hash, size, mtime],
+    )
+    .map_err(|e| format!("Cannot store content hash: {e}"))?;
+    Ok(())
+}
+
+/// SHA-256 of a project file, from the cache when its size/mtime still match.
+pub fn content_hash(project_path: &str, file_path: &Path) -> Result<String, String> {
+    let conn = crate::search::open_db(project_path)?;
+    content_hash_conn(&conn, file_path)
+}
+
+/// `content_hash` on an open project database.
+pub fn content_hash_conn(conn: &rusqlite::Connection, file_path: &Path) -> Result<String, String> {
+    let stamp = file_stamp(file_path)
+        .ok_or_else(|| format!("Cannot stat {}", file_path.display()))?;
+    let path_str = file_path.to_string_lossy().to_string();
+
+    let cached: Option<(String, i64, i64)> = conn
+        .query_row(
+            "SELECT sha256, size, mtime FROM content_hashes WHERE path = ?1",
+            params![path_str],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Cannot read content hash: {e}"))?;
+    if let Some((hash, size, mtime)) = cached {
+        if (size, mtime) == stamp {
+            return Ok(hash);
+        }
+    }
+
+    let data = std::fs::read(file_path)
+        .map_err(|e| format!("Cannot read {}: {e}", file_path.display()))?;
+    let hash = sha256_hex(&data);
+    conn.execute(
+        "INSERT OR REPLACE INTO content_hashes (path, sha256, size, mtime) VALUES (?1, ?2, ?3, ?4)",
+        params![path_str, hash, stamp.0, stamp.1],
+    )
+    .map_err(|e| format!("Cannot store content hash: {e}"))?;
+    Ok(hash)
+}
+
+/// Find a file in the project's images whose content hashes to `hash`.
+/// Only files recorded with that hash are checked; one that changed on disk
+/// since is re-hashed, and one that is gone is skipped.
+pub fn find_existing(project_path: &str, hash: &str) -> Result<Option<String>, String> {
+    let images_dir = Path::new(project_path).join("images");
+    if !images_dir.exists() {
+        return Ok(None);
+    }
+    let conn = crate::search::open_db(project_path)?;
+    let mut stmt = conn
+        .prepare("SELECT path FROM content_hashes WHERE sha256 = ?1 ORDER BY path")
+        .map_err(|e| format!("Cannot query content hashes: {e}"))?;
+    let paths: Vec<String> = stmt
+        .query_map(params![hash], |row| row.get(0))
+        .map_err(|e| format!("Cannot query content hashes: {e}"))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    for path in paths {
+        let file = Path::new(&path);
+        if !file.is_file() {
+            continue;
+        }
+        if content_hash_conn(&conn, file)? == hash {
+            return Ok(Some(path));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256_hex() {
+        assert_eq!(
+            sha256_hex(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_policy_parse() {
+        assert_eq!("skip".parse::<DuplicatePolicy>(), Ok(DuplicatePolicy::Skip));
+        assert_eq!("keep-both".parse::<DuplicatePolicy>(), Ok(DuplicatePolicy::KeepBoth));
+        assert!("maybe".parse::<DuplicatePolicy>().is_err());
+        assert_eq!(
+            serde_json::to_string(&DuplicatePolicy::KeepBoth).unwrap(),
+            "\"keep-both\""
+        );
+    }
+
+    #[test]
+    fn test_find_existing() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_string_lossy().to_string();
+        let images = dir.path().join("images");
+        std::fs::create_dir_all(&images).unwrap();
+        std::fs::write(images.join("a.png"), b"first image").unwrap();
+        std::fs::write(images.join("b.png"), b"second image").unwrap();
+        let scanned = crate::scan_images_in(&images.to_string_lossy()).unwrap();
+        crate::search::index_project_images(&project, &scanned).unwrap();
+
+        let found = find_existing(&project, &sha256_hex(b"second image")).unwrap();
+        assert_eq!(found.as_deref(), Some(images.join("b.png").to_string_lossy().as_ref()));
+        assert!(find_existing(&project, &sha256_hex(b"new")).unwrap().is_none());
+
+        // A file rewritten in place is re-hashed, not served from the cache
+        std::fs::write(images.join("b.png"), b"edited image!").unwrap();
+        assert!(find_existing(&project, &sha256_hex(b"second image")).unwrap().is_none());
+
+        // A recorded file that is gone is skipped
+        std::fs::remove_file(images.join("a.png")).unwrap();
+        assert!(find_existing(&project, &sha256_hex(b"first image")).unwrap().is_none());
+    }
+}
@@ -0,0 +1,643 @@
+//! Project integrity check and repair (`deco doctor`).
+//!
+//! Reconciles the image files with `.deco/search.db` (metadata rows, FTS5
+//! index, embeddings, hashes, ANN graph), `.deco/board.json` and
+//! `metadata.json`. Every problem is reported as an `Issue`; with `fix` set,
+//! everything that can be repaired without re-running the models is repaired
+//! in place. Missing embeddings are reported only (`deco embed` fills them in).
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+/// Kind of problem found by the check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IssueKind {
+    /// SQLite's own integrity check failed (not repairable here).
+    Database,
+    /// Rows (metadata, embedding, hashes) for a file that no longer exists.
+    OrphanRow,
+    /// An image file with no metadata row.
+    MissingRow,
+    /// A vector whose size doesn't match its recorded or the project's dimensions.
+    BadVector,
+    /// An indexed image without a CLIP embedding.
+    MissingEmbedding,
+    /// The ANN graph doesn't match the stored embeddings.
+    StaleAnnIndex,
+    /// The FTS5 index is inconsistent with the `images` table.
+    CorruptFts,
+    /// A board item pointing at a missing image.
+    BoardMissingImage,
+    /// `board.json` can't be parsed.
+    InvalidBoard,
+    /// `metadata.json` is missing, unreadable or points elsewhere.
+    Metadata,
+}
+
+impl IssueKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            IssueKind::Database => "database integrity",
+            IssueKind::OrphanRow => "orphaned index rows",
+            IssueKind::MissingRow => "unindexed images",
+            IssueKind::BadVector => "mismatched vectors",
+            IssueKind::MissingEmbedding => "missing embeddings",
+            IssueKind::StaleAnnIndex => "stale similarity index",
+            IssueKind::CorruptFts => "corrupt full-text index",
+            IssueKind::BoardMissingImage => "board items without an image",
+            IssueKind::InvalidBoard => "invalid board.json",
+            IssueKind::Metadata => "project metadata",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Issue {
+    pub kind: IssueKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    pub detail: String,
+    pub fixed: bool,
+}
+
+/// Issue counts for one kind.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IssueSummary {
+    pub kind: IssueKind,
+    pub found: usize,
+    pub fixed: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DoctorReport {
+    pub project_path: String,
+    /// Image files found in the project.
+    pub images: usize,
+    /// Whether repairs were attempted.
+    pub fix: bool,
+    pub summary: Vec<IssueSummary>,
+    pub issues: Vec<Issue>,
+}
+
+impl DoctorReport {
+    /// Issues still present after this run.
+    pub fn remaining(&self) -> usize {
+        self.issues.iter().filter(|i| !i.fixed).count()
+    }
+}
+
+/// Accumulates issues; `fix` tells each check whether to repair.
+struct Checker<'a> {
+    project_path: &'a str,
+    fix: bool,
+    issues: Vec<Issue>,
+}
+
+impl Checker<'_> {
+    fn found(&mut self, kind: IssueKind, path: Option<&str>, detail: String, fixed: bool) {
+        self.issues.push(Issue {
+            kind,
+            path: path.map(str::to_string),
+            detail,
+            fixed,
+        });
+    }
+
+    /// Record an issue, running `repair` first in fix mode. A failed repair
+    /// leaves the issue unfixed with the error appended.
+    fn repair(
+        &mut self,
+        kind: IssueKind,
+        path: Option<&str>,
+        detail: String,
+        repair: impl FnOnce() -> Result<(), String>,
+    ) {
+        if !self.fix {
+            self.found(kind, path, detail, false);
+            return;
+        }
+        match repair() {
+            Ok(()) => self.found(kind, path, detail, true),
+            Err(e) => self.found(kind, path, format!("{detail} (repair failed: {e})"), false),
+        }
+    }
+}
+
+/// Check a project and, with `fix`, repair what can be repaired.
+pub fn run(project_path: &str, fix: bool) -> Result<DoctorReport, String> {
+    if !Path::new(project_path).is_dir() {
+        return Err(format!("Project not found: {project_path}"));
+    }
+    let images = crate::scan_images_in(project_path)?;
+    let conn = crate::search::open_db(project_path)?;
+    let mut checker = Checker { project_path, fix, issues: Vec::new() };
+
+    check_database(&mut checker, &conn)?;
+    check_orphans(&mut checker, &conn)?;
+    check_missing_rows(&mut checker, &conn, &images)?;
+    check_vectors(&mut checker, &conn)?;
+    check_ann(&mut checker, &conn)?;
+    check_fts(&mut checker, &conn);
+    check_board(&mut checker)?;
+    check_metadata(&mut checker, images.len());
+
+    let mut counts: BTreeMap<IssueKind, IssueSummary> = BTreeMap::new();
+    for issue in &checker.issues {
+        let entry = counts.entry(issue.kind).or_insert(IssueSummary {
+            kind: issue.kind,
+            found: 0,
+            fixed: 0,
+        });
+        entry.found += 1;
+        entry.fixed += issue.fixed as usize;
+    }
+
+    let report = DoctorReport {
+        project_path: project_path.to_string(),
+        images: images.len(),
+        fix,
+        summary: counts.into_values().collect(),
+        issues: checker.issues,
+    };
+    crate::log::log(
+        "DOCTOR",
+        &format!(
+            "{project_path}: {} issue(s), {} remaining",
+            report.issues.len(),
+            report.remaining()
+        ),
+    );
+    Ok(report)
+}
+
+fn query_strings(conn: &Connection, sql: &str) -> Result<Vec<String>, String> {
+    let mut stmt = conn.prepare(sql).map_err(|e| format!("Cannot query database: {e}"))?;
+    let rows = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Cannot query database: {e}"))?
+        .filter_map(|r| r.ok())
+        .collect();
+    Ok(rows)
+}
+
+fn check_database(checker: &mut Checker, conn: &Connection) -> Result<(), String> {
+    let problems = query_strings(conn, "PRAGMA quick_check")?;
+    if problems.iter().any(|p| p != "ok") {
+        checker.found(IssueKind::Database, None, problems.join("; "), false);
+    }
+    Ok(())
+}
+
+/// Paths with rows in any per-image table but no file on disk.
+fn check_orphans(checker: &mut Checker, conn: &Connection) -> Result<(), String> {
+    let indexed = query_strings(
+        conn,
+        "SELECT path FROM images UNION SELECT path FROM embeddings
+         UNION SELECT path FROM image_hashes UNION SELECT path FROM content_hashes",
+    )?;
+    let project_path = checker.project_path;
+    for path in indexed.iter().filter(|p| !Path::new(p).exists()) {
+        checker.repair(
+            IssueKind::OrphanRow,
+            Some(path),
+            "indexed, but the file is missing".to_string(),
+            || {
+                crate::thumbs::remove_for(project_path, path);
+                crate::search::delete_image_data(project_path, path)
+            },
+        );
+    }
+    Ok(())
+}
+
+fn check_missing_rows(
+    checker: &mut Checker,
+    conn: &Connection,
+    images: &[crate::ImageInfo],
+) -> Result<(), String> {
+    let indexed: HashSet<String> = query_strings(conn, "SELECT path FROM images")?
+        .into_iter()
+        .collect();
+    let missing: Vec<crate::ImageInfo> = images
+        .iter()
+        .filter(|img| !indexed.contains(&img.path))
+        .cloned()
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let result = if checker.fix {
+        crate::search::index_project_images(checker.project_path, &missing).map(|_| ())
+    } else {
+        Ok(())
+    };
+    for img in &missing {
+        let detail = "image file is not indexed".to_string();
+        match &result {
+            Ok(()) => checker.found(IssueKind::MissingRow, Some(&img.path), detail, checker.fix),
+            Err(e) => checker.found(
+                IssueKind::MissingRow,
+                Some(&img.path),
+                format!("{detail} (repair failed: {e})"),
+                false,
+            ),
+        }
+    }
+    Ok(())
+}
+
+/// Vectors whose blob doesn't match their `dimensions`, or whose dimensions
+/// differ from the rest of the same model's vectors. Removing them lets
+/// `deco embed` regenerate them.
+fn check_vectors(checker: &mut Checker, conn: &Connection) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare("SELECT path, model, length(vector), dimensions FROM embeddings ORDER BY path, model")
+        .map_err(|e| format!("Embedding query failed: {e}"))?;
+    let rows: Vec<(String, String, i64, i64)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+        .map_err(|e| format!("Embedding query failed: {e}"))?
+        .filter_map(|r| r.ok())
+        .collect();
+    drop(stmt);
+
+    let mut dim_counts: HashMap<&str, HashMap<i64, usize>> = HashMap::new();
+    for (_, model, bytes, dims) in &rows {
+        if *dims > 0 && *bytes == dims * 4 {
+            *dim_counts.entry(model).or_default().entry(*dims).or_default() += 1;
+        }
+    }
+    let model_dims: HashMap<&str, i64> = dim_counts
+        .into_iter()
+        .filter_map(|(model, counts)| {
+            let dims = counts.into_iter().max_by_key(|&(d, n)| (n, d))?.0;
+            Some((model, dims))
+        })
+        .collect();
+
+    for (path, model, bytes, dims) in &rows {
+        let detail = if *dims <= 0 || *bytes != dims * 4 {
+            format!("{model} vector is {bytes} bytes but records {dims} dimensions")
+        } else if model_dims.get(model.as_str()) != Some(dims) {
+            format!("{dims} dimensions, {model} uses {}", model_dims.get(model.as_str()).unwrap_or(&0))
+        } else {
+            continue;
+        };
+        checker.repair(IssueKind::BadVector, Some(path), detail, || {
+            crate::ann::remove(conn, model, path)?;
+            conn.execute("DELETE FROM embeddings WHERE path = ?1 AND model = ?2", params![path, model])
+                .map(|_| ())
+                .map_err(|e| format!("Cannot delete embedding: {e}"))
+        });
+    }
+
+    // Only meaningful once the project has been embedded at all
+    if !rows.is_empty() {
+        let unembedded = query_strings(
+            conn,
+            "SELECT path FROM images WHERE path NOT IN (SELECT path FROM embeddings) ORDER BY path",
+        )?;
+        for path in unembedded.iter().filter(|p| Path::new(p).exists()) {
+            checker.found(
+                IssueKind::MissingEmbedding,
+                Some(path),
+                "no embedding (run `deco embed`)".to_string(),
+                false,
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Compare each model's ANN graph with the embeddings it should contain.
+fn check_ann(checker: &mut Checker, conn: &Connection) -> Result<(), String> {
+    for model in query_strings(conn, "SELECT DISTINCT model FROM ann_meta ORDER BY model")? {
+        check_ann_model(checker, conn, &model)?;
+    }
+    Ok(())
+}
+
+fn check_ann_model(checker: &mut Checker, conn: &Connection, model: &str) -> Result<(), String> {
+    if !crate::ann::is_present(conn, model)? {
+        return Ok(());
+    }
+    let dims: i64 = conn
+        .query_row(
+            "SELECT value FROM ann_meta WHERE model = ?1 AND key = 'dimensions'",
+            params![model],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Cannot read ANN metadata: {e}"))?
+        .unwrap_or(0);
+
+    let mut stmt = conn
+        .prepare("SELECT path FROM ann_nodes WHERE model = ?1")
+        .map_err(|e| format!("Cannot query ANN nodes: {e}"))?;
+    let nodes: HashSet<String> = stmt
+        .query_map(params![model], |row| row.get(0))
+        .map_err(|e| format!("Cannot query ANN nodes: {e}"))?
+        .filter_map(|r| r.ok())
+        .collect();
+    drop(stmt);
+    let mut stmt = conn
+        .prepare(
+            "SELECT path, dimensions FROM embeddings
+             WHERE model = ?1 AND length(vector) = dimensions * 4",
+        )
+        .map_err(|e| format!("Embedding query failed: {e}"))?;
+    let embedded: Vec<(String, i64)> = stmt
+        .query_map(params![model], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("Embedding query failed: {e}"))?
+        .filter_map(|r| r.ok())
+        .collect();
+    drop(stmt);
+
+    let expected: HashSet<String> = embedded
+        .iter()
+        .filter(|(_, d)| *d == dims)
+        .map(|(p, _)| p.clone())
+        .collect();
+    let stray = nodes.difference(&expected).count();
+    let unlinked = expected.difference(&nodes).count();
+    let other_dims = embedded.iter().filter(|(_, d)| *d != dims).count();
+    // A lone straggler dimension is fine; the index follows the majority
+    let wrong_dims = dims != 0 && other_dims > expected.len();
+    if stray == 0 && unlinked == 0 && !wrong_dims {
+        return Ok(());
+    }
+
+    let detail = format!(
+        "{model}: {stray} node(s) without a vector, {unlinked} vector(s) not indexed{}",
+        if wrong_dims { ", indexed dimensions are not the model's" } else { "" }
+    );
+    checker.repair(IssueKind::StaleAnnIndex, None, detail, || {
+        crate::ann::rebuild(conn, model).map(|_| ())
+    });
+    Ok(())
+}
+
+fn check_fts(checker: &mut Checker, conn: &Connection) {
+    // rank = 1 also compares the index against the `images` content table
+    let Err(e) = conn.execute(
+        "INSERT INTO images_fts(images_fts, rank) VALUES('integrity-check', 1)",
+        [],
+    ) else {
+        return;
+    };
+    checker.repair(IssueKind::CorruptFts, None, e.to_string(), || {
+        conn.execute("INSERT INTO images_fts(images_fts) VALUES('rebuild')", [])
+            .map(|_| ())
+            .map_err(|e| format!("Cannot rebuild full-text index: {e}"))
+    });
+}
+
+fn check_board(checker: &mut Checker) -> Result<(), String> {
+    let board_path = Path::new(checker.project_path).join(".deco").join("board.json");
+    let Ok(contents) = fs::read_to_string(&board_path) else {
+        return Ok(());
+    };
+
+    let state: serde_json::Value = match serde_json::from_str(&contents) {
+        Ok(state) => state,
+        Err(e) => {
+            // Set it aside; the app lays the board out afresh without one
+            let detail = format!("cannot parse board.json: {e}");
+            checker.repair(IssueKind::InvalidBoard, None, detail, || {
+                fs::rename(&board_path, board_path.with_extension("json.corrupt"))
+                    .map_err(|e| format!("Cannot move board.json aside: {e}"))
+            });
+            return Ok(());
+        }
+    };
+
+    let missing: Vec<String> = state
+        .get("items")
+        .and_then(|v| v.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|item| item.get("path").and_then(|v| v.as_str()))
+        .filter(|path| !Path::new(path).exists())
+        .map(str::to_string)
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let result = if checker.fix {
+        crate::ops::sync_board_items(checker.project_path, &missing, &[]).map(|_| ())
+    } else {
+        Ok(())
+    };
+    for path in &missing {
+        let detail = "board item points to a missing image".to_string();
+        match &result {
+            Ok(()) => checker.found(IssueKind::BoardMissingImage, Some(path), detail, checker.fix),
+            Err(e) => checker.found(
+                IssueKind::BoardMissingImage,
+                Some(path),
+                format!("{detail} (repair failed: {e})"),
+                false,
+            ),
+        }
+    }
+    Ok(())
+}
+
+/// `metadata.json` must exist, parse, and point at the project's own folder
+/// (it goes stale when a project folder is moved by hand).
+fn check_metadata(checker: &mut Checker, image_count: usize) {
+    let project_path = checker.project_path;
+    let meta_path = Path::new(project_path).join("metadata.json");
+    let parsed = fs::read_to_string(&meta_path)
+        .ok()
+        .map(|s| serde_json::from_str::<crate::ProjectMetadata>(&s));
+    let corrupt = matches!(parsed, Some(Err(_)));
+
+    let (detail, mut meta) = match parsed {
+        Some(Ok(meta)) if meta.path == project_path => return,
+        Some(Ok(meta)) => (format!("metadata.json points to {}", meta.path), meta),
+        Some(Err(e)) => (
+            format!("cannot parse metadata.json: {e}"),
+            fresh_metadata(project_path, image_count),
+        ),
+        None => (
+            "metadata.json is missing".to_string(),
+            fresh_metadata(project_path, image_count),
+        ),
+    };
+
+    checker.repair(IssueKind::Metadata, None, detail, || {
+        if corrupt {
+            fs::copy(&meta_path, meta_path.with_extension("json.corrupt"))
+                .map_err(|e| format!("Cannot back up metadata.json: {e}"))?;
+        }
+        meta.path = project_path.to_string();
+        meta.updated_at = Some(crate::chrono_now_iso());
+        let json = serde_json::to_string_pretty(&meta)
+            .map_err(|e| format!("Cannot serialize metadata: {e}"))?;
+        fs::write(&meta_path, json).map_err(|e| format!("Cannot write metadata.json: {e}"))
+    });
+}
+
+/// Metadata for a project without a usable `metadata.json`, named after
+/// `deco.json` or else the folder.
+fn fresh_metadata(project_path: &str, image_count: usize) -> crate::ProjectMetadata {
+    let dir = Path::new(project_path);
+    let name = fs::read_to_string(dir.join("deco.json"))
+        .ok()
+        .and_then(|s| serde_json::from_str::<serde_json::Value>(&s).ok())
+        .and_then(|v| v.get("name").and_then(|n| n.as_str()).map(str::to_string))
+        .unwrap_or_else(|| dir.file_name().unwrap_or_default().to_string_lossy().to_string());
+    crate::ProjectMetadata {
+        name,
+        path: project_path.to_string(),
+        description: None,
+        tags: Vec::new(),
+        image_count,
+        created_at: Some(crate::chrono_now_iso()),
+        updated_at: None,
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tauri Commands
+// ---------------------------------------------------------------------------
+
+/// Check a project's index, board and metadata via storage backend; repairs
+/// what it can when `fix` is set.
+#[tauri::command]
+pub async fn cmd_doctor_project(
+    storage: tauri::State<'_, crate::storage::Storage>,
+    project_path: String,
+    fix: Option<bool>,
+) -> Result<DoctorReport, String> {
+    storage.check_project(&project_path, fix.unwrap_or(false)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_png(path: &Path, shade: u8) {
+        image::RgbImage::from_fn(24, 16, |x, y| image::Rgb([shade, (x * 9) as u8, (y * 13) as u8]))
+            .save(path)
+            .unwrap();
+    }
+
+    fn kinds(report: &DoctorReport) -> Vec<(IssueKind, usize)> {
+        report.summary.iter().map(|s| (s.kind, s.found)).collect()
+    }
+
+    #[test]
+    fn test_doctor_detects_and_repairs() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_string_lossy().to_string();
+        let images_dir = dir.path().join("images");
+        fs::create_dir_all(&images_dir).unwrap();
+        let path = |name: &str| images_dir.join(name).to_string_lossy().to_string();
+        for (i, name) in ["a.png", "b.png", "c.png", "d.png", "gone.png"].iter().enumerate() {
+            write_png(&images_dir.join(name), i as u8 * 50);
+        }
+        let images = crate::scan_images_in(&project).unwrap();
+        crate::search::index_project_images(&project, &images).unwrap();
+
+        let conn = crate::search::open_db(&project).unwrap();
+        for name in ["a.png", "b.png", "gone.png"] {
+            crate::search::store_embedding_conn(&conn, &path(name), "test", &[0.1, 0.2, 0.3, 0.4]).unwrap();
+        }
+        // Vector with the wrong dimensions for its model, and a truncated one
+        conn.execute(
+            "INSERT INTO embeddings (path, model, vector, dimensions) VALUES (?1, 'test', ?2, 3)",
+            params![path("c.png"), vec![0u8; 12]],
+        )
+        .unwrap();
+        // Another model's vectors are checked on their own terms
+        crate::search::store_embedding_conn(&conn, &path("a.png"), "other", &[0.5, 0.5]).unwrap();
+        conn.execute(
+            "INSERT INTO embeddings (path, model, vector, dimensions) VALUES (?1, 'test', ?2, 4)",
+            params![path("d.png"), vec![0u8; 6]],
+        )
+        .unwrap();
+        // Stray full-text entry with no image row behind it
+        conn.execute(
+            "INSERT INTO images_fts(rowid, name, description, tags, style, mood, era)
+             VALUES (999, 'ghost', '', '', '', '', '')",
+            [],
+        )
+        .unwrap();
+        drop(conn);
+
+        fs::remove_file(images_dir.join("gone.png")).unwrap();
+        write_png(&images_dir.join("new.png"), 240);
+        let board = serde_json::json!({
+            "items": [{ "path": path("a.png"), "name": "a.png" }, { "path": path("gone.png"), "name": "gone.png" }]
+        });
+        fs::create_dir_all(dir.path().join(".deco")).unwrap();
+        fs::write(dir.path().join(".deco/board.json"), board.to_string()).unwrap();
+
+        let report = run(&project, false).unwrap();
+        assert_eq!(report.images, 5);
+        assert_eq!(
+            kinds(&report),
+            vec![
+                (IssueKind::OrphanRow, 1),
+                (IssueKind::MissingRow, 1),
+                (IssueKind::BadVector, 2),
+                (IssueKind::CorruptFts, 1),
+                (IssueKind::BoardMissingImage, 1),
+                (IssueKind::Metadata, 1),
+            ]
+        );
+        assert_eq!(report.remaining(), report.issues.len());
+        // A dry run changes nothing
+        assert_eq!(kinds(&run(&project, false).unwrap()), kinds(&report));
+
+        let report = run(&project, true).unwrap();
+        assert!(report
+            .issues
+            .iter()
+            .all(|i| i.fixed || i.kind == IssueKind::MissingEmbedding));
+
+        // Only the embeddings that need the model are left
+        let report = run(&project, false).unwrap();
+        assert_eq!(kinds(&report), vec![(IssueKind::MissingEmbedding, 3)]);
+        let board: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(dir.path().join(".deco/board.json")).unwrap()).unwrap();
+        assert_eq!(board["items"].as_array().unwrap().len(), 1);
+        let meta: crate::ProjectMetadata =
+            serde_json::from_str(&fs::read_to_string(dir.path().join("metadata.json")).unwrap()).unwrap();
+        assert_eq!(meta.path, project);
+    }
+
+    #[test]
+    fn test_doctor_rebuilds_stale_ann_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_string_lossy().to_string();
+        let images_dir = dir.path().join("images");
+        fs::create_dir_all(&images_dir).unwrap();
+        let conn = crate::search::open_db(&project).unwrap();
+        for (i, name) in ["a.png", "b.png"].iter().enumerate() {
+            let file = images_dir.join(name);
+            write_png(&file, i as u8 * 90);
+            crate::search::store_embedding_conn(&conn, &file.to_string_lossy(), "test", &[1.0, i as f32]).unwrap();
+        }
+        let images = crate::scan_images_in(&project).unwrap();
+        crate::search::index_project_images(&project, &images).unwrap();
+        conn.execute("DELETE FROM ann_nodes", []).unwrap();
+        drop(conn);
+
+        let report = run(&project, true).unwrap();
+        let ann: Vec<_> = report.issues.iter().filter(|i| i.kind == IssueKind::StaleAnnIndex).collect();
+        assert_eq!(ann.len(), 1);
+        assert!(ann[0].fixed);
+        assert!(run(&project, false).unwrap().issues.is_empty());
+    }
+}
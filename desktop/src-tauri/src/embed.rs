@@ -1,32 +1,185 @@
-//! CLIP image embedding using fastembed (ONNX Runtime + CoreML on Apple Silicon).
+//! Image embedding using fastembed (ONNX Runtime + CoreML on Apple Silicon).
 //!
-//! Provides local CLIP ViT-B/32 inference for visual similarity search.
-//! Model auto-downloads on first use (~150MB).
+//! Provides local inference for visual similarity search with a choice of
+//! image models (`ImageModel`); CLIP ViT-B/32 is the default. Embeddings are
+//! stored per (image, model) and only ever compared within one model.
+//! The CLIP text tower embeds search queries into the CLIP ViT-B/32 space for
+//! cross-modal semantic search.
+//! Models auto-download on first use (~150MB CLIP image, ~250MB text).
 
-use fastembed::{ImageEmbedding, ImageEmbeddingModel, ImageInitOptions};
-use std::sync::Mutex;
+use fastembed::{
+    EmbeddingModel, ImageEmbedding, ImageEmbeddingModel, ImageInitOptions, InitOptions,
+    TextEmbedding,
+};
+use rusqlite::OptionalExtension;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard};
 
-static MODEL: Mutex<Option<ImageEmbedding>> = Mutex::new(None);
+/// A local image embedding model. `id()` is what the `embeddings.model`
+/// column stores.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum ImageModel {
+    /// CLIP ViT-B/32 (512 dims). Shares a space with the CLIP text tower.
+    #[default]
+    #[serde(rename = "clip-vit-b-32", alias = "clip")]
+    ClipVitB32,
+    /// Unicom ViT-B/16 (768 dims): finer-grained visual similarity.
+    #[serde(rename = "unicom-vit-b-16")]
+    UnicomVitB16,
+    /// Nomic Embed Vision v1.5 (768 dims).
+    #[serde(rename = "nomic-embed-vision-v1.5")]
+    NomicEmbedVisionV15,
+    /// ResNet-50 (2048 dims): texture and composition rather than content.
+    #[serde(rename = "resnet50")]
+    Resnet50,
+}
+
+impl ImageModel {
+    pub const ALL: [ImageModel; 4] = [
+        ImageModel::ClipVitB32,
+        ImageModel::UnicomVitB16,
+        ImageModel::NomicEmbedVisionV15,
+        ImageModel::Resnet50,
+    ];
+
+    pub fn id(self) -> &'static str {
+        match self {
+            ImageModel::ClipVitB32 => "clip-vit-b-32",
+            ImageModel::UnicomVitB16 => "unicom-vit-b-16",
+            ImageModel::NomicEmbedVisionV15 => "nomic-embed-vision-v1.5",
+            ImageModel::Resnet50 => "resnet50",
+        }
+    }
+
+    pub fn dimensions(self) -> usize {
+        match self {
+            ImageModel::ClipVitB32 => 512,
+            ImageModel::UnicomVitB16 | ImageModel::NomicEmbedVisionV15 => 768,
+            ImageModel::Resnet50 => 2048,
+        }
+    }
+
+    fn fastembed(self) -> ImageEmbeddingModel {
+        match self {
+            ImageModel::ClipVitB32 => ImageEmbeddingModel::ClipVitB32,
+            ImageModel::UnicomVitB16 => ImageEmbeddingModel::UnicomVitB16,
+            ImageModel::NomicEmbedVisionV15 => ImageEmbeddingModel::NomicEmbedVisionV15,
+            ImageModel::Resnet50 => ImageEmbeddingModel::Resnet50,
+        }
+    }
+}
+
+impl std::fmt::Display for ImageModel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+impl std::str::FromStr for ImageModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "clip" => Ok(ImageModel::ClipVitB32),
+            "unicom" => Ok(ImageModel::UnicomVitB16),
+            "nomic" => Ok(ImageModel::NomicEmbedVisionV15),
+            other => ImageModel::ALL
+                .into_iter()
+                .find(|m| m.id() == other)
+                .ok_or_else(|| {
+                    let known: Vec<&str> = ImageModel::ALL.iter().map(|m| m.id()).collect();
+                    format!("Unknown embedding model: {s} (expected one of {})", known.join(", "))
+                }),
+        }
+    }
+}
+
+/// The image model whose vector space `embed_text` queries live in.
+pub const TEXT_SEARCH_MODEL: ImageModel = ImageModel::ClipVitB32;
+
+/// Default model from `visionModel` in ~/.deco/config.json. Values that
+/// aren't embedding models (e.g. "grounding-dino-sam") fall back to CLIP.
+pub fn default_model() -> ImageModel {
+    let home = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
+    let path = Path::new(&home).join(".deco").join("config.json");
+
+    #[derive(Deserialize, Default)]
+    #[serde(rename_all = "camelCase", default)]
+    struct FullConfig {
+        vision_model: Option<String>,
+    }
+
+    std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|s| serde_json::from_str::<FullConfig>(&s).ok())
+        .and_then(|c| c.vision_model)
+        .and_then(|m| m.parse().ok())
+        .unwrap_or_default()
+}
+
+/// Resolve an optional model name, defaulting to `default_model()`.
+pub fn model_or_default(name: Option<&str>) -> Result<ImageModel, String> {
+    name.map_or_else(|| Ok(default_model()), str::parse)
+}
+
+static MODELS: Mutex<Option<HashMap<ImageModel, ImageEmbedding>>> = Mutex::new(None);
+static TEXT_MODEL: Mutex<Option<TextEmbedding>> = Mutex::new(None);
+
+type ModelGuard = MutexGuard<'static, Option<HashMap<ImageModel, ImageEmbedding>>>;
+
+fn get_or_init_model(model: ImageModel) -> Result<ModelGuard, String> {
+    let mut guard = MODELS.lock().map_err(|e| format!("Model lock poisoned: {e}"))?;
+    if let std::collections::hash_map::Entry::Vacant(slot) =
+        guard.get_or_insert_with(HashMap::new).entry(model)
+    {
+        crate::log::log("CLIP", &format!("Downloading/loading {model} model..."));
+        let options = ImageInitOptions::new(model.fastembed())
+            .with_show_download_progress(true);
+        let embedding = ImageEmbedding::try_new(options)
+            .map_err(|e| format!("Cannot initialize {model} model: {e}"))?;
+        slot.insert(embedding);
+        crate::log::log("CLIP", &format!("{model} model initialized successfully"));
+    }
+    Ok(guard)
+}
 
-fn get_or_init_model() -> Result<std::sync::MutexGuard<'static, Option<ImageEmbedding>>, String> {
-    let mut guard = MODEL.lock().map_err(|e| format!("Model lock poisoned: {e}"))?;
+fn get_or_init_text_model() -> Result<std::sync::MutexGuard<'static, Option<TextEmbedding>>, String> {
+    let mut guard = TEXT_MODEL.lock().map_err(|e| format!("Text model lock poisoned: {e}"))?;
     if guard.is_none() {
-        crate::log::log("CLIP", "Downloading/loading model (~150MB)...");
-        let options = ImageInitOptions::new(ImageEmbeddingModel::ClipVitB32)
+        crate::log::log("CLIP", "Downloading/loading text model...");
+        let options = InitOptions::new(EmbeddingModel::ClipVitB32)
             .with_show_download_progress(true);
-        let model = ImageEmbedding::try_new(options)
-            .map_err(|e| format!("Cannot initialize CLIP model: {e}"))?;
+        let model = TextEmbedding::try_new(options)
+            .map_err(|e| format!("Cannot initialize CLIP text model: {e}"))?;
         *guard = Some(model);
-        crate::log::log("CLIP", "Model initialized successfully");
+        crate::log::log("CLIP", "Text model initialized successfully");
     }
     Ok(guard)
 }
 
-/// Embed a batch of image files using CLIP ViT-B/32.
-pub fn embed_image_files(paths: &[String]) -> Result<Vec<Vec<f32>>, String> {
-    let guard = get_or_init_model()?;
+/// Embed a text query using the CLIP ViT-B/32 text tower.
+/// The result lives in the same vector space as `embed_image_files`.
+pub fn embed_text(query: &str) -> Result<Vec<f32>, String> {
+    let guard = get_or_init_text_model()?;
     let model = guard.as_ref().unwrap();
     model
+        .embed(vec![query], None)
+        .map_err(|e| {
+            crate::log::log("CLIP", &format!("Text embedding failed: {e}"));
+            format!("Text embedding failed: {e}")
+        })?
+        .into_iter()
+        .next()
+        .ok_or_else(|| "Text embedding returned no vector".to_string())
+}
+
+/// Embed a batch of image files with `model`.
+pub fn embed_image_files(paths: &[String], model: ImageModel) -> Result<Vec<Vec<f32>>, String> {
+    let guard = get_or_init_model(model)?;
+    let embedding = &guard.as_ref().unwrap()[&model];
+    embedding
         .embed(paths.to_vec(), Some(32))
         .map_err(|e| {
             crate::log::log("CLIP", &format!("Embedding failed: {e}"));
@@ -34,48 +187,156 @@ pub fn embed_image_files(paths: &[String]) -> Result<Vec<Vec<f32>>, String> {
         })
 }
 
-/// Embed images that don't already have embeddings and store them in the project DB.
-///
-/// Note: This function is called from the storage layer which already sets up
-/// the FASTEMBED_CACHE_PATH env var at startup. If the models_folder config changes
-/// after app launch, the user must restart the app for it to take effect here.
-pub fn embed_and_store(project_path: &str, image_paths: &[String]) -> Result<usize, String> {
-    let conn = crate::search::open_db(project_path)?;
+/// How `embed_and_store` treated each image.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmbedStats {
+    /// Images embedded for the first time.
+    pub fresh: usize,
+    /// Images re-embedded because the file changed (or `force` was set).
+    pub stale: usize,
+    /// Images whose stored embedding still matches the file.
+    pub reused: usize,
+}
+
+impl EmbedStats {
+    /// Embeddings computed in this run.
+    pub fn embedded(&self) -> usize {
+        self.fresh + self.stale
+    }
+}
+
+impl std::ops::AddAssign for EmbedStats {
+    fn add_assign(&mut self, other: Self) {
+        self.fresh += other.fresh;
+        self.stale += other.stale;
+        self.reused += other.reused;
+    }
+}
 
-    // Filter out images that already have embeddings
+/// Pick the images whose `model` embedding is missing or no longer matches
+/// the file on disk (everything, with `force`).
+///
+/// A changed mtime alone isn't enough: the content hash has to differ too.
+/// Rows stored before sources were tracked adopt the file's current hash.
+pub fn plan(
+    conn: &rusqlite::Connection,
+    image_paths: &[String],
+    model: &str,
+    force: bool,
+) -> Result<(Vec<String>, EmbedStats), String> {
+    let mut stats = EmbedStats::default();
     let mut to_embed: Vec<String> = Vec::new();
+
     for path in image_paths {
-        let count: i64 = conn
+        let stored: Option<(Option<String>, Option<i64>)> = conn
             .query_row(
-                "SELECT COUNT(*) FROM embeddings WHERE path = ?1",
-                rusqlite::params![path],
-                |row| row.get(0),
+                "SELECT source_sha256, source_mtime FROM embeddings WHERE path = ?1 AND model = ?2",
+                rusqlite::params![path, model],
+                |row| Ok((row.get(0)?, row.get(1)?)),
             )
+            .optional()
             .map_err(|e| format!("Cannot check embedding: {e}"))?;
-        if count == 0 {
+
+        let Some((stored_hash, stored_mtime)) = stored else {
+            stats.fresh += 1;
+            to_embed.push(path.clone());
+            continue;
+        };
+        if force {
+            stats.stale += 1;
+            to_embed.push(path.clone());
+            continue;
+        }
+        // Missing files keep their vector; the watcher/doctor clean those up
+        let Some((_, mtime)) = crate::dedup::file_stamp(Path::new(path)) else {
+            stats.reused += 1;
+            continue;
+        };
+        if stored_mtime == Some(mtime) {
+            stats.reused += 1;
+            continue;
+        }
+
+        let hash = crate::dedup::content_hash_conn(conn, Path::new(path))?;
+        if stored_hash.as_deref().is_some_and(|h| h != hash) {
+            stats.stale += 1;
             to_embed.push(path.clone());
+        } else {
+            record_source(conn, path, model, &hash, mtime)?;
+            stats.reused += 1;
         }
     }
+    Ok((to_embed, stats))
+}
+
+/// Remember which version of the file `path`'s `model` embedding came from.
+pub fn record_sources(conn: &rusqlite::Connection, image_paths: &[String], model: &str) -> Result<(), String> {
+    for path in image_paths {
+        let file = Path::new(path);
+        let Some((_, mtime)) = crate::dedup::file_stamp(file) else {
+            continue;
+        };
+        let hash = crate::dedup::content_hash_conn(conn, file)?;
+        record_source(conn, path, model, &hash, mtime)?;
+    }
+    Ok(())
+}
+
+fn record_source(
+    conn: &rusqlite::Connection,
+    path: &str,
+    model: &str,
+    hash: &str,
+    mtime: i64,
+) -> Result<(), String> {
+    conn.execute(
+        "UPDATE embeddings SET source_sha256 = ?3, source_mtime = ?4 WHERE path = ?1 AND model = ?2",
+        rusqlite::params![path, model, hash, mtime],
+    )
+    .map(|_| ())
+    .map_err(|e| format!("Cannot record embedding source: {e}"))
+}
+
+/// Embed images whose `model` embedding is missing or stale (all of them with
+/// `force`) and store them in the project DB.
+///
+/// Note: This function is called from the storage layer which already sets up
+/// the FASTEMBED_CACHE_PATH env var at startup. If the models_folder config changes
+/// after app launch, the user must restart the app for it to take effect here.
+pub fn embed_and_store(
+    project_path: &str,
+    image_paths: &[String],
+    model: ImageModel,
+    force: bool,
+) -> Result<EmbedStats, String> {
+    let conn = crate::search::open_db(project_path)?;
 
-    let cached = image_paths.len() - to_embed.len();
+    // Vectors stored before this model's ANN index existed get one built here
+    crate::ann::ensure_built(&conn, model.id())?;
+
+    let (to_embed, stats) = plan(&conn, image_paths, model.id(), force)?;
     if to_embed.is_empty() {
-        crate::log::log("CLIP", &format!("All {cached} images already embedded, skipping"));
-        return Ok(0);
+        crate::log::log("CLIP", &format!("All {} images already embedded, skipping", stats.reused));
+        return Ok(stats);
     }
 
-    crate::log::log("CLIP", &format!("{cached} already embedded, {} new to process", to_embed.len()));
-    crate::log::log("CLIP", &format!("Embedding batch of {} images...", to_embed.len()));
-    let embeddings = embed_image_files(&to_embed)?;
+    crate::log::log(
+        "CLIP",
+        &format!("{} new, {} changed, {} unchanged", stats.fresh, stats.stale, stats.reused),
+    );
+    crate::log::log("CLIP", &format!("Embedding batch of {} images with {model}...", to_embed.len()));
+    let embeddings = embed_image_files(&to_embed, model)?;
 
     for (path, embedding) in to_embed.iter().zip(embeddings.iter()) {
-        crate::search::store_embedding_conn(&conn, path, "clip-vit-b-32", embedding)?;
+        crate::search::store_embedding_conn(&conn, path, model.id(), embedding)?;
     }
+    record_sources(&conn, &to_embed, model.id())?;
 
-    crate::log::log("CLIP", &format!("Done: {} new embeddings stored", to_embed.len()));
-    Ok(to_embed.len())
+    crate::log::log("CLIP", &format!("Done: {} embeddings stored", to_embed.len()));
+    Ok(stats)
 }
 
-/// Warm up the CLIP model (download + initialize ONNX runtime).
+/// Warm up the default image model (download + initialize ONNX runtime).
 /// Call this on app startup to avoid lag on first embed.
 ///
 /// Re-reads the app config to ensure the models_folder is up-to-date.
@@ -92,24 +353,43 @@ pub async fn cmd_warmup_clip(
     }
 
     crate::log::log("CLIP", "Warmup: initializing model...");
-    match get_or_init_model() {
-        Ok(_guard) => {
-            crate::log::log("CLIP", "Warmup: model ready");
-            Ok(())
-        }
-        Err(e) => {
-            crate::log::log("CLIP", &format!("Warmup: error — {e}"));
-            Err(e)
-        }
+    if let Err(e) = get_or_init_model(default_model()) {
+        crate::log::log("CLIP", &format!("Warmup: error — {e}"));
+        return Err(e);
     }
+
+    // Text tower is only needed for semantic search — don't fail warmup over it
+    match get_or_init_text_model() {
+        Ok(_guard) => crate::log::log("CLIP", "Warmup: model ready"),
+        Err(e) => crate::log::log("CLIP", &format!("Warmup: text model unavailable — {e}")),
+    }
+    Ok(())
 }
 
+/// Embed a project's images with `model` (default: `visionModel` from config)
+/// as a background job, waiting for it to finish.
+/// Returns how many embeddings were (re)computed; `force` redoes all of them.
 #[tauri::command]
 pub async fn cmd_embed_project(
-    storage: tauri::State<'_, crate::storage::Storage>,
+    jobs: tauri::State<'_, crate::jobs::Jobs>,
     project_path: String,
+    model: Option<String>,
+    force: Option<bool>,
 ) -> Result<usize, String> {
-    storage.embed_project(&project_path).await
+    let model = model_or_default(model.as_deref())?;
+    let job = jobs
+        .run(crate::jobs::JobSpec::Embed {
+            project_path,
+            image_paths: None,
+            model: Some(model),
+            force: force.unwrap_or(false),
+        })
+        .await?;
+    let stats: EmbedStats = job
+        .result
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+    Ok(stats.embedded())
 }
 
 #[tauri::command]
@@ -117,6 +397,68 @@ pub async fn cmd_has_embedding(
     storage: tauri::State<'_, crate::storage::Storage>,
     project_path: String,
     image_path: String,
+    model: Option<String>,
 ) -> Result<bool, String> {
-    storage.has_embedding(&project_path, &image_path).await
+    let model = model_or_default(model.as_deref())?;
+    storage.has_embedding(&project_path, &image_path, model).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_image_model_names() {
+        for model in ImageModel::ALL {
+            assert_eq!(model.id().parse::<ImageModel>().unwrap(), model);
+            let json = serde_json::to_string(&model).unwrap();
+            assert_eq!(json, format!("\"{}\"", model.id()));
+        }
+        assert_eq!("clip".parse::<ImageModel>().unwrap(), ImageModel::ClipVitB32);
+        assert_eq!("CLIP-ViT-B-32".parse::<ImageModel>().unwrap(), ImageModel::ClipVitB32);
+        assert!("dinov2".parse::<ImageModel>().unwrap_err().contains("clip-vit-b-32"));
+        assert_eq!(model_or_default(Some("resnet50")).unwrap(), ImageModel::Resnet50);
+    }
+
+    #[test]
+    fn test_plan_detects_changed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_string_lossy().to_string();
+        let conn = crate::search::open_db(&project).unwrap();
+        let file = |name: &str| dir.path().join(name).to_string_lossy().to_string();
+        for name in ["same.png", "touched.png", "replaced.png", "legacy.png", "new.png"] {
+            std::fs::write(file(name), name).unwrap();
+        }
+        let stored: Vec<String> = ["same.png", "touched.png", "replaced.png", "legacy.png"]
+            .iter()
+            .map(|n| file(n))
+            .collect();
+        for path in &stored {
+            crate::search::store_embedding_conn(&conn, path, "test", &[1.0, 0.0]).unwrap();
+        }
+        record_sources(&conn, &stored[..3], "test").unwrap();
+
+        // Touched: new mtime, same bytes. Replaced: new bytes.
+        conn.execute(
+            "UPDATE embeddings SET source_mtime = 0 WHERE path = ?1",
+            rusqlite::params![file("touched.png")],
+        )
+        .unwrap();
+        std::fs::write(file("replaced.png"), "a different image").unwrap();
+
+        let all: Vec<String> = stored.iter().cloned().chain([file("new.png")]).collect();
+        let (to_embed, stats) = plan(&conn, &all, "test", false).unwrap();
+        assert_eq!(to_embed, vec![file("replaced.png"), file("new.png")]);
+        assert_eq!(stats, EmbedStats { fresh: 1, stale: 1, reused: 3 });
+
+        // The touched and legacy rows now carry the file's current stamp
+        let (to_embed, stats) = plan(&conn, &stored, "test", false).unwrap();
+        assert_eq!(to_embed, vec![file("replaced.png")]);
+        assert_eq!(stats.reused, 3);
+
+        let (to_embed, stats) = plan(&conn, &all, "test", true).unwrap();
+        assert_eq!(to_embed.len(), 5);
+        assert_eq!(stats, EmbedStats { fresh: 1, stale: 4, reused: 0 });
+        assert_eq!(stats.embedded(), 5);
+    }
 }
@@ -0,0 +1,950 @@
+//! Background jobs: embedding, batch AI analysis and web image downloads.
+//!
+//! The queue lives in `~/.deco/jobs.json` and is the source of truth: every
+//! change re-reads the file under an OS lock on `jobs.lock`, so `deco jobs`
+//! can list, cancel and retry jobs while the app is running. Workers check the
+//! file between items, so cancelling a running job takes effect after the
+//! current image. Jobs that were running when the app quit are queued again on
+//! the next start.
+//!
+//! Analysis results are appended to `~/.deco/jobs/<id>.jsonl` as each image
+//! finishes, so a resumed job skips them without the queue file growing with
+//! every item; the full result moves into the queue when the job ends.
+//!
+//! Each `JobKind` has its own concurrency limit. Every state change is emitted
+//! to the frontend as a `jobs:update` event carrying the `Job`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tauri::Emitter;
+
+/// Finished jobs kept in the queue file for inspection and retry.
+const KEEP_FINISHED: usize = 100;
+/// Images embedded per step (between progress updates and cancel checks).
+const EMBED_CHUNK: usize = 16;
+/// How often the runner looks for jobs queued by another process (the CLI).
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// How often `JobRunner::wait` re-reads a job's status.
+const WAIT_INTERVAL: Duration = Duration::from_millis(250);
+/// Minimum time between progress writes to the queue file for one job.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Error a worker returns when it stopped because of a cancel request.
+const CANCELLED: &str = "Cancelled";
+
+/// Serializes read-modify-write cycles on the queue file within a process;
+/// `JobStore::lock` does the same across processes.
+static QUEUE_LOCK: Mutex<()> = Mutex::new(());
+
+// ---------------------------------------------------------------------------
+// Types
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum JobKind {
+    Embed,
+    Analyze,
+    Download,
+}
+
+impl JobKind {
+    /// Jobs of this kind that may run at the same time.
+    pub fn concurrency(self) -> usize {
+        match self {
+            // ONNX inference already uses every core
+            JobKind::Embed => 1,
+            // Provider rate limits
+            JobKind::Analyze => 2,
+            JobKind::Download => 4,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            JobKind::Embed => "embed",
+            JobKind::Analyze => "analyze",
+            JobKind::Download => "download",
+        }
+    }
+}
+
+/// What a job does. Serialized with a `kind` tag.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum JobSpec {
+    /// Embed a project's images (every image when `image_paths` is None).
+    #[serde(rename_all = "camelCase")]
+    Embed {
+        project_path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        image_paths: Option<Vec<String>>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        model: Option<crate::embed::ImageModel>,
+        #[serde(default)]
+        force: bool,
+    },
+    /// Analyze images with an AI provider, saving results to the project.
+    #[serde(rename_all = "camelCase")]
+    Analyze {
+        project_path: String,
+        image_paths: Vec<String>,
+        provider_config: Box<crate::ai::AiProviderConfig>,
+        #[serde(default)]
+        existing_tags: Vec<String>,
+        /// Call the provider even when a cached result exists.
+        #[serde(default)]
+        force: bool,
+    },
+    /// Download a web image into the project.
+    #[serde(rename_all = "camelCase")]
+    Download {
+        project_path: String,
+        image_url: String,
+        #[serde(default)]
+        source_url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        duplicate_policy: Option<crate::dedup::DuplicatePolicy>,
+    },
+}
+
+impl JobSpec {
+    pub fn kind(&self) -> JobKind {
+        match self {
+            JobSpec::Embed { .. } => JobKind::Embed,
+            JobSpec::Analyze { .. } => JobKind::Analyze,
+            JobSpec::Download { .. } => JobKind::Download,
+        }
+    }
+
+    pub fn project_path(&self) -> &str {
+        match self {
+            JobSpec::Embed { project_path, .. }
+            | JobSpec::Analyze { project_path, .. }
+            | JobSpec::Download { project_path, .. } => project_path,
+        }
+    }
+
+    /// Remove API keys so they are never written to the queue file,
+    /// returning the provider config that still has them.
+    fn take_api_keys(&mut self) -> Option<crate::ai::AiProviderConfig> {
+        match self {
+            JobSpec::Analyze { provider_config, .. } => {
+                let keyed = (**provider_config).clone();
+                provider_config.strip_api_keys();
+                Some(keyed)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(self) -> bool {
+        matches!(self, JobStatus::Completed | JobStatus::Failed | JobStatus::Cancelled)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Completed => "completed",
+            JobStatus::Failed => "failed",
+            JobStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    pub id: u64,
+    #[serde(flatten)]
+    pub spec: JobSpec,
+    pub status: JobStatus,
+    /// Items processed so far, of `total` (0 until known).
+    #[serde(default)]
+    pub done: usize,
+    #[serde(default)]
+    pub total: usize,
+    /// What the job is working on, for display.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Kind-specific outcome (embedding counts, analysis items, download).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    /// Times the job has been retried.
+    #[serde(default)]
+    pub attempts: u32,
+    /// Set on a running job; its worker stops after the current item.
+    #[serde(default)]
+    pub cancel_requested: bool,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+fn now() -> String {
+    chrono::Utc::now().to_rfc3339()
+}
+
+fn find_mut(jobs: &mut [Job], id: u64) -> Result<&mut Job, String> {
+    jobs.iter_mut()
+        .find(|j| j.id == id)
+        .ok_or_else(|| format!("No job {id}"))
+}
+
+// ---------------------------------------------------------------------------
+// Queue file
+// ---------------------------------------------------------------------------
+
+/// The persisted job queue.
+#[derive(Debug, Clone)]
+pub struct JobStore {
+    path: PathBuf,
+}
+
+impl JobStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// `~/.deco/jobs.json`
+    pub fn open_default() -> Self {
+        let home = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
+        Self::new(PathBuf::from(home).join(".deco").join("jobs.json"))
+    }
+
+    /// Take the OS lock on `jobs.lock` next to the queue file, shared for
+    /// reads and exclusive for changes. Released when the file is dropped.
+    fn lock(&self, exclusive: bool) -> Result<std::fs::File, String> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {e}", dir.display()))?;
+        }
+        let path = self.path.with_extension("lock");
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .map_err(|e| format!("Cannot open {}: {e}", path.display()))?;
+        let locked = if exclusive { file.lock() } else { file.lock_shared() };
+        locked.map_err(|e| format!("Cannot lock {}: {e}", path.display()))?;
+        Ok(file)
+    }
+
+    fn load(&self) -> Result<Vec<Job>, String> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(s) => serde_json::from_str(&s)
+                .map_err(|e| format!("Cannot parse {}: {e}", self.path.display())),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(format!("Cannot read {}: {e}", self.path.display())),
+        }
+    }
+
+    fn save(&self, jobs: &mut Vec<Job>) -> Result<(), String> {
+        // Keep every unfinished job and the most recent finished ones
+        let finished = jobs.iter().filter(|j| j.status.is_finished()).count();
+        if finished > KEEP_FINISHED {
+            let mut drop = finished - KEEP_FINISHED;
+            jobs.retain(|j| {
+                if drop > 0 && j.status.is_finished() {
+                    drop -= 1;
+                    return false;
+                }
+                true
+            });
+        }
+
+        let json = serde_json::to_string_pretty(jobs).map_err(|e| format!("Cannot serialize jobs: {e}"))?;
+        let tmp = self.path.with_extension(format!("json.{}.tmp", std::process::id()));
+        std::fs::write(&tmp, json).map_err(|e| format!("Cannot write {}: {e}", tmp.display()))?;
+        std::fs::rename(&tmp, &self.path).map_err(|e| format!("Cannot write {}: {e}", self.path.display()))
+    }
+
+    /// Load, change and save the queue as one step.
+    fn update<T>(&self, f: impl FnOnce(&mut Vec<Job>) -> Result<T, String>) -> Result<T, String> {
+        let _guard = QUEUE_LOCK.lock().map_err(|e| format!("Job queue lock poisoned: {e}"))?;
+        let _file_lock = self.lock(true)?;
+        let mut jobs = self.load()?;
+        let out = f(&mut jobs)?;
+        self.save(&mut jobs)?;
+        Ok(out)
+    }
+
+    /// All jobs, oldest first.
+    pub fn list(&self) -> Result<Vec<Job>, String> {
+        let _guard = QUEUE_LOCK.lock().map_err(|e| format!("Job queue lock poisoned: {e}"))?;
+        let _file_lock = self.lock(false)?;
+        self.load()
+    }
+
+    pub fn get(&self, id: u64) -> Result<Job, String> {
+        self.list()?
+            .into_iter()
+            .find(|j| j.id == id)
+            .ok_or_else(|| format!("No job {id}"))
+    }
+
+    pub fn enqueue(&self, spec: JobSpec) -> Result<Job, String> {
+        self.update(|jobs| {
+            let id = jobs.iter().map(|j| j.id).max().unwrap_or(0) + 1;
+            let job = Job {
+                id,
+                spec,
+                status: JobStatus::Queued,
+                done: 0,
+                total: 0,
+                message: None,
+                error: None,
+                result: None,
+                attempts: 0,
+                cancel_requested: false,
+                created_at: now(),
+                updated_at: now(),
+            };
+            jobs.push(job.clone());
+            Ok(job)
+        })
+    }
+
+    /// Cancel a queued job now, or ask a running one to stop.
+    pub fn cancel(&self, id: u64) -> Result<Job, String> {
+        self.update(|jobs| {
+            let job = find_mut(jobs, id)?;
+            match job.status {
+                JobStatus::Queued => job.status = JobStatus::Cancelled,
+                JobStatus::Running => job.cancel_requested = true,
+                status => return Err(format!("Job {id} is already {}", status.label())),
+            }
+            job.updated_at = now();
+            Ok(job.clone())
+        })
+    }
+
+    /// Queue a failed or cancelled job again from the start.
+    pub fn retry(&self, id: u64) -> Result<Job, String> {
+        self.update(|jobs| {
+            let job = find_mut(jobs, id)?;
+            if !matches!(job.status, JobStatus::Failed | JobStatus::Cancelled) {
+                return Err(format!("Job {id} is {}; only failed or cancelled jobs can be retried", job.status.label()));
+            }
+            job.status = JobStatus::Queued;
+            job.attempts += 1;
+            job.done = 0;
+            job.total = 0;
+            job.message = None;
+            job.error = None;
+            job.result = None;
+            job.cancel_requested = false;
+            job.updated_at = now();
+            Ok(job.clone())
+        })
+        .inspect(|job| self.clear_partial(job.id))
+    }
+
+    /// Re-queue jobs left running by a previous app session.
+    pub fn recover(&self) -> Result<Vec<Job>, String> {
+        self.update(|jobs| {
+            let mut recovered = Vec::new();
+            for job in jobs.iter_mut().filter(|j| j.status == JobStatus::Running) {
+                job.status = if job.cancel_requested { JobStatus::Cancelled } else { JobStatus::Queued };
+                job.updated_at = now();
+                recovered.push(job.clone());
+            }
+            Ok(recovered)
+        })
+    }
+
+    /// Mark the oldest queued job whose kind has a free slot as running.
+    pub fn claim_next(&self) -> Result<Option<Job>, String> {
+        self.update(|jobs| {
+            let mut running: HashMap<JobKind, usize> = HashMap::new();
+            for job in jobs.iter().filter(|j| j.status == JobStatus::Running) {
+                *running.entry(job.spec.kind()).or_default() += 1;
+            }
+            let next = jobs.iter_mut().find(|j| {
+                j.status == JobStatus::Queued
+                    && running.get(&j.spec.kind()).copied().unwrap_or(0) < j.spec.kind().concurrency()
+            });
+            Ok(next.map(|job| {
+                job.status = JobStatus::Running;
+                job.updated_at = now();
+                job.clone()
+            }))
+        })
+    }
+
+    /// Record a running job's progress.
+    pub fn progress(&self, id: u64, done: usize, total: usize, message: Option<String>) -> Result<Job, String> {
+        self.update(|jobs| {
+            let job = find_mut(jobs, id)?;
+            job.done = done;
+            job.total = total;
+            job.message = message;
+            job.updated_at = now();
+            Ok(job.clone())
+        })
+    }
+
+    /// `~/.deco/jobs/<id>.jsonl`
+    fn partial_path(&self, id: u64) -> PathBuf {
+        self.path.with_extension("").join(format!("{id}.jsonl"))
+    }
+
+    /// Append one item to a running job's partial result.
+    pub fn append_partial(&self, id: u64, item: &impl Serialize) -> Result<(), String> {
+        use std::io::Write;
+        let path = self.partial_path(id);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("Cannot create {}: {e}", dir.display()))?;
+        }
+        let mut line = serde_json::to_string(item).map_err(|e| format!("Cannot serialize result: {e}"))?;
+        line.push('\n');
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .and_then(|mut f| f.write_all(line.as_bytes()))
+            .map_err(|e| format!("Cannot write {}: {e}", path.display()))
+    }
+
+    /// Items appended to a job's partial result; a torn last line (from a
+    /// crash mid-write) is skipped.
+    pub fn partial<T: serde::de::DeserializeOwned>(&self, id: u64) -> Result<Vec<T>, String> {
+        let path = self.partial_path(id);
+        match std::fs::read_to_string(&path) {
+            Ok(s) => Ok(s.lines().filter_map(|line| serde_json::from_str(line).ok()).collect()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(format!("Cannot read {}: {e}", path.display())),
+        }
+    }
+
+    fn clear_partial(&self, id: u64) {
+        let _ = std::fs::remove_file(self.partial_path(id));
+    }
+
+    /// Record how a running job ended. A requested cancel wins over the outcome.
+    pub fn finish(&self, id: u64, outcome: Result<serde_json::Value, String>) -> Result<Job, String> {
+        self.update(|jobs| {
+            let job = find_mut(jobs, id)?;
+            job.message = None;
+            match outcome {
+                _ if job.cancel_requested => job.status = JobStatus::Cancelled,
+                Ok(result) => {
+                    job.status = JobStatus::Completed;
+                    job.done = job.total;
+                    job.result = Some(result);
+                }
+                Err(e) => {
+                    job.status = JobStatus::Failed;
+                    job.error = Some(e);
+                }
+            }
+            job.updated_at = now();
+            Ok(job.clone())
+        })
+        .inspect(|_| self.clear_partial(id))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Runner
+// ---------------------------------------------------------------------------
+
+/// The app's job runner (managed Tauri state).
+pub type Jobs = Arc<JobRunner>;
+
+pub struct JobRunner {
+    store: JobStore,
+    storage: crate::storage::Storage,
+    app: OnceLock<tauri::AppHandle>,
+    wake: tokio::sync::Notify,
+    /// Provider configs, with API keys, passed with analysis jobs; the queue
+    /// file only has them without keys.
+    secrets: Mutex<HashMap<u64, crate::ai::AiProviderConfig>>,
+    /// When each running job last wrote its progress to the queue file.
+    last_progress: Mutex<HashMap<u64, std::time::Instant>>,
+}
+
+impl JobRunner {
+    pub fn new(store: JobStore, storage: crate::storage::Storage) -> Jobs {
+        Arc::new(Self {
+            store,
+            storage,
+            app: OnceLock::new(),
+            wake: tokio::sync::Notify::new(),
+            secrets: Mutex::new(HashMap::new()),
+            last_progress: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub fn store(&self) -> &JobStore {
+        &self.store
+    }
+
+    /// Re-queue interrupted jobs and start running the queue (app setup).
+    pub fn start(self: &Arc<Self>, app: tauri::AppHandle) {
+        let _ = self.app.set(app);
+        match self.store.recover() {
+            Ok(recovered) if !recovered.is_empty() => {
+                crate::log::log("JOBS", &format!("Resuming {} interrupted job(s)", recovered.len()));
+            }
+            Ok(_) => {}
+            Err(e) => crate::log::log("JOBS", &format!("Cannot recover jobs: {e}")),
+        }
+        let runner = self.clone();
+        tauri::async_runtime::spawn(async move { runner.dispatch().await });
+    }
+
+    fn emit(&self, job: &Job) {
+        if let Some(app) = self.app.get() {
+            let _ = app.emit("jobs:update", job);
+        }
+    }
+
+    pub fn submit(&self, mut spec: JobSpec) -> Result<Job, String> {
+        let keyed = spec.take_api_keys();
+        let job = self.store.enqueue(spec)?;
+        if let Some(config) = keyed {
+            if let Ok(mut secrets) = self.secrets.lock() {
+                secrets.insert(job.id, config);
+            }
+        }
+        crate::log::log("JOBS", &format!("Queued {} job {}", job.spec.kind().label(), job.id));
+        self.emit(&job);
+        self.wake.notify_one();
+        Ok(job)
+    }
+
+    pub fn cancel(&self, id: u64) -> Result<Job, String> {
+        let job = self.store.cancel(id)?;
+        self.emit(&job);
+        Ok(job)
+    }
+
+    pub fn retry(&self, id: u64) -> Result<Job, String> {
+        let job = self.store.retry(id)?;
+        self.emit(&job);
+        self.wake.notify_one();
+        Ok(job)
+    }
+
+    /// Submit a job and wait for it to finish. Fails unless it completes.
+    pub async fn run(&self, spec: JobSpec) -> Result<Job, String> {
+        let job = self.submit(spec)?;
+        self.wait(job.id).await
+    }
+
+    pub async fn wait(&self, id: u64) -> Result<Job, String> {
+        loop {
+            let job = self.store.get(id)?;
+            match job.status {
+                JobStatus::Completed => return Ok(job),
+                JobStatus::Failed => return Err(job.error.unwrap_or_else(|| format!("Job {id} failed"))),
+                JobStatus::Cancelled => return Err(format!("Job {id} was cancelled")),
+                JobStatus::Queued | JobStatus::Running => tokio::time::sleep(WAIT_INTERVAL).await,
+            }
+        }
+    }
+
+    async fn dispatch(self: Arc<Self>) {
+        loop {
+            loop {
+                match self.store.claim_next() {
+                    Ok(Some(job)) => {
+                        self.emit(&job);
+                        let runner = self.clone();
+                        tauri::async_runtime::spawn(async move { runner.execute(job).await });
+                    }
+                    Ok(None) => break,
+                    Err(e) => {
+                        crate::log::log("JOBS", &format!("Cannot read job queue: {e}"));
+                        break;
+                    }
+                }
+            }
+            tokio::select! {
+                _ = self.wake.notified() => {}
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            }
+        }
+    }
+
+    async fn execute(self: Arc<Self>, job: Job) {
+        let id = job.id;
+        let kind = job.spec.kind().label();
+        crate::log::log("JOBS", &format!("Running {kind} job {id}"));
+
+        let outcome = match job.spec.clone() {
+            JobSpec::Embed { project_path, image_paths, model, force } => {
+                self.run_embed(id, project_path, image_paths, model, force).await
+            }
+            JobSpec::Analyze { project_path, image_paths, provider_config, existing_tags, force } => {
+                self.run_analyze(&job, project_path, image_paths, *provider_config, existing_tags, force).await
+            }
+            JobSpec::Download { project_path, image_url, source_url, duplicate_policy } => {
+                self.run_download(id, project_path, image_url, source_url, duplicate_policy).await
+            }
+        };
+
+        match self.store.finish(id, outcome) {
+            Ok(job) => {
+                let detail = job.error.as_deref().map(|e| format!(": {e}")).unwrap_or_default();
+                crate::log::log("JOBS", &format!("{kind} job {id} {}{detail}", job.status.label()));
+                self.emit(&job);
+            }
+            Err(e) => crate::log::log("JOBS", &format!("Cannot record end of job {id}: {e}")),
+        }
+        if let Ok(mut secrets) = self.secrets.lock() {
+            secrets.remove(&id);
+        }
+        if let Ok(mut last) = self.last_progress.lock() {
+            last.remove(&id);
+        }
+        self.wake.notify_one();
+    }
+
+    /// Record progress, stopping the worker if a cancel was requested.
+    /// Progress is written at most every `PROGRESS_INTERVAL`; in between the
+    /// queue is only read for the cancel flag.
+    fn step(&self, id: u64, done: usize, total: usize, message: Option<String>) -> Result<(), String> {
+        let due = self.last_progress.lock().map_or(true, |mut last| {
+            let now = std::time::Instant::now();
+            let due = last.get(&id).is_none_or(|t| now.duration_since(*t) >= PROGRESS_INTERVAL);
+            if due {
+                last.insert(id, now);
+            }
+            due
+        });
+        let job = if due {
+            self.store.progress(id, done, total, message)?
+        } else {
+            Job { done, total, message, ..self.store.get(id)? }
+        };
+        self.emit(&job);
+        if job.cancel_requested {
+            return Err(CANCELLED.to_string());
+        }
+        Ok(())
+    }
+
+    async fn run_embed(
+        &self,
+        id: u64,
+        project_path: String,
+        image_paths: Option<Vec<String>>,
+        model: Option<crate::embed::ImageModel>,
+        force: bool,
+    ) -> Result<serde_json::Value, String> {
+        let model = model.unwrap_or_else(crate::embed::default_model);
+        let paths = match image_paths {
+            Some(paths) => paths,
+            None => {
+                // Index first so embeddings have a metadata row to join against
+                let images = crate::scan_images_in(&project_path)?;
+                self.storage.index_images(&project_path, &images).await?;
+                images.into_iter().map(|i| i.path).collect()
+            }
+        };
+
+        let total = paths.len();
+        let mut stats = crate::embed::EmbedStats::default();
+        for (i, chunk) in paths.chunks(EMBED_CHUNK).enumerate() {
+            self.step(id, i * EMBED_CHUNK, total, Some(format!("Embedding with {model}")))?;
+            let project = project_path.clone();
+            let chunk = chunk.to_vec();
+            stats += tokio::task::spawn_blocking(move || {
+                crate::embed::embed_and_store(&project, &chunk, model, force)
+            })
+            .await
+            .map_err(|e| format!("Task join error: {e}"))??;
+        }
+        serde_json::to_value(stats).map_err(|e| format!("Cannot serialize result: {e}"))
+    }
+
+    async fn run_analyze(
+        &self,
+        job: &Job,
+        project_path: String,
+        image_paths: Vec<String>,
+        provider_config: crate::ai::AiProviderConfig,
+        existing_tags: Vec<String>,
+        force: bool,
+    ) -> Result<serde_json::Value, String> {
+        let app = self.app.get().ok_or("Job runner is not started")?;
+        // After a restart the keys are gone and providers fall back to the
+        // saved ones
+        let provider_config = self
+            .secrets
+            .lock()
+            .ok()
+            .and_then(|s| s.get(&job.id).cloned())
+            .unwrap_or(provider_config);
+
+        // Resume after the images a previous session already analyzed
+        let mut items: Vec<crate::ai::BatchAnalysisItem> = self.store.partial(job.id)?;
+        items.retain(|item| image_paths.contains(&item.image_path));
+        let mut running_tags = existing_tags;
+        for tag in items.iter().filter_map(|i| i.result.as_ref()).flat_map(|r| &r.tags) {
+            if !running_tags.contains(tag) {
+                running_tags.push(tag.clone());
+            }
+        }
+        let pending: Vec<String> = image_paths
+            .iter()
+            .filter(|path| !items.iter().any(|item| &item.image_path == *path))
+            .cloned()
+            .collect();
+
+        let total = image_paths.len();
+        let events: Arc<dyn crate::ai::AnalysisEvents> = Arc::new(app.clone());
+        let message = format!("Analyzing {} at a time", provider_config.concurrency());
+        self.step(job.id, items.len(), total, Some(message))?;
+
+        crate::ai::analyze_batch(
+            events.clone(),
+            pending,
+            &project_path,
+            &provider_config,
+            running_tags,
+            force,
+            |item| {
+                events.batch_progress(&crate::ai::BatchProgress {
+                    current: items.len() + 1,
+                    total,
+                    image_path: item.image_path.clone(),
+                    success: item.success,
+                    result: item.result.clone(),
+                });
+                let message = Some(item.image_path.clone());
+                self.store.append_partial(job.id, &item)?;
+                items.push(item);
+                self.step(job.id, items.len(), total, message)
+            },
+        )
+        .await?;
+
+        // Report items in the order they were requested
+        items.sort_by_key(|item| image_paths.iter().position(|p| p == &item.image_path));
+        events.batch_complete(&items);
+        serde_json::to_value(&items).map_err(|e| format!("Cannot serialize result: {e}"))
+    }
+
+    async fn run_download(
+        &self,
+        id: u64,
+        project_path: String,
+        image_url: String,
+        source_url: String,
+        duplicate_policy: Option<crate::dedup::DuplicatePolicy>,
+    ) -> Result<serde_json::Value, String> {
+        self.step(id, 0, 1, Some(image_url.clone()))?;
+        let policy = duplicate_policy.unwrap_or_else(crate::dedup::default_policy);
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(30))
+            .build()
+            .map_err(|e| format!("Cannot create HTTP client: {e}"))?;
+        let result =
+            crate::web::download_image_to_project(&client, &image_url, &project_path, &source_url, policy).await?;
+        serde_json::to_value(&result).map_err(|e| format!("Cannot serialize result: {e}"))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tauri Commands
+// ---------------------------------------------------------------------------
+
+#[tauri::command]
+pub async fn cmd_list_jobs(jobs: tauri::State<'_, Jobs>) -> Result<Vec<Job>, String> {
+    jobs.store().list()
+}
+
+#[tauri::command]
+pub async fn cmd_enqueue_job(jobs: tauri::State<'_, Jobs>, spec: JobSpec) -> Result<Job, String> {
+    jobs.submit(spec)
+}
+
+#[tauri::command]
+pub async fn cmd_cancel_job(jobs: tauri::State<'_, Jobs>, id: u64) -> Result<Job, String> {
+    jobs.cancel(id)
+}
+
+#[tauri::command]
+pub async fn cmd_retry_job(jobs: tauri::State<'_, Jobs>, id: u64) -> Result<Job, String> {
+    jobs.retry(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn embed(project: &str) -> JobSpec {
+        JobSpec::Embed {
+            project_path: project.to_string(),
+            image_paths: None,
+            model: None,
+            force: false,
+        }
+    }
+
+    fn download(url: &str) -> JobSpec {
+        JobSpec::Download {
+            project_path: "/p".to_string(),
+            image_url: url.to_string(),
+            source_url: String::new(),
+            duplicate_policy: None,
+        }
+    }
+
+    #[test]
+    fn test_queue_lifecycle() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = JobStore::new(dir.path().join("jobs.json"));
+        let first = store.enqueue(embed("/a")).unwrap();
+        let second = store.enqueue(embed("/b")).unwrap();
+        let third = store.enqueue(download("https://example.com/x.jpg")).unwrap();
+        assert_eq!((first.id, second.id, third.id), (1, 2, 3));
+
+        // One embed at a time; the download runs alongside it
+        assert_eq!(store.claim_next().unwrap().unwrap().id, 1);
+        assert_eq!(store.claim_next().unwrap().unwrap().id, 3);
+        assert!(store.claim_next().unwrap().is_none());
+
+        // Queued jobs cancel at once; running ones finish as cancelled
+        assert_eq!(store.cancel(2).unwrap().status, JobStatus::Cancelled);
+        let running = store.cancel(1).unwrap();
+        assert_eq!(running.status, JobStatus::Running);
+        assert!(running.cancel_requested);
+        assert!(store.progress(1, 16, 40, None).unwrap().cancel_requested);
+        assert_eq!(store.finish(1, Ok(serde_json::json!({}))).unwrap().status, JobStatus::Cancelled);
+
+        let failed = store.finish(3, Err("Download HTTP error: 404".to_string())).unwrap();
+        assert_eq!(failed.status, JobStatus::Failed);
+        assert!(store.cancel(3).unwrap_err().contains("already failed"));
+
+        let retried = store.retry(3).unwrap();
+        assert_eq!((retried.status, retried.attempts, retried.error), (JobStatus::Queued, 1, None));
+        assert!(store.retry(3).unwrap_err().contains("queued"));
+        assert_eq!(store.claim_next().unwrap().unwrap().id, 3);
+        let done = store.finish(3, Ok(serde_json::json!({ "localPath": "/p/images/x.jpg" }))).unwrap();
+        assert_eq!(done.status, JobStatus::Completed);
+        assert!(store.get(9).unwrap_err().contains("No job 9"));
+    }
+
+    #[test]
+    fn test_queue_persists_across_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jobs.json");
+        let store = JobStore::new(&path);
+        store.enqueue(embed("/a")).unwrap();
+        store.enqueue(embed("/b")).unwrap();
+        store.claim_next().unwrap();
+        store.progress(1, 16, 40, Some("Embedding".to_string())).unwrap();
+
+        // A new session sees the same queue and re-queues the interrupted job
+        let store = JobStore::new(&path);
+        let recovered = store.recover().unwrap();
+        assert_eq!(recovered.iter().map(|j| j.id).collect::<Vec<_>>(), [1]);
+        let jobs = store.list().unwrap();
+        assert!(jobs.iter().all(|j| j.status == JobStatus::Queued));
+        assert_eq!(jobs[0].done, 16);
+        assert_eq!(jobs[0].spec.project_path(), "/a");
+    }
+
+    #[test]
+    fn test_partial_results_live_outside_the_queue() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jobs.json");
+        let store = JobStore::new(&path);
+        let job = store.enqueue(embed("/a")).unwrap();
+        store.claim_next().unwrap();
+        store.append_partial(job.id, &serde_json::json!({ "imagePath": "/a/1.jpg" })).unwrap();
+        store.append_partial(job.id, &serde_json::json!({ "imagePath": "/a/2.jpg" })).unwrap();
+        store.progress(job.id, 2, 3, None).unwrap();
+        assert!(!std::fs::read_to_string(&path).unwrap().contains("/a/1.jpg"));
+
+        // A crash mid-append leaves a torn line, which resume skips
+        let partial_path = dir.path().join("jobs").join(format!("{}.jsonl", job.id));
+        let mut torn = std::fs::read_to_string(&partial_path).unwrap();
+        torn.push_str("{\"imagePath\": \"/a/3");
+        std::fs::write(&partial_path, torn).unwrap();
+        let items: Vec<serde_json::Value> = store.partial(job.id).unwrap();
+        assert_eq!(items.len(), 2);
+
+        store.finish(job.id, Ok(serde_json::json!(items))).unwrap();
+        assert!(!partial_path.exists());
+        assert!(store.partial::<serde_json::Value>(job.id).unwrap().is_empty());
+        assert!(dir.path().join("jobs.lock").exists());
+    }
+
+    #[test]
+    fn test_enqueue_from_many_threads_gets_unique_ids() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jobs.json");
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let store = JobStore::new(&path);
+                std::thread::spawn(move || store.enqueue(embed(&format!("/{i}"))).unwrap().id)
+            })
+            .collect();
+        let mut ids: Vec<u64> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        ids.sort();
+        assert_eq!(ids, (1..=8).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_api_keys_are_not_persisted() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jobs.json");
+        let mut spec: JobSpec = serde_json::from_value(serde_json::json!({
+            "kind": "analyze",
+            "projectPath": "/p",
+            "imagePaths": ["/p/images/a.jpg"],
+            "providerConfig": {
+                "provider": "anthropic",
+                "apiKey": "sk-secret",
+                "fallbacks": [{ "provider": "openai", "apiKey": "sk-fallback" }],
+            },
+        }))
+        .unwrap();
+        let keyed = spec.take_api_keys().unwrap();
+        assert_eq!(keyed.api_key.as_deref(), Some("sk-secret"));
+        assert_eq!(keyed.fallbacks[0].api_key.as_deref(), Some("sk-fallback"));
+        JobStore::new(&path).enqueue(spec).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        assert!(saved.contains("\"kind\": \"analyze\""));
+        assert!(!saved.contains("sk-secret"));
+        assert!(!saved.contains("sk-fallback"));
+    }
+
+    #[test]
+    fn test_finished_jobs_are_pruned() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = JobStore::new(dir.path().join("jobs.json"));
+        let pending = store.enqueue(embed("/keep")).unwrap();
+        for i in 0..KEEP_FINISHED + 5 {
+            let job = store.enqueue(download(&format!("https://example.com/{i}.jpg"))).unwrap();
+            store.cancel(job.id).unwrap();
+        }
+        let jobs = store.list().unwrap();
+        assert_eq!(jobs.len(), KEEP_FINISHED + 1);
+        assert_eq!(jobs[0].id, pending.id);
+        // The oldest finished jobs went first
+        assert_eq!(jobs[1].id, pending.id + 6);
+    }
+}
@@ -11,6 +11,19 @@ use std::path::PathBuf;
 pub const AI_API_KEY: &str = "ai-api-key";
 pub const BRAVE_API_KEY: &str = "brave-api-key";
 
+#[cfg(test)]
+thread_local! {
+    /// Each test thread keeps its secrets in a directory of its own, so tests
+    /// never touch the real `~/.deco/secrets.json` or each other's keys.
+    static TEST_SECRETS_DIR: tempfile::TempDir = tempfile::tempdir().unwrap();
+}
+
+#[cfg(test)]
+fn secrets_path() -> PathBuf {
+    TEST_SECRETS_DIR.with(|dir| dir.path().join("secrets.json"))
+}
+
+#[cfg(not(test))]
 fn secrets_path() -> PathBuf {
     let home = std::env::var("HOME").unwrap_or_else(|_| "/tmp".to_string());
     PathBuf::from(home).join(".deco").join("secrets.json")
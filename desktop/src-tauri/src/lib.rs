@@ -1,12 +1,28 @@
 mod ai;
+mod analysis_cache;
+mod analysis_schema;
+mod ann;
 mod api;
 pub mod cli;
+mod collections;
+pub mod dedup;
+mod doctor;
 mod embed;
+mod jobs;
 mod keyring;
 pub mod log;
+mod migrate;
 pub mod ops;
+mod palette;
+mod phash;
+mod properties;
+mod ratelimit;
+mod query;
 mod search;
 pub mod storage;
+mod thumbs;
+mod vocab;
+mod watch;
 mod web;
 
 use serde::{Deserialize, Serialize};
@@ -23,13 +39,16 @@ const IMAGE_EXTENSIONS: &[&str] = &[
     "png", "jpg", "jpeg", "gif", "webp", "svg", "bmp", "avif", "tiff",
 ];
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ImageInfo {
     pub name: String,
     pub path: String,
     pub size_bytes: u64,
     pub extension: String,
+    /// Set when the imported content already existed in the project.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicate: Option<dedup::DuplicateInfo>,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -91,8 +110,9 @@ fn walk_for_images(dir: &Path, images: &mut Vec<ImageInfo>) -> Result<(), String
         let name = entry.file_name().to_string_lossy().to_string();
 
         if path.is_dir() {
-            // Skip hidden directories
-            if !name.starts_with('.') {
+            // Skip hidden directories and a project's generated thumbnails
+            let is_thumbnails = name == "thumbnails" && dir.join(".deco").is_dir();
+            if !name.starts_with('.') && !is_thumbnails {
                 walk_for_images(&path, images)?;
             }
         } else if let Some(ext) = path.extension() {
@@ -104,6 +124,7 @@ fn walk_for_images(dir: &Path, images: &mut Vec<ImageInfo>) -> Result<(), String
                     path: path.to_string_lossy().to_string(),
                     size_bytes: metadata.len(),
                     extension: ext_lower,
+                    duplicate: None,
                 });
             }
         }
@@ -111,12 +132,75 @@ fn walk_for_images(dir: &Path, images: &mut Vec<ImageInfo>) -> Result<(), String
     Ok(())
 }
 
+/// Write image data to `dest` unless the project already holds identical
+/// content, in which case `policy` decides. Shared by every import path
+/// (Tauri, CLI, HTTP API, web download).
+pub fn save_image_data(
+    data: &[u8],
+    dest: &Path,
+    project_path: &str,
+    policy: dedup::DuplicatePolicy,
+) -> Result<ImageInfo, String> {
+    let hash = dedup::sha256_hex(data);
+    let existing = dedup::find_existing(project_path, &hash)?;
+
+    if let Some(existing_path) = &existing {
+        if policy != dedup::DuplicatePolicy::KeepBoth {
+            crate::log::log("IMPORT", &format!("Duplicate of {existing_path} ({policy:?})"));
+            let existing = Path::new(existing_path);
+            return Ok(ImageInfo {
+                name: existing.file_name().unwrap_or_default().to_string_lossy().to_string(),
+                path: existing_path.clone(),
+                size_bytes: data.len() as u64,
+                extension: existing
+                    .extension()
+                    .map(|e| e.to_string_lossy().to_lowercase())
+                    .unwrap_or_default(),
+                duplicate: Some(dedup::DuplicateInfo {
+                    existing_path: existing_path.clone(),
+                    action: policy,
+                }),
+            });
+        }
+    }
+
+    watch::note_own_write(dest);
+    fs::write(dest, data).map_err(|e| format!("Cannot write {}: {}", dest.display(), e))?;
+    dedup::record(project_path, dest, &hash)?;
+
+    Ok(ImageInfo {
+        name: dest.file_name().unwrap_or_default().to_string_lossy().to_string(),
+        path: dest.to_string_lossy().to_string(),
+        size_bytes: data.len() as u64,
+        extension: dest
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default(),
+        duplicate: existing.map(|existing_path| dedup::DuplicateInfo {
+            existing_path,
+            action: dedup::DuplicatePolicy::KeepBoth,
+        }),
+    })
+}
+
+/// Pick `{stem}.{ext}` in `dir`, or `{stem}-N.{ext}` if taken.
+fn unique_dest(dir: &Path, stem: &str, ext: &str) -> std::path::PathBuf {
+    let mut dest = dir.join(format!("{}.{}", stem, ext));
+    let mut counter = 2u32;
+    while dest.exists() {
+        dest = dir.join(format!("{}-{}.{}", stem, counter, ext));
+        counter += 1;
+    }
+    dest
+}
+
 /// Import raw image bytes into a project's images/ directory.
 /// Core logic shared by the Tauri command and the HTTP API.
 pub fn import_image_bytes(
     data: Vec<u8>,
     extension: String,
     project_path: String,
+    policy: dedup::DuplicatePolicy,
 ) -> Result<ImageInfo, String> {
     let images_dir = Path::new(&project_path).join("images");
     fs::create_dir_all(&images_dir)
@@ -132,34 +216,58 @@ pub fn import_image_bytes(
         .duration_since(std::time::SystemTime::UNIX_EPOCH)
         .unwrap_or_default()
         .as_millis();
-    let stem = format!("paste-{}", ts);
-    let mut dest = images_dir.join(format!("{}.{}", stem, ext));
-    let mut counter = 2u32;
-    while dest.exists() {
-        dest = images_dir.join(format!("{}-{}.{}", stem, counter, ext));
-        counter += 1;
+    let dest = unique_dest(&images_dir, &format!("paste-{}", ts), &ext);
+
+    save_image_data(&data, &dest, &project_path, policy)
+}
+
+/// Import an image file into a project's images/ directory, keeping its name
+/// (with a `-N` suffix on collisions). Shared by the Tauri command and the CLI.
+pub fn import_image_file(
+    src: &Path,
+    project_path: &str,
+    policy: dedup::DuplicatePolicy,
+) -> Result<ImageInfo, String> {
+    if !src.is_file() {
+        return Err(format!("Not a file: {}", src.display()));
+    }
+    let ext = src
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .ok_or_else(|| format!("No extension: {}", src.display()))?;
+    if !IMAGE_EXTENSIONS.contains(&ext.as_str()) {
+        return Err(format!("Unsupported format: {}", src.display()));
     }
 
-    fs::write(&dest, &data).map_err(|e| format!("Cannot write pasted image: {}", e))?;
+    let images_dir = Path::new(project_path).join("images");
+    fs::create_dir_all(&images_dir)
+        .map_err(|e| format!("Cannot create images dir: {}", e))?;
 
-    Ok(ImageInfo {
-        name: dest.file_name().unwrap().to_string_lossy().to_string(),
-        path: dest.to_string_lossy().to_string(),
-        size_bytes: data.len() as u64,
-        extension: ext,
-    })
+    let stem = src
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "image".to_string());
+    let dest = unique_dest(&images_dir, &stem, &ext);
+
+    let data = fs::read(src).map_err(|e| format!("Cannot read {}: {}", src.display(), e))?;
+    save_image_data(&data, &dest, project_path, policy)
 }
 
 /// Tauri command wrapper for import_image_bytes.
 /// After saving the file, auto-indexes in FTS5 and queues CLIP embedding.
 #[tauri::command]
 async fn import_clipboard_image(
+    jobs: tauri::State<'_, jobs::Jobs>,
     data: Vec<u8>,
     extension: String,
     project_path: String,
+    duplicate_policy: Option<dedup::DuplicatePolicy>,
 ) -> Result<ImageInfo, String> {
-    let info = import_image_bytes(data, extension, project_path.clone())?;
-    spawn_auto_index(project_path, vec![info.clone()]);
+    let policy = duplicate_policy.unwrap_or_else(dedup::default_policy);
+    let info = import_image_bytes(data, extension, project_path.clone(), policy)?;
+    if info.duplicate.is_none() || policy == dedup::DuplicatePolicy::KeepBoth {
+        spawn_auto_index(jobs.inner().clone(), project_path, vec![info.clone()]);
+    }
     Ok(info)
 }
 
@@ -174,27 +282,8 @@ async fn delete_image(project_path: String, image_path: String) -> Result<(), St
             .map_err(|e| format!("Cannot delete image file: {e}"))?;
     }
 
-    // 2. Delete thumbnails (try common extensions)
-    if let Some(stem) = path.file_stem() {
-        let thumb_dir = Path::new(&project_path).join("thumbnails");
-        if thumb_dir.is_dir() {
-            let stem_str = stem.to_string_lossy();
-            // Thumbnails may have a different extension than the original
-            for ext in &["jpg", "jpeg", "png", "webp"] {
-                let thumb = thumb_dir.join(format!("{stem_str}.{ext}"));
-                if thumb.exists() {
-                    let _ = fs::remove_file(&thumb);
-                }
-            }
-            // Also try exact filename match
-            if let Some(filename) = path.file_name() {
-                let thumb_exact = thumb_dir.join(filename);
-                if thumb_exact.exists() {
-                    let _ = fs::remove_file(&thumb_exact);
-                }
-            }
-        }
-    }
+    // 2. Delete thumbnails
+    thumbs::remove_for(&project_path, &image_path);
 
     // 3. Delete from search database (metadata + embeddings)
     if let Err(e) = search::delete_image_data(&project_path, &image_path) {
@@ -328,15 +417,15 @@ async fn set_font_size(
 // Auto-Index + Embed
 // ---------------------------------------------------------------------------
 
-/// Spawn background FTS5 indexing + CLIP embedding for newly imported images.
-/// Fire-and-forget: failures are logged but never block the import response.
-fn spawn_auto_index(project_path: String, images: Vec<ImageInfo>) {
+/// Spawn background FTS5 indexing for newly imported images, then queue an
+/// embedding job for them. Failures are logged but never block the import response.
+fn spawn_auto_index(jobs: jobs::Jobs, project_path: String, images: Vec<ImageInfo>) {
     tokio::task::spawn_blocking(move || {
         for img in &images {
             crate::log::log("IMPORT", &format!("Auto-indexing: {}", img.name));
         }
 
-        // 1. FTS5 index (fast, synchronous)
+        // 1. FTS5 index, plus file properties, perceptual hash and local palette
         match search::index_project_images(&project_path, &images) {
             Ok(n) if n > 0 => {
                 crate::log::log("IMPORT", &format!("Indexed {n} new images in search DB"));
@@ -348,16 +437,21 @@ fn spawn_auto_index(project_path: String, images: Vec<ImageInfo>) {
             }
         }
 
-        // 2. CLIP embedding (slow, best-effort — skipped if model not loaded)
-        let paths: Vec<String> = images.iter().map(|i| i.path.clone()).collect();
-        match embed::embed_and_store(&project_path, &paths) {
-            Ok(n) if n > 0 => {
-                crate::log::log("IMPORT", &format!("Auto-embedded {n} images via CLIP"));
-            }
-            Ok(_) => {}
-            Err(e) => {
-                crate::log::log("IMPORT", &format!("Auto-embed skipped (model not ready?): {e}"));
-            }
+        // 2. Thumbnails for the canvas
+        let n = thumbs::ensure_all(&project_path, &images);
+        if n > 0 {
+            crate::log::log("IMPORT", &format!("Generated thumbnails for {n} images"));
+        }
+
+        // 3. Embedding with the default model (slow; runs as a job)
+        let spec = jobs::JobSpec::Embed {
+            project_path,
+            image_paths: Some(images.iter().map(|i| i.path.clone()).collect()),
+            model: None,
+            force: false,
+        };
+        if let Err(e) = jobs.submit(spec) {
+            crate::log::log("IMPORT", &format!("Cannot queue embedding: {e}"));
         }
     });
 }
@@ -428,52 +522,26 @@ async fn load_board_state(
 /// Import image files into a project's images/ directory.
 /// After copying, auto-indexes in FTS5 and queues CLIP embedding in background.
 #[tauri::command]
-async fn import_images(paths: Vec<String>, project_path: String) -> Result<Vec<ImageInfo>, String> {
+async fn import_images(
+    jobs: tauri::State<'_, jobs::Jobs>,
+    paths: Vec<String>,
+    project_path: String,
+    duplicate_policy: Option<dedup::DuplicatePolicy>,
+) -> Result<Vec<ImageInfo>, String> {
+    let policy = duplicate_policy.unwrap_or_else(dedup::default_policy);
     let proj = project_path.clone();
     let imported = tokio::task::spawn_blocking(move || {
-        let images_dir = Path::new(&proj).join("images");
-        fs::create_dir_all(&images_dir)
-            .map_err(|e| format!("Cannot create images dir: {}", e))?;
-
         let mut imported = Vec::new();
 
         for src_path_str in &paths {
             let src = Path::new(src_path_str);
-            if !src.is_file() {
-                continue;
-            }
-
-            // Validate extension
-            let ext = match src.extension() {
-                Some(e) => e.to_string_lossy().to_lowercase(),
-                None => continue,
-            };
-            if !IMAGE_EXTENSIONS.contains(&ext.as_str()) {
+            // Silently skip folders and non-image files, as before
+            if !src.is_file() || !src.extension().is_some_and(|e| {
+                IMAGE_EXTENSIONS.contains(&e.to_string_lossy().to_lowercase().as_str())
+            }) {
                 continue;
             }
-
-            // Determine destination filename, adding counter suffix if needed
-            let stem = src
-                .file_stem()
-                .map(|s| s.to_string_lossy().to_string())
-                .unwrap_or_else(|| "image".to_string());
-
-            let mut dest = images_dir.join(format!("{}.{}", stem, ext));
-            let mut counter = 2u32;
-            while dest.exists() {
-                dest = images_dir.join(format!("{}-{}.{}", stem, counter, ext));
-                counter += 1;
-            }
-
-            fs::copy(src, &dest).map_err(|e| format!("Cannot copy {}: {}", src_path_str, e))?;
-
-            let metadata = fs::metadata(&dest).map_err(|e| e.to_string())?;
-            imported.push(ImageInfo {
-                name: dest.file_name().unwrap().to_string_lossy().to_string(),
-                path: dest.to_string_lossy().to_string(),
-                size_bytes: metadata.len(),
-                extension: ext,
-            });
+            imported.push(import_image_file(src, &proj, policy)?);
         }
 
         Ok::<Vec<ImageInfo>, String>(imported)
@@ -481,8 +549,14 @@ async fn import_images(paths: Vec<String>, project_path: String) -> Result<Vec<I
     .await
     .map_err(|e| format!("Task join: {e}"))??;
 
-    if !imported.is_empty() {
-        spawn_auto_index(project_path, imported.clone());
+    // Linked/skipped duplicates are already indexed
+    let new_files: Vec<ImageInfo> = imported
+        .iter()
+        .filter(|info| info.duplicate.is_none() || policy == dedup::DuplicatePolicy::KeepBoth)
+        .cloned()
+        .collect();
+    if !new_files.is_empty() {
+        spawn_auto_index(jobs.inner().clone(), project_path, new_files);
     }
 
     Ok(imported)
@@ -508,7 +582,11 @@ fn export_metadata(project_path: String, output_path: String) -> Result<usize, S
         if let Some(ref conn) = conn {
             // Try to get metadata from search DB
             if let Ok(row) = conn.query_row(
-                "SELECT description, tags, style, mood, colors, era FROM images WHERE path = ?1",
+                &format!(
+                    "SELECT description, tags, style, mood, colors, era, extra, {}, {} FROM images WHERE path = ?1",
+                    search::PROPERTY_SELECT,
+                    search::ANNOTATION_SELECT
+                ),
                 rusqlite::params![img.path],
                 |row| {
                     Ok(serde_json::json!({
@@ -518,6 +596,9 @@ fn export_metadata(project_path: String, output_path: String) -> Result<usize, S
                         "mood": row.get::<_, String>(3)?.split_whitespace().collect::<Vec<_>>(),
                         "colors": row.get::<_, String>(4)?.split_whitespace().collect::<Vec<_>>(),
                         "era": row.get::<_, Option<String>>(5)?,
+                        "extra": search::parse_extra(row.get(6)?),
+                        "properties": search::properties_from_row(row, 7)?,
+                        "annotations": search::annotations_from_row(row, 7 + search::PROPERTY_COLUMNS)?,
                     }))
                 },
             ) {
@@ -572,12 +653,17 @@ pub fn run() {
         }
     });
 
+    // Background jobs persist in ~/.deco/jobs.json; they start running in setup
+    let job_runner = jobs::JobRunner::new(jobs::JobStore::open_default(), store.clone());
+
     tauri::Builder::default()
         .plugin(tauri_plugin_fs::init())
         .plugin(tauri_plugin_http::init())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_dialog::init())
         .manage(store.clone())
+        .manage(job_runner.clone())
+        .manage(watch::Watchers::default())
         .setup(move |app| {
             // Native macOS menu bar
             let app_menu = SubmenuBuilder::new(app, "Deco")
@@ -633,10 +719,13 @@ pub fn run() {
                 let _ = app_handle.emit("menu-event", event.id().0.as_str());
             });
 
+            job_runner.start(app.handle().clone());
+
             let handle = app.handle().clone();
             let api_storage = store;
+            let api_jobs = job_runner.clone();
             tauri::async_runtime::spawn(async move {
-                api::start_server(handle, api_storage).await;
+                api::start_server(handle, api_storage, api_jobs).await;
             });
             Ok(())
         })
@@ -666,10 +755,22 @@ pub fn run() {
             ai::cmd_generate_image,
             search::cmd_index_project,
             search::cmd_search_text,
+            search::cmd_search_semantic,
+            search::cmd_find_duplicates,
+            thumbs::cmd_get_thumbnail,
+            doctor::cmd_doctor_project,
+            watch::cmd_watch_project,
+            watch::cmd_unwatch_project,
             search::cmd_get_all_tags,
             search::cmd_filter_by_tag,
+            collections::cmd_query_images,
+            collections::cmd_list_collections,
+            collections::cmd_save_collection,
+            collections::cmd_delete_collection,
+            collections::cmd_collection_images,
             search::cmd_find_similar,
             search::cmd_update_search_metadata,
+            search::cmd_update_annotations,
             search::cmd_cluster_project,
             search::cmd_search_by_color,
             web::cmd_web_search,
@@ -680,6 +781,10 @@ pub fn run() {
             embed::cmd_embed_project,
             embed::cmd_has_embedding,
             embed::cmd_warmup_clip,
+            jobs::cmd_list_jobs,
+            jobs::cmd_enqueue_job,
+            jobs::cmd_cancel_job,
+            jobs::cmd_retry_job,
             log::cmd_read_log,
             api::cmd_get_api_port,
             save_board_state,
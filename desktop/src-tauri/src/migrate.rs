@@ -0,0 +1,614 @@
+//! Versioned schema migrations for `search.db`.
+//!
+//! The schema version is kept in `PRAGMA user_version`. On open, every
+//! migration newer than the database's version runs in order inside a single
+//! transaction, after the database has been copied to
+//! `search.db.v{old}.bak`. A database written by a newer Deco is refused
+//! rather than opened.
+//!
+//! Databases from before versioning report version 0 but may already hold any
+//! part of migrations 1–6, so those are written to be idempotent. Later
+//! migrations can rely on running against exactly the previous version.
+//! Migrations are append-only: never edit one that has shipped.
+
+use rusqlite::{params, Connection};
+use std::path::{Path, PathBuf};
+
+/// One schema step, taking the database from `version - 1` to `version`.
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    up: fn(&Connection) -> Result<(), String>,
+}
+
+/// Every migration, in order. Versions start at 1 and have no gaps.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration { version: 1, name: "images, full-text index and embeddings", up: base_tables },
+    Migration { version: 2, name: "ANN index", up: ann_index },
+    Migration { version: 3, name: "perceptual hashes", up: perceptual_hashes },
+    Migration { version: 4, name: "content hashes", up: content_hashes },
+    Migration { version: 5, name: "image properties", up: image_properties },
+    Migration { version: 6, name: "colour palettes", up: colour_palettes },
+    Migration { version: 7, name: "embeddings per model", up: embeddings_per_model },
+    Migration { version: 8, name: "embedding sources", up: embedding_sources },
+    Migration { version: 9, name: "analysis cache", up: analysis_cache },
+    Migration { version: 10, name: "extra analysis fields", up: extra_fields },
+    Migration { version: 11, name: "image tags", up: image_tags },
+    Migration { version: 12, name: "annotations", up: annotations },
+];
+
+/// Schema version this build writes.
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map(|m| m.version).unwrap_or(0)
+}
+
+pub fn user_version(conn: &Connection) -> Result<u32, String> {
+    conn.query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(|e| format!("Cannot read schema version: {e}"))
+}
+
+/// Where the copy taken before upgrading from `version` goes.
+pub fn backup_path(db_path: &Path, version: u32) -> PathBuf {
+    let name = db_path.file_name().unwrap_or_default().to_string_lossy();
+    db_path.with_file_name(format!("{name}.v{version}.bak"))
+}
+
+/// Bring the database at `db_path` up to `latest_version()`.
+pub fn run(conn: &Connection, db_path: &Path) -> Result<(), String> {
+    migrate_to(conn, db_path, latest_version())
+}
+
+fn migrate_to(conn: &Connection, db_path: &Path, target: u32) -> Result<(), String> {
+    let current = user_version(conn)?;
+    if current > latest_version() {
+        return Err(format!(
+            "{} was written by a newer version of Deco (schema version {current}, this version supports up to {}). Update Deco to open this project.",
+            db_path.display(),
+            latest_version()
+        ));
+    }
+    if current >= target {
+        return Ok(());
+    }
+
+    // Fresh databases have nothing to lose
+    let has_tables: bool = conn
+        .query_row("SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table')", [], |row| row.get(0))
+        .map_err(|e| format!("Cannot inspect database: {e}"))?;
+    if has_tables {
+        backup(conn, db_path, current)?;
+    }
+
+    // IMMEDIATE takes the write lock up front, so a second process opening
+    // the same project waits here and then finds the work already done
+    conn.execute_batch("BEGIN IMMEDIATE")
+        .map_err(|e| format!("Cannot begin migration: {e}"))?;
+    let result = apply(conn, target);
+    match result {
+        Ok(()) => conn
+            .execute_batch("COMMIT")
+            .map_err(|e| format!("Cannot commit migration: {e}")),
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK");
+            Err(e)
+        }
+    }
+}
+
+fn apply(conn: &Connection, target: u32) -> Result<(), String> {
+    let current = user_version(conn)?;
+    for migration in MIGRATIONS.iter().filter(|m| m.version > current && m.version <= target) {
+        (migration.up)(conn).map_err(|e| {
+            format!("Migration {} ({}) failed: {e}", migration.version, migration.name)
+        })?;
+        conn.pragma_update(None, "user_version", migration.version)
+            .map_err(|e| format!("Cannot record schema version: {e}"))?;
+        crate::log::log(
+            "DB",
+            &format!("Migrated search.db to version {} ({})", migration.version, migration.name),
+        );
+    }
+    Ok(())
+}
+
+/// Consistent copy of the database (including un-checkpointed WAL pages).
+fn backup(conn: &Connection, db_path: &Path, version: u32) -> Result<(), String> {
+    let dest = backup_path(db_path, version);
+    // VACUUM INTO refuses to overwrite; a leftover is from an earlier failed upgrade
+    if dest.exists() {
+        std::fs::remove_file(&dest).map_err(|e| format!("Cannot replace old backup: {e}"))?;
+    }
+    conn.execute("VACUUM INTO ?1", params![dest.to_string_lossy()])
+        .map_err(|e| format!("Cannot back up search database before upgrading: {e}"))?;
+    crate::log::log("DB", &format!("Backed up search.db (version {version}) to {}", dest.display()));
+    Ok(())
+}
+
+fn add_missing_columns(conn: &Connection, table: &str, columns: &[(&str, &str)]) -> Result<(), String> {
+    let mut stmt = conn
+        .prepare(&format!("PRAGMA table_info({table})"))
+        .map_err(|e| format!("Cannot read {table} schema: {e}"))?;
+    let existing: Vec<String> = stmt
+        .query_map([], |row| row.get::<_, String>(1))
+        .map_err(|e| format!("Cannot read {table} schema: {e}"))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    for (name, kind) in columns {
+        if !existing.iter().any(|c| c == name) {
+            conn.execute(&format!("ALTER TABLE {table} ADD COLUMN {name} {kind}"), [])
+                .map_err(|e| format!("Cannot add column {table}.{name}: {e}"))?;
+        }
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Migrations
+// ---------------------------------------------------------------------------
+
+fn base_tables(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        -- Image metadata table
+        CREATE TABLE IF NOT EXISTS images (
+            path TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            description TEXT,
+            tags TEXT DEFAULT '',
+            style TEXT DEFAULT '',
+            mood TEXT DEFAULT '',
+            colors TEXT DEFAULT '',
+            era TEXT
+        );
+
+        -- FTS5 virtual table for full-text search
+        CREATE VIRTUAL TABLE IF NOT EXISTS images_fts USING fts5(
+            name,
+            description,
+            tags,
+            style,
+            mood,
+            era,
+            content=images,
+            content_rowid=rowid
+        );
+
+        -- Triggers to keep FTS in sync
+        CREATE TRIGGER IF NOT EXISTS images_ai AFTER INSERT ON images BEGIN
+            INSERT INTO images_fts(rowid, name, description, tags, style, mood, era)
+            VALUES (new.rowid, new.name, new.description, new.tags, new.style, new.mood, new.era);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS images_ad AFTER DELETE ON images BEGIN
+            INSERT INTO images_fts(images_fts, rowid, name, description, tags, style, mood, era)
+            VALUES ('delete', old.rowid, old.name, old.description, old.tags, old.style, old.mood, old.era);
+        END;
+
+        CREATE TRIGGER IF NOT EXISTS images_au AFTER UPDATE ON images BEGIN
+            INSERT INTO images_fts(images_fts, rowid, name, description, tags, style, mood, era)
+            VALUES ('delete', old.rowid, old.name, old.description, old.tags, old.style, old.mood, old.era);
+            INSERT INTO images_fts(rowid, name, description, tags, style, mood, era)
+            VALUES (new.rowid, new.name, new.description, new.tags, new.style, new.mood, new.era);
+        END;
+
+        -- Embeddings table for CLIP vectors (BLOB storage)
+        CREATE TABLE IF NOT EXISTS embeddings (
+            path TEXT PRIMARY KEY,
+            model TEXT NOT NULL DEFAULT 'clip-vit-b-32',
+            vector BLOB NOT NULL,
+            dimensions INTEGER NOT NULL,
+            created_at TEXT DEFAULT (datetime('now'))
+        );
+        ",
+    )
+    .map_err(|e| format!("Schema init failed: {e}"))
+}
+
+/// HNSW graph tables (see `ann`).
+fn ann_index(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS ann_nodes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            path TEXT NOT NULL UNIQUE,
+            level INTEGER NOT NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS ann_links (
+            id INTEGER NOT NULL,
+            layer INTEGER NOT NULL,
+            neighbors BLOB NOT NULL,
+            PRIMARY KEY (id, layer)
+        );
+
+        CREATE TABLE IF NOT EXISTS ann_meta (
+            key TEXT PRIMARY KEY,
+            value INTEGER NOT NULL
+        );
+
+        -- Databases without vectors start with an (empty) index right away
+        INSERT OR IGNORE INTO ann_meta (key, value)
+            SELECT 'dimensions', 0 WHERE NOT EXISTS (SELECT 1 FROM embeddings);
+        ",
+    )
+    .map_err(|e| format!("ANN schema init failed: {e}"))
+}
+
+/// dHashes for near-duplicate detection.
+fn perceptual_hashes(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS image_hashes (
+            path TEXT PRIMARY KEY,
+            dhash INTEGER NOT NULL,
+            mtime INTEGER NOT NULL
+        );
+        ",
+    )
+    .map_err(|e| format!("Cannot create image_hashes: {e}"))
+}
+
+/// SHA-256 of image files (import-time duplicate checks).
+fn content_hashes(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS content_hashes (
+            path TEXT PRIMARY KEY,
+            sha256 TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            mtime INTEGER NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS content_hashes_sha256 ON content_hashes(sha256);
+        ",
+    )
+    .map_err(|e| format!("Cannot create content_hashes: {e}"))
+}
+
+/// Dimensions, EXIF and colour profile (see `properties`).
+fn image_properties(conn: &Connection) -> Result<(), String> {
+    add_missing_columns(
+        conn,
+        "images",
+        &[
+            ("width", "INTEGER"),
+            ("height", "INTEGER"),
+            ("orientation", "INTEGER"),
+            ("camera_make", "TEXT"),
+            ("camera_model", "TEXT"),
+            ("lens", "TEXT"),
+            ("taken_at", "TEXT"),
+            ("gps_latitude", "REAL"),
+            ("gps_longitude", "REAL"),
+            ("color_profile", "TEXT"),
+            ("properties_mtime", "INTEGER"),
+        ],
+    )
+}
+
+/// Locally extracted palette weights (see `palette`).
+fn colour_palettes(conn: &Connection) -> Result<(), String> {
+    add_missing_columns(
+        conn,
+        "images",
+        &[("color_weights", "TEXT"), ("palette_mtime", "INTEGER")],
+    )
+}
+
+/// Key embeddings by (path, model) and give each model its own ANN graph.
+/// The graphs are rebuilt from the vectors on next use.
+fn embeddings_per_model(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        CREATE TABLE embeddings_new (
+            path TEXT NOT NULL,
+            model TEXT NOT NULL DEFAULT 'clip-vit-b-32',
+            vector BLOB NOT NULL,
+            dimensions INTEGER NOT NULL,
+            created_at TEXT DEFAULT (datetime('now')),
+            PRIMARY KEY (path, model)
+        );
+        INSERT INTO embeddings_new (path, model, vector, dimensions, created_at)
+            SELECT path, model, vector, dimensions, created_at FROM embeddings;
+        DROP TABLE embeddings;
+        ALTER TABLE embeddings_new RENAME TO embeddings;
+        CREATE INDEX embeddings_model ON embeddings(model);
+
+        DROP TABLE ann_links;
+        DROP TABLE ann_nodes;
+        DROP TABLE ann_meta;
+
+        CREATE TABLE ann_nodes (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            model TEXT NOT NULL,
+            path TEXT NOT NULL,
+            level INTEGER NOT NULL,
+            UNIQUE (model, path)
+        );
+
+        CREATE TABLE ann_links (
+            id INTEGER NOT NULL,
+            layer INTEGER NOT NULL,
+            neighbors BLOB NOT NULL,
+            PRIMARY KEY (id, layer)
+        );
+
+        CREATE TABLE ann_meta (
+            model TEXT NOT NULL,
+            key TEXT NOT NULL,
+            value INTEGER NOT NULL,
+            PRIMARY KEY (model, key)
+        );
+        ",
+    )
+    .map_err(|e| format!("Cannot rebuild embeddings table: {e}"))
+}
+
+/// Content hash and mtime of the file each embedding was computed from, so
+/// files replaced in place get re-embedded (see `embed::plan`).
+fn embedding_sources(conn: &Connection) -> Result<(), String> {
+    add_missing_columns(
+        conn,
+        "embeddings",
+        &[("source_sha256", "TEXT"), ("source_mtime", "INTEGER")],
+    )
+}
+
+/// AI analysis results keyed by image content, provider, model and prompt
+/// (see `analysis_cache`).
+fn analysis_cache(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        CREATE TABLE IF NOT EXISTS analysis_cache (
+            content_hash TEXT NOT NULL,
+            provider TEXT NOT NULL,
+            model TEXT NOT NULL,
+            prompt_hash TEXT NOT NULL,
+            result TEXT NOT NULL,
+            hits INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT DEFAULT (datetime('now')),
+            PRIMARY KEY (content_hash, provider, model, prompt_hash)
+        );
+        ",
+    )
+    .map_err(|e| format!("Cannot create analysis_cache: {e}"))
+}
+
+/// Project-defined analysis fields, stored as a JSON object and indexed for
+/// full-text search alongside the built-in columns.
+fn extra_fields(conn: &Connection) -> Result<(), String> {
+    add_missing_columns(conn, "images", &[("extra", "TEXT")])?;
+    rebuild_fts(conn, &["name", "description", "tags", "style", "mood", "era", "extra"])
+}
+
+/// Recreate `images_fts` and the triggers that keep it in sync over
+/// `columns`, then re-index every row. FTS5 tables can't gain columns, so
+/// each newly searchable column comes through here.
+fn rebuild_fts(conn: &Connection, columns: &[&str]) -> Result<(), String> {
+    let cols = columns.join(", ");
+    let values = |prefix: &str| columns.iter().map(|c| format!("{prefix}.{c}")).collect::<Vec<_>>().join(", ");
+    let (new, old) = (values("new"), values("old"));
+    conn.execute_batch(&format!(
+        "
+        DROP TRIGGER IF EXISTS images_ai;
+        DROP TRIGGER IF EXISTS images_ad;
+        DROP TRIGGER IF EXISTS images_au;
+        DROP TABLE IF EXISTS images_fts;
+
+        CREATE VIRTUAL TABLE images_fts USING fts5(
+            {cols},
+            content=images,
+            content_rowid=rowid
+        );
+
+        CREATE TRIGGER images_ai AFTER INSERT ON images BEGIN
+            INSERT INTO images_fts(rowid, {cols})
+            VALUES (new.rowid, {new});
+        END;
+
+        CREATE TRIGGER images_ad AFTER DELETE ON images BEGIN
+            INSERT INTO images_fts(images_fts, rowid, {cols})
+            VALUES ('delete', old.rowid, {old});
+        END;
+
+        CREATE TRIGGER images_au AFTER UPDATE ON images BEGIN
+            INSERT INTO images_fts(images_fts, rowid, {cols})
+            VALUES ('delete', old.rowid, {old});
+            INSERT INTO images_fts(rowid, {cols})
+            VALUES (new.rowid, {new});
+        END;
+
+        INSERT INTO images_fts(images_fts) VALUES ('rebuild');
+        "
+    ))
+    .map_err(|e| format!("Cannot rebuild images_fts: {e}"))
+}
+
+/// One row per image and tag, so hierarchical tags (`material/bronze`) can
+/// be counted and matched by prefix. `images.tags` stays the FTS source.
+fn image_tags(conn: &Connection) -> Result<(), String> {
+    conn.execute_batch(
+        "
+        CREATE TABLE image_tags (
+            path TEXT NOT NULL,
+            tag TEXT NOT NULL,
+            PRIMARY KEY (path, tag)
+        );
+        CREATE INDEX idx_image_tags_tag ON image_tags(tag);
+
+        CREATE TRIGGER images_tags_ad AFTER DELETE ON images BEGIN
+            DELETE FROM image_tags WHERE path = old.path;
+        END;
+
+        CREATE TRIGGER images_tags_au AFTER UPDATE OF path ON images BEGIN
+            UPDATE image_tags SET path = new.path WHERE path = old.path;
+        END;
+        ",
+    )
+    .map_err(|e| format!("Cannot create image_tags: {e}"))?;
+
+    let mut stmt = conn
+        .prepare("SELECT path, tags FROM images WHERE tags != ''")
+        .map_err(|e| format!("Cannot read tags: {e}"))?;
+    let rows: Vec<(String, String)> = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+        .map_err(|e| format!("Cannot read tags: {e}"))?
+        .collect::<Result<_, _>>()
+        .map_err(|e| format!("Cannot read tags: {e}"))?;
+    for (path, tags) in rows {
+        for tag in tags.split_whitespace() {
+            conn.execute(
+                "INSERT OR IGNORE INTO image_tags (path, tag) VALUES (?1, ?2)",
+                params![path, tag.to_lowercase()],
+            )
+            .map_err(|e| format!("Cannot copy tags: {e}"))?;
+        }
+    }
+    Ok(())
+}
+
+/// Manual notes, rating and source attribution. Notes are searchable.
+fn annotations(conn: &Connection) -> Result<(), String> {
+    add_missing_columns(
+        conn,
+        "images",
+        &[
+            ("source_url", "TEXT"),
+            ("source_page", "TEXT"),
+            ("license", "TEXT"),
+            ("credit", "TEXT"),
+            ("author", "TEXT"),
+            ("rating", "INTEGER"),
+            ("favorite", "INTEGER NOT NULL DEFAULT 0"),
+            ("notes", "TEXT"),
+        ],
+    )?;
+    rebuild_fts(conn, &["name", "description", "tags", "style", "mood", "era", "extra", "notes"])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open(path: &Path) -> Connection {
+        let conn = Connection::open(path).unwrap();
+        conn.pragma_update(None, "journal_mode", "WAL").unwrap();
+        conn
+    }
+
+    /// Table name → column names, for comparing schemas.
+    fn schema(conn: &Connection) -> Vec<(String, Vec<String>)> {
+        let mut stmt = conn
+            .prepare("SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name")
+            .unwrap();
+        let tables: Vec<String> = stmt.query_map([], |r| r.get(0)).unwrap().map(|r| r.unwrap()).collect();
+        tables
+            .into_iter()
+            .map(|table| {
+                let mut stmt = conn.prepare(&format!("PRAGMA table_info({table})")).unwrap();
+                let mut cols: Vec<String> = stmt.query_map([], |r| r.get(1)).unwrap().map(|r| r.unwrap()).collect();
+                cols.sort();
+                (table, cols)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_migrations_are_ordered() {
+        for (i, m) in MIGRATIONS.iter().enumerate() {
+            assert_eq!(m.version as usize, i + 1, "{} is out of order", m.name);
+        }
+    }
+
+    #[test]
+    fn test_every_version_upgrades_to_latest() {
+        let dir = tempfile::tempdir().unwrap();
+        let fresh_path = dir.path().join("fresh.db");
+        let fresh = open(&fresh_path);
+        run(&fresh, &fresh_path).unwrap();
+        assert_eq!(user_version(&fresh).unwrap(), latest_version());
+        // Nothing to back up for a new database
+        assert!(!backup_path(&fresh_path, 0).exists());
+        let expected = schema(&fresh);
+
+        for from in 1..latest_version() {
+            let path = dir.path().join(format!("v{from}.db"));
+            let conn = open(&path);
+            migrate_to(&conn, &path, from).unwrap();
+            assert_eq!(user_version(&conn).unwrap(), from);
+            conn.execute("INSERT INTO images (path, name) VALUES ('/p/a.png', 'a.png')", []).unwrap();
+            conn.execute(
+                "INSERT INTO embeddings (path, model, vector, dimensions) VALUES ('/p/a.png', 'clip-vit-b-32', x'0000803f', 1)",
+                [],
+            )
+            .unwrap();
+
+            run(&conn, &path).unwrap();
+            assert_eq!(user_version(&conn).unwrap(), latest_version());
+            assert_eq!(schema(&conn), expected, "upgrading from version {from}");
+            let names: i64 = conn.query_row("SELECT COUNT(*) FROM images_fts WHERE images_fts MATCH 'a'", [], |r| r.get(0)).unwrap();
+            assert_eq!(names, 1);
+            let vectors: i64 = conn.query_row("SELECT COUNT(*) FROM embeddings WHERE model = 'clip-vit-b-32'", [], |r| r.get(0)).unwrap();
+            assert_eq!(vectors, 1);
+
+            let backup = Connection::open(backup_path(&path, from)).unwrap();
+            assert_eq!(user_version(&backup).unwrap(), from);
+        }
+    }
+
+    #[test]
+    fn test_unversioned_database_is_adopted() {
+        // A database from before versioning, with some later columns added ad hoc
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("search.db");
+        let conn = open(&path);
+        base_tables(&conn).unwrap();
+        image_properties(&conn).unwrap();
+        conn.execute("INSERT INTO images (path, name, width) VALUES ('/p/a.png', 'a.png', 640)", []).unwrap();
+        assert_eq!(user_version(&conn).unwrap(), 0);
+
+        run(&conn, &path).unwrap();
+        assert_eq!(user_version(&conn).unwrap(), latest_version());
+        let width: i64 = conn.query_row("SELECT width FROM images", [], |r| r.get(0)).unwrap();
+        assert_eq!(width, 640);
+        assert!(backup_path(&path, 0).exists());
+    }
+
+    #[test]
+    fn test_image_tags_are_backfilled_and_follow_images() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("search.db");
+        let conn = open(&path);
+        migrate_to(&conn, &path, 10).unwrap();
+        conn.execute(
+            "INSERT INTO images (path, name, tags) VALUES ('/p/a.png', 'a.png', 'Material/Bronze era/1920s')",
+            [],
+        )
+        .unwrap();
+
+        run(&conn, &path).unwrap();
+        let tags = |p: &str| -> Vec<String> {
+            let mut stmt = conn.prepare("SELECT tag FROM image_tags WHERE path = ?1 ORDER BY tag").unwrap();
+            stmt.query_map([p], |r| r.get(0)).unwrap().map(|r| r.unwrap()).collect()
+        };
+        assert_eq!(tags("/p/a.png"), vec!["era/1920s", "material/bronze"]);
+
+        conn.execute("UPDATE images SET path = '/p/b.png' WHERE path = '/p/a.png'", []).unwrap();
+        assert!(tags("/p/a.png").is_empty());
+        assert_eq!(tags("/p/b.png").len(), 2);
+        conn.execute("DELETE FROM images", []).unwrap();
+        assert!(tags("/p/b.png").is_empty());
+    }
+
+    #[test]
+    fn test_newer_database_is_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("search.db");
+        let conn = open(&path);
+        run(&conn, &path).unwrap();
+        conn.pragma_update(None, "user_version", latest_version() + 1).unwrap();
+
+        let err = run(&conn, &path).unwrap_err();
+        assert!(err.contains("newer version of Deco"), "{err}");
+    }
+}
@@ -5,6 +5,7 @@
 //! `spawn_blocking`.
 
 use serde::Serialize;
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
@@ -168,6 +169,57 @@ pub fn move_board_item(
     Ok(())
 }
 
+/// Drop items for deleted images from board.json and repoint renamed ones
+/// (`(old_path, new_path)`). Returns whether the board changed; a project
+/// without a board is left alone.
+pub fn sync_board_items(
+    project_path: &str,
+    removed: &[String],
+    renamed: &[(String, String)],
+) -> Result<bool, String> {
+    let board_path = Path::new(project_path)
+        .join(".deco")
+        .join("board.json");
+    if !board_path.exists() || (removed.is_empty() && renamed.is_empty()) {
+        return Ok(false);
+    }
+
+    let contents = fs::read_to_string(&board_path)
+        .map_err(|e| format!("Cannot read board.json: {e}"))?;
+    let mut state: serde_json::Value = serde_json::from_str(&contents)
+        .map_err(|e| format!("Invalid board.json: {e}"))?;
+    let Some(items) = state.get_mut("items").and_then(|v| v.as_array_mut()) else {
+        return Ok(false);
+    };
+
+    let item_path = |item: &serde_json::Value| {
+        item.get("path").and_then(|v| v.as_str()).map(str::to_string)
+    };
+    let before = items.len();
+    items.retain(|item| item_path(item).is_none_or(|p| !removed.contains(&p)));
+    let mut changed = items.len() != before;
+
+    for item in items.iter_mut() {
+        let Some(path) = item_path(item) else { continue };
+        if let Some((_, to)) = renamed.iter().find(|(from, _)| *from == path) {
+            if let Some(obj) = item.as_object_mut() {
+                let name = Path::new(to).file_name().unwrap_or_default().to_string_lossy();
+                obj.insert("path".to_string(), serde_json::json!(to));
+                obj.insert("name".to_string(), serde_json::json!(name));
+                changed = true;
+            }
+        }
+    }
+
+    if changed {
+        let json = serde_json::to_string_pretty(&state)
+            .map_err(|e| format!("Cannot serialize board state: {e}"))?;
+        fs::write(&board_path, json)
+            .map_err(|e| format!("Cannot write board.json: {e}"))?;
+    }
+    Ok(changed)
+}
+
 // ---------------------------------------------------------------------------
 // Update Item Metadata
 // ---------------------------------------------------------------------------
@@ -180,6 +232,10 @@ pub struct UpdateFields {
     pub styles: Option<Vec<String>>,
     pub moods: Option<Vec<String>>,
     pub era: Option<String>,
+    /// Project-defined analysis fields.
+    pub extra: Option<BTreeMap<String, serde_json::Value>>,
+    /// Notes, rating and attribution.
+    pub annotations: crate::search::AnnotationUpdate,
 }
 
 /// Update metadata for a single image in the search database.
@@ -199,6 +255,7 @@ pub fn update_item_metadata(
     // Read existing metadata (if any) to merge
     let existing = crate::search::get_image_metadata(project_path, &image_path_str)
         .unwrap_or(None);
+    let vocab = crate::vocab::Vocabulary::load_or_default(project_path);
 
     let metadata = crate::search::ImageMetadataRow {
         image_path: image_path_str,
@@ -206,9 +263,9 @@ pub fn update_item_metadata(
         description: fields.description.or_else(|| {
             existing.as_ref().and_then(|e| e.description.clone())
         }),
-        tags: fields.tags.unwrap_or_else(|| {
+        tags: vocab.apply(&fields.tags.unwrap_or_else(|| {
             existing.as_ref().map(|e| e.tags.clone()).unwrap_or_default()
-        }),
+        })),
         style: fields.styles.unwrap_or_else(|| {
             existing.as_ref().map(|e| e.style.clone()).unwrap_or_default()
         }),
@@ -222,12 +279,139 @@ pub fn update_item_metadata(
         era: fields.era.or_else(|| {
             existing.as_ref().and_then(|e| e.era.clone())
         }),
+        color_weights: Vec::new(),
+        properties: None,
+        extra: fields.extra.unwrap_or_else(|| {
+            existing.as_ref().map(|e| e.extra.clone()).unwrap_or_default()
+        }),
+        annotations: existing.and_then(|e| e.annotations),
     };
 
     let conn = crate::search::open_db(project_path)?;
-    crate::search::upsert_image(&conn, &metadata)?;
+    match crate::search::upsert_image_with_annotations(&conn, &metadata, &vocab, &fields.annotations)? {
+        Some(annotations) => Ok(crate::search::ImageMetadataRow {
+            annotations: Some(annotations).filter(|a| !a.is_empty()),
+            ..metadata
+        }),
+        None => Ok(metadata),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Tag Rewrites
+// ---------------------------------------------------------------------------
+
+/// What a bulk tag rewrite changed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagRewrite {
+    /// Images whose tags changed.
+    pub images: usize,
+    /// Old tag → the tag it became.
+    pub renamed: BTreeMap<String, String>,
+    /// Tags dropped (banned, or outside a strict vocabulary).
+    pub removed: Vec<String>,
+}
+
+/// Make `sources` synonyms of `target` in the project's vocabulary and
+/// rewrite every image tagged with one of them.
+pub fn merge_tags(project_path: &str, sources: &[String], target: &str) -> Result<TagRewrite, String> {
+    if crate::vocab::normalize_tag(target).is_empty() {
+        return Err("Target tag cannot be empty".to_string());
+    }
+    let mut vocab = crate::vocab::Vocabulary::load(project_path)?;
+    vocab.merge(sources, target);
+    vocab.save(project_path)?;
+    rewrite_tags(project_path, &vocab, |tag| tag.to_string())
+}
 
-    Ok(metadata)
+/// Rename a tag on every image and in the vocabulary, where the old name
+/// stays as a synonym of the new one. Tags beneath it move too: renaming `material` turns
+/// `material/bronze` into `materials/bronze`. Refuses to rename onto a tag
+/// already in use; merge those instead.
+pub fn rename_tag(project_path: &str, from: &str, to: &str) -> Result<TagRewrite, String> {
+    use crate::vocab::normalize_tag;
+
+    let (from, to) = (normalize_tag(from), normalize_tag(to));
+    if to.is_empty() {
+        return Err("New tag name cannot be empty".to_string());
+    }
+    let in_use = crate::search::get_all_tags(project_path)?;
+    if from != to && in_use.iter().any(|t| t.tag == to) {
+        return Err(format!("Tag \"{to}\" is already in use; merge the tags instead"));
+    }
+    let mut vocab = crate::vocab::Vocabulary::load(project_path)?;
+    if !in_use.iter().any(|t| t.tag == from) && !vocab.tags.contains_key(&from) {
+        return Err(format!("Tag not found: {from}"));
+    }
+    vocab.rename(&from, &to);
+    vocab.save(project_path)?;
+    rewrite_tags(project_path, &vocab, |tag| {
+        let tag = normalize_tag(tag);
+        match tag.strip_prefix(&from) {
+            Some("") => to.clone(),
+            Some(rest) if rest.starts_with('/') => format!("{to}{rest}"),
+            _ => tag,
+        }
+    })
+}
+
+/// Rewrite every image's tags through the project's vocabulary, e.g. after
+/// editing `vocabulary.json` by hand.
+pub fn normalize_tags(project_path: &str) -> Result<TagRewrite, String> {
+    let vocab = crate::vocab::Vocabulary::load(project_path)?;
+    rewrite_tags(project_path, &vocab, |tag| tag.to_string())
+}
+
+/// Map each image's tags through `map` and then `vocab`, saving the images
+/// that changed in one transaction.
+fn rewrite_tags(
+    project_path: &str,
+    vocab: &crate::vocab::Vocabulary,
+    map: impl Fn(&str) -> String,
+) -> Result<TagRewrite, String> {
+    let conn = crate::search::open_db(project_path)?;
+    let rows: Vec<(String, String)> = conn
+        .prepare("SELECT path, tags FROM images WHERE tags != ''")
+        .and_then(|mut stmt| {
+            stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect::<Result<_, _>>()
+        })
+        .map_err(|e| format!("Cannot read tags: {e}"))?;
+
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Cannot begin transaction: {e}"))?;
+    let mut rewrite = TagRewrite::default();
+    for (path, tags) in rows {
+        let mut new_tags: Vec<String> = Vec::new();
+        for tag in tags.split_whitespace() {
+            match vocab.canonical(&map(tag)) {
+                Some(new) => {
+                    if new != tag {
+                        rewrite.renamed.insert(tag.to_string(), new.clone());
+                    }
+                    if !new_tags.contains(&new) {
+                        new_tags.push(new);
+                    }
+                }
+                None => {
+                    if !rewrite.removed.iter().any(|t| t == tag) {
+                        rewrite.removed.push(tag.to_string());
+                    }
+                }
+            }
+        }
+        if new_tags.join(" ") != tags {
+            tx.execute("UPDATE images SET tags = ?1 WHERE path = ?2", rusqlite::params![new_tags.join(" "), path])
+                .map_err(|e| format!("Cannot update tags: {e}"))?;
+            crate::search::set_image_tags(&tx, &path, &new_tags)?;
+            rewrite.images += 1;
+        }
+    }
+    tx.commit().map_err(|e| format!("Cannot commit transaction: {e}"))?;
+    rewrite.removed.sort();
+    Ok(rewrite)
 }
 
 // ---------------------------------------------------------------------------
@@ -459,6 +643,41 @@ mod tests {
         assert!(result.unwrap_err().contains("Item not found"));
     }
 
+    #[test]
+    fn test_sync_board_items() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_string_lossy().to_string();
+        let deco_dir = dir.path().join(".deco");
+        std::fs::create_dir_all(&deco_dir).unwrap();
+
+        let board = serde_json::json!({
+            "items": [
+                {"path": "/p/images/a.png", "name": "a.png", "x": 0, "y": 0},
+                {"path": "/p/images/b.png", "name": "b.png", "x": 10, "y": 0},
+                {"type": "text", "text": "note"},
+            ]
+        });
+        std::fs::write(deco_dir.join("board.json"), board.to_string()).unwrap();
+
+        let changed = sync_board_items(
+            &project,
+            &["/p/images/a.png".to_string()],
+            &[("/p/images/b.png".to_string(), "/p/images/lobby.png".to_string())],
+        )
+        .unwrap();
+        assert!(changed);
+
+        let contents = std::fs::read_to_string(deco_dir.join("board.json")).unwrap();
+        let updated: serde_json::Value = serde_json::from_str(&contents).unwrap();
+        let items = updated["items"].as_array().unwrap();
+        assert_eq!(items.len(), 2);
+        assert_eq!(items[0]["path"], "/p/images/lobby.png");
+        assert_eq!(items[0]["name"], "lobby.png");
+        assert_eq!(items[0]["x"], 10);
+
+        assert!(!sync_board_items(&project, &["/p/images/zzz.png".to_string()], &[]).unwrap());
+    }
+
     #[test]
     fn test_update_item_metadata() {
         let dir = tempfile::tempdir().unwrap();
@@ -480,4 +699,73 @@ mod tests {
         assert_eq!(meta.description, Some("A test image".to_string()));
         assert_eq!(meta.tags, vec!["art", "modern"]);
     }
+
+    #[test]
+    fn test_update_item_metadata_uses_vocabulary() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_string_lossy().to_string();
+        let vocab = crate::vocab::Vocabulary {
+            tags: BTreeMap::from([("art-deco".to_string(), vec!["deco".to_string()])]),
+            banned: vec!["photo".to_string()],
+            strict: false,
+        };
+        vocab.save(&project).unwrap();
+
+        let fields = UpdateFields {
+            tags: Some(vec!["Deco".to_string(), "art_deco".to_string(), "photo".to_string(), "Lamp".to_string()]),
+            ..Default::default()
+        };
+        let meta = update_item_metadata(&project, "test.png", fields).unwrap();
+        assert_eq!(meta.tags, vec!["art-deco", "lamp"]);
+    }
+
+    #[test]
+    fn test_merge_and_rename_tags() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_string_lossy().to_string();
+        let conn = crate::search::open_db(&project).unwrap();
+        for (path, tags) in [("/p/a.png", "artdeco lamp"), ("/p/b.png", "art_deco"), ("/p/c.png", "lamp")] {
+            conn.execute("INSERT INTO images (path, name, tags) VALUES (?1, ?1, ?2)", rusqlite::params![path, tags])
+                .unwrap();
+        }
+
+        let merged = merge_tags(&project, &["artdeco".to_string(), "art_deco".to_string()], "art-deco").unwrap();
+        assert_eq!(merged.images, 2);
+        assert_eq!(merged.renamed.get("artdeco").map(String::as_str), Some("art-deco"));
+        assert_eq!(crate::search::get_images_by_tag(&project, "art-deco").unwrap().len(), 2);
+        // Later writes use the new synonym too
+        let vocab = crate::vocab::Vocabulary::load(&project).unwrap();
+        assert_eq!(vocab.canonical("art deco").as_deref(), Some("art-deco"));
+
+        let err = rename_tag(&project, "lamp", "art-deco").unwrap_err();
+        assert!(err.contains("merge"), "{err}");
+        assert!(rename_tag(&project, "chair", "seat").unwrap_err().contains("not found"));
+
+        conn.execute("UPDATE images SET tags = 'lamp/desk' WHERE path = '/p/c.png'", []).unwrap();
+        crate::search::set_image_tags(&conn, "/p/c.png", &["lamp/desk".to_string()]).unwrap();
+        let renamed = rename_tag(&project, "lamp", "Light Fitting").unwrap();
+        assert_eq!(renamed.images, 2);
+        assert!(crate::search::get_images_by_tag(&project, "lamp").unwrap().is_empty());
+        let hits = crate::search::search_text(&project, "tag:light-fitting", 10).unwrap();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(crate::search::get_images_by_tag(&project, "light-fitting/desk").unwrap(), vec!["/p/c.png"]);
+
+        // Banning a tag by hand and normalizing drops it everywhere
+        let mut vocab = crate::vocab::Vocabulary::load(&project).unwrap();
+        vocab.banned.push("light-fitting".to_string());
+        vocab.save(&project).unwrap();
+        let normalized = normalize_tags(&project).unwrap();
+        assert_eq!(normalized.images, 1);
+        assert_eq!(normalized.removed, vec!["light-fitting"]);
+        assert_eq!(crate::search::get_images_by_tag(&project, "light-fitting").unwrap(), vec!["/p/c.png"]);
+        assert_eq!(normalize_tags(&project).unwrap(), TagRewrite::default());
+
+        // A malformed vocabulary blocks the rewrite commands but not saving tags
+        std::fs::write(crate::vocab::vocabulary_path(&project), "{not json").unwrap();
+        assert!(normalize_tags(&project).unwrap_err().contains("Invalid vocabulary.json"));
+        std::fs::create_dir_all(dir.path().join("images")).unwrap();
+        let fields = UpdateFields { tags: Some(vec!["Desk Lamp".to_string()]), ..Default::default() };
+        let saved = update_item_metadata(&project, "d.png", fields).unwrap();
+        assert_eq!(saved.tags, vec!["desk-lamp"]);
+    }
 }
@@ -0,0 +1,298 @@
+//! Local dominant-colour extraction and perceptual colour distance.
+//!
+//! Palettes come from k-means over a downscaled copy of the image in CIELAB
+//! space, so they're available without any AI provider. Colours are compared
+//! with CIEDE2000 ΔE, which tracks perceived difference far better than RGB
+//! Euclidean distance (dark blues and greys no longer "match").
+
+use image::DynamicImage;
+
+/// Maximum number of colours kept per image.
+pub const PALETTE_SIZE: usize = 5;
+
+/// Pixels per side of the copy the clustering runs on.
+const SAMPLE_SIDE: u32 = 64;
+const MAX_ITERATIONS: usize = 12;
+/// Clusters closer than this (ΔE) are merged into one colour.
+const MERGE_DELTA_E: f64 = 6.0;
+/// Colours covering less of the image than this are dropped.
+const MIN_WEIGHT: f64 = 0.03;
+
+/// A dominant colour and the fraction of the (opaque) image it covers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaletteColor {
+    pub hex: String,
+    pub weight: f64,
+}
+
+/// A colour in CIELAB (D65).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lab {
+    pub l: f64,
+    pub a: f64,
+    pub b: f64,
+}
+
+/// Convert sRGB to CIELAB (D65 white point).
+pub fn rgb_to_lab(rgb: (u8, u8, u8)) -> Lab {
+    let linear = |c: u8| {
+        let c = c as f64 / 255.0;
+        if c <= 0.04045 {
+            c / 12.92
+        } else {
+            ((c + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let (r, g, b) = (linear(rgb.0), linear(rgb.1), linear(rgb.2));
+
+    let x = (0.4124 * r + 0.3576 * g + 0.1805 * b) / 0.95047;
+    let y = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let z = (0.0193 * r + 0.1192 * g + 0.9505 * b) / 1.08883;
+
+    let f = |t: f64| {
+        if t > 216.0 / 24389.0 {
+            t.cbrt()
+        } else {
+            (24389.0 / 27.0 * t + 16.0) / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+    Lab {
+        l: 116.0 * fy - 16.0,
+        a: 500.0 * (fx - fy),
+        b: 200.0 * (fy - fz),
+    }
+}
+
+/// CIEDE2000 colour difference between two Lab colours.
+pub fn ciede2000(c1: Lab, c2: Lab) -> f64 {
+    use std::f64::consts::PI;
+    let deg = |r: f64| r * 180.0 / PI;
+    let rad = |d: f64| d * PI / 180.0;
+
+    let c_bar = ((c1.a * c1.a + c1.b * c1.b).sqrt() + (c2.a * c2.a + c2.b * c2.b).sqrt()) / 2.0;
+    let c_bar7 = c_bar.powi(7);
+    let g = 0.5 * (1.0 - (c_bar7 / (c_bar7 + 25f64.powi(7))).sqrt());
+
+    let a1 = (1.0 + g) * c1.a;
+    let a2 = (1.0 + g) * c2.a;
+    let cp1 = (a1 * a1 + c1.b * c1.b).sqrt();
+    let cp2 = (a2 * a2 + c2.b * c2.b).sqrt();
+    let hue = |b: f64, a: f64| {
+        if b == 0.0 && a == 0.0 {
+            0.0
+        } else {
+            deg(b.atan2(a)).rem_euclid(360.0)
+        }
+    };
+    let hp1 = hue(c1.b, a1);
+    let hp2 = hue(c2.b, a2);
+
+    let dl = c2.l - c1.l;
+    let dc = cp2 - cp1;
+    let dh = if cp1 * cp2 == 0.0 {
+        0.0
+    } else if (hp2 - hp1).abs() <= 180.0 {
+        hp2 - hp1
+    } else if hp2 - hp1 > 180.0 {
+        hp2 - hp1 - 360.0
+    } else {
+        hp2 - hp1 + 360.0
+    };
+    let dh_big = 2.0 * (cp1 * cp2).sqrt() * rad(dh / 2.0).sin();
+
+    let l_bar = (c1.l + c2.l) / 2.0;
+    let cp_bar = (cp1 + cp2) / 2.0;
+    let hp_bar = if cp1 * cp2 == 0.0 {
+        hp1 + hp2
+    } else if (hp1 - hp2).abs() <= 180.0 {
+        (hp1 + hp2) / 2.0
+    } else if hp1 + hp2 < 360.0 {
+        (hp1 + hp2 + 360.0) / 2.0
+    } else {
+        (hp1 + hp2 - 360.0) / 2.0
+    };
+
+    let t = 1.0 - 0.17 * rad(hp_bar - 30.0).cos()
+        + 0.24 * rad(2.0 * hp_bar).cos()
+        + 0.32 * rad(3.0 * hp_bar + 6.0).cos()
+        - 0.20 * rad(4.0 * hp_bar - 63.0).cos();
+    let d_theta = 30.0 * (-((hp_bar - 275.0) / 25.0).powi(2)).exp();
+    let cp_bar7 = cp_bar.powi(7);
+    let r_c = 2.0 * (cp_bar7 / (cp_bar7 + 25f64.powi(7))).sqrt();
+    let l50 = (l_bar - 50.0).powi(2);
+    let s_l = 1.0 + 0.015 * l50 / (20.0 + l50).sqrt();
+    let s_c = 1.0 + 0.045 * cp_bar;
+    let s_h = 1.0 + 0.015 * cp_bar * t;
+    let r_t = -rad(2.0 * d_theta).sin() * r_c;
+
+    let (tl, tc, th) = (dl / s_l, dc / s_c, dh_big / s_h);
+    (tl * tl + tc * tc + th * th + r_t * tc * th).sqrt()
+}
+
+/// Perceptual distance (CIEDE2000 ΔE) between two sRGB colours.
+pub fn delta_e(a: (u8, u8, u8), b: (u8, u8, u8)) -> f64 {
+    ciede2000(rgb_to_lab(a), rgb_to_lab(b))
+}
+
+fn lab_distance2(a: Lab, b: Lab) -> f64 {
+    (a.l - b.l).powi(2) + (a.a - b.a).powi(2) + (a.b - b.b).powi(2)
+}
+
+struct Cluster {
+    center: Lab,
+    rgb_sum: [f64; 3],
+    count: usize,
+}
+
+/// Extract up to `PALETTE_SIZE` dominant colours, heaviest first.
+/// Transparent pixels are ignored; fully transparent images yield nothing.
+pub fn extract(img: &DynamicImage) -> Vec<PaletteColor> {
+    let small = img.thumbnail(SAMPLE_SIDE, SAMPLE_SIDE).to_rgba8();
+    let pixels: Vec<((u8, u8, u8), Lab)> = small
+        .pixels()
+        .filter(|p| p[3] >= 128)
+        .map(|p| {
+            let rgb = (p[0], p[1], p[2]);
+            (rgb, rgb_to_lab(rgb))
+        })
+        .collect();
+    if pixels.is_empty() {
+        return Vec::new();
+    }
+
+    // Deterministic seeds: evenly spaced along the lightness ordering
+    let mut by_lightness: Vec<Lab> = pixels.iter().map(|p| p.1).collect();
+    by_lightness.sort_by(|a, b| a.l.total_cmp(&b.l));
+    let k = PALETTE_SIZE.min(by_lightness.len());
+    let mut centers: Vec<Lab> = (0..k)
+        .map(|i| by_lightness[(2 * i + 1) * by_lightness.len() / (2 * k)])
+        .collect();
+
+    let mut assignment = vec![usize::MAX; pixels.len()];
+    for _ in 0..MAX_ITERATIONS {
+        let mut changed = false;
+        for (i, (_, lab)) in pixels.iter().enumerate() {
+            let nearest = (0..centers.len())
+                .min_by(|&a, &b| lab_distance2(*lab, centers[a]).total_cmp(&lab_distance2(*lab, centers[b])))
+                .unwrap_or(0);
+            if assignment[i] != nearest {
+                assignment[i] = nearest;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+        let mut sums = vec![(0.0, 0.0, 0.0, 0usize); centers.len()];
+        for (i, (_, lab)) in pixels.iter().enumerate() {
+            let s = &mut sums[assignment[i]];
+            s.0 += lab.l;
+            s.1 += lab.a;
+            s.2 += lab.b;
+            s.3 += 1;
+        }
+        for (center, s) in centers.iter_mut().zip(&sums) {
+            if s.3 > 0 {
+                let n = s.3 as f64;
+                *center = Lab { l: s.0 / n, a: s.1 / n, b: s.2 / n };
+            }
+        }
+    }
+
+    let mut clusters: Vec<Cluster> = centers
+        .iter()
+        .map(|&center| Cluster { center, rgb_sum: [0.0; 3], count: 0 })
+        .collect();
+    for (i, (rgb, _)) in pixels.iter().enumerate() {
+        let c = &mut clusters[assignment[i]];
+        c.rgb_sum[0] += rgb.0 as f64;
+        c.rgb_sum[1] += rgb.1 as f64;
+        c.rgb_sum[2] += rgb.2 as f64;
+        c.count += 1;
+    }
+    clusters.retain(|c| c.count > 0);
+    clusters.sort_by_key(|c| std::cmp::Reverse(c.count));
+
+    // Fold near-identical clusters into the heavier one
+    let mut merged: Vec<Cluster> = Vec::new();
+    for c in clusters {
+        match merged.iter_mut().find(|m| ciede2000(m.center, c.center) < MERGE_DELTA_E) {
+            Some(m) => {
+                for ch in 0..3 {
+                    m.rgb_sum[ch] += c.rgb_sum[ch];
+                }
+                m.count += c.count;
+            }
+            None => merged.push(c),
+        }
+    }
+    merged.sort_by_key(|c| std::cmp::Reverse(c.count));
+
+    let total = pixels.len() as f64;
+    merged
+        .iter()
+        .map(|c| {
+            let n = c.count as f64;
+            let channel = |ch: usize| (c.rgb_sum[ch] / n).round().clamp(0.0, 255.0) as u8;
+            PaletteColor {
+                hex: format!("#{:02x}{:02x}{:02x}", channel(0), channel(1), channel(2)),
+                weight: n / total,
+            }
+        })
+        .filter(|c| c.weight >= MIN_WEIGHT)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ciede2000_reference_pairs() {
+        // Sharma, Wu & Dalal (2005) test data
+        let pairs = [
+            ((50.0, 2.6772, -79.7751), (50.0, 0.0, -82.7485), 2.0425),
+            ((50.0, 2.5, 0.0), (73.0, 25.0, -18.0), 27.1492),
+            ((60.2574, -34.0099, 36.2677), (60.4626, -34.1751, 39.4387), 1.2644),
+            ((22.7233, 20.0904, -46.694), (23.0331, 14.973, -42.5619), 2.0373),
+        ];
+        for ((l1, a1, b1), (l2, a2, b2), expected) in pairs {
+            let got = ciede2000(Lab { l: l1, a: a1, b: b1 }, Lab { l: l2, a: a2, b: b2 });
+            assert!((got - expected).abs() < 1e-3, "expected {expected}, got {got}");
+        }
+    }
+
+    #[test]
+    fn test_delta_e_is_perceptual() {
+        assert!(delta_e((200, 40, 40), (200, 40, 40)) < 1e-9);
+        let white = rgb_to_lab((255, 255, 255));
+        assert!((white.l - 100.0).abs() < 0.01 && white.a.abs() < 0.01);
+        // Equal RGB steps are not equal perceptual steps: the same change is
+        // far more visible in mid-greens than in deep blues
+        let blues = delta_e((0, 0, 120), (0, 0, 160));
+        let greens = delta_e((0, 120, 0), (0, 160, 0));
+        assert!(greens > blues * 1.2, "greens {greens} vs blues {blues}");
+    }
+
+    #[test]
+    fn test_extract_palette() {
+        // Three quarters brass, one quarter navy
+        let img = DynamicImage::ImageRgb8(image::ImageBuffer::from_fn(100, 100, |x, _| {
+            if x < 75 {
+                image::Rgb([192, 160, 96])
+            } else {
+                image::Rgb([16, 24, 64])
+            }
+        }));
+        let palette = extract(&img);
+        assert_eq!(palette.len(), 2);
+        assert_eq!(palette[0].hex, "#c0a060");
+        assert!((palette[0].weight - 0.75).abs() < 0.05);
+        assert_eq!(palette[1].hex, "#101840");
+
+        // Transparent pixels don't count
+        let clear = DynamicImage::ImageRgba8(image::RgbaImage::new(10, 10));
+        assert!(extract(&clear).is_empty());
+    }
+}
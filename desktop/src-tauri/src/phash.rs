@@ -0,0 +1,65 @@
+//! Perceptual hashing (dHash) for near-duplicate detection.
+//!
+//! The image is shrunk to 9×8 grayscale and each bit records whether a pixel
+//! is brighter than its right-hand neighbour. Re-encodes, resizes and light
+//! edits of the same picture land within a few bits of each other.
+
+use image::imageops::FilterType;
+use std::path::Path;
+
+/// Compute the 64-bit dHash of an image file.
+pub fn dhash_file(path: &Path) -> Result<u64, String> {
+    let img = image::open(path).map_err(|e| format!("Cannot decode {}: {e}", path.display()))?;
+    Ok(dhash(&img))
+}
+
+/// Compute the 64-bit dHash of a decoded image.
+pub fn dhash(img: &image::DynamicImage) -> u64 {
+    let small = image::imageops::resize(&img.to_luma8(), 9, 8, FilterType::Triangle);
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            let left = small.get_pixel(x, y)[0];
+            let right = small.get_pixel(x + 1, y)[0];
+            hash = (hash << 1) | u64::from(left > right);
+        }
+    }
+    hash
+}
+
+/// Number of differing bits between two hashes.
+pub fn hamming(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gradient(width: u32, height: u32) -> image::DynamicImage {
+        image::DynamicImage::ImageLuma8(image::ImageBuffer::from_fn(width, height, |x, y| {
+            image::Luma([((x * 7 + y * 3) % 256) as u8])
+        }))
+    }
+
+    #[test]
+    fn test_dhash_stable_across_resize() {
+        let original = gradient(640, 480);
+        let smaller = original.resize_exact(320, 240, FilterType::Triangle);
+        assert!(hamming(dhash(&original), dhash(&smaller)) <= 4);
+    }
+
+    #[test]
+    fn test_dhash_differs_for_different_images() {
+        let a = gradient(200, 200);
+        let b = a.fliph();
+        assert!(hamming(dhash(&a), dhash(&b)) > 20);
+    }
+
+    #[test]
+    fn test_hamming() {
+        assert_eq!(hamming(0, 0), 0);
+        assert_eq!(hamming(0b1011, 0b0001), 2);
+        assert_eq!(hamming(u64::MAX, 0), 64);
+    }
+}
@@ -0,0 +1,308 @@
+//! File-level image properties: pixel dimensions, EXIF and colour profile.
+//!
+//! Read during indexing and stored on the `images` row, so they can be
+//! reported (and filtered on) without opening the file again. `read` only
+//! reads headers; `read_decoded` also decodes the pixels with the same
+//! decoder, for indexing passes that need both.
+
+use exif::{In, Tag, Value};
+use image::ImageDecoder;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Properties of an image file. Everything but the dimensions is optional
+/// and absent when the format or file doesn't carry it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageProperties {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub height: Option<u32>,
+    /// EXIF orientation (1–8); 5–8 mean the image displays rotated by 90°.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub orientation: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera_make: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera_model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lens: Option<String>,
+    /// Capture time from EXIF `DateTimeOriginal` (`YYYY-MM-DDTHH:MM:SS`, local time).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub taken_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gps_latitude: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gps_longitude: Option<f64>,
+    /// Description of the embedded ICC profile (e.g. "Display P3").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_profile: Option<String>,
+}
+
+impl ImageProperties {
+    /// Width and height as displayed, i.e. swapped for rotated orientations.
+    pub fn display_size(&self) -> Option<(u32, u32)> {
+        let (w, h) = (self.width?, self.height?);
+        match self.orientation {
+            Some(5..=8) => Some((h, w)),
+            _ => Some((w, h)),
+        }
+    }
+
+    /// Displayed width divided by height.
+    pub fn aspect_ratio(&self) -> Option<f64> {
+        let (w, h) = self.display_size()?;
+        (h > 0).then(|| w as f64 / h as f64)
+    }
+}
+
+/// Read the properties of an image file. Formats the decoder doesn't know
+/// (SVG, AVIF) yield an empty set rather than an error.
+pub fn read(path: &Path) -> Result<ImageProperties, String> {
+    open(path).map(|(props, _)| props)
+}
+
+/// Read the properties of an image file and decode its pixels, opening the
+/// file once. The image is an error when the format can't be decoded.
+pub fn read_decoded(
+    path: &Path,
+) -> Result<(ImageProperties, Result<image::DynamicImage, String>), String> {
+    let (props, decoder) = open(path)?;
+    let image = match decoder {
+        Some(decoder) => image::DynamicImage::from_decoder(decoder)
+            .map_err(|e| format!("Cannot decode {}: {e}", path.display())),
+        None => Err(format!("Cannot decode {}: unsupported format", path.display())),
+    };
+    Ok((props, image))
+}
+
+/// Read the properties, returning the decoder positioned at the pixels
+/// (`None` for formats the decoder doesn't know).
+fn open(path: &Path) -> Result<(ImageProperties, Option<Box<dyn ImageDecoder>>), String> {
+    let mut props = ImageProperties::default();
+
+    let reader = image::ImageReader::open(path)
+        .and_then(|r| r.with_guessed_format())
+        .map_err(|e| format!("Cannot open {}: {e}", path.display()))?;
+    let Ok(mut decoder) = reader.into_decoder() else {
+        return Ok((props, None));
+    };
+
+    let (width, height) = decoder.dimensions();
+    props.width = Some(width);
+    props.height = Some(height);
+
+    if let Ok(Some(icc)) = decoder.icc_profile() {
+        props.color_profile = icc_description(&icc);
+    }
+    if let Ok(Some(raw)) = decoder.exif_metadata() {
+        apply_exif(&mut props, raw);
+    }
+
+    Ok((props, Some(Box::new(decoder))))
+}
+
+fn apply_exif(props: &mut ImageProperties, mut raw: Vec<u8>) {
+    // JPEG APP1 payloads may still carry the "Exif\0\0" marker
+    if raw.starts_with(b"Exif\0\0") {
+        raw.drain(..6);
+    }
+    let Ok(exif) = exif::Reader::new().read_raw(raw) else {
+        return;
+    };
+
+    let ascii = |tag: Tag| -> Option<String> {
+        match &exif.get_field(tag, In::PRIMARY)?.value {
+            Value::Ascii(parts) => {
+                let text = String::from_utf8_lossy(parts.first()?);
+                let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+                (!text.is_empty()).then(|| text.to_string())
+            }
+            _ => None,
+        }
+    };
+
+    props.orientation = exif
+        .get_field(Tag::Orientation, In::PRIMARY)
+        .and_then(|f| f.value.get_uint(0))
+        .and_then(|v| u16::try_from(v).ok())
+        .filter(|v| (1..=8).contains(v));
+    props.camera_make = ascii(Tag::Make);
+    props.camera_model = ascii(Tag::Model);
+    props.lens = ascii(Tag::LensModel);
+    props.taken_at = exif
+        .get_field(Tag::DateTimeOriginal, In::PRIMARY)
+        .and_then(|f| match &f.value {
+            Value::Ascii(parts) => exif::DateTime::from_ascii(parts.first()?).ok(),
+            _ => None,
+        })
+        .map(|dt| {
+            format!(
+                "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+                dt.year, dt.month, dt.day, dt.hour, dt.minute, dt.second
+            )
+        });
+
+    let coordinate = |tag: Tag, reference: Tag, negative: &str| -> Option<f64> {
+        let Value::Rational(parts) = &exif.get_field(tag, In::PRIMARY)?.value else {
+            return None;
+        };
+        let dms: Vec<f64> = parts.iter().map(|r| r.to_f64()).collect();
+        let degrees = dms.first()? + dms.get(1).unwrap_or(&0.0) / 60.0 + dms.get(2).unwrap_or(&0.0) / 3600.0;
+        if !degrees.is_finite() {
+            return None;
+        }
+        let sign = if ascii(reference).as_deref() == Some(negative) { -1.0 } else { 1.0 };
+        Some(sign * degrees)
+    };
+    props.gps_latitude = coordinate(Tag::GPSLatitude, Tag::GPSLatitudeRef, "S");
+    props.gps_longitude = coordinate(Tag::GPSLongitude, Tag::GPSLongitudeRef, "W");
+}
+
+/// Read the `desc` tag of an ICC profile (v2 `desc` or v4 `mluc` encoding).
+fn icc_description(icc: &[u8]) -> Option<String> {
+    let be32 = |at: usize| -> Option<usize> {
+        icc.get(at..at + 4)
+            .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) as usize)
+    };
+
+    let tag_count = be32(128)?;
+    let (offset, size) = (0..tag_count.min(256)).find_map(|i| {
+        let entry = 132 + i * 12;
+        if icc.get(entry..entry + 4)? != b"desc" {
+            return None;
+        }
+        Some((be32(entry + 4)?, be32(entry + 8)?))
+    })?;
+    let data = icc.get(offset..offset.checked_add(size)?)?;
+
+    let text = match data.get(0..4)? {
+        b"desc" => {
+            let len = u32::from_be_bytes(data.get(8..12)?.try_into().ok()?) as usize;
+            let bytes = data.get(12..12 + len.min(data.len().saturating_sub(12)))?;
+            String::from_utf8_lossy(bytes).to_string()
+        }
+        b"mluc" => {
+            // First record: lang(2) country(2) length(4) offset(4), UTF-16BE text
+            let len = u32::from_be_bytes(data.get(20..24)?.try_into().ok()?) as usize;
+            let at = u32::from_be_bytes(data.get(24..28)?.try_into().ok()?) as usize;
+            let units: Vec<u16> = data
+                .get(at..at.checked_add(len)?)?
+                .chunks_exact(2)
+                .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        _ => return None,
+    };
+
+    let text = text.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal little-endian TIFF/EXIF block with Make, Orientation and GPS.
+    fn exif_block() -> Vec<u8> {
+        let mut b = b"II*\0".to_vec();
+        b.extend(8u32.to_le_bytes());
+        // IFD0: Make (ascii), Orientation (short), GPS IFD pointer (long)
+        let ifd0_entries = 3u16;
+        let ifd0_len = 2 + ifd0_entries as usize * 12 + 4;
+        let make_at = 8 + ifd0_len;
+        let make = b"Leica\0";
+        let gps_at = make_at + make.len() + (make.len() % 2);
+        b.extend(ifd0_entries.to_le_bytes());
+        b.extend(0x010fu16.to_le_bytes());
+        b.extend(2u16.to_le_bytes());
+        b.extend((make.len() as u32).to_le_bytes());
+        b.extend((make_at as u32).to_le_bytes());
+        b.extend(0x0112u16.to_le_bytes());
+        b.extend(3u16.to_le_bytes());
+        b.extend(1u32.to_le_bytes());
+        b.extend(6u32.to_le_bytes());
+        b.extend(0x8825u16.to_le_bytes());
+        b.extend(4u16.to_le_bytes());
+        b.extend(1u32.to_le_bytes());
+        b.extend((gps_at as u32).to_le_bytes());
+        b.extend(0u32.to_le_bytes());
+        b.extend(make);
+        b.resize(gps_at, 0);
+
+        // GPS IFD: LatitudeRef "S", Latitude 33°52'0"
+        let gps_entries = 2u16;
+        let rationals_at = gps_at + 2 + gps_entries as usize * 12 + 4;
+        b.extend(gps_entries.to_le_bytes());
+        b.extend(0x0001u16.to_le_bytes());
+        b.extend(2u16.to_le_bytes());
+        b.extend(2u32.to_le_bytes());
+        b.extend(b"S\0\0\0");
+        b.extend(0x0002u16.to_le_bytes());
+        b.extend(5u16.to_le_bytes());
+        b.extend(3u32.to_le_bytes());
+        b.extend((rationals_at as u32).to_le_bytes());
+        b.extend(0u32.to_le_bytes());
+        for (num, den) in [(33u32, 1u32), (52, 1), (0, 1)] {
+            b.extend(num.to_le_bytes());
+            b.extend(den.to_le_bytes());
+        }
+        b
+    }
+
+    #[test]
+    fn test_apply_exif() {
+        let mut props = ImageProperties { width: Some(4000), height: Some(3000), ..Default::default() };
+        let mut raw = b"Exif\0\0".to_vec();
+        raw.extend(exif_block());
+        apply_exif(&mut props, raw);
+
+        assert_eq!(props.camera_make.as_deref(), Some("Leica"));
+        assert_eq!(props.orientation, Some(6));
+        let lat = props.gps_latitude.unwrap();
+        assert!((lat + 33.8667).abs() < 1e-3, "got {lat}");
+        assert!(props.gps_longitude.is_none());
+        // Rotated 90°: displayed portrait
+        assert_eq!(props.display_size(), Some((3000, 4000)));
+        assert!((props.aspect_ratio().unwrap() - 0.75).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_icc_description() {
+        // Header (128) + tag count + one tag entry, then a v2 'desc' element
+        let mut icc = vec![0u8; 128];
+        icc.extend(1u32.to_be_bytes());
+        icc.extend(b"desc");
+        icc.extend(144u32.to_be_bytes());
+        let text = b"sRGB IEC61966-2.1\0";
+        let element_len = 12 + text.len();
+        icc.extend((element_len as u32).to_be_bytes());
+        icc.extend(b"desc\0\0\0\0");
+        icc.extend((text.len() as u32).to_be_bytes());
+        icc.extend(text);
+        assert_eq!(icc_description(&icc).as_deref(), Some("sRGB IEC61966-2.1"));
+
+        assert!(icc_description(&[0u8; 64]).is_none());
+    }
+
+    #[test]
+    fn test_read_dimensions() {
+        let dir = tempfile::tempdir().unwrap();
+        let png = dir.path().join("a.png");
+        image::RgbImage::new(40, 30).save(&png).unwrap();
+        let props = read(&png).unwrap();
+        assert_eq!((props.width, props.height), (Some(40), Some(30)));
+        assert!(props.camera_make.is_none());
+        let (decoded_props, image) = read_decoded(&png).unwrap();
+        assert_eq!(decoded_props, props);
+        let image = image.unwrap();
+        assert_eq!((image.width(), image.height()), (40, 30));
+
+        let svg = dir.path().join("b.svg");
+        std::fs::write(&svg, "<svg xmlns=\"http://www.w3.org/2000/svg\"/>").unwrap();
+        assert_eq!(read(&svg).unwrap(), ImageProperties::default());
+        assert!(read_decoded(&svg).unwrap().1.is_err());
+    }
+}
@@ -0,0 +1,279 @@
+//! Search query grammar.
+//!
+//! ```text
+//! art deco tag:art-deco style:geometric era:1920s -mood:dark color:#c0a060
+//! ```
+//!
+//! - bare words → prefix match on any column (`"word"*`)
+//! - `"quoted phrase"` → phrase match on any column
+//! - `field:value` → FTS5 column filter (`tag`, `style`, `mood`, `era`,
+//!   `name`, `desc`, `notes`, and `extra` for project-defined analysis fields);
+//!   `field:"two words"` for phrases
+//! - `-term` / `-field:value` → excluded with NOT
+//! - `color:#rrggbb` / `-color:#rrggbb` → palette match on the `colors` column
+//! - `AND` / `OR` / `NOT` are passed through to FTS5; ones without a clause
+//!   on both sides (`lamp OR`, `NOT lamp`) are dropped
+
+/// A parsed search query, ready to be executed by `search::search_text`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedQuery {
+    /// Positive FTS5 clauses (implicitly ANDed).
+    pub include: Vec<String>,
+    /// Negated FTS5 clauses.
+    pub exclude: Vec<String>,
+    /// Colours an image's palette must contain.
+    pub colors: Vec<(u8, u8, u8)>,
+    /// Colours an image's palette must not contain.
+    pub exclude_colors: Vec<(u8, u8, u8)>,
+    /// Bare words and phrases, without field prefixes or quotes.
+    pub words: Vec<String>,
+    /// Positive field clauses (also in `include`), which results found by
+    /// other means than FTS must still satisfy.
+    pub filters: Vec<String>,
+}
+
+impl ParsedQuery {
+    /// FTS5 MATCH expression for the positive clauses, with negations
+    /// attached as `NOT`. `None` when there are no positive clauses.
+    pub fn fts_match(&self) -> Option<String> {
+        if self.include.is_empty() {
+            return None;
+        }
+        let mut expr = self.include.join(" ");
+        if let Some(excluded) = self.fts_exclude() {
+            expr = format!("({expr}) NOT ({excluded})");
+        }
+        Some(expr)
+    }
+
+    /// FTS5 MATCH expression matching anything that should be excluded.
+    pub fn fts_exclude(&self) -> Option<String> {
+        if self.exclude.is_empty() {
+            None
+        } else {
+            Some(self.exclude.join(" OR "))
+        }
+    }
+
+    /// FTS5 MATCH expression for the field filters, with negations attached
+    /// as `NOT`. `None` when there are no field filters.
+    pub fn fts_filter(&self) -> Option<String> {
+        if self.filters.is_empty() {
+            return None;
+        }
+        let mut expr = self.filters.join(" ");
+        if let Some(excluded) = self.fts_exclude() {
+            expr = format!("({expr}) NOT ({excluded})");
+        }
+        Some(expr)
+    }
+
+    /// Whether the query filters on palette colours.
+    pub fn has_colors(&self) -> bool {
+        !self.colors.is_empty() || !self.exclude_colors.is_empty()
+    }
+
+    /// Free text for embedding-based search (field filters removed).
+    pub fn free_text(&self) -> String {
+        self.words.join(" ")
+    }
+}
+
+/// Map a query field prefix to its FTS5 column.
+fn column_for(field: &str) -> Option<&'static str> {
+    match field.to_lowercase().as_str() {
+        "tag" | "tags" => Some("tags"),
+        "style" => Some("style"),
+        "mood" => Some("mood"),
+        "era" => Some("era"),
+        "name" => Some("name"),
+        "desc" | "description" => Some("description"),
+        "extra" | "field" => Some("extra"),
+        "notes" | "note" => Some("notes"),
+        _ => None,
+    }
+}
+
+/// Quote a value as an FTS5 string (embedded quotes are doubled).
+fn fts_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\"\""))
+}
+
+fn is_operator(token: &str) -> bool {
+    matches!(token, "AND" | "OR" | "NOT")
+}
+
+/// Drop operators that are leading, trailing or follow another operator,
+/// which FTS5 would reject as a syntax error.
+fn drop_dangling_operators(clauses: Vec<String>) -> Vec<String> {
+    let mut kept: Vec<String> = Vec::new();
+    for clause in clauses {
+        if is_operator(&clause) && kept.last().is_none_or(|last| is_operator(last)) {
+            continue;
+        }
+        kept.push(clause);
+    }
+    while kept.last().is_some_and(|last| is_operator(last)) {
+        kept.pop();
+    }
+    kept
+}
+
+/// Split on whitespace, keeping double-quoted runs together.
+fn tokenize(query: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+
+    for c in query.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                current.push(c);
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+/// Parse a search query. Fails only on malformed `color:` values.
+pub fn parse(query: &str) -> Result<ParsedQuery, String> {
+    let mut parsed = ParsedQuery::default();
+
+    for token in tokenize(query) {
+        if is_operator(&token) {
+            parsed.include.push(token);
+            continue;
+        }
+
+        let (negated, term) = match token.strip_prefix('-') {
+            Some(rest) if !rest.is_empty() => (true, rest),
+            _ => (false, token.as_str()),
+        };
+
+        if let Some((field, value)) = term.split_once(':') {
+            let value = value.trim_matches('"');
+            if field.eq_ignore_ascii_case("color") || field.eq_ignore_ascii_case("colour") {
+                let rgb = crate::search::hex_to_rgb(value)
+                    .ok_or_else(|| format!("Invalid hex color: {value}"))?;
+                if negated {
+                    parsed.exclude_colors.push(rgb);
+                } else {
+                    parsed.colors.push(rgb);
+                }
+                continue;
+            }
+            if let Some(column) = column_for(field) {
+                if value.is_empty() {
+                    continue;
+                }
+                let clause = format!("{column} : {}", fts_string(value));
+                if negated {
+                    parsed.exclude.push(clause);
+                } else {
+                    parsed.filters.push(clause.clone());
+                    parsed.include.push(clause);
+                }
+                continue;
+            }
+        }
+
+        // Plain word or phrase (unknown `field:` prefixes are searched literally)
+        let (word, clause) = if term.starts_with('"') {
+            let phrase = term.trim_matches('"');
+            if phrase.is_empty() {
+                continue;
+            }
+            (phrase, fts_string(phrase))
+        } else {
+            (term, format!("{}*", fts_string(term)))
+        };
+        if negated {
+            parsed.exclude.push(clause);
+        } else {
+            parsed.words.push(word.to_string());
+            parsed.include.push(clause);
+        }
+    }
+
+    parsed.include = drop_dangling_operators(parsed.include);
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_plain_words() {
+        let q = parse("art deco").unwrap();
+        assert_eq!(q.fts_match().unwrap(), "\"art\"* \"deco\"*");
+        assert_eq!(q.free_text(), "art deco");
+        assert!(q.fts_exclude().is_none());
+    }
+
+    #[test]
+    fn test_parse_fields_and_negation() {
+        let q = parse("tag:art-deco style:geometric era:1920s -mood:dark").unwrap();
+        assert_eq!(
+            q.include,
+            vec!["tags : \"art-deco\"", "style : \"geometric\"", "era : \"1920s\""]
+        );
+        assert_eq!(q.exclude, vec!["mood : \"dark\""]);
+        assert_eq!(q.filters, q.include);
+        assert_eq!(
+            q.fts_match().unwrap(),
+            "(tags : \"art-deco\" style : \"geometric\" era : \"1920s\") NOT (mood : \"dark\")"
+        );
+        assert!(q.free_text().is_empty());
+    }
+
+    #[test]
+    fn test_parse_phrases_and_operators() {
+        let q = parse("\"golden ratio\" OR tag:\"art nouveau\" -\"dark wood\"").unwrap();
+        assert_eq!(q.include, vec!["\"golden ratio\"", "OR", "tags : \"art nouveau\""]);
+        assert_eq!(q.exclude, vec!["\"dark wood\""]);
+        assert_eq!(q.words, vec!["golden ratio"]);
+    }
+
+    #[test]
+    fn test_parse_dangling_operators() {
+        assert_eq!(parse("lamp OR").unwrap().fts_match().unwrap(), "\"lamp\"*");
+        assert_eq!(parse("NOT lamp").unwrap().fts_match().unwrap(), "\"lamp\"*");
+        assert_eq!(parse("OR lamp AND").unwrap().include, vec!["\"lamp\"*"]);
+        assert_eq!(parse("lamp AND OR chair").unwrap().include, vec!["\"lamp\"*", "AND", "\"chair\"*"]);
+        // The clause after the operator is a negation, kept apart
+        let q = parse("lamp OR -chair").unwrap();
+        assert_eq!(q.fts_match().unwrap(), "(\"lamp\"*) NOT (\"chair\"*)");
+        assert!(parse("AND OR NOT").unwrap().fts_match().is_none());
+    }
+
+    #[test]
+    fn test_parse_colors() {
+        let q = parse("brass color:#c0a060 -color:000000").unwrap();
+        assert_eq!(q.colors, vec![(0xc0, 0xa0, 0x60)]);
+        assert_eq!(q.exclude_colors, vec![(0, 0, 0)]);
+        assert!(q.has_colors());
+        assert_eq!(q.include, vec!["\"brass\"*"]);
+
+        assert!(parse("color:#zzz").is_err());
+        // Six bytes but not six ASCII hex digits
+        assert!(parse("color:aéabc").is_err());
+        assert!(parse("color:+fabcd").is_err());
+    }
+
+    #[test]
+    fn test_parse_unknown_field_and_quotes() {
+        let q = parse("foo:bar say\"hi").unwrap();
+        assert_eq!(q.include, vec!["\"foo:bar\"*", "\"say\"\"hi\"*"]);
+    }
+}
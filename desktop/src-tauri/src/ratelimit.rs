@@ -0,0 +1,297 @@
+//! Client-side rate limiting and retries for AI provider requests.
+//!
+//! Every provider endpoint gets one token bucket, shared by all requests to
+//! it — a parallel batch and single-image analyses draw from the same budget.
+//! Changing the limits updates that bucket rather than adding a second one.
+//! Requests that fail with 429, a 5xx or a network timeout are retried with
+//! exponential backoff, waiting for `Retry-After` when the server sends one.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Retries after the first attempt before giving up.
+const MAX_RETRIES: u32 = 4;
+/// Backoff before the first retry; doubled for each one after.
+const BASE_DELAY: Duration = Duration::from_secs(1);
+/// Longest wait between attempts, including a server's `Retry-After`.
+const MAX_DELAY: Duration = Duration::from_secs(60);
+
+/// Shared limiters keyed by endpoint.
+static LIMITERS: Mutex<Option<HashMap<String, Arc<RateLimiter>>>> = Mutex::new(None);
+
+// ---------------------------------------------------------------------------
+// Token Bucket
+// ---------------------------------------------------------------------------
+
+/// Allows `burst` requests at once, refilling at `per_minute` per minute.
+/// A rate of 0 means unlimited.
+pub struct TokenBucket {
+    state: Mutex<BucketState>,
+}
+
+struct BucketState {
+    capacity: f64,
+    per_sec: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl BucketState {
+    /// Add the tokens earned since the last refill.
+    fn refill(&mut self) {
+        let now = Instant::now();
+        self.tokens = (self.tokens + now.duration_since(self.last).as_secs_f64() * self.per_sec).min(self.capacity);
+        self.last = now;
+    }
+}
+
+impl TokenBucket {
+    pub fn new(per_minute: u32, burst: u32) -> Self {
+        let capacity = burst.max(1) as f64;
+        Self {
+            state: Mutex::new(BucketState {
+                capacity,
+                per_sec: per_minute as f64 / 60.0,
+                tokens: capacity,
+                last: Instant::now(),
+            }),
+        }
+    }
+
+    /// Change the rate and burst, keeping the tokens already earned (up to
+    /// the new burst).
+    pub fn set_limits(&self, per_minute: u32, burst: u32) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.refill();
+        state.capacity = burst.max(1) as f64;
+        state.per_sec = per_minute as f64 / 60.0;
+        state.tokens = state.tokens.min(state.capacity);
+    }
+
+    /// Wait until a request may be sent, and take its token.
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+                if state.per_sec <= 0.0 {
+                    return;
+                }
+                state.refill();
+                if state.tokens >= 1.0 {
+                    state.tokens -= 1.0;
+                    return;
+                }
+                Duration::from_secs_f64((1.0 - state.tokens) / state.per_sec)
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Retries
+// ---------------------------------------------------------------------------
+
+/// Whether a response status is worth retrying.
+pub fn is_retryable(status: reqwest::StatusCode) -> bool {
+    status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Parse a `Retry-After` value: delay seconds or an HTTP date.
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let when = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let secs = (when.with_timezone(&chrono::Utc) - chrono::Utc::now()).num_milliseconds().max(0);
+    Some(Duration::from_millis(secs as u64))
+}
+
+/// Delay before retry number `attempt` (0-based), capped at `MAX_DELAY`.
+pub fn backoff(attempt: u32, retry_after: Option<Duration>) -> Duration {
+    retry_after
+        .unwrap_or_else(|| BASE_DELAY.saturating_mul(1 << attempt.min(16)))
+        .min(MAX_DELAY)
+}
+
+// ---------------------------------------------------------------------------
+// Rate Limiter
+// ---------------------------------------------------------------------------
+
+/// A token bucket plus the retry loop around each request.
+pub struct RateLimiter {
+    bucket: TokenBucket,
+    max_retries: u32,
+}
+
+impl RateLimiter {
+    pub fn new(per_minute: u32, burst: u32) -> Self {
+        Self {
+            bucket: TokenBucket::new(per_minute, burst),
+            max_retries: MAX_RETRIES,
+        }
+    }
+
+    /// No rate limit and no retries, for one-off requests such as a
+    /// connection test.
+    pub fn unlimited() -> Self {
+        Self {
+            bucket: TokenBucket::new(0, 1),
+            max_retries: 0,
+        }
+    }
+
+    /// Send a request, waiting for a token before each attempt and retrying
+    /// rate-limited, server-error and timed-out attempts. Other responses,
+    /// including the last failed attempt, are returned for the caller to read.
+    pub async fn send(&self, request: reqwest::RequestBuilder, label: &str) -> Result<reqwest::Response, String> {
+        let mut attempt = 0;
+        loop {
+            let req = request
+                .try_clone()
+                .ok_or_else(|| format!("{label} request cannot be retried"))?;
+            self.bucket.acquire().await;
+
+            let delay = match req.send().await {
+                Ok(resp) if is_retryable(resp.status()) && attempt < self.max_retries => {
+                    let retry_after = resp
+                        .headers()
+                        .get(reqwest::header::RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(parse_retry_after);
+                    let delay = backoff(attempt, retry_after);
+                    crate::log::log("AI", &format!(
+                        "{label} returned {}, retrying in {:.1}s", resp.status(), delay.as_secs_f64()
+                    ));
+                    delay
+                }
+                Ok(resp) => return Ok(resp),
+                Err(e) if (e.is_timeout() || e.is_connect()) && attempt < self.max_retries => {
+                    let delay = backoff(attempt, None);
+                    crate::log::log("AI", &format!(
+                        "{label} request failed ({e}), retrying in {:.1}s", delay.as_secs_f64()
+                    ));
+                    delay
+                }
+                Err(e) => return Err(format!("{label} request failed: {e}")),
+            };
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// The limiter shared by every request to `endpoint`, set to these limits.
+pub fn shared(endpoint: &str, per_minute: u32, burst: u32) -> Arc<RateLimiter> {
+    let mut guard = LIMITERS.lock().unwrap_or_else(|e| e.into_inner());
+    let limiters = guard.get_or_insert_with(HashMap::new);
+    if let Some(limiter) = limiters.get(endpoint) {
+        limiter.bucket.set_limits(per_minute, burst);
+        return limiter.clone();
+    }
+    let limiter = Arc::new(RateLimiter::new(per_minute, burst));
+    limiters.insert(endpoint.to_string(), limiter.clone());
+    limiter
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("7"), Some(Duration::from_secs(7)));
+        assert_eq!(parse_retry_after(" 0 "), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+        let later = (chrono::Utc::now() + chrono::Duration::seconds(30)).to_rfc2822();
+        let delay = parse_retry_after(&later).unwrap();
+        assert!(delay > Duration::from_secs(25) && delay <= Duration::from_secs(30));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[test]
+    fn test_backoff_doubles_and_caps() {
+        assert_eq!(backoff(0, None), Duration::from_secs(1));
+        assert_eq!(backoff(3, None), Duration::from_secs(8));
+        assert_eq!(backoff(10, None), MAX_DELAY);
+        assert_eq!(backoff(0, Some(Duration::from_secs(5))), Duration::from_secs(5));
+        assert_eq!(backoff(0, Some(Duration::from_secs(600))), MAX_DELAY);
+    }
+
+    #[tokio::test]
+    async fn test_token_bucket_limits_rate() {
+        // 600/min = one token every 100ms after a burst of 2
+        let bucket = TokenBucket::new(600, 2);
+        let start = Instant::now();
+        for _ in 0..4 {
+            bucket.acquire().await;
+        }
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(180), "took {elapsed:?}");
+
+        let unlimited = TokenBucket::new(0, 1);
+        let start = Instant::now();
+        for _ in 0..100 {
+            unlimited.acquire().await;
+        }
+        assert!(start.elapsed() < Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn test_shared_limiter_follows_new_limits() {
+        let endpoint = "https://ratelimit.test/v1";
+        let first = shared(endpoint, 0, 1);
+        // Raising the limit mid-run updates the same bucket
+        let second = shared(endpoint, 60, 1);
+        assert!(Arc::ptr_eq(&first, &second));
+
+        // One token, then the next is a second away at 60/min
+        first.bucket.acquire().await;
+        let start = Instant::now();
+        let waited = tokio::time::timeout(Duration::from_millis(200), second.bucket.acquire()).await;
+        assert!(waited.is_err(), "second token came after {:?}", start.elapsed());
+
+        // Back to unlimited for everyone holding the limiter
+        shared(endpoint, 0, 1);
+        tokio::time::timeout(Duration::from_millis(50), first.bucket.acquire()).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_send_retries_rate_limited_requests() {
+        use axum::{http::StatusCode, response::IntoResponse, routing::get, Router};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let app = Router::new().route(
+            "/",
+            get(move || {
+                let counter = counter.clone();
+                async move {
+                    match counter.fetch_add(1, Ordering::SeqCst) {
+                        0 => (StatusCode::TOO_MANY_REQUESTS, [("retry-after", "0")], "slow down").into_response(),
+                        1 => (StatusCode::SERVICE_UNAVAILABLE, [("retry-after", "0")], "busy").into_response(),
+                        _ => "ok".into_response(),
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let limiter = RateLimiter::new(0, 1);
+        let client = reqwest::Client::new();
+        let resp = limiter.send(client.get(format!("http://{addr}/")), "Test").await.unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::OK);
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+
+        // Out of retries: the last error response is handed back
+        let limiter = RateLimiter { bucket: TokenBucket::new(0, 1), max_retries: 0 };
+        hits.store(0, Ordering::SeqCst);
+        let resp = limiter.send(client.get(format!("http://{addr}/")), "Test").await.unwrap();
+        assert_eq!(resp.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+    }
+}
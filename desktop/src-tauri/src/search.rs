@@ -3,12 +3,15 @@
 //! Provides:
 //! - SQLite-backed metadata indexing with FTS5 full-text search
 //! - Tag collection and filtering
-//! - Embedding storage (BLOB) and brute-force cosine similarity
+//! - Embedding storage (BLOB) with an HNSW index for cosine similarity
+//!   (brute-force scan when the index is absent)
+//! - Text-to-image semantic search via the CLIP text encoder
 //! - Per-project database stored at `{project_path}/.deco/search.db`
+//!   (schema versioned by `migrate`)
 
 use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 
 // ---------------------------------------------------------------------------
@@ -23,17 +26,114 @@ pub struct SearchResult {
     pub score: f64,
     pub description: Option<String>,
     pub tags: Vec<String>,
+    /// Signals that produced this hit (hybrid search only).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub matched_by: Vec<MatchSignal>,
+    /// Per-signal ranks and scores behind `score` (hybrid search only).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub breakdown: Option<ScoreBreakdown>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchSignal {
+    /// FTS5 keyword match over metadata.
+    Text,
+    /// CLIP text-to-image embedding similarity.
+    Semantic,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScoreBreakdown {
+    pub text_rank: Option<usize>,
+    /// bm25 magnitude (higher is better).
+    pub text_score: Option<f64>,
+    pub semantic_rank: Option<usize>,
+    /// Cosine similarity.
+    pub semantic_score: Option<f64>,
+}
+
+/// How hybrid search combines the text and semantic rankings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Fusion {
+    /// Weighted reciprocal-rank fusion: `sum(w / (60 + rank))`.
+    #[default]
+    Rrf,
+    /// Weighted sum of normalized scores (bm25 min-max, cosine as-is).
+    Weighted,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HybridOptions {
+    #[serde(default)]
+    pub fusion: Fusion,
+    #[serde(default = "default_hybrid_weight")]
+    pub text_weight: f64,
+    #[serde(default = "default_hybrid_weight")]
+    pub semantic_weight: f64,
+}
+
+fn default_hybrid_weight() -> f64 {
+    0.5
+}
+
+impl Default for HybridOptions {
+    fn default() -> Self {
+        HybridOptions {
+            fusion: Fusion::Rrf,
+            text_weight: default_hybrid_weight(),
+            semantic_weight: default_hybrid_weight(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TagCount {
     pub tag: String,
+    /// Images tagged with `tag` or anything beneath it.
+    pub count: usize,
+    /// Images tagged with exactly `tag` (0 for a level only used as a prefix).
+    #[serde(default)]
+    pub direct: usize,
+}
+
+/// A level of the tag hierarchy, for tree views.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagNode {
+    /// Full tag (`material/bronze`).
+    pub tag: String,
+    /// Last segment (`bronze`).
+    pub name: String,
     pub count: usize,
+    pub direct: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<TagNode>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct DuplicateImage {
+    pub image_path: String,
+    pub name: String,
+    pub size_bytes: u64,
+    /// Hamming distance from the first image in the group.
+    pub distance: u32,
+}
+
+/// Near-identical images, largest file first (the natural one to keep).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateGroup {
+    pub images: Vec<DuplicateImage>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ImageMetadataRow {
     pub image_path: String,
     pub name: String,
@@ -49,6 +149,113 @@ pub struct ImageMetadataRow {
     pub colors: Vec<String>,
     #[serde(default)]
     pub era: Option<String>,
+    /// Share of the image covered by each of `colors`, when the palette was
+    /// extracted locally (AI-provided colours carry no weights).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub color_weights: Vec<f64>,
+    /// Dimensions, EXIF and colour profile, read from the file at index time.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub properties: Option<crate::properties::ImageProperties>,
+    /// Project-defined analysis fields, stored as JSON in `images.extra`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra: BTreeMap<String, serde_json::Value>,
+    /// Notes, rating and attribution someone recorded. Read-only here:
+    /// `upsert_image` leaves them alone, `update_annotations` edits them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ImageAnnotations>,
+}
+
+/// What a person recorded about an image, as opposed to what analysis
+/// found. Re-analysis and re-indexing never touch these.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImageAnnotations {
+    /// Where the file itself came from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_url: Option<String>,
+    /// The page it was found on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_page: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// Credit line to print alongside the image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credit: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// 0–5 stars.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rating: Option<u8>,
+    #[serde(default)]
+    pub favorite: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notes: Option<String>,
+}
+
+/// Changes to an image's annotations. `None` leaves a field as it is; an
+/// empty string clears it.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnnotationUpdate {
+    #[serde(default)]
+    pub source_url: Option<String>,
+    #[serde(default)]
+    pub source_page: Option<String>,
+    #[serde(default)]
+    pub license: Option<String>,
+    #[serde(default)]
+    pub credit: Option<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+    #[serde(default)]
+    pub rating: Option<u8>,
+    #[serde(default)]
+    pub favorite: Option<bool>,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+impl AnnotationUpdate {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Check the update before anything is written.
+    pub fn validate(&self) -> Result<(), String> {
+        match self.rating {
+            Some(rating) if rating > 5 => Err(format!("Rating must be between 0 and 5, got {rating}")),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl ImageAnnotations {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    fn apply(&mut self, update: &AnnotationUpdate) -> Result<(), String> {
+        fn set(field: &mut Option<String>, value: &Option<String>) {
+            if let Some(value) = value {
+                let value = value.trim();
+                *field = (!value.is_empty()).then(|| value.to_string());
+            }
+        }
+        update.validate()?;
+        if let Some(rating) = update.rating {
+            self.rating = Some(rating);
+        }
+        set(&mut self.source_url, &update.source_url);
+        set(&mut self.source_page, &update.source_page);
+        set(&mut self.license, &update.license);
+        set(&mut self.credit, &update.credit);
+        set(&mut self.author, &update.author);
+        set(&mut self.notes, &update.notes);
+        if let Some(favorite) = update.favorite {
+            self.favorite = favorite;
+        }
+        Ok(())
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -78,96 +285,54 @@ pub fn open_db(project_path: &str) -> Result<Connection, String> {
     conn.pragma_update(None, "journal_mode", "WAL")
         .map_err(|e| format!("Cannot set WAL mode: {e}"))?;
 
-    init_schema(&conn)?;
+    crate::migrate::run(&conn, &path)?;
     Ok(conn)
 }
 
-// ---------------------------------------------------------------------------
-// Schema
-// ---------------------------------------------------------------------------
-
-fn init_schema(conn: &Connection) -> Result<(), String> {
-    conn.execute_batch(
-        "
-        -- Image metadata table
-        CREATE TABLE IF NOT EXISTS images (
-            path TEXT PRIMARY KEY,
-            name TEXT NOT NULL,
-            description TEXT,
-            tags TEXT DEFAULT '',
-            style TEXT DEFAULT '',
-            mood TEXT DEFAULT '',
-            colors TEXT DEFAULT '',
-            era TEXT
-        );
-
-        -- FTS5 virtual table for full-text search
-        CREATE VIRTUAL TABLE IF NOT EXISTS images_fts USING fts5(
-            name,
-            description,
-            tags,
-            style,
-            mood,
-            era,
-            content=images,
-            content_rowid=rowid
-        );
-
-        -- Triggers to keep FTS in sync
-        CREATE TRIGGER IF NOT EXISTS images_ai AFTER INSERT ON images BEGIN
-            INSERT INTO images_fts(rowid, name, description, tags, style, mood, era)
-            VALUES (new.rowid, new.name, new.description, new.tags, new.style, new.mood, new.era);
-        END;
-
-        CREATE TRIGGER IF NOT EXISTS images_ad AFTER DELETE ON images BEGIN
-            INSERT INTO images_fts(images_fts, rowid, name, description, tags, style, mood, era)
-            VALUES ('delete', old.rowid, old.name, old.description, old.tags, old.style, old.mood, old.era);
-        END;
-
-        CREATE TRIGGER IF NOT EXISTS images_au AFTER UPDATE ON images BEGIN
-            INSERT INTO images_fts(images_fts, rowid, name, description, tags, style, mood, era)
-            VALUES ('delete', old.rowid, old.name, old.description, old.tags, old.style, old.mood, old.era);
-            INSERT INTO images_fts(rowid, name, description, tags, style, mood, era)
-            VALUES (new.rowid, new.name, new.description, new.tags, new.style, new.mood, new.era);
-        END;
-
-        -- Embeddings table for CLIP vectors (BLOB storage)
-        CREATE TABLE IF NOT EXISTS embeddings (
-            path TEXT PRIMARY KEY,
-            model TEXT NOT NULL DEFAULT 'clip-vit-b-32',
-            vector BLOB NOT NULL,
-            dimensions INTEGER NOT NULL,
-            created_at TEXT DEFAULT (datetime('now'))
-        );
-        ",
-    )
-    .map_err(|e| format!("Schema init failed: {e}"))?;
-
-    Ok(())
-}
-
 // ---------------------------------------------------------------------------
 // Indexing
 // ---------------------------------------------------------------------------
 
-/// Index a single image's metadata into the search database.
-pub fn upsert_image(conn: &Connection, meta: &ImageMetadataRow) -> Result<(), String> {
+/// Index a single image's metadata into the search database. Tags are
+/// normalized through the project's vocabulary first.
+pub fn upsert_image(
+    conn: &Connection,
+    meta: &ImageMetadataRow,
+    vocab: &crate::vocab::Vocabulary,
+) -> Result<(), String> {
+    let tags = vocab.apply(&meta.tags);
     conn.execute(
-        "INSERT OR REPLACE INTO images (path, name, description, tags, style, mood, colors, era)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        "INSERT INTO images (path, name, description, tags, style, mood, colors, era, extra)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)
+         ON CONFLICT(path) DO UPDATE SET
+            name = excluded.name, description = excluded.description, tags = excluded.tags,
+            style = excluded.style, mood = excluded.mood, colors = excluded.colors, era = excluded.era,
+            extra = excluded.extra,
+            color_weights = CASE WHEN excluded.colors = images.colors THEN images.color_weights END",
         params![
             meta.image_path,
             meta.name,
             meta.description,
-            meta.tags.join(" "),
+            tags.join(" "),
             meta.style.join(" "),
             meta.mood.join(" "),
             meta.colors.join(" "),
             meta.era,
+            extra_json(&meta.extra),
         ],
     )
     .map_err(|e| format!("Cannot upsert image metadata: {e}"))?;
-    Ok(())
+    set_image_tags(conn, &meta.image_path, &tags)
+}
+
+/// `images.extra` for a set of extra fields: a JSON object, or NULL when empty.
+pub fn extra_json(extra: &BTreeMap<String, serde_json::Value>) -> Option<String> {
+    serde_json::to_string(extra).ok().filter(|_| !extra.is_empty())
+}
+
+/// Parse `images.extra`; missing or malformed values read as no fields.
+pub fn parse_extra(json: Option<String>) -> BTreeMap<String, serde_json::Value> {
+    json.and_then(|j| serde_json::from_str(&j).ok()).unwrap_or_default()
 }
 
 /// Index all images in a project directory. Scans for images and inserts
@@ -209,16 +374,226 @@ pub fn index_project_images(
 
     tx.commit()
         .map_err(|e| format!("Cannot commit transaction: {e}"))?;
+
+    update_image_features(&conn, images)?;
+
+    // Record content hashes so imports find these files as duplicates
+    for img in images {
+        if let Err(e) = crate::dedup::content_hash_conn(&conn, Path::new(&img.path)) {
+            crate::log::log("SEARCH", &format!("Skipping content hash: {e}"));
+        }
+    }
     Ok(count)
 }
 
+fn file_mtime(path: &str) -> Option<i64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    let secs = modified.duration_since(std::time::UNIX_EPOCH).ok()?.as_secs();
+    Some(secs as i64)
+}
+
+/// Compute dHashes, read properties and extract palettes for images that
+/// need them, opening and decoding each changed file once.
+///
+/// - dHash: none yet, or the file changed since
+/// - properties: indexed but not read yet, or the file changed since
+/// - palette: no colours yet, or a locally extracted palette is out of date
+///   (colours from AI analysis are kept)
+///
+/// Files that can't be read or decoded are skipped.
+fn update_image_features(conn: &Connection, images: &[crate::ImageInfo]) -> Result<(), String> {
+    let (mut hashes, mut properties, mut palettes) = (0, 0, 0);
+    for img in images {
+        let Some(mtime) = file_mtime(&img.path) else {
+            continue;
+        };
+        let needs_hash = hash_stale(conn, &img.path, mtime)?;
+        let needs_properties = properties_stale(conn, &img.path, mtime)?;
+        let needs_palette = palette_stale(conn, &img.path, mtime)?;
+        let path = Path::new(&img.path);
+
+        // Properties alone only need the headers
+        if !needs_hash && !needs_palette {
+            if needs_properties {
+                match crate::properties::read(path) {
+                    Ok(p) => {
+                        store_properties(conn, &img.path, &p, mtime)?;
+                        properties += 1;
+                    }
+                    Err(e) => crate::log::log("SEARCH", &format!("Skipping image properties: {e}")),
+                }
+            }
+            continue;
+        }
+
+        let (p, image) = match crate::properties::read_decoded(path) {
+            Ok(read) => read,
+            Err(e) => {
+                crate::log::log("SEARCH", &format!("Skipping image: {e}"));
+                continue;
+            }
+        };
+        if needs_properties {
+            store_properties(conn, &img.path, &p, mtime)?;
+            properties += 1;
+        }
+        let image = match image {
+            Ok(image) => image,
+            Err(e) => {
+                crate::log::log("SEARCH", &format!("Skipping perceptual hash and palette: {e}"));
+                continue;
+            }
+        };
+        if needs_hash {
+            conn.execute(
+                "INSERT OR REPLACE INTO image_hashes (path, dhash, mtime) VALUES (?1, ?2, ?3)",
+                params![img.path, crate::phash::dhash(&image) as i64, mtime],
+            )
+            .map_err(|e| format!("Cannot store image hash: {e}"))?;
+            hashes += 1;
+        }
+        if needs_palette {
+            let palette = crate::palette::extract(&image);
+            let hexes: Vec<&str> = palette.iter().map(|c| c.hex.as_str()).collect();
+            let weights: Vec<String> = palette.iter().map(|c| format!("{:.3}", c.weight)).collect();
+            conn.execute(
+                "UPDATE images SET colors = ?2, color_weights = ?3, palette_mtime = ?4 WHERE path = ?1",
+                params![img.path, hexes.join(" "), weights.join(" "), mtime],
+            )
+            .map_err(|e| format!("Cannot store palette: {e}"))?;
+            palettes += 1;
+        }
+    }
+    if hashes > 0 {
+        crate::log::log("SEARCH", &format!("Computed {hashes} perceptual hashes"));
+    }
+    if properties > 0 {
+        crate::log::log("SEARCH", &format!("Read properties of {properties} images"));
+    }
+    if palettes > 0 {
+        crate::log::log("SEARCH", &format!("Extracted palettes for {palettes} images"));
+    }
+    Ok(())
+}
+
+/// Whether an image has no dHash yet or changed on disk since.
+fn hash_stale(conn: &Connection, path: &str, mtime: i64) -> Result<bool, String> {
+    let stored: Option<i64> = conn
+        .query_row(
+            "SELECT mtime FROM image_hashes WHERE path = ?1",
+            params![path],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Cannot read image hash: {e}"))?;
+    Ok(stored != Some(mtime))
+}
+
+/// Whether an indexed image's properties haven't been read yet or the file
+/// changed on disk since.
+fn properties_stale(conn: &Connection, path: &str, mtime: i64) -> Result<bool, String> {
+    let stored: Option<Option<i64>> = conn
+        .query_row(
+            "SELECT properties_mtime FROM images WHERE path = ?1",
+            params![path],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(|e| format!("Cannot read image properties: {e}"))?;
+    // Not indexed, or already up to date
+    Ok(stored.is_some() && stored != Some(Some(mtime)))
+}
+
+/// Whether an indexed image needs its palette extracted.
+fn palette_stale(conn: &Connection, path: &str, mtime: i64) -> Result<bool, String> {
+    let stored: Option<(String, Option<String>, Option<i64>)> = conn
+        .query_row(
+            "SELECT colors, color_weights, palette_mtime FROM images WHERE path = ?1",
+            params![path],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()
+        .map_err(|e| format!("Cannot read palette: {e}"))?;
+    let Some((colors, weights, palette_mtime)) = stored else {
+        return Ok(false);
+    };
+    Ok(match weights {
+        Some(_) => palette_mtime != Some(mtime),
+        None => colors.is_empty(),
+    })
+}
+
+fn store_properties(
+    conn: &Connection,
+    path: &str,
+    p: &crate::properties::ImageProperties,
+    mtime: i64,
+) -> Result<(), String> {
+    conn.execute(
+        "UPDATE images SET width = ?2, height = ?3, orientation = ?4, camera_make = ?5,
+            camera_model = ?6, lens = ?7, taken_at = ?8, gps_latitude = ?9,
+            gps_longitude = ?10, color_profile = ?11, properties_mtime = ?12
+         WHERE path = ?1",
+        params![
+            path, p.width, p.height, p.orientation, p.camera_make,
+            p.camera_model, p.lens, p.taken_at, p.gps_latitude,
+            p.gps_longitude, p.color_profile, mtime,
+        ],
+    )
+    .map_err(|e| format!("Cannot store image properties: {e}"))?;
+    Ok(())
+}
+
+/// Columns read by `properties_from_row`, in order.
+pub const PROPERTY_SELECT: &str = "width, height, orientation, camera_make, camera_model, lens, \
+    taken_at, gps_latitude, gps_longitude, color_profile, properties_mtime";
+
+/// Number of columns in `PROPERTY_SELECT`, for finding the ones after it.
+pub const PROPERTY_COLUMNS: usize = column_count(PROPERTY_SELECT);
+
+const fn column_count(select: &str) -> usize {
+    let bytes = select.as_bytes();
+    let (mut count, mut i) = (1, 0);
+    while i < bytes.len() {
+        if bytes[i] == b',' {
+            count += 1;
+        }
+        i += 1;
+    }
+    count
+}
+
+/// Build properties from `PROPERTY_SELECT` columns starting at `first`.
+/// `None` when the file hasn't been read yet.
+pub fn properties_from_row(
+    row: &rusqlite::Row,
+    first: usize,
+) -> rusqlite::Result<Option<crate::properties::ImageProperties>> {
+    let read_at: Option<i64> = row.get(first + 10)?;
+    if read_at.is_none() {
+        return Ok(None);
+    }
+    Ok(Some(crate::properties::ImageProperties {
+        width: row.get(first)?,
+        height: row.get(first + 1)?,
+        orientation: row.get(first + 2)?,
+        camera_make: row.get(first + 3)?,
+        camera_model: row.get(first + 4)?,
+        lens: row.get(first + 5)?,
+        taken_at: row.get(first + 6)?,
+        gps_latitude: row.get(first + 7)?,
+        gps_longitude: row.get(first + 8)?,
+        color_profile: row.get(first + 9)?,
+    }))
+}
+
 /// Update metadata for a single image (after AI analysis or manual edit).
 pub fn update_image_metadata(
     project_path: &str,
     meta: &ImageMetadataRow,
 ) -> Result<(), String> {
     let conn = open_db(project_path)?;
-    upsert_image(&conn, meta)
+    upsert_image(&conn, meta, &crate::vocab::Vocabulary::load_or_default(project_path))
 }
 
 /// Get metadata for a single image by path.
@@ -229,13 +604,18 @@ pub fn get_image_metadata(
     let conn = open_db(project_path)?;
     let result = conn
         .query_row(
-            "SELECT path, name, description, tags, style, mood, colors, era FROM images WHERE path = ?1",
+            &format!(
+                "SELECT path, name, description, tags, style, mood, colors, era, {PROPERTY_SELECT},
+                    color_weights, extra, {ANNOTATION_SELECT}
+                 FROM images WHERE path = ?1"
+            ),
             params![image_path],
             |row| {
                 let tags_str: String = row.get(3)?;
                 let style_str: String = row.get(4)?;
                 let mood_str: String = row.get(5)?;
                 let colors_str: String = row.get(6)?;
+                let after_properties = 8 + PROPERTY_COLUMNS;
                 Ok(ImageMetadataRow {
                     image_path: row.get(0)?,
                     name: row.get(1)?,
@@ -245,6 +625,13 @@ pub fn get_image_metadata(
                     mood: mood_str.split_whitespace().map(String::from).collect(),
                     colors: colors_str.split_whitespace().map(String::from).collect(),
                     era: row.get(7)?,
+                    color_weights: row
+                        .get::<_, Option<String>>(after_properties)?
+                        .map(|w| w.split_whitespace().filter_map(|v| v.parse().ok()).collect())
+                        .unwrap_or_default(),
+                    properties: properties_from_row(row, 8)?,
+                    extra: parse_extra(row.get(after_properties + 1)?),
+                    annotations: Some(annotations_from_row(row, after_properties + 2)?).filter(|a| !a.is_empty()),
                 })
             },
         )
@@ -253,6 +640,118 @@ pub fn get_image_metadata(
     Ok(result)
 }
 
+// ---------------------------------------------------------------------------
+// Annotations
+// ---------------------------------------------------------------------------
+
+/// Columns read by `annotations_from_row`, in order.
+pub const ANNOTATION_SELECT: &str = "source_url, source_page, license, credit, author, rating, favorite, notes";
+
+/// Build annotations from `ANNOTATION_SELECT` columns starting at `first`.
+pub fn annotations_from_row(row: &rusqlite::Row, first: usize) -> rusqlite::Result<ImageAnnotations> {
+    Ok(ImageAnnotations {
+        source_url: row.get(first)?,
+        source_page: row.get(first + 1)?,
+        license: row.get(first + 2)?,
+        credit: row.get(first + 3)?,
+        author: row.get(first + 4)?,
+        rating: row.get(first + 5)?,
+        favorite: row.get::<_, Option<bool>>(first + 6)?.unwrap_or(false),
+        notes: row.get(first + 7)?,
+    })
+}
+
+/// An image's annotations; empty when it has none or isn't indexed yet.
+pub fn get_annotations(conn: &Connection, image_path: &str) -> Result<ImageAnnotations, String> {
+    conn.query_row(
+        &format!("SELECT {ANNOTATION_SELECT} FROM images WHERE path = ?1"),
+        params![image_path],
+        |row| annotations_from_row(row, 0),
+    )
+    .optional()
+    .map(Option::unwrap_or_default)
+    .map_err(|e| format!("Cannot read annotations: {e}"))
+}
+
+/// Apply `update` to an image's annotations, indexing the image if it
+/// isn't yet, and return the result.
+pub fn update_annotations(
+    conn: &Connection,
+    image_path: &str,
+    update: &AnnotationUpdate,
+) -> Result<ImageAnnotations, String> {
+    let mut annotations = get_annotations(conn, image_path)?;
+    annotations.apply(update)?;
+
+    let name = Path::new(image_path).file_name().unwrap_or_default().to_string_lossy().to_string();
+    conn.execute(
+        "INSERT INTO images (path, name, source_url, source_page, license, credit, author, rating, favorite, notes)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+         ON CONFLICT(path) DO UPDATE SET
+            source_url = excluded.source_url, source_page = excluded.source_page,
+            license = excluded.license, credit = excluded.credit, author = excluded.author,
+            rating = excluded.rating, favorite = excluded.favorite, notes = excluded.notes",
+        params![
+            image_path,
+            name,
+            annotations.source_url,
+            annotations.source_page,
+            annotations.license,
+            annotations.credit,
+            annotations.author,
+            annotations.rating,
+            annotations.favorite,
+            annotations.notes,
+        ],
+    )
+    .map_err(|e| format!("Cannot update annotations: {e}"))?;
+    Ok(annotations)
+}
+
+/// Upsert an image's metadata and apply `update` to its annotations in one
+/// transaction. The update is checked first, so an invalid one writes
+/// nothing. Returns the annotations when `update` changed any.
+pub fn upsert_image_with_annotations(
+    conn: &Connection,
+    meta: &ImageMetadataRow,
+    vocab: &crate::vocab::Vocabulary,
+    update: &AnnotationUpdate,
+) -> Result<Option<ImageAnnotations>, String> {
+    update.validate()?;
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Cannot begin transaction: {e}"))?;
+    upsert_image(&tx, meta, vocab)?;
+    let annotations = if update.is_empty() {
+        None
+    } else {
+        Some(update_annotations(&tx, &meta.image_path, update)?)
+    };
+    tx.commit().map_err(|e| format!("Cannot commit transaction: {e}"))?;
+    Ok(annotations)
+}
+
+/// Remember where a downloaded image came from, unless someone already
+/// recorded a source for it.
+pub fn record_source(
+    project_path: &str,
+    image_path: &str,
+    source_url: &str,
+    source_page: Option<&str>,
+) -> Result<(), String> {
+    let conn = open_db(project_path)?;
+    let name = Path::new(image_path).file_name().unwrap_or_default().to_string_lossy().to_string();
+    conn.execute(
+        "INSERT INTO images (path, name, source_url, source_page) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(path) DO UPDATE SET
+            source_url = COALESCE(images.source_url, excluded.source_url),
+            source_page = COALESCE(images.source_page, excluded.source_page)",
+        params![image_path, name, source_url, source_page.filter(|p| !p.is_empty())],
+    )
+    .map_err(|e| format!("Cannot record image source: {e}"))?;
+    Ok(())
+}
+
 // ---------------------------------------------------------------------------
 // Deletion
 // ---------------------------------------------------------------------------
@@ -261,13 +760,43 @@ pub fn get_image_metadata(
 /// FTS5 triggers handle cleaning up the `images_fts` table automatically.
 pub fn delete_image_data(project_path: &str, image_path: &str) -> Result<(), String> {
     let conn = open_db(project_path)?;
+    crate::ann::remove_path(&conn, image_path)?;
     conn.execute("DELETE FROM embeddings WHERE path = ?1", params![image_path])
         .map_err(|e| format!("Cannot delete embedding: {e}"))?;
+    conn.execute("DELETE FROM image_hashes WHERE path = ?1", params![image_path])
+        .map_err(|e| format!("Cannot delete image hash: {e}"))?;
+    conn.execute("DELETE FROM content_hashes WHERE path = ?1", params![image_path])
+        .map_err(|e| format!("Cannot delete content hash: {e}"))?;
     conn.execute("DELETE FROM images WHERE path = ?1", params![image_path])
         .map_err(|e| format!("Cannot delete image metadata: {e}"))?;
     Ok(())
 }
 
+/// Move an image's metadata, embedding and hashes to its new path after the
+/// file was renamed on disk. Anything already indexed under `to` is replaced.
+pub fn rename_image_data(project_path: &str, from: &str, to: &str) -> Result<(), String> {
+    let conn = open_db(project_path)?;
+    let tx = conn
+        .unchecked_transaction()
+        .map_err(|e| format!("Cannot begin transaction: {e}"))?;
+
+    crate::ann::remove_path(&tx, to)?;
+    for table in ["embeddings", "image_hashes", "content_hashes", "images"] {
+        tx.execute(&format!("DELETE FROM {table} WHERE path = ?1"), params![to])
+            .map_err(|e| format!("Cannot clear {table} for {to}: {e}"))?;
+        tx.execute(&format!("UPDATE {table} SET path = ?2 WHERE path = ?1"), params![from, to])
+            .map_err(|e| format!("Cannot rename in {table}: {e}"))?;
+    }
+    tx.execute("UPDATE ann_nodes SET path = ?2 WHERE path = ?1", params![from, to])
+        .map_err(|e| format!("Cannot rename in ann_nodes: {e}"))?;
+
+    let name = Path::new(to).file_name().unwrap_or_default().to_string_lossy().to_string();
+    tx.execute("UPDATE images SET name = ?2 WHERE path = ?1", params![to, name])
+        .map_err(|e| format!("Cannot rename image: {e}"))?;
+
+    tx.commit().map_err(|e| format!("Cannot commit transaction: {e}"))
+}
+
 // ---------------------------------------------------------------------------
 // Full-Text Search
 // ---------------------------------------------------------------------------
@@ -279,39 +808,17 @@ pub fn search_text(project_path: &str, query: &str, limit: usize) -> Result<Vec<
         return Ok(Vec::new());
     }
 
+    // Field filters, negations and colours — see `query.rs` for the grammar
+    let parsed = crate::query::parse(query)?;
     let conn = open_db(project_path)?;
 
-    // Escape the query for FTS5 — wrap each word in quotes for safety
-    let safe_query = query
-        .split_whitespace()
-        .map(|w| {
-            // If already quoted or uses FTS5 operators, pass through
-            if w.starts_with('"') || w == "AND" || w == "OR" || w == "NOT" {
-                w.to_string()
-            } else {
-                // Add wildcard for prefix matching
-                format!("\"{w}\"*")
-            }
-        })
-        .collect::<Vec<_>>()
-        .join(" ");
+    // Colour filtering happens after SQL, so don't let LIMIT cut candidates
+    let sql_limit = if parsed.has_colors() { -1 } else { limit as i64 };
 
-    let mut stmt = conn
-        .prepare(
-            "SELECT i.path, i.name, i.description, i.tags,
-                    bm25(images_fts) as score
-             FROM images_fts
-             JOIN images i ON images_fts.rowid = i.rowid
-             WHERE images_fts MATCH ?1
-             ORDER BY score
-             LIMIT ?2",
-        )
-        .map_err(|e| format!("Search query failed: {e}"))?;
-
-    let results = stmt
-        .query_map(params![safe_query, limit as i64], |row| {
-            let tags_str: String = row.get(3)?;
-            Ok(SearchResult {
+    let map_row = |row: &rusqlite::Row| -> rusqlite::Result<(SearchResult, String)> {
+        let tags_str: String = row.get(3)?;
+        Ok((
+            SearchResult {
                 image_path: row.get(0)?,
                 name: row.get(1)?,
                 description: row.get(2)?,
@@ -320,47 +827,198 @@ pub fn search_text(project_path: &str, query: &str, limit: usize) -> Result<Vec<
                     .map(String::from)
                     .collect(),
                 score: row.get::<_, f64>(4)?.abs(), // bm25 returns negative scores
-            })
+                matched_by: Vec::new(),
+                breakdown: None,
+            },
+            row.get::<_, Option<String>>(5)?.unwrap_or_default(),
+        ))
+    };
+
+    let rows: Vec<(SearchResult, String)> = match (parsed.fts_match(), parsed.fts_exclude()) {
+        (Some(expr), _) => {
+            let mut stmt = conn
+                .prepare(
+                    "SELECT i.path, i.name, i.description, i.tags,
+                            bm25(images_fts) as score, i.colors
+                     FROM images_fts
+                     JOIN images i ON images_fts.rowid = i.rowid
+                     WHERE images_fts MATCH ?1
+                     ORDER BY score
+                     LIMIT ?2",
+                )
+                .map_err(|e| format!("Search query failed: {e}"))?;
+            let rows = stmt
+                .query_map(params![expr, sql_limit], map_row)
+                .map_err(|e| format!("Search failed: {e}"))?
+                .filter_map(|r| r.ok())
+                .collect();
+            rows
+        }
+        (None, excluded) => {
+            // Only negations and/or colours: start from every image
+            let mut stmt = conn
+                .prepare(
+                    "SELECT i.path, i.name, i.description, i.tags, 0.0, i.colors
+                     FROM images i
+                     WHERE ?1 IS NULL OR i.rowid NOT IN
+                         (SELECT rowid FROM images_fts WHERE images_fts MATCH ?1)
+                     ORDER BY i.name
+                     LIMIT ?2",
+                )
+                .map_err(|e| format!("Search query failed: {e}"))?;
+            let rows = stmt
+                .query_map(params![excluded, sql_limit], map_row)
+                .map_err(|e| format!("Search failed: {e}"))?
+                .filter_map(|r| r.ok())
+                .collect();
+            rows
+        }
+    };
+
+    if !parsed.has_colors() {
+        return Ok(rows.into_iter().map(|(r, _)| r).collect());
+    }
+
+    let color_only = parsed.include.is_empty();
+    let mut results: Vec<SearchResult> = rows
+        .into_iter()
+        .filter_map(|(mut r, colors_str)| {
+            let closeness = palette_closeness(&parsed, &colors_str)?;
+            if color_only && !parsed.colors.is_empty() {
+                r.score = closeness;
+            }
+            Some(r)
         })
-        .map_err(|e| format!("Search failed: {e}"))?
-        .filter_map(|r| r.ok())
         .collect();
 
+    // Colour-only queries rank by palette closeness; otherwise keep bm25 order
+    if color_only {
+        results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    }
+    results.truncate(limit);
     Ok(results)
 }
 
+/// Check a space-separated palette against the query's colours: `None` if
+/// it fails them, else the mean closeness (0–1) to the wanted colours.
+fn palette_closeness(parsed: &crate::query::ParsedQuery, colors_str: &str) -> Option<f64> {
+    let palette: Vec<(u8, u8, u8)> = colors_str.split_whitespace().filter_map(hex_to_rgb).collect();
+    let nearest = |c: (u8, u8, u8)| {
+        palette
+            .iter()
+            .map(|&p| color_distance(c, p))
+            .fold(f64::MAX, f64::min)
+    };
+    if parsed.exclude_colors.iter().any(|&c| nearest(c) <= DEFAULT_COLOR_THRESHOLD) {
+        return None;
+    }
+    let mut closeness = 0.0;
+    for &c in &parsed.colors {
+        let dist = nearest(c);
+        if dist > DEFAULT_COLOR_THRESHOLD {
+            return None;
+        }
+        closeness += 1.0 - dist / DEFAULT_COLOR_THRESHOLD;
+    }
+    Some(if parsed.colors.is_empty() { 0.0 } else { closeness / parsed.colors.len() as f64 })
+}
+
+/// Drop results (found by embedding) that break the query's field filters,
+/// negations or colours.
+fn apply_query_filters(
+    conn: &Connection,
+    parsed: &crate::query::ParsedQuery,
+    results: Vec<SearchResult>,
+) -> Result<Vec<SearchResult>, String> {
+    let (required, excluded) = match parsed.fts_filter() {
+        Some(expr) => (Some(expr), None),
+        None => (None, parsed.fts_exclude()),
+    };
+    if required.is_none() && excluded.is_none() && !parsed.has_colors() {
+        return Ok(results);
+    }
+
+    let mut stmt = conn
+        .prepare(
+            "SELECT i.colors FROM images i
+             WHERE i.path = ?1
+               AND (?2 IS NULL OR i.rowid IN (SELECT rowid FROM images_fts WHERE images_fts MATCH ?2))
+               AND (?3 IS NULL OR i.rowid NOT IN (SELECT rowid FROM images_fts WHERE images_fts MATCH ?3))",
+        )
+        .map_err(|e| format!("Search query failed: {e}"))?;
+    let mut kept = Vec::with_capacity(results.len());
+    for r in results {
+        let colors: Option<Option<String>> = stmt
+            .query_row(params![r.image_path, required, excluded], |row| row.get(0))
+            .optional()
+            .map_err(|e| format!("Search failed: {e}"))?;
+        let Some(colors) = colors else { continue };
+        if palette_closeness(parsed, &colors.unwrap_or_default()).is_some() {
+            kept.push(r);
+        }
+    }
+    Ok(kept)
+}
+
 // ---------------------------------------------------------------------------
 // Tag Collection
 // ---------------------------------------------------------------------------
 
-/// Get all unique tags across all images in a project, with counts.
+/// Replace an image's rows in `image_tags`.
+pub fn set_image_tags(conn: &Connection, image_path: &str, tags: &[String]) -> Result<(), String> {
+    conn.execute("DELETE FROM image_tags WHERE path = ?1", params![image_path])
+        .map_err(|e| format!("Cannot clear image tags: {e}"))?;
+    for tag in tags {
+        conn.execute(
+            "INSERT OR IGNORE INTO image_tags (path, tag) VALUES (?1, ?2)",
+            params![image_path, tag.to_lowercase()],
+        )
+        .map_err(|e| format!("Cannot store image tag: {e}"))?;
+    }
+    Ok(())
+}
+
+/// `tag` and each level above it: `a/b/c` → `a`, `a/b`, `a/b/c`.
+fn tag_levels(tag: &str) -> impl Iterator<Item = &str> {
+    tag.match_indices('/')
+        .map(move |(i, _)| &tag[..i])
+        .chain(std::iter::once(tag))
+}
+
+/// Get all unique tags across all images in a project, with counts. Each
+/// level of a hierarchical tag is listed too, counting the images tagged
+/// anywhere beneath it.
 pub fn get_all_tags(project_path: &str) -> Result<Vec<TagCount>, String> {
     let conn = open_db(project_path)?;
 
     let mut stmt = conn
-        .prepare("SELECT tags FROM images WHERE tags != ''")
+        .prepare("SELECT path, tag FROM image_tags ORDER BY path")
         .map_err(|e| format!("Tag query failed: {e}"))?;
-
-    let mut tag_counts: HashMap<String, usize> = HashMap::new();
-
     let rows = stmt
-        .query_map([], |row| row.get::<_, String>(0))
+        .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
         .map_err(|e| format!("Tag fetch failed: {e}"))?;
 
+    // tag → (images at or below it, images tagged with it exactly)
+    let mut tag_counts: HashMap<String, (usize, usize)> = HashMap::new();
+    let mut current_path = String::new();
+    let mut seen: std::collections::HashSet<String> = std::collections::HashSet::new();
     for row in rows {
-        if let Ok(tags_str) = row {
-            for tag in tags_str.split_whitespace() {
-                let tag = tag.to_lowercase();
-                if !tag.is_empty() {
-                    *tag_counts.entry(tag).or_insert(0) += 1;
-                }
+        let (path, tag) = row.map_err(|e| format!("Tag fetch failed: {e}"))?;
+        if path != current_path {
+            current_path = path;
+            seen.clear();
+        }
+        for level in tag_levels(&tag) {
+            if seen.insert(level.to_string()) {
+                tag_counts.entry(level.to_string()).or_default().0 += 1;
             }
         }
+        tag_counts.entry(tag).or_default().1 += 1;
     }
 
     let mut tags: Vec<TagCount> = tag_counts
         .into_iter()
-        .map(|(tag, count)| TagCount { tag, count })
+        .map(|(tag, (count, direct))| TagCount { tag, count, direct })
         .collect();
 
     // Sort by count (descending), then alphabetically
@@ -369,28 +1027,41 @@ pub fn get_all_tags(project_path: &str) -> Result<Vec<TagCount>, String> {
     Ok(tags)
 }
 
-/// Get images that have a specific tag.
+/// Arrange `get_all_tags` output as a tree, each level sorted like the list.
+pub fn tag_tree(tags: &[TagCount]) -> Vec<TagNode> {
+    fn children(parent: Option<&str>, tags: &[TagCount]) -> Vec<TagNode> {
+        tags.iter()
+            .filter(|t| t.tag.rsplit_once('/').map(|(p, _)| p) == parent)
+            .map(|t| TagNode {
+                name: t.tag.rsplit('/').next().unwrap_or(&t.tag).to_string(),
+                tag: t.tag.clone(),
+                count: t.count,
+                direct: t.direct,
+                children: children(Some(&t.tag), tags),
+            })
+            .collect()
+    }
+    children(None, tags)
+}
+
+/// Get images that have a specific tag or any tag beneath it
+/// (`material` matches `material/bronze`).
 pub fn get_images_by_tag(project_path: &str, tag: &str) -> Result<Vec<String>, String> {
     let conn = open_db(project_path)?;
-    let tag_lower = tag.to_lowercase();
+    let tag_lower = tag.trim_end_matches('/').to_lowercase();
 
     let mut stmt = conn
-        .prepare("SELECT path, tags FROM images WHERE tags != ''")
+        .prepare(
+            "SELECT DISTINCT path FROM image_tags
+             WHERE tag = ?1 OR substr(tag, 1, length(?1) + 1) = ?1 || '/'
+             ORDER BY path",
+        )
         .map_err(|e| format!("Tag filter query failed: {e}"))?;
 
     let paths: Vec<String> = stmt
-        .query_map([], |row| {
-            let path: String = row.get(0)?;
-            let tags: String = row.get(1)?;
-            Ok((path, tags))
-        })
+        .query_map(params![tag_lower], |row| row.get(0))
         .map_err(|e| format!("Tag filter failed: {e}"))?
         .filter_map(|r| r.ok())
-        .filter(|(_, tags)| {
-            tags.split_whitespace()
-                .any(|t| t.to_lowercase() == tag_lower)
-        })
-        .map(|(path, _)| path)
         .collect();
 
     Ok(paths)
@@ -408,16 +1079,31 @@ pub fn store_embedding_conn(
     embedding: &[f32],
 ) -> Result<(), String> {
     let bytes: Vec<u8> = embedding.iter().flat_map(|f| f.to_le_bytes()).collect();
-    conn.execute(
-        "INSERT OR REPLACE INTO embeddings (path, model, vector, dimensions)
-         VALUES (?1, ?2, ?3, ?4)",
-        params![image_path, model, bytes, embedding.len() as i64],
-    )
-    .map_err(|e| format!("Cannot store embedding: {e}"))?;
-    Ok(())
+
+    // Vector row and ANN graph update commit together
+    conn.execute_batch("SAVEPOINT store_embedding")
+        .map_err(|e| format!("Cannot begin savepoint: {e}"))?;
+    let result = conn
+        .execute(
+            "INSERT OR REPLACE INTO embeddings (path, model, vector, dimensions)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![image_path, model, bytes, embedding.len() as i64],
+        )
+        .map_err(|e| format!("Cannot store embedding: {e}"))
+        .and_then(|_| crate::ann::insert(conn, model, image_path, embedding));
+
+    match result {
+        Ok(()) => conn
+            .execute_batch("RELEASE store_embedding")
+            .map_err(|e| format!("Cannot release savepoint: {e}")),
+        Err(e) => {
+            let _ = conn.execute_batch("ROLLBACK TO store_embedding; RELEASE store_embedding");
+            Err(e)
+        }
+    }
 }
 
-/// Store a CLIP embedding vector for an image.
+/// Store an image embedding vector produced by `model`.
 #[allow(dead_code)]
 pub fn store_embedding(
     project_path: &str,
@@ -429,123 +1115,461 @@ pub fn store_embedding(
     store_embedding_conn(&conn, image_path, model, embedding)
 }
 
-/// Retrieve a stored embedding vector.
-pub fn get_embedding(conn: &Connection, image_path: &str) -> Result<Option<Vec<f32>>, String> {
-    let mut stmt = conn
-        .prepare("SELECT vector, dimensions FROM embeddings WHERE path = ?1")
-        .map_err(|e| format!("Embedding query failed: {e}"))?;
+/// Retrieve an image's stored embedding vector for `model`.
+pub fn get_embedding(
+    conn: &Connection,
+    image_path: &str,
+    model: &str,
+) -> Result<Option<Vec<f32>>, String> {
+    let mut stmt = conn
+        .prepare("SELECT vector, dimensions FROM embeddings WHERE path = ?1 AND model = ?2")
+        .map_err(|e| format!("Embedding query failed: {e}"))?;
+
+    let result = stmt
+        .query_row(params![image_path, model], |row| {
+            let bytes: Vec<u8> = row.get(0)?;
+            let dims: i64 = row.get(1)?;
+            Ok((bytes, dims))
+        })
+        .optional()
+        .map_err(|e| format!("Embedding fetch failed: {e}"))?;
+
+    match result {
+        Some((bytes, dims)) => {
+            let floats: Vec<f32> = bytes
+                .chunks_exact(4)
+                .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                .collect();
+            if floats.len() != dims as usize {
+                return Err("Embedding dimension mismatch".to_string());
+            }
+            Ok(Some(floats))
+        }
+        None => Ok(None),
+    }
+}
+
+/// Retrieve all of a project's stored embeddings for `model`.
+/// Returns a vec of (image_path, embedding_vector) pairs.
+pub fn get_all_embeddings(
+    project_path: &str,
+    model: &str,
+) -> Result<Vec<(String, Vec<f32>)>, String> {
+    let conn = open_db(project_path)?;
+    let mut stmt = conn
+        .prepare("SELECT path, vector, dimensions FROM embeddings WHERE model = ?1")
+        .map_err(|e| format!("Embedding query failed: {e}"))?;
+
+    let results: Vec<(String, Vec<f32>)> = stmt
+        .query_map(params![model], |row| {
+            let path: String = row.get(0)?;
+            let bytes: Vec<u8> = row.get(1)?;
+            let _dims: i64 = row.get(2)?;
+            Ok((path, bytes))
+        })
+        .map_err(|e| format!("Embedding fetch failed: {e}"))?
+        .filter_map(|r| r.ok())
+        .map(|(path, bytes)| {
+            let floats: Vec<f32> = bytes
+                .chunks_exact(4)
+                .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+                .collect();
+            (path, floats)
+        })
+        .collect();
+
+    Ok(results)
+}
+
+/// Find similar images using cosine similarity on stored embeddings.
+/// Only vectors from the same `model` are compared.
+/// Returns up to `limit` results, excluding the query image itself.
+pub fn find_similar(
+    project_path: &str,
+    image_path: &str,
+    model: &str,
+    limit: usize,
+) -> Result<Vec<SearchResult>, String> {
+    let conn = open_db(project_path)?;
+
+    // Get the query image's embedding
+    let query_vec = get_embedding(&conn, image_path, model)?
+        .ok_or_else(|| format!("No {model} embedding found for {image_path}"))?;
+
+    if let Some(hits) = crate::ann::search(&conn, model, &query_vec, limit + 1)? {
+        return hits_to_results(&conn, hits, Some(image_path), limit);
+    }
+
+    // No index: scan all other embeddings from the same model
+    let mut stmt = conn
+        .prepare(
+            "SELECT e.path, i.name, i.description, i.tags, e.vector, e.dimensions
+             FROM embeddings e
+             JOIN images i ON e.path = i.path
+             WHERE e.path != ?1 AND e.model = ?2",
+        )
+        .map_err(|e| format!("Similarity query failed: {e}"))?;
+
+    let mut results: Vec<SearchResult> = stmt
+        .query_map(params![image_path, model], |row| {
+            let path: String = row.get(0)?;
+            let name: String = row.get(1)?;
+            let description: Option<String> = row.get(2)?;
+            let tags_str: String = row.get(3)?;
+            let bytes: Vec<u8> = row.get(4)?;
+            let _dims: i64 = row.get(5)?;
+            Ok((path, name, description, tags_str, bytes))
+        })
+        .map_err(|e| format!("Similarity search failed: {e}"))?
+        .filter_map(|r| r.ok())
+        .map(|(path, name, description, tags_str, bytes)| {
+            let vec: Vec<f32> = bytes
+                .chunks_exact(4)
+                .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
+                .collect();
+            let score = cosine_similarity(&query_vec, &vec);
+            SearchResult {
+                image_path: path,
+                name,
+                description,
+                tags: tags_str.split_whitespace().map(String::from).collect(),
+                score,
+                matched_by: Vec::new(),
+                breakdown: None,
+            }
+        })
+        .collect();
+
+    // Sort by similarity (highest first)
+    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    results.truncate(limit);
+
+    Ok(results)
+}
+
+/// Text-to-image semantic search: encode `query` with the CLIP text tower and
+/// rank stored image embeddings by cosine similarity. Works for images that
+/// have never been through AI analysis, as long as they are embedded.
+pub fn semantic_search(
+    project_path: &str,
+    query: &str,
+    limit: usize,
+) -> Result<Vec<SearchResult>, String> {
+    if query.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let query_vec = crate::embed::embed_text(query)?;
+    search_by_vector(project_path, crate::embed::TEXT_SEARCH_MODEL.id(), &query_vec, limit)
+}
+
+/// Default dHash distance (of 64 bits) for two images to count as duplicates.
+pub const DEFAULT_DUPLICATE_DISTANCE: u32 = 6;
+
+/// RRF damping constant (from the original Cormack et al. paper).
+const RRF_K: f64 = 60.0;
+
+/// Hybrid search: run FTS5 (bm25) and CLIP semantic search, then fuse the
+/// two rankings according to `options`. If the CLIP model is unavailable the
+/// text ranking is used alone.
+pub fn hybrid_search(
+    project_path: &str,
+    query: &str,
+    limit: usize,
+    options: &HybridOptions,
+) -> Result<Vec<SearchResult>, String> {
+    if query.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+
+    // Pull a deeper candidate pool from each side than we return
+    let pool = (limit * 3).max(50);
+    let text = search_text(project_path, query, pool)?;
+    // Embed the free words only; field filters, negations and colours are
+    // then applied to the semantic hits
+    let parsed = crate::query::parse(query)?;
+    let free_text = parsed.free_text();
+    let semantic = if free_text.is_empty() {
+        Vec::new()
+    } else {
+        match crate::embed::embed_text(&free_text) {
+            Ok(query_vec) => {
+                let hits = search_by_vector(project_path, crate::embed::TEXT_SEARCH_MODEL.id(), &query_vec, pool)?;
+                apply_query_filters(&open_db(project_path)?, &parsed, hits)?
+            }
+            Err(e) => {
+                crate::log::log("SEARCH", &format!("Hybrid: semantic side unavailable ({e}), text only"));
+                Vec::new()
+            }
+        }
+    };
 
-    let result = stmt
-        .query_row(params![image_path], |row| {
-            let bytes: Vec<u8> = row.get(0)?;
-            let dims: i64 = row.get(1)?;
-            Ok((bytes, dims))
-        })
-        .optional()
-        .map_err(|e| format!("Embedding fetch failed: {e}"))?;
+    Ok(fuse_results(text, semantic, limit, options))
+}
 
-    match result {
-        Some((bytes, dims)) => {
-            let floats: Vec<f32> = bytes
-                .chunks_exact(4)
-                .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
-                .collect();
-            if floats.len() != dims as usize {
-                return Err("Embedding dimension mismatch".to_string());
-            }
-            Ok(Some(floats))
+/// Merge a text ranking and a semantic ranking (both best-first).
+pub fn fuse_results(
+    text: Vec<SearchResult>,
+    semantic: Vec<SearchResult>,
+    limit: usize,
+    options: &HybridOptions,
+) -> Vec<SearchResult> {
+    let mut merged: HashMap<String, SearchResult> = HashMap::new();
+
+    for (rank, r) in text.into_iter().enumerate() {
+        let entry = merged.entry(r.image_path.clone()).or_insert_with(|| SearchResult {
+            score: 0.0,
+            matched_by: Vec::new(),
+            breakdown: Some(ScoreBreakdown::default()),
+            ..r.clone()
+        });
+        entry.matched_by.push(MatchSignal::Text);
+        if let Some(b) = entry.breakdown.as_mut() {
+            b.text_rank = Some(rank + 1);
+            b.text_score = Some(r.score);
         }
-        None => Ok(None),
     }
-}
 
-/// Retrieve all stored embeddings for a project.
-/// Returns a vec of (image_path, embedding_vector) pairs.
-pub fn get_all_embeddings(project_path: &str) -> Result<Vec<(String, Vec<f32>)>, String> {
-    let conn = open_db(project_path)?;
-    let mut stmt = conn
-        .prepare("SELECT path, vector, dimensions FROM embeddings")
-        .map_err(|e| format!("Embedding query failed: {e}"))?;
+    for (rank, r) in semantic.into_iter().enumerate() {
+        let entry = merged.entry(r.image_path.clone()).or_insert_with(|| SearchResult {
+            score: 0.0,
+            matched_by: Vec::new(),
+            breakdown: Some(ScoreBreakdown::default()),
+            ..r.clone()
+        });
+        entry.matched_by.push(MatchSignal::Semantic);
+        if let Some(b) = entry.breakdown.as_mut() {
+            b.semantic_rank = Some(rank + 1);
+            b.semantic_score = Some(r.score);
+        }
+    }
 
-    let results: Vec<(String, Vec<f32>)> = stmt
-        .query_map([], |row| {
-            let path: String = row.get(0)?;
-            let bytes: Vec<u8> = row.get(1)?;
-            let _dims: i64 = row.get(2)?;
-            Ok((path, bytes))
-        })
-        .map_err(|e| format!("Embedding fetch failed: {e}"))?
-        .filter_map(|r| r.ok())
-        .map(|(path, bytes)| {
-            let floats: Vec<f32> = bytes
-                .chunks_exact(4)
-                .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
-                .collect();
-            (path, floats)
+    // bm25 magnitudes are unbounded; normalize them into [0, 1] for weighting
+    let (text_min, text_max) = merged
+        .values()
+        .filter_map(|r| r.breakdown.as_ref()?.text_score)
+        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), s| (lo.min(s), hi.max(s)));
+
+    let mut results: Vec<SearchResult> = merged
+        .into_values()
+        .map(|mut r| {
+            let b = r.breakdown.clone().unwrap_or_default();
+            r.score = match options.fusion {
+                Fusion::Rrf => {
+                    let text = b.text_rank.map_or(0.0, |rank| options.text_weight / (RRF_K + rank as f64));
+                    let semantic = b
+                        .semantic_rank
+                        .map_or(0.0, |rank| options.semantic_weight / (RRF_K + rank as f64));
+                    text + semantic
+                }
+                Fusion::Weighted => {
+                    let text = b.text_score.map_or(0.0, |s| {
+                        if text_max > text_min {
+                            (s - text_min) / (text_max - text_min)
+                        } else {
+                            1.0
+                        }
+                    });
+                    let semantic = b.semantic_score.map_or(0.0, |s| s.max(0.0));
+                    options.text_weight * text + options.semantic_weight * semantic
+                }
+            };
+            r
         })
         .collect();
 
-    Ok(results)
+    results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    results.truncate(limit);
+    results
 }
 
-/// Find similar images using cosine similarity on stored embeddings.
-/// Returns up to `limit` results, excluding the query image itself.
-pub fn find_similar(
+/// Rank all stored `model` embeddings by cosine similarity to `query_vec`.
+pub fn search_by_vector(
     project_path: &str,
-    image_path: &str,
+    model: &str,
+    query_vec: &[f32],
     limit: usize,
 ) -> Result<Vec<SearchResult>, String> {
     let conn = open_db(project_path)?;
+    if let Some(hits) = crate::ann::search(&conn, model, query_vec, limit)? {
+        return hits_to_results(&conn, hits, None, limit);
+    }
 
-    // Get the query image's embedding
-    let query_vec = get_embedding(&conn, image_path)?
-        .ok_or_else(|| format!("No embedding found for {image_path}"))?;
-
-    // Get all other embeddings
     let mut stmt = conn
         .prepare(
-            "SELECT e.path, i.name, i.description, i.tags, e.vector, e.dimensions
+            "SELECT e.path, i.name, i.description, i.tags, e.vector
              FROM embeddings e
              JOIN images i ON e.path = i.path
-             WHERE e.path != ?1",
+             WHERE e.model = ?1",
         )
-        .map_err(|e| format!("Similarity query failed: {e}"))?;
+        .map_err(|e| format!("Semantic query failed: {e}"))?;
 
     let mut results: Vec<SearchResult> = stmt
-        .query_map(params![image_path], |row| {
+        .query_map(params![model], |row| {
             let path: String = row.get(0)?;
             let name: String = row.get(1)?;
             let description: Option<String> = row.get(2)?;
             let tags_str: String = row.get(3)?;
             let bytes: Vec<u8> = row.get(4)?;
-            let _dims: i64 = row.get(5)?;
             Ok((path, name, description, tags_str, bytes))
         })
-        .map_err(|e| format!("Similarity search failed: {e}"))?
+        .map_err(|e| format!("Semantic search failed: {e}"))?
         .filter_map(|r| r.ok())
         .map(|(path, name, description, tags_str, bytes)| {
             let vec: Vec<f32> = bytes
                 .chunks_exact(4)
                 .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
                 .collect();
-            let score = cosine_similarity(&query_vec, &vec);
             SearchResult {
                 image_path: path,
                 name,
                 description,
                 tags: tags_str.split_whitespace().map(String::from).collect(),
-                score,
+                score: cosine_similarity(query_vec, &vec),
+                matched_by: Vec::new(),
+                breakdown: None,
             }
         })
         .collect();
 
-    // Sort by similarity (highest first)
     results.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
     results.truncate(limit);
 
     Ok(results)
 }
 
+/// Attach image metadata to ANN hits, skipping `exclude` and paths that have
+/// no `images` row (same semantics as the brute-force JOIN).
+fn hits_to_results(
+    conn: &Connection,
+    hits: Vec<(String, f64)>,
+    exclude: Option<&str>,
+    limit: usize,
+) -> Result<Vec<SearchResult>, String> {
+    let mut stmt = conn
+        .prepare_cached("SELECT name, description, tags FROM images WHERE path = ?1")
+        .map_err(|e| format!("Similarity query failed: {e}"))?;
+
+    let mut results = Vec::new();
+    for (path, score) in hits {
+        if Some(path.as_str()) == exclude {
+            continue;
+        }
+        let row = stmt
+            .query_row(params![path], |row| {
+                let name: String = row.get(0)?;
+                let description: Option<String> = row.get(1)?;
+                let tags_str: String = row.get(2)?;
+                Ok((name, description, tags_str))
+            })
+            .optional()
+            .map_err(|e| format!("Similarity search failed: {e}"))?;
+        if let Some((name, description, tags_str)) = row {
+            results.push(SearchResult {
+                image_path: path,
+                name,
+                description,
+                tags: tags_str.split_whitespace().map(String::from).collect(),
+                score,
+                matched_by: Vec::new(),
+                breakdown: None,
+            });
+        }
+        if results.len() == limit {
+            break;
+        }
+    }
+    Ok(results)
+}
+
+/// Rebuild every model's similarity (ANN) index from the stored embeddings.
+pub fn rebuild_ann_index(project_path: &str) -> Result<usize, String> {
+    let conn = open_db(project_path)?;
+    crate::ann::rebuild_all(&conn)
+}
+
+/// Group images whose perceptual hashes are within `max_distance` bits of
+/// each other (single-linkage). Only images with a stored hash take part.
+pub fn find_duplicates(project_path: &str, max_distance: u32) -> Result<Vec<DuplicateGroup>, String> {
+    let conn = open_db(project_path)?;
+    let mut stmt = conn
+        .prepare(
+            "SELECT h.path, i.name, h.dhash
+             FROM image_hashes h
+             JOIN images i ON h.path = i.path
+             ORDER BY h.path",
+        )
+        .map_err(|e| format!("Duplicate query failed: {e}"))?;
+    let hashes: Vec<(String, String, u64)> = stmt
+        .query_map([], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get::<_, i64>(2)? as u64))
+        })
+        .map_err(|e| format!("Duplicate search failed: {e}"))?
+        .filter_map(|r| r.ok())
+        .collect();
+
+    // Union-find over all pairs within the distance threshold
+    fn find(parent: &mut [usize], i: usize) -> usize {
+        let mut root = i;
+        while parent[root] != root {
+            root = parent[root];
+        }
+        let mut i = i;
+        while parent[i] != root {
+            let next = parent[i];
+            parent[i] = root;
+            i = next;
+        }
+        root
+    }
+    let mut parent: Vec<usize> = (0..hashes.len()).collect();
+    for i in 0..hashes.len() {
+        for j in (i + 1)..hashes.len() {
+            if crate::phash::hamming(hashes[i].2, hashes[j].2) <= max_distance {
+                let (a, b) = (find(&mut parent, i), find(&mut parent, j));
+                if a != b {
+                    parent[b] = a;
+                }
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for i in 0..hashes.len() {
+        let root = find(&mut parent, i);
+        groups.entry(root).or_default().push(i);
+    }
+
+    let mut result: Vec<DuplicateGroup> = groups
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .map(|members| {
+            let mut images: Vec<(usize, u64)> = members
+                .into_iter()
+                .map(|i| {
+                    let size = std::fs::metadata(&hashes[i].0).map(|m| m.len()).unwrap_or(0);
+                    (i, size)
+                })
+                .collect();
+            images.sort_by(|a, b| b.1.cmp(&a.1).then(hashes[a.0].0.cmp(&hashes[b.0].0)));
+            let first = hashes[images[0].0].2;
+            DuplicateGroup {
+                images: images
+                    .into_iter()
+                    .map(|(i, size_bytes)| DuplicateImage {
+                        image_path: hashes[i].0.clone(),
+                        name: hashes[i].1.clone(),
+                        size_bytes,
+                        distance: crate::phash::hamming(first, hashes[i].2),
+                    })
+                    .collect(),
+            }
+        })
+        .collect();
+
+    result.sort_by(|a, b| a.images[0].image_path.cmp(&b.images[0].image_path));
+    Ok(result)
+}
+
 /// Find similar images using tag/style overlap (works without CLIP embeddings).
 /// Uses Jaccard similarity on tags + style + mood fields.
 pub fn find_similar_by_tags(
@@ -624,6 +1648,8 @@ pub fn find_similar_by_tags(
                 description,
                 tags: tags.split_whitespace().map(String::from).collect(),
                 score,
+                matched_by: Vec::new(),
+                breakdown: None,
             }
         })
         .filter(|r| r.score > 0.0)
@@ -673,20 +1699,28 @@ fn cosine_similarity(a: &[f32], b: &[f32]) -> f64 {
 #[tauri::command]
 pub async fn cmd_index_project(
     storage: tauri::State<'_, crate::storage::Storage>,
+    jobs: tauri::State<'_, crate::jobs::Jobs>,
     project_path: String,
 ) -> Result<usize, String> {
     let images = crate::scan_images_in(&project_path)?;
     let count = storage.index_images(&project_path, &images).await?;
 
-    // Generate CLIP embeddings (best-effort, don't fail indexing)
-    if let Err(e) = storage.embed_project(&project_path).await {
-        eprintln!("CLIP embedding skipped: {e}");
+    // Queue embeddings with the default model (best-effort, don't fail indexing)
+    let spec = crate::jobs::JobSpec::Embed {
+        project_path,
+        image_paths: Some(images.into_iter().map(|i| i.path).collect()),
+        model: None,
+        force: false,
+    };
+    if let Err(e) = jobs.submit(spec) {
+        eprintln!("Embedding skipped: {e}");
     }
 
     Ok(count)
 }
 
 /// Full-text search across all metadata fields via storage backend.
+/// Accepts the structured query grammar (`tag:`, `-mood:`, `color:#hex`, ...).
 #[tauri::command]
 pub async fn cmd_search_text(
     storage: tauri::State<'_, crate::storage::Storage>,
@@ -716,6 +1750,17 @@ pub async fn cmd_filter_by_tag(
     storage.get_images_by_tag(&project_path, &tag).await
 }
 
+/// Edit an image's notes, rating and attribution via storage backend.
+#[tauri::command]
+pub async fn cmd_update_annotations(
+    storage: tauri::State<'_, crate::storage::Storage>,
+    project_path: String,
+    image_path: String,
+    update: AnnotationUpdate,
+) -> Result<ImageAnnotations, String> {
+    storage.update_annotations(&project_path, &image_path, &update).await
+}
+
 /// Find similar images via storage backend.
 #[tauri::command]
 pub async fn cmd_find_similar(
@@ -723,8 +1768,33 @@ pub async fn cmd_find_similar(
     project_path: String,
     image_path: String,
     limit: Option<usize>,
+    model: Option<String>,
 ) -> Result<Vec<SearchResult>, String> {
-    storage.find_similar(&project_path, &image_path, limit.unwrap_or(10)).await
+    let model = crate::embed::model_or_default(model.as_deref())?;
+    storage.find_similar(&project_path, &image_path, model, limit.unwrap_or(10)).await
+}
+
+/// Text-to-image semantic search (CLIP text encoder) via storage backend.
+#[tauri::command]
+pub async fn cmd_search_semantic(
+    storage: tauri::State<'_, crate::storage::Storage>,
+    project_path: String,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<SearchResult>, String> {
+    storage.semantic_search(&project_path, &query, limit.unwrap_or(50)).await
+}
+
+/// Find groups of near-identical images via storage backend.
+#[tauri::command]
+pub async fn cmd_find_duplicates(
+    storage: tauri::State<'_, crate::storage::Storage>,
+    project_path: String,
+    max_distance: Option<u32>,
+) -> Result<Vec<DuplicateGroup>, String> {
+    storage
+        .find_duplicates(&project_path, max_distance.unwrap_or(DEFAULT_DUPLICATE_DISTANCE))
+        .await
 }
 
 /// Update metadata for a single image via storage backend.
@@ -745,7 +1815,7 @@ pub async fn cmd_search_by_color(
     color: String,
     threshold: Option<f64>,
 ) -> Result<Vec<SearchResult>, String> {
-    let threshold = threshold.unwrap_or(60.0); // RGB euclidean distance threshold
+    let threshold = threshold.unwrap_or(DEFAULT_COLOR_THRESHOLD);
     let query_rgb = hex_to_rgb(&color).ok_or_else(|| format!("Invalid hex color: {color}"))?;
 
     let conn = open_db(&project_path)?;
@@ -785,6 +1855,8 @@ pub async fn cmd_search_by_color(
                         score: 1.0 - (best_dist / threshold).min(1.0),
                         description: desc,
                         tags: tags_str.split_whitespace().map(String::from).collect(),
+                        matched_by: Vec::new(),
+                        breakdown: None,
                     },
                     best_dist,
                 ))
@@ -800,10 +1872,14 @@ pub async fn cmd_search_by_color(
     Ok(results)
 }
 
+/// Max CIEDE2000 ΔE for two colours to count as a match.
+pub const DEFAULT_COLOR_THRESHOLD: f64 = 20.0;
+
 /// Parse hex color string to (r, g, b) tuple.
-fn hex_to_rgb(hex: &str) -> Option<(u8, u8, u8)> {
+pub fn hex_to_rgb(hex: &str) -> Option<(u8, u8, u8)> {
     let hex = hex.trim_start_matches('#');
-    if hex.len() != 6 {
+    // Checking bytes first keeps the slices below on char boundaries
+    if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
         return None;
     }
     let r = u8::from_str_radix(&hex[0..2], 16).ok()?;
@@ -812,24 +1888,23 @@ fn hex_to_rgb(hex: &str) -> Option<(u8, u8, u8)> {
     Some((r, g, b))
 }
 
-/// Euclidean distance between two RGB colors.
+/// Perceptual distance (CIEDE2000 ΔE) between two RGB colors.
 fn color_distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> f64 {
-    let dr = a.0 as f64 - b.0 as f64;
-    let dg = a.1 as f64 - b.1 as f64;
-    let db = a.2 as f64 - b.2 as f64;
-    (dr * dr + dg * dg + db * db).sqrt()
+    crate::palette::delta_e(a, b)
 }
 
-/// Cluster project images by CLIP embedding similarity.
+/// Cluster project images by embedding similarity within one model.
 #[tauri::command]
 pub async fn cmd_cluster_project(
     project_path: String,
     threshold: Option<f64>,
+    model: Option<String>,
 ) -> Result<crate::ops::ClusterResult, String> {
     let threshold = threshold.unwrap_or(0.7);
-    let embeddings = get_all_embeddings(&project_path)?;
+    let model = crate::embed::model_or_default(model.as_deref())?;
+    let embeddings = get_all_embeddings(&project_path, model.id())?;
     if embeddings.is_empty() {
-        return Err("No embeddings found. Analyze images first to generate CLIP embeddings.".to_string());
+        return Err(format!("No {model} embeddings found. Analyze images first to generate embeddings."));
     }
     crate::log::log("SEARCH", &format!("Clustering {} images with threshold {}", embeddings.len(), threshold));
     let result = crate::ops::greedy_cluster(&embeddings, threshold);
@@ -894,8 +1969,9 @@ mod tests {
             mood: vec!["elegant".to_string()],
             colors: vec!["#D4AF37".to_string()],
             era: Some("1920s".to_string()),
+            ..Default::default()
         };
-        upsert_image(&conn, &meta).unwrap();
+        upsert_image(&conn, &meta, &Default::default()).unwrap();
 
         // Search by description
         let results = search_text(&path, "bronze dancer", 10).unwrap();
@@ -903,6 +1979,55 @@ mod tests {
         assert_eq!(results[0].image_path, "/test/sculpture.jpg");
     }
 
+    #[test]
+    fn test_structured_query() {
+        let (path, _dir) = temp_project();
+        let conn = open_db(&path).unwrap();
+
+        upsert_image(&conn, &ImageMetadataRow {
+            image_path: "/test/lobby.jpg".to_string(),
+            name: "lobby.jpg".to_string(),
+            description: Some("Chrysler lobby".to_string()),
+            tags: vec!["art-deco".to_string(), "interior".to_string()],
+            style: vec!["geometric".to_string()],
+            mood: vec!["dark".to_string()],
+            colors: vec!["#C0A060".to_string(), "#101010".to_string()],
+            era: Some("1920s".to_string()),
+            ..Default::default()
+        }, &Default::default()).unwrap();
+        upsert_image(&conn, &ImageMetadataRow {
+            image_path: "/test/poster.jpg".to_string(),
+            name: "poster.jpg".to_string(),
+            description: Some("Travel poster with a dark sky".to_string()),
+            tags: vec!["art-deco".to_string(), "poster".to_string()],
+            style: vec!["geometric".to_string()],
+            mood: vec!["bright".to_string()],
+            colors: vec!["#2040C0".to_string()],
+            era: Some("1930s".to_string()),
+            ..Default::default()
+        }, &Default::default()).unwrap();
+
+        let paths = |q: &str| -> Vec<String> {
+            let mut p: Vec<String> = search_text(&path, q, 10)
+                .unwrap()
+                .into_iter()
+                .map(|r| r.image_path)
+                .collect();
+            p.sort();
+            p
+        };
+
+        assert_eq!(paths("tag:art-deco style:geometric").len(), 2);
+        assert_eq!(paths("tag:art-deco era:1920s"), vec!["/test/lobby.jpg"]);
+        // "dark" appears in the poster's description, but only the lobby's mood
+        assert_eq!(paths("tag:art-deco -mood:dark"), vec!["/test/poster.jpg"]);
+        assert_eq!(paths("-mood:dark"), vec!["/test/poster.jpg"]);
+        assert_eq!(paths("tag:poster -dark").len(), 0);
+        assert_eq!(paths("color:#c8a868"), vec!["/test/lobby.jpg"]);
+        assert_eq!(paths("tag:art-deco -color:#c0a060"), vec!["/test/poster.jpg"]);
+        assert!(search_text(&path, "color:nope", 10).is_err());
+    }
+
     #[test]
     fn test_tag_collection() {
         let (path, _dir) = temp_project();
@@ -911,18 +2036,16 @@ mod tests {
         upsert_image(&conn, &ImageMetadataRow {
             image_path: "/test/a.jpg".to_string(),
             name: "a.jpg".to_string(),
-            description: None,
             tags: vec!["art-deco".to_string(), "sculpture".to_string()],
-            style: vec![], mood: vec![], colors: vec![], era: None,
-        }).unwrap();
+            ..Default::default()
+        }, &Default::default()).unwrap();
 
         upsert_image(&conn, &ImageMetadataRow {
             image_path: "/test/b.jpg".to_string(),
             name: "b.jpg".to_string(),
-            description: None,
             tags: vec!["art-deco".to_string(), "painting".to_string()],
-            style: vec![], mood: vec![], colors: vec![], era: None,
-        }).unwrap();
+            ..Default::default()
+        }, &Default::default()).unwrap();
 
         let tags = get_all_tags(&path).unwrap();
         assert_eq!(tags[0].tag, "art-deco");
@@ -930,6 +2053,137 @@ mod tests {
         assert_eq!(tags.len(), 3);
     }
 
+    #[test]
+    fn test_hierarchical_tags() {
+        let (path, _dir) = temp_project();
+        let conn = open_db(&path).unwrap();
+
+        for (image, tags) in [
+            ("/test/a.jpg", vec!["material/bronze", "material/glass", "era/1920s"]),
+            ("/test/b.jpg", vec!["material/bronze/patinated", "lamp"]),
+            ("/test/c.jpg", vec!["material", "materials"]),
+        ] {
+            upsert_image(&conn, &ImageMetadataRow {
+                image_path: image.to_string(),
+                name: image.trim_start_matches("/test/").to_string(),
+                tags: tags.into_iter().map(String::from).collect(),
+                ..Default::default()
+            }, &Default::default()).unwrap();
+        }
+
+        let tags = get_all_tags(&path).unwrap();
+        let count = |tag: &str| tags.iter().find(|t| t.tag == tag).map(|t| (t.count, t.direct));
+        // Each image counts once per level, however many tags it has below it
+        assert_eq!(count("material"), Some((3, 1)));
+        assert_eq!(count("material/bronze"), Some((2, 1)));
+        assert_eq!(count("material/bronze/patinated"), Some((1, 1)));
+        assert_eq!(count("era"), Some((1, 0)));
+        assert_eq!(tags[0].tag, "material");
+
+        let tree = tag_tree(&tags);
+        let material = tree.iter().find(|n| n.tag == "material").unwrap();
+        assert_eq!(material.children.iter().map(|n| n.name.as_str()).collect::<Vec<_>>(), vec!["bronze", "glass"]);
+        assert_eq!(material.children[0].children[0].tag, "material/bronze/patinated");
+        assert!(tree.iter().any(|n| n.tag == "lamp" && n.children.is_empty()));
+
+        assert_eq!(get_images_by_tag(&path, "material").unwrap(), vec!["/test/a.jpg", "/test/b.jpg", "/test/c.jpg"]);
+        assert_eq!(get_images_by_tag(&path, "Material/Bronze/").unwrap(), vec!["/test/a.jpg", "/test/b.jpg"]);
+        assert_eq!(get_images_by_tag(&path, "materials").unwrap(), vec!["/test/c.jpg"]);
+        assert_eq!(get_images_by_tag(&path, "mat").unwrap(), Vec::<String>::new());
+
+        // Rewriting an image's tags replaces its rows
+        set_image_tags(&conn, "/test/b.jpg", &["lamp".to_string()]).unwrap();
+        assert_eq!(get_images_by_tag(&path, "material/bronze").unwrap(), vec!["/test/a.jpg"]);
+    }
+
+    #[test]
+    fn test_annotations_survive_reanalysis() {
+        let (path, _dir) = temp_project();
+        let conn = open_db(&path).unwrap();
+
+        // A downloaded image gets its source before it is analyzed
+        record_source(&path, "/test/a.jpg", "https://example.com/a.jpg", Some("https://example.com/page")).unwrap();
+        let update = AnnotationUpdate {
+            rating: Some(4),
+            favorite: Some(true),
+            notes: Some("Use for the hotel lobby".to_string()),
+            license: Some("CC BY 4.0".to_string()),
+            ..Default::default()
+        };
+        update_annotations(&conn, "/test/a.jpg", &update).unwrap();
+
+        upsert_image(&conn, &ImageMetadataRow {
+            image_path: "/test/a.jpg".to_string(),
+            name: "a.jpg".to_string(),
+            description: Some("Brass wall sconce".to_string()),
+            tags: vec!["lamp".to_string()],
+            ..Default::default()
+        }, &Default::default()).unwrap();
+
+        let annotations = get_image_metadata(&path, "/test/a.jpg").unwrap().unwrap().annotations.unwrap();
+        assert_eq!(annotations.source_url.as_deref(), Some("https://example.com/a.jpg"));
+        assert_eq!(annotations.source_page.as_deref(), Some("https://example.com/page"));
+        assert_eq!(annotations.rating, Some(4));
+        assert!(annotations.favorite);
+        assert_eq!(annotations.license.as_deref(), Some("CC BY 4.0"));
+
+        // Notes are searchable, alone and as a field
+        let hits = search_text(&path, "lobby", 10).unwrap();
+        assert_eq!(hits.len(), 1);
+        assert_eq!(search_text(&path, "notes:lobby", 10).unwrap().len(), 1);
+        // Dangling operators don't turn into FTS5 syntax errors
+        assert_eq!(search_text(&path, "lobby OR", 10).unwrap().len(), 1);
+        assert_eq!(search_text(&path, "NOT lobby", 10).unwrap().len(), 1);
+
+        // A later download of the same file doesn't overwrite the source
+        record_source(&path, "/test/a.jpg", "https://mirror.example.com/a.jpg", None).unwrap();
+        assert_eq!(get_annotations(&conn, "/test/a.jpg").unwrap().source_url.as_deref(), Some("https://example.com/a.jpg"));
+
+        // Empty strings clear, out-of-range ratings are rejected
+        let cleared = update_annotations(&conn, "/test/a.jpg", &AnnotationUpdate {
+            notes: Some(String::new()),
+            rating: Some(0),
+            ..Default::default()
+        }).unwrap();
+        assert_eq!(cleared.notes, None);
+        assert_eq!(cleared.rating, Some(0));
+        assert!(search_text(&path, "lobby", 10).unwrap().is_empty());
+        let err = update_annotations(&conn, "/test/a.jpg", &AnnotationUpdate { rating: Some(6), ..Default::default() }).unwrap_err();
+        assert!(err.contains("between 0 and 5"), "{err}");
+    }
+
+    #[test]
+    fn test_invalid_annotation_update_writes_nothing() {
+        let (path, _dir) = temp_project();
+        let conn = open_db(&path).unwrap();
+        let row = |description: &str, tag: &str| ImageMetadataRow {
+            image_path: "/test/a.jpg".to_string(),
+            name: "a.jpg".to_string(),
+            description: Some(description.to_string()),
+            tags: vec![tag.to_string()],
+            ..Default::default()
+        };
+        let bad = AnnotationUpdate { rating: Some(6), notes: Some("lobby".to_string()), ..Default::default() };
+
+        // A new image isn't inserted
+        assert!(upsert_image_with_annotations(&conn, &row("Brass sconce", "lamp"), &Default::default(), &bad).is_err());
+        assert!(get_image_metadata(&path, "/test/a.jpg").unwrap().is_none());
+
+        // An existing image keeps its description and tags
+        let good = AnnotationUpdate { rating: Some(3), ..Default::default() };
+        let saved = upsert_image_with_annotations(&conn, &row("Brass sconce", "lamp"), &Default::default(), &good).unwrap();
+        assert_eq!(saved.unwrap().rating, Some(3));
+        let err = upsert_image_with_annotations(&conn, &row("Chrome sconce", "chrome"), &Default::default(), &bad).unwrap_err();
+        assert!(err.contains("between 0 and 5"), "{err}");
+        let meta = get_image_metadata(&path, "/test/a.jpg").unwrap().unwrap();
+        assert_eq!(meta.description.as_deref(), Some("Brass sconce"));
+        assert_eq!(meta.tags, vec!["lamp"]);
+        assert_eq!(meta.annotations.unwrap().rating, Some(3));
+
+        // An empty update changes no annotations
+        assert!(upsert_image_with_annotations(&conn, &row("Brass sconce", "lamp"), &Default::default(), &AnnotationUpdate::default()).unwrap().is_none());
+    }
+
     #[test]
     fn test_embedding_storage_and_similarity() {
         let (path, _dir) = temp_project();
@@ -941,16 +2195,16 @@ mod tests {
             name: "a.jpg".to_string(),
             description: Some("Image A".to_string()),
             tags: vec!["test".to_string()],
-            style: vec![], mood: vec![], colors: vec![], era: None,
-        }).unwrap();
+            ..Default::default()
+        }, &Default::default()).unwrap();
 
         upsert_image(&conn, &ImageMetadataRow {
             image_path: "/test/b.jpg".to_string(),
             name: "b.jpg".to_string(),
             description: Some("Image B".to_string()),
             tags: vec!["test".to_string()],
-            style: vec![], mood: vec![], colors: vec![], era: None,
-        }).unwrap();
+            ..Default::default()
+        }, &Default::default()).unwrap();
 
         // Store embeddings
         let vec_a = vec![1.0f32, 0.0, 0.0, 0.5];
@@ -959,12 +2213,261 @@ mod tests {
         store_embedding(&path, "/test/b.jpg", "test", &vec_b).unwrap();
 
         // Find similar to a.jpg
-        let results = find_similar(&path, "/test/a.jpg", 5).unwrap();
+        let results = find_similar(&path, "/test/a.jpg", "test", 5).unwrap();
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].image_path, "/test/b.jpg");
         assert!(results[0].score > 0.95); // Very similar vectors
     }
 
+    #[test]
+    fn test_find_similar_stays_within_model() {
+        let (path, _dir) = temp_project();
+        let conn = open_db(&path).unwrap();
+        for name in ["a.jpg", "b.jpg", "c.jpg"] {
+            upsert_image(&conn, &ImageMetadataRow {
+                image_path: format!("/test/{name}"),
+                name: name.to_string(),
+                ..Default::default()
+            }, &Default::default()).unwrap();
+        }
+
+        // a and b share a model; c only has a vector from another model
+        store_embedding(&path, "/test/a.jpg", "small", &[1.0, 0.0]).unwrap();
+        store_embedding(&path, "/test/b.jpg", "small", &[0.0, 1.0]).unwrap();
+        store_embedding(&path, "/test/a.jpg", "large", &[1.0, 0.0, 0.0, 0.0]).unwrap();
+        store_embedding(&path, "/test/c.jpg", "large", &[1.0, 0.0, 0.0, 0.1]).unwrap();
+
+        let small = find_similar(&path, "/test/a.jpg", "small", 5).unwrap();
+        assert_eq!(small.iter().map(|r| r.image_path.as_str()).collect::<Vec<_>>(), ["/test/b.jpg"]);
+        let large = find_similar(&path, "/test/a.jpg", "large", 5).unwrap();
+        assert_eq!(large.iter().map(|r| r.image_path.as_str()).collect::<Vec<_>>(), ["/test/c.jpg"]);
+
+        assert_eq!(get_all_embeddings(&path, "small").unwrap().len(), 2);
+        assert!(find_similar(&path, "/test/b.jpg", "large", 5).unwrap_err().contains("No large embedding"));
+
+        // Deleting an image drops its vectors from every model
+        delete_image_data(&path, "/test/a.jpg").unwrap();
+        assert_eq!(get_all_embeddings(&path, "small").unwrap().len(), 1);
+        assert_eq!(get_all_embeddings(&path, "large").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_search_by_vector_ranks_unanalyzed_images() {
+        let (path, _dir) = temp_project();
+        let images = vec![
+            crate::ImageInfo {
+                name: "stairs.jpg".to_string(),
+                path: "/test/stairs.jpg".to_string(),
+                size_bytes: 0,
+                extension: "jpg".to_string(),
+                duplicate: None,
+            },
+            crate::ImageInfo {
+                name: "lamp.jpg".to_string(),
+                path: "/test/lamp.jpg".to_string(),
+                size_bytes: 0,
+                extension: "jpg".to_string(),
+                duplicate: None,
+            },
+        ];
+        // Indexed only — no description or tags to match via FTS
+        index_project_images(&path, &images).unwrap();
+        store_embedding(&path, "/test/stairs.jpg", "test", &[0.1, 0.9, 0.0]).unwrap();
+        store_embedding(&path, "/test/lamp.jpg", "test", &[0.9, 0.1, 0.0]).unwrap();
+
+        let results = search_by_vector(&path, "test", &[0.0, 1.0, 0.0], 10).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].image_path, "/test/stairs.jpg");
+        assert!(results[0].score > results[1].score);
+
+        let top = search_by_vector(&path, "test", &[0.0, 1.0, 0.0], 1).unwrap();
+        assert_eq!(top.len(), 1);
+    }
+
+    fn hit(path: &str, score: f64) -> SearchResult {
+        SearchResult {
+            image_path: path.to_string(),
+            name: path.to_string(),
+            score,
+            description: None,
+            tags: vec![],
+            matched_by: Vec::new(),
+            breakdown: None,
+        }
+    }
+
+    #[test]
+    fn test_fuse_results_rrf() {
+        let text = vec![hit("a", 9.0), hit("b", 4.0)];
+        let semantic = vec![hit("c", 0.31), hit("b", 0.29)];
+
+        let fused = fuse_results(text, semantic, 10, &HybridOptions::default());
+        assert_eq!(fused.len(), 3);
+        // "b" appears in both rankings, so RRF puts it first
+        assert_eq!(fused[0].image_path, "b");
+        assert_eq!(fused[0].matched_by, vec![MatchSignal::Text, MatchSignal::Semantic]);
+        let b = fused[0].breakdown.as_ref().unwrap();
+        assert_eq!(b.text_rank, Some(2));
+        assert_eq!(b.semantic_rank, Some(2));
+        assert_eq!(b.semantic_score, Some(0.29));
+
+        let a = fused.iter().find(|r| r.image_path == "a").unwrap();
+        assert_eq!(a.matched_by, vec![MatchSignal::Text]);
+        assert!(a.breakdown.as_ref().unwrap().semantic_rank.is_none());
+    }
+
+    #[test]
+    fn test_fuse_results_weights() {
+        let text = vec![hit("a", 9.0), hit("b", 4.0)];
+        let semantic = vec![hit("c", 0.31), hit("b", 0.29)];
+
+        let text_only = HybridOptions { text_weight: 1.0, semantic_weight: 0.0, ..Default::default() };
+        let fused = fuse_results(text.clone(), semantic.clone(), 1, &text_only);
+        assert_eq!(fused.len(), 1);
+        assert_eq!(fused[0].image_path, "a");
+
+        let semantic_only = HybridOptions {
+            fusion: Fusion::Weighted,
+            text_weight: 0.0,
+            semantic_weight: 1.0,
+        };
+        let fused = fuse_results(text, semantic, 10, &semantic_only);
+        assert_eq!(fused[0].image_path, "c");
+        assert!((fused[0].score - 0.31).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_semantic_hits_follow_query_filters() {
+        let (path, _dir) = temp_project();
+        let conn = open_db(&path).unwrap();
+        for (image, mood, color) in [
+            ("/test/a.jpg", "dark", "#C0A060"),
+            ("/test/b.jpg", "bright", "#C0A060"),
+            ("/test/c.jpg", "bright", "#2040C0"),
+        ] {
+            upsert_image(&conn, &ImageMetadataRow {
+                image_path: image.to_string(),
+                name: image.trim_start_matches("/test/").to_string(),
+                tags: vec!["chair".to_string()],
+                mood: vec![mood.to_string()], colors: vec![color.to_string()],
+                ..Default::default()
+            }, &Default::default()).unwrap();
+        }
+        // Semantic hits ignore the query text; filters must still apply
+        let hits = || vec![hit("/test/a.jpg", 0.3), hit("/test/b.jpg", 0.2), hit("/test/c.jpg", 0.1), hit("/test/gone.jpg", 0.1)];
+        let kept = |query: &str| -> Vec<String> {
+            let parsed = crate::query::parse(query).unwrap();
+            apply_query_filters(&conn, &parsed, hits()).unwrap().into_iter().map(|r| r.image_path).collect()
+        };
+        assert_eq!(kept("art -mood:dark"), vec!["/test/b.jpg", "/test/c.jpg"]);
+        assert_eq!(kept("chair color:#c0a060"), vec!["/test/a.jpg", "/test/b.jpg"]);
+        assert_eq!(kept("chair mood:bright -color:#c0a060"), vec!["/test/c.jpg"]);
+        assert_eq!(kept("chair").len(), 4);
+    }
+
+    #[test]
+    fn test_find_duplicates() {
+        let (path, _dir) = temp_project();
+        let gradient = image::DynamicImage::ImageLuma8(image::ImageBuffer::from_fn(256, 192, |x, y| {
+            image::Luma([((x * 5 + y * 2) % 256) as u8])
+        }));
+
+        let write = |name: &str, img: &image::DynamicImage| -> crate::ImageInfo {
+            let file = Path::new(&path).join(name);
+            img.save(&file).unwrap();
+            crate::ImageInfo {
+                name: name.to_string(),
+                path: file.to_string_lossy().to_string(),
+                size_bytes: 0,
+                extension: "png".to_string(),
+                duplicate: None,
+            }
+        };
+        let images = vec![
+            write("original.png", &gradient),
+            write("paste-copy.png", &gradient.resize_exact(128, 96, image::imageops::FilterType::Triangle)),
+            write("other.png", &gradient.fliph()),
+        ];
+        index_project_images(&path, &images).unwrap();
+
+        let groups = find_duplicates(&path, DEFAULT_DUPLICATE_DISTANCE).unwrap();
+        assert_eq!(groups.len(), 1);
+        let names: Vec<&str> = groups[0].images.iter().map(|i| i.name.as_str()).collect();
+        assert_eq!(names.len(), 2);
+        assert!(names.contains(&"original.png") && names.contains(&"paste-copy.png"));
+        // Largest file leads the group
+        assert_eq!(groups[0].images[0].name, "original.png");
+        assert_eq!(groups[0].images[0].distance, 0);
+
+        // Deleting one side dissolves the group
+        delete_image_data(&path, &images[1].path).unwrap();
+        assert!(find_duplicates(&path, DEFAULT_DUPLICATE_DISTANCE).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_index_reads_properties() {
+        let (path, _dir) = temp_project();
+        let file = Path::new(&path).join("wide.png");
+        image::RgbImage::new(320, 180).save(&file).unwrap();
+        let image_path = file.to_string_lossy().to_string();
+        let info = crate::ImageInfo {
+            name: "wide.png".to_string(),
+            path: image_path.clone(),
+            size_bytes: 0,
+            extension: "png".to_string(),
+            duplicate: None,
+        };
+        index_project_images(&path, &[info]).unwrap();
+
+        let props = get_image_metadata(&path, &image_path).unwrap().unwrap().properties.unwrap();
+        assert_eq!((props.width, props.height), (Some(320), Some(180)));
+
+        // AI metadata updates leave file properties in place
+        let conn = open_db(&path).unwrap();
+        upsert_image(&conn, &ImageMetadataRow {
+            image_path: image_path.clone(), name: "wide.png".to_string(),
+            description: Some("banner".to_string()),
+            ..Default::default()
+        }, &Default::default()).unwrap();
+        let meta = get_image_metadata(&path, &image_path).unwrap().unwrap();
+        assert_eq!(meta.description.as_deref(), Some("banner"));
+        assert_eq!(meta.properties.unwrap().width, Some(320));
+    }
+
+    #[test]
+    fn test_index_extracts_palette() {
+        let (path, _dir) = temp_project();
+        let file = Path::new(&path).join("brass.png");
+        image::RgbImage::from_pixel(32, 32, image::Rgb([192, 160, 96])).save(&file).unwrap();
+        let image_path = file.to_string_lossy().to_string();
+        let info = crate::ImageInfo {
+            name: "brass.png".to_string(),
+            path: image_path.clone(),
+            size_bytes: 0,
+            extension: "png".to_string(),
+            duplicate: None,
+        };
+        index_project_images(&path, &[info]).unwrap();
+
+        let meta = get_image_metadata(&path, &image_path).unwrap().unwrap();
+        assert_eq!(meta.colors, vec!["#c0a060"]);
+        assert_eq!(meta.color_weights, vec![1.0]);
+        // Found by a nearby shade without any AI analysis
+        let results = search_text(&path, "color:#c8a868", 10).unwrap();
+        assert_eq!(results.len(), 1);
+
+        // AI colours replace the local palette and drop its weights
+        let conn = open_db(&path).unwrap();
+        upsert_image(&conn, &ImageMetadataRow {
+            image_path: image_path.clone(), name: "brass.png".to_string(),
+            colors: vec!["#b09050".to_string()],
+            ..Default::default()
+        }, &Default::default()).unwrap();
+        let meta = get_image_metadata(&path, &image_path).unwrap().unwrap();
+        assert_eq!(meta.colors, vec!["#b09050"]);
+        assert!(meta.color_weights.is_empty());
+    }
+
     #[test]
     fn test_tag_similarity() {
         let (path, _dir) = temp_project();
@@ -973,32 +2476,28 @@ mod tests {
         upsert_image(&conn, &ImageMetadataRow {
             image_path: "/test/a.jpg".to_string(),
             name: "a.jpg".to_string(),
-            description: None,
             tags: vec!["art-deco".to_string(), "sculpture".to_string(), "bronze".to_string()],
             style: vec!["geometric".to_string()],
             mood: vec!["elegant".to_string()],
-            colors: vec![], era: None,
-        }).unwrap();
+            ..Default::default()
+        }, &Default::default()).unwrap();
 
         upsert_image(&conn, &ImageMetadataRow {
             image_path: "/test/b.jpg".to_string(),
             name: "b.jpg".to_string(),
-            description: None,
             tags: vec!["art-deco".to_string(), "lamp".to_string(), "bronze".to_string()],
             style: vec!["geometric".to_string()],
-            mood: vec![],
-            colors: vec![], era: None,
-        }).unwrap();
+            ..Default::default()
+        }, &Default::default()).unwrap();
 
         upsert_image(&conn, &ImageMetadataRow {
             image_path: "/test/c.jpg".to_string(),
             name: "c.jpg".to_string(),
-            description: None,
             tags: vec!["modern".to_string(), "painting".to_string()],
             style: vec!["abstract".to_string()],
             mood: vec!["energetic".to_string()],
-            colors: vec![], era: None,
-        }).unwrap();
+            ..Default::default()
+        }, &Default::default()).unwrap();
 
         let results = find_similar_by_tags(&path, "/test/a.jpg", 10).unwrap();
         assert!(!results.is_empty());
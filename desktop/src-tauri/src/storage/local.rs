@@ -315,6 +315,44 @@ impl StorageProvider for LocalStorage {
         .map_err(|e| format!("Task join error: {e}"))?
     }
 
+    async fn semantic_search(
+        &self,
+        project_path: &str,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<crate::search::SearchResult>, String> {
+        let project_path = project_path.to_string();
+        let query = query.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            // Try CLIP text-to-image first, fall back to FTS when no model/embeddings
+            match crate::search::semantic_search(&project_path, &query, limit) {
+                Ok(results) if !results.is_empty() => Ok(results),
+                _ => crate::search::search_text(&project_path, &query, limit),
+            }
+        })
+        .await
+        .map_err(|e| format!("Task join error: {e}"))?
+    }
+
+    async fn update_annotations(
+        &self,
+        project_path: &str,
+        image_path: &str,
+        update: &crate::search::AnnotationUpdate,
+    ) -> Result<crate::search::ImageAnnotations, String> {
+        let project_path = project_path.to_string();
+        let image_path = image_path.to_string();
+        let update = update.clone();
+
+        tokio::task::spawn_blocking(move || {
+            let conn = crate::search::open_db(&project_path)?;
+            crate::search::update_annotations(&conn, &image_path, &update)
+        })
+        .await
+        .map_err(|e| format!("Task join error: {e}"))?
+    }
+
     async fn get_all_tags(
         &self,
         project_path: &str,
@@ -353,7 +391,10 @@ impl StorageProvider for LocalStorage {
             let conn = crate::search::open_db(&project_path)?;
             let result = conn
                 .query_row(
-                    "SELECT description, tags, style, mood, colors, era FROM images WHERE path = ?1",
+                    &format!(
+                        "SELECT description, tags, style, mood, colors, era, {} FROM images WHERE path = ?1",
+                        crate::search::ANNOTATION_SELECT
+                    ),
                     rusqlite::params![image_path],
                     |row| {
                         Ok(serde_json::json!({
@@ -363,6 +404,7 @@ impl StorageProvider for LocalStorage {
                             "mood": row.get::<_, String>(3)?.split_whitespace().collect::<Vec<_>>(),
                             "colors": row.get::<_, String>(4)?.split_whitespace().collect::<Vec<_>>(),
                             "era": row.get::<_, Option<String>>(5)?,
+                            "annotations": crate::search::annotations_from_row(row, 6)?,
                         }))
                     },
                 )
@@ -398,7 +440,9 @@ impl StorageProvider for LocalStorage {
     async fn embed_project(
         &self,
         project_path: &str,
-    ) -> Result<usize, String> {
+        model: crate::embed::ImageModel,
+        force: bool,
+    ) -> Result<crate::embed::EmbedStats, String> {
         let project_path = project_path.to_string();
 
         tokio::task::spawn_blocking(move || {
@@ -406,7 +450,7 @@ impl StorageProvider for LocalStorage {
             crate::log::log(
                 "CLIP",
                 &format!(
-                    "Embedding project: {project_path} ({} images found)",
+                    "Embedding project with {model}: {project_path} ({} images found)",
                     images.len()
                 ),
             );
@@ -423,7 +467,7 @@ impl StorageProvider for LocalStorage {
             }
 
             let paths: Vec<String> = images.iter().map(|i| i.path.clone()).collect();
-            crate::embed::embed_and_store(&project_path, &paths)
+            crate::embed::embed_and_store(&project_path, &paths, model, force)
         })
         .await
         .map_err(|e| format!("Task join error: {e}"))?
@@ -433,6 +477,7 @@ impl StorageProvider for LocalStorage {
         &self,
         project_path: &str,
         image_path: &str,
+        model: crate::embed::ImageModel,
         limit: usize,
     ) -> Result<Vec<crate::search::SearchResult>, String> {
         let project_path = project_path.to_string();
@@ -440,7 +485,7 @@ impl StorageProvider for LocalStorage {
 
         tokio::task::spawn_blocking(move || {
             // Try embedding-based similarity first, fall back to tag similarity
-            match crate::search::find_similar(&project_path, &image_path, limit) {
+            match crate::search::find_similar(&project_path, &image_path, model.id(), limit) {
                 Ok(results) if !results.is_empty() => Ok(results),
                 _ => crate::search::find_similar_by_tags(&project_path, &image_path, limit),
             }
@@ -449,10 +494,125 @@ impl StorageProvider for LocalStorage {
         .map_err(|e| format!("Task join error: {e}"))?
     }
 
+    async fn find_duplicates(
+        &self,
+        project_path: &str,
+        max_distance: u32,
+    ) -> Result<Vec<crate::search::DuplicateGroup>, String> {
+        let project_path = project_path.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            // Indexing computes hashes for new or changed files
+            let images = crate::scan_images_in(&project_path)?;
+            crate::search::index_project_images(&project_path, &images)?;
+            crate::search::find_duplicates(&project_path, max_distance)
+        })
+        .await
+        .map_err(|e| format!("Task join error: {e}"))?
+    }
+
+    async fn query_images(
+        &self,
+        project_path: &str,
+        query: &str,
+    ) -> Result<Vec<String>, String> {
+        let project_path = project_path.to_string();
+        let query = query.to_string();
+
+        tokio::task::spawn_blocking(move || crate::collections::query_images(&project_path, &query))
+            .await
+            .map_err(|e| format!("Task join error: {e}"))?
+    }
+
+    async fn list_collections(
+        &self,
+        project_path: &str,
+    ) -> Result<Vec<crate::collections::CollectionInfo>, String> {
+        let project_path = project_path.to_string();
+
+        tokio::task::spawn_blocking(move || crate::collections::list_collections(&project_path))
+            .await
+            .map_err(|e| format!("Task join error: {e}"))?
+    }
+
+    async fn save_collection(
+        &self,
+        project_path: &str,
+        name: &str,
+        query: &str,
+        replace: bool,
+    ) -> Result<crate::collections::Collection, String> {
+        let project_path = project_path.to_string();
+        let name = name.to_string();
+        let query = query.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            crate::collections::save_collection(&project_path, &name, &query, replace)
+        })
+        .await
+        .map_err(|e| format!("Task join error: {e}"))?
+    }
+
+    async fn delete_collection(
+        &self,
+        project_path: &str,
+        name: &str,
+    ) -> Result<(), String> {
+        let project_path = project_path.to_string();
+        let name = name.to_string();
+
+        tokio::task::spawn_blocking(move || crate::collections::delete_collection(&project_path, &name))
+            .await
+            .map_err(|e| format!("Task join error: {e}"))?
+    }
+
+    async fn collection_images(
+        &self,
+        project_path: &str,
+        name: &str,
+    ) -> Result<Vec<String>, String> {
+        let project_path = project_path.to_string();
+        let name = name.to_string();
+
+        tokio::task::spawn_blocking(move || crate::collections::collection_images(&project_path, &name))
+            .await
+            .map_err(|e| format!("Task join error: {e}"))?
+    }
+
+    async fn check_project(
+        &self,
+        project_path: &str,
+        fix: bool,
+    ) -> Result<crate::doctor::DoctorReport, String> {
+        let project_path = project_path.to_string();
+
+        tokio::task::spawn_blocking(move || crate::doctor::run(&project_path, fix))
+            .await
+            .map_err(|e| format!("Task join error: {e}"))?
+    }
+
+    async fn get_thumbnail(
+        &self,
+        project_path: &str,
+        image_path: &str,
+        size: crate::thumbs::ThumbSize,
+    ) -> Result<String, String> {
+        let project_path = project_path.to_string();
+        let image_path = image_path.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            crate::thumbs::ensure(&project_path, &image_path, size)
+                .map(|p| p.to_string_lossy().to_string())
+        })
+        .await
+        .map_err(|e| format!("Task join error: {e}"))?
+    }
+
     async fn has_embedding(
         &self,
         project_path: &str,
         image_path: &str,
+        model: crate::embed::ImageModel,
     ) -> Result<bool, String> {
         let project_path = project_path.to_string();
         let image_path = image_path.to_string();
@@ -461,8 +621,8 @@ impl StorageProvider for LocalStorage {
             let conn = crate::search::open_db(&project_path)?;
             let count: i64 = conn
                 .query_row(
-                    "SELECT COUNT(*) FROM embeddings WHERE path = ?1",
-                    rusqlite::params![image_path],
+                    "SELECT COUNT(*) FROM embeddings WHERE path = ?1 AND model = ?2",
+                    rusqlite::params![image_path, model.id()],
                     |row| row.get(0),
                 )
                 .map_err(|e| format!("Cannot check embedding: {e}"))?;
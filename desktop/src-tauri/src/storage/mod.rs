@@ -92,6 +92,14 @@ pub trait StorageProvider: Send + Sync {
         meta: &crate::search::ImageMetadataRow,
     ) -> Result<(), String>;
 
+    /// Edit an image's notes, rating and attribution; returns the result.
+    async fn update_annotations(
+        &self,
+        project_path: &str,
+        image_path: &str,
+        update: &crate::search::AnnotationUpdate,
+    ) -> Result<crate::search::ImageAnnotations, String>;
+
     /// Full-text search across all metadata fields.
     async fn search_text(
         &self,
@@ -100,6 +108,14 @@ pub trait StorageProvider: Send + Sync {
         limit: usize,
     ) -> Result<Vec<crate::search::SearchResult>, String>;
 
+    /// Text-to-image semantic search (CLIP text encoder, falls back to full-text).
+    async fn semantic_search(
+        &self,
+        project_path: &str,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<crate::search::SearchResult>, String>;
+
     /// Get all tags with usage counts.
     async fn get_all_tags(
         &self,
@@ -131,25 +147,96 @@ pub trait StorageProvider: Send + Sync {
         embedding: &[f32],
     ) -> Result<(), String>;
 
-    /// Embed all images in a project that don't already have embeddings.
+    /// Embed all images in a project whose `model` embedding is missing or
+    /// stale (every image when `force` is set).
     async fn embed_project(
         &self,
         project_path: &str,
-    ) -> Result<usize, String>;
+        model: crate::embed::ImageModel,
+        force: bool,
+    ) -> Result<crate::embed::EmbedStats, String>;
 
-    /// Find similar images (embeddings first, falls back to tag similarity).
+    /// Find similar images (`model` embeddings first, falls back to tag similarity).
     async fn find_similar(
         &self,
         project_path: &str,
         image_path: &str,
+        model: crate::embed::ImageModel,
         limit: usize,
     ) -> Result<Vec<crate::search::SearchResult>, String>;
 
-    /// Check if a CLIP embedding exists for a specific image.
+    /// Refresh perceptual hashes, then group near-identical images.
+    async fn find_duplicates(
+        &self,
+        project_path: &str,
+        max_distance: u32,
+    ) -> Result<Vec<crate::search::DuplicateGroup>, String>;
+
+    // ---- Smart Collections ----
+
+    /// Image paths matching a boolean query (`tag-a (tag-b OR style:x) -mood:y`).
+    async fn query_images(
+        &self,
+        project_path: &str,
+        query: &str,
+    ) -> Result<Vec<String>, String>;
+
+    /// Saved collections with their current member counts.
+    async fn list_collections(
+        &self,
+        project_path: &str,
+    ) -> Result<Vec<crate::collections::CollectionInfo>, String>;
+
+    /// Save a named query as a smart collection (`replace` overwrites one
+    /// with the same name).
+    async fn save_collection(
+        &self,
+        project_path: &str,
+        name: &str,
+        query: &str,
+        replace: bool,
+    ) -> Result<crate::collections::Collection, String>;
+
+    /// Delete a saved collection (the images are untouched).
+    async fn delete_collection(
+        &self,
+        project_path: &str,
+        name: &str,
+    ) -> Result<(), String>;
+
+    /// Image paths currently in a saved collection.
+    async fn collection_images(
+        &self,
+        project_path: &str,
+        name: &str,
+    ) -> Result<Vec<String>, String>;
+
+    // ---- Maintenance ----
+
+    /// Check the project's index, board and metadata for inconsistencies,
+    /// repairing them when `fix` is set.
+    async fn check_project(
+        &self,
+        project_path: &str,
+        fix: bool,
+    ) -> Result<crate::doctor::DoctorReport, String>;
+
+    // ---- Thumbnails ----
+
+    /// Path to a thumbnail of an image, generating it if missing or stale.
+    async fn get_thumbnail(
+        &self,
+        project_path: &str,
+        image_path: &str,
+        size: crate::thumbs::ThumbSize,
+    ) -> Result<String, String>;
+
+    /// Check if a `model` embedding exists for a specific image.
     async fn has_embedding(
         &self,
         project_path: &str,
         image_path: &str,
+        model: crate::embed::ImageModel,
     ) -> Result<bool, String>;
 
     // ---- App Config ----
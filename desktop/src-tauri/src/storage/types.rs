@@ -30,9 +30,14 @@ pub struct AppConfig {
     /// UI font size preset: "compact" | "default" | "large". None = "default".
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub font_size: Option<String>,
-    /// Vision model: "clip" | "grounding-dino-sam". None = "clip".
+    /// Vision model: "clip" | "unicom-vit-b-16" | "nomic-embed-vision-v1.5" |
+    /// "resnet50" | "grounding-dino-sam". None = "clip". Embedding models are
+    /// the default for `embed`, `similar` and `cluster` (see `embed::default_model`).
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub vision_model: Option<String>,
+    /// What to do when an import matches an existing file. None = "link".
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicate_policy: Option<crate::dedup::DuplicatePolicy>,
 }
 
 /// Recent project entry for the home screen.
@@ -0,0 +1,343 @@
+//! Thumbnail generation.
+//!
+//! Thumbnails live in `{project}/thumbnails/{sha256}-{px}.{jpg|webp}`, keyed by
+//! the source's content hash (`dedup::content_hash`), so an edited source gets
+//! fresh thumbnails and identical files share theirs. Opaque images are
+//! encoded as JPEG; images with an alpha channel as lossless WebP. Every size
+//! is written from a single decode.
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::{DynamicImage, ImageFormat};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const JPEG_QUALITY: u8 = 85;
+
+/// Thumbnail size; images are scaled to fit a square of this many pixels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ThumbSize {
+    Small,
+    #[default]
+    Medium,
+    Large,
+}
+
+impl ThumbSize {
+    pub const ALL: [ThumbSize; 3] = [ThumbSize::Small, ThumbSize::Medium, ThumbSize::Large];
+
+    /// Longest edge in pixels.
+    pub fn pixels(self) -> u32 {
+        match self {
+            ThumbSize::Small => 128,
+            ThumbSize::Medium => 256,
+            ThumbSize::Large => 512,
+        }
+    }
+}
+
+impl std::str::FromStr for ThumbSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "small" | "s" | "128" => Ok(ThumbSize::Small),
+            "medium" | "m" | "256" => Ok(ThumbSize::Medium),
+            "large" | "l" | "512" => Ok(ThumbSize::Large),
+            other => Err(format!(
+                "Unknown thumbnail size: {other} (expected small, medium or large)"
+            )),
+        }
+    }
+}
+
+fn thumbs_dir(project_path: &str) -> PathBuf {
+    Path::new(project_path).join("thumbnails")
+}
+
+/// Whether `path` is in a format we can rasterise. SVG and (without a native
+/// decoder) AVIF are served as the original instead.
+fn can_decode(path: &Path) -> bool {
+    path.extension()
+        .and_then(ImageFormat::from_extension)
+        .is_some_and(|f| f.reading_enabled())
+}
+
+/// Existing thumbnail for a hash and size, whichever encoding it used.
+fn existing(project_path: &str, hash: &str, size: ThumbSize) -> Option<PathBuf> {
+    let dir = thumbs_dir(project_path);
+    ["jpg", "webp"]
+        .iter()
+        .map(|ext| dir.join(format!("{hash}-{}.{ext}", size.pixels())))
+        .find(|p| p.is_file())
+}
+
+/// Hash last recorded for `image_path`, without touching the file.
+fn recorded_hash(project_path: &str, image_path: &Path) -> Result<Option<String>, String> {
+    let conn = crate::search::open_db(project_path)?;
+    conn.query_row(
+        "SELECT sha256 FROM content_hashes WHERE path = ?1",
+        params![image_path.to_string_lossy()],
+        |row| row.get(0),
+    )
+    .optional()
+    .map_err(|e| format!("Cannot read content hash: {e}"))
+}
+
+/// Delete the thumbnails for `hash` unless another file still has that content.
+fn remove_unused(project_path: &str, hash: &str) -> Result<(), String> {
+    let conn = crate::search::open_db(project_path)?;
+    let users: i64 = conn
+        .query_row(
+            "SELECT COUNT(*) FROM content_hashes WHERE sha256 = ?1",
+            params![hash],
+            |row| row.get(0),
+        )
+        .map_err(|e| format!("Cannot query content hashes: {e}"))?;
+    if users > 0 {
+        return Ok(());
+    }
+    for size in ThumbSize::ALL {
+        while let Some(path) = existing(project_path, hash, size) {
+            std::fs::remove_file(&path)
+                .map_err(|e| format!("Cannot delete thumbnail {}: {e}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+fn encode(img: &DynamicImage, dest: &Path) -> Result<(), String> {
+    let tmp = dest.with_extension("tmp");
+    let file = std::fs::File::create(&tmp)
+        .map_err(|e| format!("Cannot create {}: {e}", tmp.display()))?;
+    let mut writer = std::io::BufWriter::new(file);
+
+    let result = if img.color().has_alpha() {
+        img.to_rgba8()
+            .write_with_encoder(WebPEncoder::new_lossless(&mut writer))
+    } else {
+        img.to_rgb8()
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut writer, JPEG_QUALITY))
+    };
+    drop(writer);
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&tmp);
+        return Err(format!("Cannot encode thumbnail: {e}"));
+    }
+
+    std::fs::rename(&tmp, dest).map_err(|e| format!("Cannot write {}: {e}", dest.display()))
+}
+
+/// Decode `image_path` once and write every thumbnail size for `hash`.
+fn generate(project_path: &str, image_path: &Path, hash: &str) -> Result<(), String> {
+    let img = image::ImageReader::open(image_path)
+        .and_then(|r| r.with_guessed_format())
+        .map_err(|e| format!("Cannot open {}: {e}", image_path.display()))?
+        .decode()
+        .map_err(|e| format!("Cannot decode {}: {e}", image_path.display()))?;
+
+    let dir = thumbs_dir(project_path);
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| format!("Cannot create thumbnails directory: {e}"))?;
+    let ext = if img.color().has_alpha() { "webp" } else { "jpg" };
+
+    for size in ThumbSize::ALL {
+        let px = size.pixels();
+        // Never upscale: small sources are re-encoded at their own size
+        let scaled = if img.width() <= px && img.height() <= px {
+            img.clone()
+        } else {
+            img.thumbnail(px, px)
+        };
+        encode(&scaled, &dir.join(format!("{hash}-{px}.{ext}")))?;
+    }
+    Ok(())
+}
+
+/// Path to the thumbnail of `image_path` at `size`, generating it (and the
+/// other sizes) when missing or when the source has changed. Formats that
+/// can't be rasterised are returned as the original path.
+pub fn ensure(project_path: &str, image_path: &str, size: ThumbSize) -> Result<PathBuf, String> {
+    ensure_inner(project_path, image_path, size).map(|(path, _)| path)
+}
+
+/// `ensure`, also reporting whether thumbnails had to be generated.
+fn ensure_inner(project_path: &str, image_path: &str, size: ThumbSize) -> Result<(PathBuf, bool), String> {
+    let src = Path::new(image_path);
+    if !src.is_file() {
+        return Err(format!("Image not found: {image_path}"));
+    }
+    if !can_decode(src) {
+        return Ok((src.to_path_buf(), false));
+    }
+
+    let previous = recorded_hash(project_path, src)?;
+    let hash = crate::dedup::content_hash(project_path, src)?;
+    if let Some(previous) = previous.filter(|p| *p != hash) {
+        remove_unused(project_path, &previous)?;
+    }
+
+    if let Some(path) = existing(project_path, &hash, size) {
+        return Ok((path, false));
+    }
+    generate(project_path, src, &hash)?;
+    crate::log::log("THUMBS", &format!("Generated thumbnails for {image_path}"));
+    existing(project_path, &hash, size)
+        .map(|path| (path, true))
+        .ok_or_else(|| format!("Thumbnail missing for {image_path}"))
+}
+
+/// Generate missing thumbnails for a batch of images. Returns how many
+/// sources were (re)generated; failures are logged and skipped.
+pub fn ensure_all(project_path: &str, images: &[crate::ImageInfo]) -> usize {
+    let mut count = 0;
+    for img in images {
+        if !can_decode(Path::new(&img.path)) {
+            continue;
+        }
+        match ensure_inner(project_path, &img.path, ThumbSize::Large) {
+            Ok((_, generated)) => count += usize::from(generated),
+            Err(e) => crate::log::log("THUMBS", &format!("Skipping thumbnail: {e}")),
+        }
+    }
+    count
+}
+
+/// Delete the thumbnails of an image that is being removed. Call before
+/// `search::delete_image_data`, which forgets the image's content hash.
+pub fn remove_for(project_path: &str, image_path: &str) {
+    let path = Path::new(image_path);
+
+    if let Ok(Some(hash)) = recorded_hash(project_path, path) {
+        // Forget this path first so the hash only counts other files
+        if let Ok(conn) = crate::search::open_db(project_path) {
+            let _ = conn.execute(
+                "DELETE FROM content_hashes WHERE path = ?1",
+                params![image_path],
+            );
+        }
+        if let Err(e) = remove_unused(project_path, &hash) {
+            crate::log::log("THUMBS", &format!("Cleanup warning: {e}"));
+        }
+    }
+
+    // Thumbnails named after the source file (written by older versions)
+    let thumb_dir = thumbs_dir(project_path);
+    if let (Some(stem), true) = (path.file_stem(), thumb_dir.is_dir()) {
+        let stem_str = stem.to_string_lossy();
+        for ext in &["jpg", "jpeg", "png", "webp"] {
+            let _ = std::fs::remove_file(thumb_dir.join(format!("{stem_str}.{ext}")));
+        }
+        if let Some(filename) = path.file_name() {
+            let _ = std::fs::remove_file(thumb_dir.join(filename));
+        }
+    }
+}
+
+/// Project folder of an image stored in `{project}/images/`.
+pub fn project_of(image_path: &str) -> Result<String, String> {
+    let images_dir = Path::new(image_path)
+        .parent()
+        .filter(|p| p.file_name().is_some_and(|n| n == "images"))
+        .ok_or_else(|| format!("Not a project image: {image_path}"))?;
+    images_dir
+        .parent()
+        .map(|p| p.to_string_lossy().to_string())
+        .ok_or_else(|| format!("Not a project image: {image_path}"))
+}
+
+/// Get (generating if needed) a thumbnail for an image on the canvas.
+#[tauri::command]
+pub async fn cmd_get_thumbnail(
+    storage: tauri::State<'_, crate::storage::Storage>,
+    image_path: String,
+    size: Option<ThumbSize>,
+) -> Result<String, String> {
+    let project_path = project_of(&image_path)?;
+    storage
+        .get_thumbnail(&project_path, &image_path, size.unwrap_or_default())
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_png(path: &Path, width: u32, height: u32, alpha: bool) {
+        let img = if alpha {
+            DynamicImage::ImageRgba8(image::ImageBuffer::from_fn(width, height, |x, y| {
+                image::Rgba([(x % 256) as u8, (y % 256) as u8, 90, 128])
+            }))
+        } else {
+            DynamicImage::ImageRgb8(image::ImageBuffer::from_fn(width, height, |x, y| {
+                image::Rgb([(x % 256) as u8, (y % 256) as u8, 90])
+            }))
+        };
+        img.save(path).unwrap();
+    }
+
+    #[test]
+    fn test_ensure_generates_all_sizes() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_string_lossy().to_string();
+        let images = dir.path().join("images");
+        std::fs::create_dir_all(&images).unwrap();
+        let src = images.join("wide.png");
+        write_png(&src, 1000, 500, false);
+        let src_str = src.to_string_lossy().to_string();
+
+        let medium = ensure(&project, &src_str, ThumbSize::Medium).unwrap();
+        assert_eq!(medium.extension().unwrap(), "jpg");
+        assert_eq!(image::image_dimensions(&medium).unwrap(), (256, 128));
+        let large = existing(&project, &crate::dedup::content_hash(&project, &src).unwrap(), ThumbSize::Large);
+        assert_eq!(image::image_dimensions(large.unwrap()).unwrap(), (512, 256));
+
+        // Sources smaller than the box are not upscaled; alpha keeps WebP
+        let icon = images.join("icon.png");
+        write_png(&icon, 64, 48, true);
+        let thumb = ensure(&project, &icon.to_string_lossy(), ThumbSize::Large).unwrap();
+        assert_eq!(thumb.extension().unwrap(), "webp");
+        assert_eq!(image::image_dimensions(&thumb).unwrap(), (64, 48));
+
+        // SVGs are served as-is
+        let svg = images.join("logo.svg");
+        std::fs::write(&svg, "<svg xmlns=\"http://www.w3.org/2000/svg\"/>").unwrap();
+        assert_eq!(ensure(&project, &svg.to_string_lossy(), ThumbSize::Small).unwrap(), svg);
+
+        // Project scans don't pick up the generated files
+        assert_eq!(crate::scan_images_in(&project).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_ensure_regenerates_on_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_string_lossy().to_string();
+        let images = dir.path().join("images");
+        std::fs::create_dir_all(&images).unwrap();
+        let src = images.join("photo.png");
+        let src_str = src.to_string_lossy().to_string();
+
+        write_png(&src, 400, 400, false);
+        let first = ensure(&project, &src_str, ThumbSize::Small).unwrap();
+        write_png(&src, 400, 200, false);
+        let second = ensure(&project, &src_str, ThumbSize::Small).unwrap();
+
+        assert_ne!(first, second);
+        assert!(!first.exists(), "stale thumbnail should be removed");
+        assert_eq!(image::image_dimensions(&second).unwrap(), (128, 64));
+
+        remove_for(&project, &src_str);
+        assert!(!second.exists());
+    }
+
+    #[test]
+    fn test_size_parse_and_project_of() {
+        assert_eq!("large".parse::<ThumbSize>(), Ok(ThumbSize::Large));
+        assert_eq!("128".parse::<ThumbSize>(), Ok(ThumbSize::Small));
+        assert!("huge".parse::<ThumbSize>().is_err());
+        assert_eq!(project_of("/p/proj/images/a.png").unwrap(), "/p/proj");
+        assert!(project_of("/p/proj/a.png").is_err());
+    }
+}
@@ -0,0 +1,287 @@
+//! Per-project tag vocabulary: canonical tags, their synonyms and banned
+//! tags, kept in `.deco/vocabulary.json`.
+//!
+//! ```json
+//! {
+//!   "tags": { "art-deco": ["deco", "jazz-age"], "sculpture": [] },
+//!   "banned": ["image", "photo"],
+//!   "strict": false
+//! }
+//! ```
+//!
+//! Every tag written to the index goes through `Vocabulary::apply`. It is
+//! lowercased with spaces and underscores turned into hyphens. Then it is
+//! mapped to the canonical tag it or a synonym matches. Hyphens are ignored
+//! when matching, so `artdeco` finds `art-deco`. Banned tags are dropped,
+//! and so is any tag outside the vocabulary when `strict` is set.
+//! A project without the file only gets the spelling cleanup.
+//!
+//! A malformed file doesn't stop images being saved: analysis, edits and
+//! imports log it and fall back to the spelling cleanup (`load_or_default`).
+//! The merge, rename and normalize commands do refuse to run, so they never
+//! overwrite a file someone is halfway through editing.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Vocabulary {
+    /// Canonical tag → synonyms that are rewritten to it.
+    #[serde(default)]
+    pub tags: BTreeMap<String, Vec<String>>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub banned: Vec<String>,
+    /// Drop tags that aren't canonical tags or synonyms.
+    #[serde(default)]
+    pub strict: bool,
+}
+
+/// Where a project's vocabulary lives.
+pub fn vocabulary_path(project_path: &str) -> PathBuf {
+    Path::new(project_path).join(".deco").join("vocabulary.json")
+}
+
+/// Tidy a tag's spelling: lowercase, hyphens for spaces and underscores,
+/// no repeated or surrounding hyphens. Each level of a hierarchical tag
+/// (`material/bronze`) is tidied on its own.
+pub fn normalize_tag(tag: &str) -> String {
+    tag.split('/')
+        .map(|level| {
+            level
+                .to_lowercase()
+                .split(|c: char| c == '-' || c == '_' || c.is_whitespace())
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join("-")
+        })
+        .filter(|level| !level.is_empty())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// The form tags are matched in, so spellings that differ only in hyphens
+/// meet.
+fn fold(tag: &str) -> String {
+    normalize_tag(tag).replace('-', "")
+}
+
+impl Vocabulary {
+    /// Read a project's vocabulary; a project without one gets the default.
+    pub fn load(project_path: &str) -> Result<Self, String> {
+        Self::load_file(&vocabulary_path(project_path))
+    }
+
+    /// Read a project's vocabulary for saving image tags: an unreadable or
+    /// malformed file is logged and only the spelling cleanup applies.
+    pub fn load_or_default(project_path: &str) -> Self {
+        Self::load(project_path).unwrap_or_else(|e| {
+            crate::log::log("SEARCH", &format!("Ignoring vocabulary ({e}); tags get spelling cleanup only"));
+            Self::default()
+        })
+    }
+
+    fn load_file(path: &Path) -> Result<Self, String> {
+        let content = match std::fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(format!("Cannot read vocabulary.json: {e}")),
+        };
+        serde_json::from_str(&content).map_err(|e| format!("Invalid vocabulary.json: {e}"))
+    }
+
+    /// Write the vocabulary, tidied, to the project's `.deco/`.
+    pub fn save(&self, project_path: &str) -> Result<(), String> {
+        let path = vocabulary_path(project_path);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("Cannot create .deco dir: {e}"))?;
+        }
+        let json = serde_json::to_string_pretty(&self.tidied())
+            .map_err(|e| format!("Cannot serialize vocabulary: {e}"))?;
+        std::fs::write(&path, json).map_err(|e| format!("Cannot write vocabulary.json: {e}"))
+    }
+
+    /// The same vocabulary with every tag normalized and no synonym listed
+    /// twice or under its own canonical tag.
+    fn tidied(&self) -> Self {
+        let mut tags: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for (canonical, synonyms) in &self.tags {
+            let canonical = normalize_tag(canonical);
+            if canonical.is_empty() {
+                continue;
+            }
+            let entry = tags.entry(canonical.clone()).or_default();
+            for synonym in synonyms.iter().map(|s| normalize_tag(s)) {
+                if !synonym.is_empty() && synonym != canonical && !entry.contains(&synonym) {
+                    entry.push(synonym);
+                }
+            }
+        }
+        let mut banned: Vec<String> = self.banned.iter().map(|t| normalize_tag(t)).filter(|t| !t.is_empty()).collect();
+        banned.sort();
+        banned.dedup();
+        Self { tags, banned, strict: self.strict }
+    }
+
+    /// What `tag` is written as, or `None` if it is dropped.
+    pub fn canonical(&self, tag: &str) -> Option<String> {
+        let tag = normalize_tag(tag);
+        if tag.is_empty() {
+            return None;
+        }
+        let folded = fold(&tag);
+        if self.banned.iter().any(|b| fold(b) == folded) {
+            return None;
+        }
+        let found = self.tags.iter().find(|(canonical, synonyms)| {
+            fold(canonical) == folded || synonyms.iter().any(|s| fold(s) == folded)
+        });
+        match found {
+            Some((canonical, _)) => Some(normalize_tag(canonical)),
+            None if self.strict => None,
+            None => Some(tag),
+        }
+    }
+
+    /// `tags` as written to the index: canonical, without dropped tags or
+    /// duplicates, in their original order.
+    pub fn apply(&self, tags: &[String]) -> Vec<String> {
+        let mut out: Vec<String> = Vec::new();
+        for tag in tags.iter().filter_map(|t| self.canonical(t)) {
+            if !out.contains(&tag) {
+                out.push(tag);
+            }
+        }
+        out
+    }
+
+    /// `known` followed by the canonical tags it lacks, for prompts.
+    pub fn with_canonical(&self, known: &[String]) -> Vec<String> {
+        let mut tags = known.to_vec();
+        for tag in self.tags.keys().map(|t| normalize_tag(t)) {
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        tags
+    }
+
+    /// Make each of `sources` a synonym of `target`, which becomes canonical.
+    /// Synonyms of a source that was canonical move along with it.
+    pub fn merge(&mut self, sources: &[String], target: &str) {
+        let target = normalize_tag(target);
+        let mut moved = Vec::new();
+        for source in sources.iter().map(|s| normalize_tag(s)).filter(|s| *s != target) {
+            if let Some(synonyms) = self.tags.remove(&source) {
+                moved.extend(synonyms);
+            }
+            self.remove_synonym(&source);
+            moved.push(source);
+        }
+        self.banned.retain(|b| normalize_tag(b) != target);
+        self.remove_synonym(&target);
+        self.tags.entry(target).or_default().extend(moved);
+        *self = self.tidied();
+    }
+
+    /// Rename a tag: `to` becomes canonical with `from` and its synonyms as
+    /// synonyms, so an analysis that still says `from` gets `to`.
+    pub fn rename(&mut self, from: &str, to: &str) {
+        self.merge(&[from.to_string()], to);
+    }
+
+    fn remove_synonym(&mut self, tag: &str) {
+        for synonyms in self.tags.values_mut() {
+            synonyms.retain(|s| normalize_tag(s) != tag);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(list: &[&str]) -> Vec<String> {
+        list.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_normalize_tag() {
+        assert_eq!(normalize_tag("Art Deco"), "art-deco");
+        assert_eq!(normalize_tag("art_deco"), "art-deco");
+        assert_eq!(normalize_tag(" --art--deco- "), "art-deco");
+        assert_eq!(normalize_tag("___"), "");
+        assert_eq!(normalize_tag("Material / Cast Bronze/"), "material/cast-bronze");
+    }
+
+    #[test]
+    fn test_apply_vocabulary() {
+        // Without a vocabulary only the spelling is cleaned up
+        let open = Vocabulary::default();
+        assert_eq!(open.apply(&tags(&["Art_Deco", "art-deco", "lamp", ""])), tags(&["art-deco", "lamp"]));
+
+        let vocab = Vocabulary {
+            tags: BTreeMap::from([
+                ("art-deco".to_string(), tags(&["jazz age"])),
+                ("sculpture".to_string(), Vec::new()),
+            ]),
+            banned: tags(&["photo"]),
+            strict: false,
+        };
+        assert_eq!(
+            vocab.apply(&tags(&["artdeco", "Jazz-Age", "art_deco", "photo", "bronze", "sculpture"])),
+            tags(&["art-deco", "bronze", "sculpture"])
+        );
+
+        let strict = Vocabulary { strict: true, ..vocab };
+        assert_eq!(strict.apply(&tags(&["artdeco", "bronze"])), tags(&["art-deco"]));
+        assert_eq!(strict.with_canonical(&tags(&["lamp", "sculpture"])), tags(&["lamp", "sculpture", "art-deco"]));
+    }
+
+    #[test]
+    fn test_merge_and_rename_vocabulary() {
+        let mut vocab = Vocabulary {
+            tags: BTreeMap::from([("deco".to_string(), tags(&["jazz-age"]))]),
+            banned: tags(&["art-deco"]),
+            strict: false,
+        };
+        vocab.merge(&tags(&["Deco", "artdeco"]), "Art Deco");
+        assert_eq!(vocab.tags, BTreeMap::from([("art-deco".to_string(), tags(&["jazz-age", "deco", "artdeco"]))]));
+        assert!(vocab.banned.is_empty());
+        assert_eq!(vocab.canonical("jazz_age").as_deref(), Some("art-deco"));
+
+        vocab.rename("art-deco", "deco-style");
+        assert_eq!(vocab.canonical("deco").as_deref(), Some("deco-style"));
+        assert!(!vocab.tags.contains_key("art-deco"));
+        // The old name stays as a synonym, so later analyses don't bring it back
+        assert_eq!(vocab.canonical("Art Deco").as_deref(), Some("deco-style"));
+        assert_eq!(vocab.apply(&tags(&["art-deco", "lamp"])), tags(&["deco-style", "lamp"]));
+
+        // Renaming a tag the vocabulary didn't know yet also keeps the old name
+        vocab.rename("lamp", "lighting");
+        assert_eq!(vocab.canonical("lamp").as_deref(), Some("lighting"));
+    }
+
+    #[test]
+    fn test_load_and_save() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_str().unwrap();
+        assert_eq!(Vocabulary::load(project).unwrap(), Vocabulary::default());
+
+        let vocab = Vocabulary {
+            tags: BTreeMap::from([("Art Deco".to_string(), tags(&["artdeco", "art_deco", "art-deco"]))]),
+            banned: tags(&["Photo", "photo"]),
+            strict: true,
+        };
+        vocab.save(project).unwrap();
+        let loaded = Vocabulary::load(project).unwrap();
+        assert_eq!(loaded.tags, BTreeMap::from([("art-deco".to_string(), tags(&["artdeco"]))]));
+        assert_eq!(loaded.banned, tags(&["photo"]));
+        assert!(loaded.strict);
+
+        std::fs::write(vocabulary_path(project), "{not json").unwrap();
+        assert!(Vocabulary::load(project).unwrap_err().contains("Invalid vocabulary.json"));
+        assert_eq!(Vocabulary::load_or_default(project), Vocabulary::default());
+    }
+}
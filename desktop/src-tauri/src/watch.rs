@@ -0,0 +1,412 @@
+//! Filesystem watcher that keeps a project's index in sync with `images/`.
+//!
+//! Events are debounced, then the touched paths are reconciled against
+//! `search.db`: new files are indexed (and embedded), missing ones are cleaned
+//! up, and a file that disappears while one with the same content appears is
+//! treated as a rename, so its tags, description and embedding carry over.
+//! `board.json` is updated to match. Used by the app (one watcher per open
+//! project, reporting via the `watch:sync` event) and by `deco watch`.
+
+use notify::{EventKind, RecursiveMode, Watcher};
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::Emitter;
+
+/// Quiet period before a batch of changes is processed.
+const DEBOUNCE: Duration = Duration::from_millis(750);
+/// Longest a busy folder (e.g. a large copy) can delay processing.
+const MAX_BATCH_DELAY: Duration = Duration::from_secs(5);
+/// How long a file written by this process is kept out of reports.
+const OWN_WRITE_WINDOW: Duration = Duration::from_secs(30);
+
+/// Files this process just wrote into a project (imports); the watcher
+/// indexes them but doesn't report them, since the caller already shows them.
+static OWN_WRITES: Mutex<Vec<(PathBuf, Instant)>> = Mutex::new(Vec::new());
+
+/// Record that this process wrote `path`, so watchers don't report it as added.
+pub fn note_own_write(path: &Path) {
+    if let Ok(mut writes) = OWN_WRITES.lock() {
+        writes.retain(|(_, at)| at.elapsed() < OWN_WRITE_WINDOW);
+        writes.push((path.to_path_buf(), Instant::now()));
+    }
+}
+
+fn is_own_write(path: &Path) -> bool {
+    OWN_WRITES
+        .lock()
+        .map(|writes| {
+            writes
+                .iter()
+                .any(|(p, at)| p == path && at.elapsed() < OWN_WRITE_WINDOW)
+        })
+        .unwrap_or(false)
+}
+
+/// An image whose file was renamed (or moved within `images/`).
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenamedImage {
+    pub from: String,
+    pub to: crate::ImageInfo,
+}
+
+/// What a sync changed.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncReport {
+    pub project_path: String,
+    pub added: Vec<crate::ImageInfo>,
+    pub removed: Vec<String>,
+    pub renamed: Vec<RenamedImage>,
+}
+
+impl SyncReport {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.renamed.is_empty()
+    }
+}
+
+fn image_info(path: &Path) -> Option<crate::ImageInfo> {
+    let extension = path.extension()?.to_string_lossy().to_lowercase();
+    if !crate::IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+        return None;
+    }
+    let meta = std::fs::metadata(path).ok().filter(|m| m.is_file())?;
+    Some(crate::ImageInfo {
+        name: path.file_name()?.to_string_lossy().to_string(),
+        path: path.to_string_lossy().to_string(),
+        size_bytes: meta.len(),
+        extension,
+        duplicate: None,
+    })
+}
+
+/// Whether `path` is an image file (or former image file) under `images/`,
+/// outside hidden folders.
+fn in_images_dir(images_dir: &Path, path: &Path) -> bool {
+    path.strip_prefix(images_dir).is_ok_and(|rel| {
+        rel.components()
+            .all(|c| !c.as_os_str().to_string_lossy().starts_with('.'))
+    })
+}
+
+/// Reconcile the given paths (created, changed, removed or renamed files)
+/// with the project index. Embedding is left to the caller (see `embed_added`).
+pub fn sync_paths(project_path: &str, paths: &[PathBuf]) -> Result<SyncReport, String> {
+    let images_dir = Path::new(project_path).join("images");
+    let conn = crate::search::open_db(project_path)?;
+    let indexed = |path: &str| -> Result<bool, String> {
+        conn.query_row("SELECT 1 FROM images WHERE path = ?1", params![path], |_| Ok(()))
+            .optional()
+            .map(|r| r.is_some())
+            .map_err(|e| format!("Cannot query images: {e}"))
+    };
+
+    let mut present: Vec<crate::ImageInfo> = Vec::new();
+    let mut appeared: Vec<crate::ImageInfo> = Vec::new();
+    let mut vanished: Vec<String> = Vec::new();
+    let mut seen = HashSet::new();
+    for path in paths {
+        if !seen.insert(path) || !in_images_dir(&images_dir, path) {
+            continue;
+        }
+        let path_str = path.to_string_lossy().to_string();
+        if let Some(info) = image_info(path) {
+            if !indexed(&path_str)? {
+                appeared.push(info.clone());
+            }
+            present.push(info);
+        } else if !path.exists() && indexed(&path_str)? {
+            vanished.push(path_str);
+        }
+    }
+
+    let mut report = SyncReport {
+        project_path: project_path.to_string(),
+        ..Default::default()
+    };
+
+    // Pair vanished files with new ones holding the same content
+    for from in vanished {
+        let sha: Option<String> = conn
+            .query_row("SELECT sha256 FROM content_hashes WHERE path = ?1", params![from], |r| r.get(0))
+            .optional()
+            .map_err(|e| format!("Cannot read content hash: {e}"))?;
+        let dhash: Option<i64> = conn
+            .query_row("SELECT dhash FROM image_hashes WHERE path = ?1", params![from], |r| r.get(0))
+            .optional()
+            .map_err(|e| format!("Cannot read image hash: {e}"))?;
+
+        let twin = appeared.iter().position(|candidate| {
+            let path = Path::new(&candidate.path);
+            match (&sha, dhash) {
+                (Some(sha), _) => crate::dedup::content_hash(project_path, path).is_ok_and(|h| h == *sha),
+                (None, Some(dhash)) => crate::phash::dhash_file(path).is_ok_and(|h| h as i64 == dhash),
+                (None, None) => false,
+            }
+        });
+
+        match twin {
+            Some(i) => {
+                let to = appeared.remove(i);
+                crate::search::rename_image_data(project_path, &from, &to.path)?;
+                crate::log::log("WATCH", &format!("Renamed: {from} → {}", to.path));
+                report.renamed.push(RenamedImage { from, to });
+            }
+            None => {
+                crate::thumbs::remove_for(project_path, &from);
+                crate::search::delete_image_data(project_path, &from)?;
+                crate::log::log("WATCH", &format!("Removed: {from}"));
+                report.removed.push(from);
+            }
+        }
+    }
+
+    // New and changed files: index rows, properties, hashes, palettes, thumbnails
+    if !present.is_empty() {
+        crate::search::index_project_images(project_path, &present)?;
+        crate::thumbs::ensure_all(project_path, &present);
+    }
+    report.added = appeared
+        .into_iter()
+        .filter(|info| !is_own_write(Path::new(&info.path)))
+        .collect();
+
+    let renamed: Vec<(String, String)> = report
+        .renamed
+        .iter()
+        .map(|r| (r.from.clone(), r.to.path.clone()))
+        .collect();
+    crate::ops::sync_board_items(project_path, &report.removed, &renamed)?;
+
+    Ok(report)
+}
+
+/// Reconcile everything: files on disk that aren't indexed and index rows
+/// whose file is gone (changes made while nothing was watching).
+pub fn sync_all(project_path: &str) -> Result<SyncReport, String> {
+    let images_dir = Path::new(project_path).join("images");
+    let mut paths: Vec<PathBuf> = if images_dir.is_dir() {
+        crate::scan_images_in(&images_dir.to_string_lossy())?
+            .into_iter()
+            .map(|img| PathBuf::from(img.path))
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let conn = crate::search::open_db(project_path)?;
+    let mut stmt = conn
+        .prepare("SELECT path FROM images")
+        .map_err(|e| format!("Cannot query images: {e}"))?;
+    let rows = stmt
+        .query_map([], |row| row.get::<_, String>(0))
+        .map_err(|e| format!("Cannot query images: {e}"))?;
+    paths.extend(rows.filter_map(|r| r.ok()).map(PathBuf::from));
+
+    sync_paths(project_path, &paths)
+}
+
+/// Embed newly added images (best-effort: the model may not be available).
+pub fn embed_added(report: &SyncReport) {
+    let paths: Vec<String> = report.added.iter().map(|i| i.path.clone()).collect();
+    if paths.is_empty() {
+        return;
+    }
+    let model = crate::embed::default_model();
+    if let Err(e) = crate::embed::embed_and_store(&report.project_path, &paths, model, false) {
+        crate::log::log("WATCH", &format!("Embedding skipped: {e}"));
+    }
+}
+
+/// A running watcher; dropping it stops watching.
+pub struct ProjectWatcher {
+    _watcher: notify::RecommendedWatcher,
+}
+
+/// Watch a project's `images/` folder. Catches up with `sync_all` first, then
+/// calls `on_sync` (on a background thread) for every non-empty batch.
+pub fn start<F>(project_path: &str, mut on_sync: F) -> Result<ProjectWatcher, String>
+where
+    F: FnMut(SyncReport) + Send + 'static,
+{
+    let images_dir = Path::new(project_path).join("images");
+    std::fs::create_dir_all(&images_dir)
+        .map_err(|e| format!("Cannot create images dir: {e}"))?;
+    // Some backends report canonical paths; map them back onto ours
+    let canonical_dir = images_dir.canonicalize().unwrap_or_else(|_| images_dir.clone());
+
+    let (tx, rx) = mpsc::channel::<PathBuf>();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        let Ok(event) = res else { return };
+        if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        }
+        for path in event.paths {
+            let _ = tx.send(path);
+        }
+    })
+    .map_err(|e| format!("Cannot create watcher: {e}"))?;
+    watcher
+        .watch(&images_dir, RecursiveMode::Recursive)
+        .map_err(|e| format!("Cannot watch {}: {e}", images_dir.display()))?;
+
+    let project = project_path.to_string();
+    std::thread::spawn(move || {
+        let mut run = |result: Result<SyncReport, String>| match result {
+            Ok(report) if !report.is_empty() => {
+                embed_added(&report);
+                on_sync(report);
+            }
+            Ok(_) => {}
+            Err(e) => crate::log::log("WATCH", &format!("Sync failed: {e}")),
+        };
+
+        run(sync_all(&project));
+
+        let mut pending: HashSet<PathBuf> = HashSet::new();
+        let mut first_at: Option<Instant> = None;
+        loop {
+            let received = rx.recv_timeout(DEBOUNCE);
+            let quiet = matches!(received, Err(RecvTimeoutError::Timeout));
+            match received {
+                Ok(path) => {
+                    let path = match path.strip_prefix(&canonical_dir) {
+                        Ok(rel) => images_dir.join(rel),
+                        Err(_) => path,
+                    };
+                    pending.insert(path);
+                    first_at.get_or_insert_with(Instant::now);
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+
+            let overdue = first_at.is_some_and(|at| at.elapsed() >= MAX_BATCH_DELAY);
+            if !pending.is_empty() && (quiet || overdue) {
+                let paths: Vec<PathBuf> = pending.drain().collect();
+                first_at = None;
+                run(sync_paths(&project, &paths));
+            }
+        }
+        crate::log::log("WATCH", &format!("Stopped watching {project}"));
+    });
+
+    crate::log::log("WATCH", &format!("Watching {project_path}/images"));
+    Ok(ProjectWatcher { _watcher: watcher })
+}
+
+// ---------------------------------------------------------------------------
+// Tauri Commands
+// ---------------------------------------------------------------------------
+
+/// Watchers for the projects open in the app, keyed by project path.
+#[derive(Default)]
+pub struct Watchers(Mutex<HashMap<String, ProjectWatcher>>);
+
+/// Start watching a project (no-op if already watched). Changes are reported
+/// through the `watch:sync` event with a `SyncReport` payload.
+#[tauri::command]
+pub async fn cmd_watch_project(
+    app: tauri::AppHandle,
+    watchers: tauri::State<'_, Watchers>,
+    project_path: String,
+) -> Result<(), String> {
+    let mut map = watchers.inner().0.lock().map_err(|e| format!("Watcher lock poisoned: {e}"))?;
+    if map.contains_key(&project_path) {
+        return Ok(());
+    }
+    let watcher = start(&project_path, move |report| {
+        let _ = app.emit("watch:sync", &report);
+    })?;
+    map.insert(project_path, watcher);
+    Ok(())
+}
+
+/// Stop watching a project.
+#[tauri::command]
+pub async fn cmd_unwatch_project(
+    watchers: tauri::State<'_, Watchers>,
+    project_path: String,
+) -> Result<(), String> {
+    let mut map = watchers.inner().0.lock().map_err(|e| format!("Watcher lock poisoned: {e}"))?;
+    map.remove(&project_path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_png(path: &Path, shade: u8) {
+        image::RgbImage::from_fn(48, 32, |x, y| image::Rgb([shade, (x * 5) as u8, (y * 7) as u8]))
+            .save(path)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_sync_add_rename_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_string_lossy().to_string();
+        let images = dir.path().join("images");
+        std::fs::create_dir_all(&images).unwrap();
+        let a = images.join("a.png");
+        let b = images.join("b.png");
+        write_png(&a, 10);
+        write_png(&b, 200);
+
+        // Both files show up as added
+        let report = sync_all(&project).unwrap();
+        assert_eq!(report.added.len(), 2);
+
+        // Tag one of them, then rename it outside the app
+        crate::ops::update_item_metadata(&project, "a.png", crate::ops::UpdateFields {
+            tags: Some(vec!["brass".to_string()]),
+            ..Default::default()
+        })
+        .unwrap();
+        let renamed = images.join("lobby.png");
+        std::fs::rename(&a, &renamed).unwrap();
+        let report = sync_paths(&project, &[a.clone(), renamed.clone()]).unwrap();
+        assert_eq!(report.renamed.len(), 1);
+        assert_eq!(report.renamed[0].to.name, "lobby.png");
+        assert!(report.added.is_empty() && report.removed.is_empty());
+        let meta = crate::search::get_image_metadata(&project, &renamed.to_string_lossy())
+            .unwrap()
+            .unwrap();
+        assert_eq!(meta.tags, vec!["brass"]);
+        assert_eq!(meta.name, "lobby.png");
+
+        // Deleting a file drops its row
+        std::fs::remove_file(&b).unwrap();
+        let report = sync_all(&project).unwrap();
+        assert_eq!(report.removed, vec![b.to_string_lossy().to_string()]);
+        assert!(crate::search::get_image_metadata(&project, &b.to_string_lossy())
+            .unwrap()
+            .is_none());
+        assert!(sync_all(&project).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_own_writes_not_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().to_string_lossy().to_string();
+        let images = dir.path().join("images");
+        std::fs::create_dir_all(&images).unwrap();
+        let pasted = images.join("paste.png");
+        write_png(&pasted, 90);
+        note_own_write(&pasted);
+
+        let report = sync_paths(&project, std::slice::from_ref(&pasted)).unwrap();
+        assert!(report.added.is_empty());
+        // Still indexed
+        assert!(crate::search::get_image_metadata(&project, &pasted.to_string_lossy())
+            .unwrap()
+            .is_some());
+    }
+}
@@ -35,6 +35,9 @@ pub struct DownloadResult {
     pub name: String,
     pub size_bytes: u64,
     pub source_url: String,
+    /// Set when the downloaded content already existed in the project.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicate: Option<crate::dedup::DuplicateInfo>,
 }
 
 /// Web collection configuration stored in app config.
@@ -242,11 +245,12 @@ pub fn generate_queries_from_analysis(
 
 /// Download an image from a URL and save it to the project's images directory.
 /// Returns the local path and metadata.
-async fn download_image_to_project(
+pub(crate) async fn download_image_to_project(
     client: &reqwest::Client,
     image_url: &str,
     project_path: &str,
     source_url: &str,
+    policy: crate::dedup::DuplicatePolicy,
 ) -> Result<DownloadResult, String> {
     let project_dir = Path::new(project_path);
     let images_dir = project_dir.join("images");
@@ -293,22 +297,19 @@ async fn download_image_to_project(
         .await
         .map_err(|e| format!("Download read error: {e}"))?;
 
-    let size = bytes.len() as u64;
-
-    std::fs::write(&final_path, &bytes)
+    // Checks the project for identical content before writing
+    let info = crate::save_image_data(&bytes, &final_path, project_path, policy)
         .map_err(|e| format!("Cannot save image: {e}"))?;
-
-    let name = final_path
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("unknown")
-        .to_string();
+    if let Err(e) = crate::search::record_source(project_path, &info.path, image_url, Some(source_url)) {
+        crate::log::log("IMPORT", &format!("Cannot record source of {}: {e}", info.name));
+    }
 
     Ok(DownloadResult {
-        local_path: final_path.to_string_lossy().to_string(),
-        name,
-        size_bytes: size,
+        local_path: info.path,
+        name: info.name,
+        size_bytes: info.size_bytes,
         source_url: source_url.to_string(),
+        duplicate: info.duplicate,
     })
 }
 
@@ -478,6 +479,9 @@ pub async fn cmd_find_more_like(
                         .collect(),
                     colors: Vec::new(),
                     era: row.get(4)?,
+                    provider: None,
+                    model: None,
+                    extra: Default::default(),
                 })
             },
         )
@@ -518,21 +522,28 @@ pub async fn cmd_find_more_like(
 }
 
 /// Download an image from the web and save it to the project.
+/// Runs as a download job (see `jobs`) and waits for it to finish.
 #[tauri::command]
 pub async fn cmd_download_web_image(
     app: tauri::AppHandle,
+    jobs: tauri::State<'_, crate::jobs::Jobs>,
     image_url: String,
     project_path: String,
     source_url: String,
+    duplicate_policy: Option<crate::dedup::DuplicatePolicy>,
 ) -> Result<DownloadResult, String> {
     let _ = app.emit("web:download:start", &image_url);
 
-    let client = reqwest::Client::builder()
-        .timeout(std::time::Duration::from_secs(30))
-        .build()
-        .map_err(|e| format!("Cannot create HTTP client: {e}"))?;
-
-    let result = download_image_to_project(&client, &image_url, &project_path, &source_url).await?;
+    let job = jobs
+        .run(crate::jobs::JobSpec::Download {
+            project_path,
+            image_url,
+            source_url,
+            duplicate_policy,
+        })
+        .await?;
+    let result: DownloadResult = serde_json::from_value(job.result.unwrap_or_default())
+        .map_err(|e| format!("Cannot read download result: {e}"))?;
 
     let _ = app.emit("web:download:complete", &result.local_path);
 
@@ -610,6 +621,9 @@ mod tests {
             mood: vec!["dynamic".to_string(), "bold".to_string()],
             colors: vec!["#D4AF37".to_string()],
             era: Some("1920s".to_string()),
+            provider: None,
+            model: None,
+            extra: Default::default(),
         };
 
         let queries = generate_queries_from_analysis(&analysis, None);
@@ -628,6 +642,9 @@ mod tests {
             mood: vec!["calm".to_string()],
             colors: vec![],
             era: None,
+            provider: None,
+            model: None,
+            extra: Default::default(),
         };
 
         let queries = generate_queries_from_analysis(&analysis, Some("more colorful"));
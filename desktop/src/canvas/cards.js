@@ -1,7 +1,7 @@
 // Deco 2.0 — Card CRUD, Text/Shape, Resize, Texture
 
 import { Container, Sprite, Graphics, Assets, Rectangle, Text, TextStyle } from 'pixi.js';
-import { convertFileSrc } from '@tauri-apps/api/core';
+import { invoke, convertFileSrc } from '@tauri-apps/api/core';
 import {
   state, THEME,
   CARD_MAX_WIDTH, CARD_PADDING, CARD_RADIUS,
@@ -70,8 +70,20 @@ export function createPlaceholderCard(imageInfo, x, y) {
   return card;
 }
 
+async function resolveThumbnail(card) {
+  if (card._thumbResolved) return;
+  card._thumbResolved = true;
+  try {
+    const thumbPath = await invoke('cmd_get_thumbnail', { imagePath: card.data.path, size: 'large' });
+    card._textureUrl = convertFileSrc(thumbPath);
+  } catch {
+    // Keep the original (e.g. image outside a project folder)
+  }
+}
+
 export async function loadTextureIntoCard(card) {
   try {
+    await resolveThumbnail(card);
     const texture = await Assets.load(card._textureUrl);
     if (!card.container.parent) return;
 
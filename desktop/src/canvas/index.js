@@ -124,7 +124,7 @@ export { groupSelected, ungroupSelected, updateGroupBounds } from './groups.js';
 export { clearSelection, setCardSelected, selectAll, getSelectionScreenBounds } from './selection.js';
 
 // shortcuts.js
-export { handleContextAction, tidyUp, undo, redo, deleteSelected, alignSelected, distributeSelected } from './shortcuts.js';
+export { handleContextAction, tidyUp, undo, redo, deleteSelected, removeCardsByPath, alignSelected, distributeSelected } from './shortcuts.js';
 export { tidyUp as autoLayout } from './shortcuts.js';
 
 // connections.js
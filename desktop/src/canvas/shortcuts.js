@@ -216,6 +216,22 @@ export function deleteSelected() {
   }));
 }
 
+/** Remove image cards whose files are already gone from disk (no undo, no file deletion). */
+export function removeCardsByPath(paths) {
+  const gone = new Set(paths);
+  const cards = state.allCards.filter(c => !c.isText && !c.isShape && gone.has(c.data?.path));
+  if (cards.length === 0) return 0;
+  for (const card of cards) {
+    removeConnectionsForCard(card);
+    removeCardFromCanvas(card);
+  }
+  hideResizeHandles();
+  requestCull();
+  markDirty();
+  updateColorPaletteVisibility();
+  return cards.length;
+}
+
 export function copySelected() {
   if (state.selection.size === 0) return;
   state.clipboard = Array.from(state.selection).map(card => ({
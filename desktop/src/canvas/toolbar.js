@@ -363,7 +363,7 @@ export function showLightboxImage(card) {
   const caption = document.getElementById('lightbox-caption');
   if (!img) return;
 
-  img.src = card._textureUrl || convertFileSrc(card.data.path);
+  img.src = convertFileSrc(card.data.path);
   if (caption) {
     const name = card.data.name || card.data.path.split('/').pop();
     caption.textContent = name;
@@ -272,7 +272,11 @@ async function downloadImage(result, cardEl) {
       cardEl.classList.add('downloaded');
     }
 
-    if (statusEl) statusEl.textContent = `Downloaded: ${dlResult.name}`;
+    if (statusEl) {
+      statusEl.textContent = dlResult.duplicate
+        ? `Already in project: ${dlResult.name}`
+        : `Downloaded: ${dlResult.name}`;
+    }
 
     if (onImageAddedCallback) {
       onImageAddedCallback(dlResult);
@@ -144,6 +144,7 @@ function runFilter(query) {
       ...(d.style || []),
       ...(d.mood || []),
       d.era,
+      ...Object.values(d.extra || {}).flat().map(String),
     ].filter(Boolean).join(' ').toLowerCase();
 
     if (searchable.includes(q)) {
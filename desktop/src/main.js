@@ -6,9 +6,9 @@ import './styles/index.css';
 import { invoke, convertFileSrc } from '@tauri-apps/api/core';
 import { listen } from '@tauri-apps/api/event';
 import { getCurrentWindow } from '@tauri-apps/api/window';
-import { initCanvas, loadProject, fitAll, setUIElements, onCardSelect, applyFilter, getBoardState, restoreBoardState, startAutoSave, getSelection, addImageCard, getViewport, applySavedTheme, setThemeMode, exportCanvasPNG, getAllCards, getSelectionScreenBounds, handleContextAction, changeSelectionColor, changeShapeStrokeWidth, changeTextFontSize, toggleTextBold, toggleTextItalic, toggleSelectionFill, toggleSelectionLineStyle } from './canvas/index.js';
+import { initCanvas, loadProject, fitAll, setUIElements, onCardSelect, applyFilter, getBoardState, restoreBoardState, startAutoSave, getSelection, addImageCard, getViewport, applySavedTheme, setThemeMode, exportCanvasPNG, getAllCards, removeCardsByPath, getSelectionScreenBounds, handleContextAction, changeSelectionColor, changeShapeStrokeWidth, changeTextFontSize, toggleTextBold, toggleTextItalic, toggleSelectionFill, toggleSelectionLineStyle } from './canvas/index.js';
 import { initPanels, showMetadata, closePanel, openSettings, closeSettings, analyzeCard, analyzeBatch, openGenerateDialog, startGenerate, initGenerateDialog, closeGenerateDialog, isAutoAnalyzeEnabled, loadFontSizeOnStartup } from './panels.js';
-import { initSearch, setProject, updateSearchMetadata, findSimilar, clusterProject, searchByColor } from './search.js';
+import { initSearch, setProject, updateSearchMetadata, refreshTags, findSimilar, clusterProject, searchByColor } from './search.js';
 import { initCollection, setCollectionProject, findMoreLike, toggleWebPanel } from './collection.js';
 
 // Extracted modules
@@ -103,7 +103,7 @@ async function main() {
             extension: result.ext,
             projectPath: currentProjectPath,
           });
-          imported.push(info);
+          if (info.duplicate?.action !== 'skip') imported.push(info);
           compressed++;
         } else {
           // Import original file (no compression or below threshold)
@@ -111,7 +111,7 @@ async function main() {
             paths: [filePath],
             projectPath: currentProjectPath,
           });
-          if (info) imported.push(info);
+          if (info && info.duplicate?.action !== 'skip') imported.push(info);
         }
       }
 
@@ -138,7 +138,7 @@ async function main() {
           const saveFn = () => {
             invoke('save_board_state', { projectPath: currentProjectPath, state: getBoardState() }).catch(() => {});
           };
-          analyzeBatch(imageCards, saveFn);
+          analyzeBatch(imageCards, saveFn, currentProjectPath);
         }
       }
     } catch (err) {
@@ -193,6 +193,10 @@ async function main() {
             extension: ext,
             projectPath: currentProjectPath,
           });
+          if (info.duplicate?.action === 'skip') {
+            setStatus(`Already in project: ${info.name}`);
+            return;
+          }
           // Place at center of current viewport
           const vp = getViewport();
           const rect = container.getBoundingClientRect();
@@ -207,7 +211,7 @@ async function main() {
             const saveFn = () => {
               invoke('save_board_state', { projectPath: currentProjectPath, state: getBoardState() }).catch(() => {});
             };
-            analyzeBatch([card], saveFn);
+            analyzeBatch([card], saveFn, currentProjectPath);
           }
           // Generate embedding in background with dialog feedback
           console.log('[CLIP] Paste detected, requesting embedding for project');
@@ -459,18 +463,90 @@ async function main() {
     }
   }).catch(() => {});
 
-  // Listen for analyze requests from HTTP API (analyze=true on import)
-  listen('api:analyze-request', async (event) => {
-    const imagePath = event.payload;
-    if (!imagePath) return;
-    // Find the card by path and trigger analysis
-    const allCards = getAllCards();
-    const card = allCards.find(c => c.data?.path === imagePath);
-    if (card) {
-      analyzeCard(card);
+  // Keep the board in sync with files added, renamed or removed outside Deco
+  listen('watch:sync', async (event) => {
+    const { projectPath, added = [], removed = [], renamed = [] } = event.payload || {};
+    if (!projectPath || projectPath !== currentProjectPath) return;
+
+    const cards = getAllCards();
+    for (const { from, to } of renamed) {
+      const card = cards.find(c => c.data?.path === from);
+      if (!card) continue;
+      card.data = { ...card.data, path: to.path, name: to.name };
+      card._textureUrl = convertFileSrc(to.path);
+      card._thumbResolved = false;
+    }
+
+    const removedCount = removeCardsByPath(removed);
+
+    const onBoard = new Set(getAllCards().map(c => c.data?.path));
+    const fresh = added.filter(img => !onBoard.has(img.path));
+    if (fresh.length > 0) {
+      const vp = getViewport();
+      const rect = container.getBoundingClientRect();
+      const centerX = (rect.width / 2 - vp.x) / vp.scale;
+      const centerY = (rect.height / 2 - vp.y) / vp.scale;
+      const stagger = 220;
+      for (let i = 0; i < fresh.length; i++) {
+        await addImageCard(fresh[i], centerX + i * stagger, centerY);
+      }
+    }
+
+    if (fresh.length === 0 && removedCount === 0 && renamed.length === 0) return;
+    saveNow();
+    const parts = [];
+    if (fresh.length) parts.push(`${fresh.length} added`);
+    if (renamed.length) parts.push(`${renamed.length} renamed`);
+    if (removedCount) parts.push(`${removedCount} removed`);
+    setStatus(`Images changed on disk: ${parts.join(', ')}`);
+  }).catch(() => {});
+
+  // Background job progress (embedding, batch analysis, downloads)
+  listen('jobs:update', (event) => {
+    const job = event.payload;
+    if (!job || job.projectPath !== currentProjectPath) return;
+    const label = job.kind === 'embed' ? 'Indexing' : job.kind === 'analyze' ? 'Analyzing' : 'Downloading';
+    if (job.status === 'running' && job.total > 0) {
+      setStatus(`${label} ${job.done}/${job.total}…`);
+    } else if (job.status === 'failed') {
+      setStatus(`${label} failed: ${job.error || 'unknown error'}`);
+    } else if (job.status === 'cancelled') {
+      setStatus(`${label} cancelled`);
     }
   }).catch(() => {});
 
+  // Analysis run by the HTTP API (analyze=true on import): merge into the card
+  listen('api:image-analyzed', (event) => {
+    const { imagePath, analysis } = event.payload || {};
+    if (!imagePath || !analysis) return;
+    const card = getAllCards().find(c => c.data?.path === imagePath);
+    if (!card) return;
+    card.data.description = analysis.description;
+    card.data.tags = analysis.tags;
+    card.data.style = analysis.style;
+    card.data.mood = analysis.mood;
+    card.data.colors = analysis.colors;
+    card.data.era = analysis.era;
+    card.data.extra = analysis.extra || {};
+    saveNow();
+    setStatus(`Analyzed via API: ${card.data.name}`);
+  }).catch(() => {});
+
+  // Tags merged, renamed or normalized via the HTTP API: rewrite cards in place
+  listen('api:tags-changed', (event) => {
+    const { projectPath, renamed = {}, removed = [] } = event.payload || {};
+    if (projectPath !== currentProjectPath) return;
+    for (const card of getAllCards()) {
+      if (!card.data?.tags) continue;
+      const tags = card.data.tags
+        .filter(t => !removed.includes(t))
+        .map(t => renamed[t] || t);
+      card.data.tags = [...new Set(tags)];
+    }
+    refreshTags();
+    setStatus('Tags updated via API');
+  }).catch(() => {});
+
   // Immediate save after destructive operations (delete) + real file deletion
   window.addEventListener('deco:cards-deleted', (e) => {
     saveNow();
@@ -551,7 +627,12 @@ async function openProject(dirPath, loading) {
   try {
     const result = await loadProject(dirPath);
     if (result) {
+      if (currentProjectPath && currentProjectPath !== dirPath) {
+        invoke('cmd_unwatch_project', { projectPath: currentProjectPath }).catch(() => {});
+      }
       currentProjectPath = dirPath;
+      invoke('cmd_watch_project', { projectPath: dirPath })
+        .catch((err) => console.warn('[WATCH] Could not watch project folder:', err));
 
       // Try to restore saved board state (positions, groups, viewport)
       loading.textContent = `Loaded ${result.loaded} images. Restoring layout...`;
@@ -20,7 +20,8 @@ let onAcceptCallback = null; // Called when user accepts suggestions
 let onFindSimilarCallback = null; // Called when user clicks "Find Similar"
 let onFindOnlineCallback = null; // Called when user clicks "Find Online"
 let _getAllCards = null; // Injected from main.js
-let batchCancelled = false; // Batch analysis cancel flag
+let batchJobId = null; // Running batch analysis job, for cancelling
+let loadedAiConfig = null; // Saved AI config; keeps fallbacks and task routes the form doesn't edit
 
 // Provider presets — frontend dropdown value → backend provider + defaults
 const PROVIDER_PRESETS = {
@@ -28,13 +29,14 @@ const PROVIDER_PRESETS = {
   openrouter: { backend: 'openai',    baseUrl: 'https://openrouter.ai/api/v1',                       model: 'google/gemini-2.0-flash',         label: 'OpenRouter',        desc: 'Unified API for multiple providers',          needsKey: true  },
   anthropic:  { backend: 'anthropic', baseUrl: 'https://api.anthropic.com',                          model: 'claude-3-5-haiku-latest',         label: 'Claude',            desc: 'Anthropic Claude vision models',              needsKey: true  },
   ollama:     { backend: 'ollama',    baseUrl: 'http://localhost:11434/v1',                           model: 'llava:13b',                       label: 'Ollama',            desc: 'Run models locally on your machine',          needsKey: false },
-  google:     { backend: 'openai',    baseUrl: 'https://generativelanguage.googleapis.com/v1beta',   model: 'gemini-2.0-flash',                label: 'Google AI',         desc: 'Gemini models via Google AI Studio',          needsKey: true  },
+  google:     { backend: 'gemini',    baseUrl: 'https://generativelanguage.googleapis.com/v1beta',   model: 'gemini-2.0-flash',                label: 'Google AI',         desc: 'Gemini models via Google AI Studio',          needsKey: true  },
   moonshot:   { backend: 'openai',    baseUrl: 'https://api.moonshot.cn/v1',                         model: 'moonshot-v1-8k-vision-preview',   label: 'Moonshot',          desc: 'Moonshot AI vision models',                   needsKey: true  },
   deepseek:   { backend: 'openai',    baseUrl: 'https://api.deepseek.com/v1',                        model: 'deepseek-chat',                   label: 'DeepSeek',          desc: 'DeepSeek vision and reasoning',               needsKey: true  },
   qwen:       { backend: 'openai',    baseUrl: 'https://dashscope.aliyuncs.com/compatible-mode/v1', model: 'qwen-vl-max',                     label: 'Qwen',              desc: 'Alibaba Qwen vision-language models',         needsKey: true  },
   together:   { backend: 'openai',    baseUrl: 'https://api.together.xyz/v1',                      model: 'meta-llama/Llama-Vision-Free',    label: 'Together AI',       desc: 'Open-source models via Together',             needsKey: true  },
   groq:       { backend: 'openai',    baseUrl: 'https://api.groq.com/openai/v1',                   model: 'llava-v1.5-7b-4096-preview',      label: 'Groq',              desc: 'Fast inference with LPU hardware',            needsKey: true  },
   minimax:    { backend: 'openai',    baseUrl: 'https://api.minimax.chat/v1',                      model: 'MiniMax-VL-01',                   label: 'MiniMax',           desc: 'MiniMax multimodal models',                   needsKey: true  },
+  compatible: { backend: 'openai-compatible', baseUrl: 'http://localhost:1234/v1',                   model: '',                                label: 'OpenAI-compatible', desc: 'LM Studio, vLLM, llama.cpp or any OpenAI-style server', needsKey: false, optionalKey: true, customHeaders: true },
 };
 
 // ============================================================
@@ -138,9 +140,54 @@ export function showSuggestions(card, analysis) {
     eraEl.parentElement.style.display = 'none';
   }
 
+  // Project-defined fields
+  renderExtraFields('suggestion-extra', analysis.extra);
+
+  // Provider that answered (may be a fallback)
+  const providerEl = document.getElementById('suggestion-provider');
+  if (providerEl) {
+    const source = [analysis.provider, analysis.model].filter(Boolean).join(' / ');
+    providerEl.textContent = source;
+    providerEl.parentElement.style.display = source ? 'flex' : 'none';
+  }
+
   openPanel('suggestion');
 }
 
+/**
+ * Render a project's extra analysis fields as label/value sections.
+ * Lists become chips; other values are shown as text.
+ */
+function renderExtraFields(containerId, extra) {
+  const container = document.getElementById(containerId);
+  if (!container) return;
+  container.innerHTML = '';
+
+  Object.entries(extra || {}).forEach(([name, value], i) => {
+    const section = document.createElement('div');
+    section.className = 'panel-section';
+    const label = document.createElement('span');
+    label.className = 'panel-section-label';
+    label.textContent = name;
+    section.appendChild(label);
+
+    if (Array.isArray(value)) {
+      const chips = document.createElement('div');
+      chips.className = 'chips-container';
+      chips.id = `${containerId}-${i}`;
+      section.appendChild(chips);
+      container.appendChild(section);
+      renderChips(chips.id, value.map(String), false);
+    } else {
+      const text = document.createElement('span');
+      text.className = 'panel-section-value';
+      text.textContent = typeof value === 'boolean' ? (value ? 'Yes' : 'No') : String(value);
+      section.appendChild(text);
+      container.appendChild(section);
+    }
+  });
+}
+
 function renderChips(containerId, items, editable) {
   const container = document.getElementById(containerId);
   container.innerHTML = '';
@@ -288,6 +335,7 @@ async function acceptSuggestions(analysis) {
   currentCard.data.mood = analysis.mood;
   currentCard.data.colors = analysis.colors;
   currentCard.data.era = analysis.era;
+  currentCard.data.extra = analysis.extra || {};
 
   // Notify callback
   if (onAcceptCallback) {
@@ -344,6 +392,9 @@ export function showMetadata(card) {
     eraEl.parentElement.style.display = 'none';
   }
 
+  // Project-defined fields
+  renderExtraFields('meta-extra', card.data.extra);
+
   // Connections
   renderConnectionsSection(card);
 
@@ -494,68 +545,86 @@ function showAnalysisError(message) {
 // ============================================================
 
 /**
- * Analyze multiple cards sequentially with progress UI.
+ * Analyze multiple cards as a background batch job with progress UI.
+ * Images run in parallel, limited by the provider's concurrency and
+ * requests-per-minute settings.
  * @param {object[]} cards - Array of canvas card objects (images only)
  * @param {function} [onSaveBoard] - Called after batch to persist board state
+ * @param {string} projectPath - Project the results are saved to
  */
-export async function analyzeBatch(cards, onSaveBoard) {
+export async function analyzeBatch(cards, onSaveBoard, projectPath) {
   if (analyzing) return;
   analyzing = true;
-  batchCancelled = false;
 
   const total = cards.length;
+  const cardsByPath = new Map(cards.map(card => [card.data.path, card]));
   let completed = 0;
   let failed = 0;
+  let status = null;
+  const unlisten = [];
 
   try {
     const config = await invoke('get_ai_config');
-    const existingTags = collectAllTags();
 
     showBatchProgress(0, total);
 
-    for (let i = 0; i < cards.length; i++) {
-      if (batchCancelled) break;
-
-      const card = cards[i];
-      const filename = card.data?.name || card.data?.path?.split('/').pop() || 'unknown';
-      updateBatchProgress(i + 1, total, filename);
-
-      try {
-        const result = await invoke('analyze_image', {
-          imagePath: card.data.path,
-          providerConfig: config,
-          existingTags,
-        });
-
-        // Merge result into card data
+    // Merge each result into its card as soon as the image finishes
+    unlisten.push(await listen('ai:batch:progress', (event) => {
+      const { current, imagePath, success, result } = event.payload || {};
+      const card = cardsByPath.get(imagePath);
+      if (!card) return;
+      updateBatchProgress(current, total, imagePath.split('/').pop());
+      if (success && result) {
         card.data.description = result.description;
         card.data.tags = result.tags;
         card.data.style = result.style;
         card.data.mood = result.mood;
         card.data.colors = result.colors;
         card.data.era = result.era;
-
-        // Add new tags to context for subsequent analyses
-        if (result.tags) {
-          result.tags.forEach(t => { if (!existingTags.includes(t)) existingTags.push(t); });
-        }
-
         completed++;
-      } catch (err) {
-        console.error(`[AI] Batch: failed to analyze ${filename}:`, err);
+      } else {
+        console.error(`[AI] Batch: failed to analyze ${imagePath}`);
         failed++;
       }
+    }));
+
+    // Wait for the job to finish (it may do so before its id comes back)
+    let resolveFinished;
+    const finished = new Promise((resolve) => { resolveFinished = resolve; });
+    const finishedJobs = new Map();
+    unlisten.push(await listen('jobs:update', (event) => {
+      const job = event.payload;
+      if (!job || !['completed', 'failed', 'cancelled'].includes(job.status)) return;
+      if (job.id === batchJobId) resolveFinished(job);
+      else finishedJobs.set(job.id, job);
+    }));
+
+    const job = await invoke('cmd_analyze_batch', {
+      imagePaths: [...cardsByPath.keys()],
+      projectPath,
+      providerConfig: config,
+      existingTags: collectAllTags(),
+    });
+    batchJobId = job.id;
+    if (finishedJobs.has(job.id)) resolveFinished(finishedJobs.get(job.id));
+
+    const done = await finished;
+    status = done.status;
+    if (status === 'failed') {
+      showAnalysisError(`Batch analysis failed: ${done.error || 'unknown error'}`);
     }
   } catch (err) {
     showAnalysisError(`Batch analysis failed: ${err}`);
   } finally {
+    unlisten.forEach(stop => stop());
     analyzing = false;
+    batchJobId = null;
     hideBatchProgress();
 
     // Show summary in status bar
     const statusText = document.getElementById('status-text');
     if (statusText) {
-      if (batchCancelled) {
+      if (status === 'cancelled') {
         statusText.textContent = `Analysis cancelled (${completed}/${total} done)`;
       } else if (failed > 0) {
         statusText.textContent = `Analyzed ${completed}/${total} images (${failed} failed)`;
@@ -595,7 +664,7 @@ function hideBatchProgress() {
 
 function setupBatchProgressEvents() {
   document.getElementById('batch-progress-cancel')?.addEventListener('click', () => {
-    batchCancelled = true;
+    if (batchJobId !== null) invoke('cmd_cancel_job', { id: batchJobId }).catch(() => {});
   });
 }
 
@@ -690,6 +759,8 @@ function detectFrontendProvider(config) {
   const p = config.provider || 'openai';
   if (p === 'anthropic') return 'anthropic';
   if (p === 'ollama') return 'ollama';
+  if (p === 'gemini') return 'google';
+  if (p === 'openai-compatible') return 'compatible';
   // For openai backend, match by endpoint URL
   const ep = (config.endpoint || '').toLowerCase();
   if (ep.includes('openrouter.ai')) return 'openrouter';
@@ -704,6 +775,7 @@ function detectFrontendProvider(config) {
 }
 
 function populateSettings(config) {
+  loadedAiConfig = config;
   const frontendProvider = detectFrontendProvider(config);
   const providerSelect = document.getElementById('settings-provider');
   providerSelect.value = frontendProvider;
@@ -717,6 +789,12 @@ function populateSettings(config) {
   const maxTokensInput = document.getElementById('settings-max-tokens');
   if (tempInput) tempInput.value = config.temperature ?? 0.7;
   if (maxTokensInput) maxTokensInput.value = config.maxTokens ?? 4096;
+  const concurrencyInput = document.getElementById('settings-concurrency');
+  const rpmInput = document.getElementById('settings-rpm');
+  if (concurrencyInput) concurrencyInput.value = config.concurrency ?? '';
+  if (rpmInput) rpmInput.value = config.requestsPerMinute ?? '';
+  const headersInput = document.getElementById('settings-headers');
+  if (headersInput) headersInput.value = formatHeaders(config.headers);
 
   // Sync provider list UI
   selectProviderListItem(frontendProvider);
@@ -751,7 +829,9 @@ function updateProviderFields(frontendProvider, preserveValues = false) {
   const modelInput = document.getElementById('settings-model');
 
   // Show/hide API key based on provider
-  apiKeyGroup.style.display = preset.needsKey ? 'flex' : 'none';
+  apiKeyGroup.style.display = preset.needsKey || preset.optionalKey ? 'flex' : 'none';
+  const headersGroup = document.getElementById('settings-headers-group');
+  if (headersGroup) headersGroup.style.display = preset.customHeaders ? 'flex' : 'none';
   endpointGroup.style.display = 'flex'; // Always show base URL
 
   // Update placeholders
@@ -933,6 +1013,22 @@ function setupSettingsEvents() {
   });
 }
 
+/** Parse "Name: value" lines into a headers object (null when empty). */
+function parseHeaders(text) {
+  const headers = {};
+  for (const line of (text || '').split('\n')) {
+    const idx = line.indexOf(':');
+    if (idx <= 0) continue;
+    const name = line.slice(0, idx).trim();
+    if (name) headers[name] = line.slice(idx + 1).trim();
+  }
+  return Object.keys(headers).length ? headers : null;
+}
+
+function formatHeaders(headers) {
+  return Object.entries(headers || {}).map(([name, value]) => `${name}: ${value}`).join('\n');
+}
+
 async function saveSettings() {
   const frontendProvider = document.getElementById('settings-provider').value;
   const apiKey = document.getElementById('settings-api-key').value.trim();
@@ -942,6 +1038,9 @@ async function saveSettings() {
 
   const temperature = parseFloat(document.getElementById('settings-temperature')?.value);
   const maxTokens = parseInt(document.getElementById('settings-max-tokens')?.value);
+  const concurrency = parseInt(document.getElementById('settings-concurrency')?.value);
+  const requestsPerMinute = parseInt(document.getElementById('settings-rpm')?.value);
+  const headers = preset.customHeaders ? parseHeaders(document.getElementById('settings-headers')?.value) : null;
 
   const config = {
     provider: preset.backend,
@@ -950,6 +1049,12 @@ async function saveSettings() {
     model: model || preset.model,
     temperature: isNaN(temperature) ? 0.7 : temperature,
     maxTokens: isNaN(maxTokens) ? 4096 : maxTokens,
+    concurrency: isNaN(concurrency) ? null : concurrency,
+    requestsPerMinute: isNaN(requestsPerMinute) ? null : requestsPerMinute,
+    headers,
+    timeoutSecs: loadedAiConfig?.timeoutSecs ?? null,
+    fallbacks: loadedAiConfig?.fallbacks ?? [],
+    tasks: loadedAiConfig?.tasks ?? {},
   };
 
   const statusEl = document.getElementById('settings-status');
@@ -1072,6 +1177,7 @@ async function testConnection() {
     apiKey: apiKey || null,
     endpoint: endpoint || preset.baseUrl,
     model: model || preset.model,
+    headers: preset.customHeaders ? parseHeaders(document.getElementById('settings-headers')?.value) : null,
   };
 
   try {
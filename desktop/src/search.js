@@ -70,6 +70,7 @@ export async function updateSearchMetadata(card) {
         mood: card.data.mood || [],
         colors: card.data.colors || [],
         era: card.data.era || null,
+        extra: card.data.extra || {},
       },
     });
     // Refresh tags after metadata update
@@ -166,7 +167,7 @@ function setupTagSidebar() {
   }
 }
 
-async function refreshTags() {
+export async function refreshTags() {
   if (!currentProjectPath) return;
 
   try {
@@ -206,16 +207,20 @@ function renderTagSidebar(tags) {
     container.appendChild(clearItem);
   }
 
-  tags.forEach(({ tag, count }) => {
+  treeOrder(tags).forEach(({ tag, count, depth }) => {
     const item = document.createElement('button');
     item.className = 'tag-item';
     if (activeTagFilters.has(tag)) {
       item.classList.add('active');
     }
+    if (depth > 0) {
+      item.style.paddingLeft = `${12 + depth * 12}px`;
+    }
 
     const label = document.createElement('span');
     label.className = 'tag-label';
-    label.textContent = tag;
+    label.textContent = tag.split('/').pop();
+    label.title = tag;
 
     const badge = document.createElement('span');
     badge.className = 'tag-count';
@@ -229,6 +234,28 @@ function renderTagSidebar(tags) {
   });
 }
 
+// Hierarchical tags (`material/bronze`) come rolled up per level; list each
+// level under its parent, keeping the count order among siblings.
+function treeOrder(tags) {
+  const children = new Map();
+  for (const t of tags) {
+    const slash = t.tag.lastIndexOf('/');
+    const parent = slash === -1 ? '' : t.tag.slice(0, slash);
+    if (!children.has(parent)) children.set(parent, []);
+    children.get(parent).push(t);
+  }
+
+  const ordered = [];
+  const visit = (parent, depth) => {
+    for (const t of children.get(parent) || []) {
+      ordered.push({ ...t, depth });
+      visit(t.tag, depth + 1);
+    }
+  };
+  visit('', 0);
+  return ordered;
+}
+
 async function toggleTagFilter(tag) {
   if (activeTagFilters.has(tag)) {
     activeTagFilters.delete(tag);
@@ -548,18 +575,18 @@ export function clearClusters() {
 }
 
 // ============================================================
-// Semantic Search (searches AI-generated descriptions, tags, mood, style)
+// Semantic Search (CLIP text-to-image, FTS5 fallback)
 // ============================================================
 
 /**
- * Search images using natural language query via FTS5 over AI metadata.
- * This searches descriptions, tags, style, mood, era fields.
+ * Search images using a natural language query via the CLIP text encoder.
+ * Falls back to FTS5 over AI metadata when no embeddings are available.
  */
 export async function semanticSearch(query) {
   if (!currentProjectPath || !query) return [];
 
   try {
-    const results = await invoke('cmd_search_text', {
+    const results = await invoke('cmd_search_semantic', {
       projectPath: currentProjectPath,
       query,
       limit: 50,
@@ -587,9 +614,9 @@ export async function semanticSearch(query) {
 /**
  * Search images by color similarity.
  * @param {string} hexColor - Hex color like "#ff6b6b"
- * @param {number} threshold - RGB distance threshold (0-442, default 60)
+ * @param {number} threshold - Perceptual distance threshold (CIEDE2000 ΔE, default 20)
  */
-export async function searchByColor(hexColor, threshold = 60) {
+export async function searchByColor(hexColor, threshold = 20) {
   if (!currentProjectPath || !hexColor) return [];
 
   const statusEl = document.getElementById('status-text');